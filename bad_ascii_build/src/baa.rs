@@ -0,0 +1,170 @@
+use crate::visible_width;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+// The parts of bad_ascii's .baa format needed to write a file it can play: version 1, every frame
+// a keyframe, uncompressed, and no chapters. See src/baa.rs in bad_ascii for the whole format.
+const MAGIC: &[u8; 8] = b"BADASCII";
+const VERSION: u16 = 1;
+const HEADER_LENGTH: u64 = 24;
+const COLOR_PLAIN: u8 = 0;
+const COLOR_ANSI: u8 = 1;
+const COMPRESSION_NONE: u8 = 0;
+const FRAMES_PER_BLOCK: usize = 64;
+const FRAME_KEY: u8 = 0;
+const INDEX_MAGIC: &[u8; 8] = b"BAAINDEX";
+const INDEX_FLAG_KEY: u8 = 1;
+
+pub fn write(
+	frames: &[String],
+	delays: &[Duration],
+	color: bool,
+	out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let columns = frames
+		.iter()
+		.flat_map(|frame| frame.lines())
+		.map(visible_width)
+		.max()
+		.unwrap_or(0);
+	let rows = frames.iter().map(|frame| frame.lines().count()).max().unwrap_or(0);
+	let total: f64 = delays.iter().map(Duration::as_secs_f64).sum();
+	let fps = if total > 0.0 {
+		(frames.len() as f64 / total) as f32
+	} else {
+		0.0
+	};
+	let frame_count = u32::try_from(frames.len()).map_err(|_| "Too many frames for a .baa file")?;
+
+	let mut out = BufWriter::new(out);
+	out.write_all(MAGIC)?;
+	out.write_all(&VERSION.to_le_bytes())?;
+	out.write_all(&fps.to_le_bytes())?;
+	out.write_all(
+		&u16::try_from(columns)
+			.map_err(|_| "Frames are too wide for a .baa file")?
+			.to_le_bytes(),
+	)?;
+	out.write_all(
+		&u16::try_from(rows)
+			.map_err(|_| "Frames are too tall for a .baa file")?
+			.to_le_bytes(),
+	)?;
+	out.write_all(&[if color { COLOR_ANSI } else { COLOR_PLAIN }])?;
+	out.write_all(&[COMPRESSION_NONE])?;
+	out.write_all(&frame_count.to_le_bytes())?;
+
+	// Each frame's delay, block and offset in it, and each block's offset, length and frame count.
+	let mut frame_index: Vec<(u32, u32, u32)> = Vec::new();
+	let mut block_index: Vec<(u64, u32, u32)> = Vec::new();
+	let mut position = HEADER_LENGTH;
+	for (block_frames, block_delays) in frames.chunks(FRAMES_PER_BLOCK).zip(delays.chunks(FRAMES_PER_BLOCK)) {
+		let mut block = Vec::new();
+		for (frame, delay) in block_frames.iter().zip(block_delays) {
+			let delay = u32::try_from(delay.as_micros()).map_err(|_| "Frame delay is too long for a .baa file")?;
+			let text = frame.lines().collect::<Vec<_>>().join("\n");
+			let length = u32::try_from(text.len()).map_err(|_| "Frame is too large for a .baa file")?;
+			frame_index.push((delay, block_index.len() as u32, block.len() as u32));
+			block.push(FRAME_KEY);
+			block.extend_from_slice(&delay.to_le_bytes());
+			block.extend_from_slice(&length.to_le_bytes());
+			block.extend_from_slice(text.as_bytes());
+		}
+		let length = u32::try_from(block.len()).map_err(|_| "Frame block is too large for a .baa file")?;
+		out.write_all(&(block_frames.len() as u32).to_le_bytes())?;
+		out.write_all(&length.to_le_bytes())?;
+		out.write_all(&block)?;
+		block_index.push((position + 8, length, block_frames.len() as u32));
+		position += 8 + block.len() as u64;
+	}
+	// A block without frames marks the end of them.
+	out.write_all(&0u32.to_le_bytes())?;
+	out.write_all(&0u32.to_le_bytes())?;
+	let index_offset = position + 8;
+
+	for (delay, block, offset) in &frame_index {
+		out.write_all(&delay.to_le_bytes())?;
+		out.write_all(&block.to_le_bytes())?;
+		out.write_all(&offset.to_le_bytes())?;
+		out.write_all(&[INDEX_FLAG_KEY])?;
+	}
+	for (offset, length, frame_count) in &block_index {
+		out.write_all(&offset.to_le_bytes())?;
+		out.write_all(&length.to_le_bytes())?;
+		out.write_all(&frame_count.to_le_bytes())?;
+	}
+	let chapters_offset = index_offset + frame_index.len() as u64 * 13 + block_index.len() as u64 * 16;
+	out.write_all(&0u32.to_le_bytes())?;
+
+	out.write_all(&index_offset.to_le_bytes())?;
+	out.write_all(&(block_index.len() as u32).to_le_bytes())?;
+	out.write_all(&chapters_offset.to_le_bytes())?;
+	out.write_all(INDEX_MAGIC)?;
+	out.flush()?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn u32_at(bytes: &[u8], at: usize) -> u32 {
+		u32::from_le_bytes(bytes[at..at + 4].try_into().expect("four bytes"))
+	}
+
+	fn u64_at(bytes: &[u8], at: usize) -> u64 {
+		u64::from_le_bytes(bytes[at..at + 8].try_into().expect("eight bytes"))
+	}
+
+	#[test]
+	fn the_header_describes_the_frames() {
+		let frames = ["ab\ncd\n".to_string(), "efg\nh".to_string()];
+		let mut out = Vec::new();
+		write(&frames, &[Duration::from_millis(100); 2], true, &mut out).expect("the file is written");
+
+		assert_eq!(&out[..8], MAGIC);
+		assert_eq!(u16::from_le_bytes([out[8], out[9]]), VERSION);
+		assert_eq!(f32::from_le_bytes(out[10..14].try_into().expect("four bytes")), 10.0);
+		assert_eq!(u16::from_le_bytes([out[14], out[15]]), 3);
+		assert_eq!(u16::from_le_bytes([out[16], out[17]]), 2);
+		assert_eq!(out[18], COLOR_ANSI);
+		assert_eq!(out[19], COMPRESSION_NONE);
+		assert_eq!(u32_at(&out, 20), 2);
+	}
+
+	#[test]
+	fn frames_are_keyframes_without_trailing_newlines() {
+		let frames = ["ab\ncd\n".to_string()];
+		let mut out = Vec::new();
+		write(&frames, &[Duration::from_millis(40)], false, &mut out).expect("the file is written");
+
+		let block = HEADER_LENGTH as usize;
+		assert_eq!(u32_at(&out, block), 1);
+		assert_eq!(u32_at(&out, block + 4), 14);
+		assert_eq!(out[block + 8], FRAME_KEY);
+		assert_eq!(u32_at(&out, block + 9), 40_000);
+		assert_eq!(u32_at(&out, block + 13), 5);
+		assert_eq!(&out[block + 17..block + 22], b"ab\ncd");
+		// The block of no frames that ends them.
+		assert_eq!(u64_at(&out, block + 22), 0);
+	}
+
+	#[test]
+	fn the_trailer_points_at_the_index() {
+		let frames = ["ab".to_string(), "cd".to_string()];
+		let mut out = Vec::new();
+		write(&frames, &[Duration::from_millis(100); 2], false, &mut out).expect("the file is written");
+
+		let trailer = out.len() - 28;
+		assert_eq!(&out[out.len() - 8..], INDEX_MAGIC);
+		let index_offset = u64_at(&out, trailer) as usize;
+		assert_eq!(u32_at(&out, trailer + 8), 1);
+		let chapters_offset = u64_at(&out, trailer + 12) as usize;
+		assert_eq!(chapters_offset, index_offset + 2 * 13 + 16);
+		assert_eq!(u32_at(&out, chapters_offset), 0);
+		assert_eq!(u32_at(&out, index_offset), 100_000);
+		assert_eq!(out[index_offset + 12], INDEX_FLAG_KEY);
+		// The one block starts right after its frame count and length.
+		assert_eq!(u64_at(&out, index_offset + 26), HEADER_LENGTH + 8);
+	}
+}
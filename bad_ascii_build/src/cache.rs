@@ -0,0 +1,152 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// Bumped whenever the conversion or the file layout changes, so that older entries stop matching.
+const FORMAT_VERSION: u32 = 1;
+
+// Frames converted by an earlier build, named after a hash of the input files' names and contents
+// and the macro's options. Each entry is the frame count, then every frame's delay in
+// nanoseconds, length and text. Extracted audio is kept as it is, next to the frames.
+pub struct Entry {
+	path: PathBuf,
+}
+
+impl Entry {
+	// In the calling crate's OUT_DIR when it has a build script, in its target directory otherwise.
+	pub fn find(inputs: &[PathBuf], settings: &str) -> io::Result<Self> {
+		let dir = match (env::var_os("OUT_DIR"), env::var_os("CARGO_TARGET_DIR")) {
+			(Some(out_dir), _) => PathBuf::from(out_dir),
+			(None, Some(target_dir)) => PathBuf::from(target_dir),
+			(None, None) => PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default()).join("target"),
+		};
+
+		let mut hasher = blake3::Hasher::new();
+		for input in inputs {
+			hasher.update(input.as_os_str().as_encoded_bytes());
+			io::copy(&mut File::open(input)?, &mut hasher)?;
+		}
+		hasher.update(&FORMAT_VERSION.to_le_bytes());
+		hasher.update(settings.as_bytes());
+		Ok(Self {
+			path: dir
+				.join("bad_ascii_frames")
+				.join(format!("{}.frames", hasher.finalize().to_hex())),
+		})
+	}
+
+	// Anything wrong with the entry counts as a miss, it is converted again and overwritten.
+	pub fn load(&self) -> Option<(Vec<String>, Vec<Duration>)> {
+		let mut input = BufReader::new(File::open(&self.path).ok()?);
+		let count = read_u64(&mut input).ok()?;
+		let mut frames = Vec::new();
+		let mut delays = Vec::new();
+		for _ in 0..count {
+			delays.push(Duration::from_nanos(read_u64(&mut input).ok()?));
+			let mut frame = vec![0; usize::try_from(read_u64(&mut input).ok()?).ok()?];
+			input.read_exact(&mut frame).ok()?;
+			frames.push(String::from_utf8(frame).ok()?);
+		}
+		Some((frames, delays))
+	}
+
+	pub fn store(&self, frames: &[String], delays: &[Duration]) -> io::Result<()> {
+		self.write(|out| {
+			out.write_all(&(frames.len() as u64).to_le_bytes())?;
+			for (frame, delay) in frames.iter().zip(delays) {
+				out.write_all(&(delay.as_nanos() as u64).to_le_bytes())?;
+				out.write_all(&(frame.len() as u64).to_le_bytes())?;
+				out.write_all(frame.as_bytes())?;
+			}
+			Ok(())
+		})
+	}
+
+	pub fn load_audio(&self) -> Option<Vec<u8>> {
+		fs::read(self.path.with_extension("audio")).ok()
+	}
+
+	pub fn store_audio(&self, audio: &[u8]) -> io::Result<()> {
+		Self {
+			path: self.path.with_extension("audio"),
+		}
+		.write(|out| out.write_all(audio))
+	}
+
+	fn write(&self, contents: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+		let dir = self
+			.path
+			.parent()
+			.expect("cache entries are inside the cache directory");
+		fs::create_dir_all(dir)?;
+
+		// Written under another name first, so that an interrupted build doesn't leave half a file
+		// behind.
+		let partial = self.path.with_extension("partial");
+		let mut out = BufWriter::new(File::create(&partial)?);
+		contents(&mut out)?;
+		out.into_inner()?.sync_all()?;
+		fs::rename(partial, &self.path)
+	}
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+	let mut bytes = [0; 8];
+	input.read_exact(&mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn temporary(name: &str) -> PathBuf {
+		env::temp_dir()
+			.join(format!("bad_ascii_cache_test_{}", std::process::id()))
+			.join(name)
+	}
+
+	#[test]
+	fn stored_frames_load_back() {
+		let entry = Entry {
+			path: temporary("round_trip.frames"),
+		};
+		let frames = vec!["ab\ncd\n".to_string(), "é\n".to_string()];
+		let delays = vec![Duration::from_millis(40), Duration::from_millis(100)];
+		entry.store(&frames, &delays).expect("the entry can be written");
+		assert_eq!(entry.load(), Some((frames, delays)));
+
+		entry.store_audio(&[1, 2, 3]).expect("the audio can be written");
+		assert_eq!(entry.load_audio(), Some(vec![1, 2, 3]));
+	}
+
+	#[test]
+	fn broken_entries_are_misses() {
+		let entry = Entry {
+			path: temporary("broken.frames"),
+		};
+		assert_eq!(entry.load(), None);
+		entry
+			.store(&["abc".to_string()], &[Duration::ZERO])
+			.expect("the entry can be written");
+		let bytes = fs::read(&entry.path).expect("the entry can be read");
+		fs::write(&entry.path, &bytes[..bytes.len() - 1]).expect("the entry can be cut short");
+		assert_eq!(entry.load(), None);
+	}
+
+	#[test]
+	fn entries_differ_by_settings() {
+		let input = temporary("input.txt");
+		fs::create_dir_all(input.parent().expect("the input is in a directory")).expect("the directory can be made");
+		fs::write(&input, "frames").expect("the input can be written");
+		let find = |settings| {
+			Entry::find(&[input.clone()], settings)
+				.expect("the input can be hashed")
+				.path
+		};
+		assert_eq!(find("width=80"), find("width=80"));
+		assert_ne!(find("width=80"), find("width=120"));
+	}
+}
@@ -0,0 +1,175 @@
+use image::DynamicImage;
+use image::imageops::{self, FilterType};
+use std::fmt::Write;
+
+// xterm's 16 basic colors, the first 16 of the 256-color palette.
+const BASIC: [[u8; 3]; 16] = [
+	[0, 0, 0],
+	[205, 0, 0],
+	[0, 205, 0],
+	[205, 205, 0],
+	[0, 0, 238],
+	[205, 0, 205],
+	[0, 205, 205],
+	[229, 229, 229],
+	[127, 127, 127],
+	[255, 0, 0],
+	[0, 255, 0],
+	[255, 255, 0],
+	[92, 92, 255],
+	[255, 0, 255],
+	[0, 255, 255],
+	[255, 255, 255],
+];
+
+// How embedded frames are colored. Colors are worked out here rather than by artem, whose output
+// depends on the environment the compiler happens to run in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Color {
+	Plain,
+	Basic,
+	Indexed,
+	Rgb,
+}
+
+impl Color {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"16" => Some(Color::Basic),
+			"256" => Some(Color::Indexed),
+			"truecolor" => Some(Color::Rgb),
+			_ => None,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Color::Plain => "plain",
+			Color::Basic => "16",
+			Color::Indexed => "256",
+			Color::Rgb => "truecolor",
+		}
+	}
+
+	// Gives every character of the plain text the color of the part of the frame it stands for,
+	// writing an escape only where the color changes.
+	pub fn paint(self, text: &str, frame: &DynamicImage) -> String {
+		if self == Color::Plain {
+			return text.to_string();
+		}
+		let lines: Vec<&str> = text.lines().collect();
+		let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+		if columns == 0 {
+			return text.to_string();
+		}
+		let cells = imageops::resize(&frame.to_rgb8(), columns, lines.len() as u32, FilterType::Triangle);
+
+		let mut painted = String::with_capacity(text.len() * 8);
+		for (row, line) in lines.iter().enumerate() {
+			if row > 0 {
+				painted.push('\n');
+			}
+			let mut previous = None;
+			for (column, character) in line.chars().enumerate() {
+				let escape = self.escape(cells.get_pixel(column as u32, row as u32).0);
+				if previous.as_ref() != Some(&escape) {
+					painted.push_str(&escape);
+					previous = Some(escape);
+				}
+				painted.push(character);
+			}
+			painted.push_str("\u{1b}[0m");
+		}
+		if text.ends_with('\n') {
+			painted.push('\n');
+		}
+		painted
+	}
+
+	fn escape(self, [red, green, blue]: [u8; 3]) -> String {
+		let mut escape = String::new();
+		match self {
+			Color::Plain => {},
+			Color::Basic => match nearest([red, green, blue], 16) {
+				color @ 0..=7 => write!(escape, "\u{1b}[{}m", 30 + color as u16),
+				color => write!(escape, "\u{1b}[{}m", 90 + color as u16 - 8),
+			}
+			.expect("writing to a String can't fail"),
+			Color::Indexed => write!(escape, "\u{1b}[38;5;{}m", nearest([red, green, blue], 256))
+				.expect("writing to a String can't fail"),
+			Color::Rgb => {
+				write!(escape, "\u{1b}[38;2;{};{};{}m", red, green, blue).expect("writing to a String can't fail")
+			},
+		}
+		escape
+	}
+}
+
+fn nearest(rgb: [u8; 3], count: u32) -> u8 {
+	let distance = |color: u32| {
+		indexed_color(color)
+			.iter()
+			.zip(rgb)
+			.map(|(&a, b)| (a as i32 - b as i32).pow(2))
+			.sum::<i32>()
+	};
+	(0..count).min_by_key(|&color| distance(color)).unwrap_or(0) as u8
+}
+
+fn indexed_color(index: u32) -> [u8; 3] {
+	match index {
+		0..=15 => BASIC[index as usize],
+		16..=231 => {
+			let level = |value: u32| if value == 0 { 0 } else { (55 + value * 40) as u8 };
+			let index = index - 16;
+			[level(index / 36), level(index / 6 % 6), level(index % 6)]
+		},
+		_ => {
+			let gray = (8 + (index.min(255) - 232) * 10) as u8;
+			[gray, gray, gray]
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::{Rgb, RgbImage};
+
+	fn red() -> DynamicImage {
+		DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([255, 0, 0])))
+	}
+
+	#[test]
+	fn the_palette_follows_xterm() {
+		assert_eq!(indexed_color(1), [205, 0, 0]);
+		assert_eq!(indexed_color(16), [0, 0, 0]);
+		assert_eq!(indexed_color(67), [95, 135, 175]);
+		assert_eq!(indexed_color(231), [255, 255, 255]);
+		assert_eq!(indexed_color(232), [8, 8, 8]);
+		assert_eq!(indexed_color(255), [238, 238, 238]);
+	}
+
+	#[test]
+	fn colors_snap_to_the_nearest_in_the_palette() {
+		assert_eq!(nearest([200, 10, 10], 16), 1);
+		assert_eq!(nearest([95, 135, 175], 256), 67);
+		assert_eq!(nearest([239, 239, 239], 256), 255);
+	}
+
+	#[test]
+	fn escapes_are_written_where_the_color_changes() {
+		assert_eq!(Color::Rgb.paint("ab\n", &red()), "\u{1b}[38;2;255;0;0mab\u{1b}[0m\n");
+		assert_eq!(Color::Basic.paint("ab", &red()), "\u{1b}[91mab\u{1b}[0m");
+		assert_eq!(Color::Indexed.paint("a", &red()), "\u{1b}[38;5;9ma\u{1b}[0m");
+		assert_eq!(Color::Plain.paint("ab\n", &red()), "ab\n");
+	}
+
+	#[test]
+	fn names_round_trip() {
+		for color in [Color::Basic, Color::Indexed, Color::Rgb] {
+			assert!(Color::from_name(color.name()) == Some(color));
+		}
+		assert!(Color::from_name("plain").is_none());
+	}
+}
@@ -0,0 +1,577 @@
+use crate::Options;
+use crate::cache::Entry;
+use crate::color::Color;
+use crate::shade::Shading;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageFormat, RgbImage};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+pub const AUDIO_SAMPLE_RATE: u32 = 48000;
+pub const AUDIO_CHANNELS: u16 = 2;
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+type TimedFrames = Vec<(DynamicImage, Duration)>;
+
+// Every width's frames, and the delays they share.
+pub type Converted = (Vec<Vec<String>>, Vec<Duration>);
+
+// Each width is cached on its own, but a single one missing means decoding the input again, so
+// then all of them are converted.
+pub fn cached_frames(input: &str, options: &Options) -> Result<Converted, Box<dyn std::error::Error>> {
+	let files = input_files(input)?;
+	let entries = options
+		.widths
+		.iter()
+		.map(|width| {
+			let settings = format!(
+				"fps={} duration={} width={} color={} {}",
+				options.fps,
+				options.duration,
+				width,
+				options.color.name(),
+				options.shading.key()
+			) + &limits(options);
+			Entry::find(&files, &settings).map_err(|error| format!("{}: {}", input, error))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	if let Some(loaded) = entries.iter().map(Entry::load).collect::<Option<Vec<_>>>() {
+		let delays = loaded[0].1.clone();
+		return Ok((loaded.into_iter().map(|(frames, _)| frames).collect(), delays));
+	}
+	let converted = convert_frames(input, options, &files)?;
+	for (entry, frames) in entries.iter().zip(&converted.0) {
+		// Only a faster next build is lost when the cache can't be written.
+		let _ = entry.store(frames, &converted.1);
+	}
+	Ok(converted)
+}
+
+// The audio track as 16-bit little endian PCM, interleaved, at AUDIO_SAMPLE_RATE and
+// AUDIO_CHANNELS.
+pub fn cached_audio(input: &str, options: &Options) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+	if is_pattern(input) || ImageFormat::from_path(input).is_ok() {
+		return Err(format!("{} has no audio track", input).into());
+	}
+	let files = input_files(input)?;
+	let settings = format!(
+		"audio duration={} rate={} channels={}",
+		options.duration, AUDIO_SAMPLE_RATE, AUDIO_CHANNELS
+	);
+	let entry = Entry::find(&files, &settings).map_err(|error| format!("{}: {}", input, error))?;
+	if let Some(audio) = entry.load_audio() {
+		return Ok(audio);
+	}
+	let audio = run(
+		"ffmpeg",
+		&[
+			"-i",
+			input,
+			"-t",
+			&options.duration.to_string(),
+			"-vn",
+			"-f",
+			"s16le",
+			"-acodec",
+			"pcm_s16le",
+			"-ac",
+			&AUDIO_CHANNELS.to_string(),
+			"-ar",
+			&AUDIO_SAMPLE_RATE.to_string(),
+			"-",
+		],
+	)?;
+	// Only a faster next build is lost when the cache can't be written.
+	let _ = entry.store_audio(&audio);
+	Ok(audio)
+}
+
+pub fn input_files(path: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+	if !is_pattern(path) {
+		return Ok(vec![PathBuf::from(path)]);
+	}
+	let mut files = glob::glob(path)?.collect::<Result<Vec<_>, _>>()?;
+	if files.is_empty() {
+		return Err(format!("{} matches no files", path).into());
+	}
+	files.sort();
+	Ok(files)
+}
+
+pub fn is_pattern(path: &str) -> bool {
+	path.contains(['*', '?', '['])
+}
+
+fn convert_frames(input: &str, options: &Options, files: &[PathBuf]) -> Result<Converted, Box<dyn std::error::Error>> {
+	let decoded = if is_pattern(input) {
+		Some(decode_sequence(input, files, options)?)
+	} else {
+		// Only the decoder's own errors need saying which input they're about.
+		decode_native(input, options).map_err(|error| match error.downcast::<image::ImageError>() {
+			Ok(error) => format!("Failed to decode {}: {}", input, error).into(),
+			Err(error) => error,
+		})?
+	};
+	let (sizes, delays) = match decoded {
+		Some(frames) => {
+			let (frames, delays): (Vec<_>, Vec<_>) = subsample(frames, options.max_frames).into_iter().unzip();
+			let sizes = options
+				.widths
+				.iter()
+				.map(|&width| convert_parallel(&frames, width, options.color, &options.shading))
+				.collect();
+			(sizes, delays)
+		},
+		None => extract_frames(input, options)?,
+	};
+	if delays.is_empty() {
+		return Err(format!("{} has no frames to embed", input).into());
+	}
+	Ok((sizes, delays))
+}
+
+// Frames are converted on every core, each thread taking a run of consecutive ones so that they
+// come back in order.
+fn convert_parallel(frames: &[DynamicImage], width: u32, color: Color, shading: &Shading) -> Vec<String> {
+	let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+	let run = frames.len().div_ceil(threads).max(1);
+	thread::scope(|scope| {
+		let converting: Vec<_> = frames
+			.chunks(run)
+			.map(|run| {
+				scope.spawn(move || {
+					run.iter()
+						.map(|frame| frame_to_ascii(frame, width, color, shading))
+						.collect::<Vec<_>>()
+				})
+			})
+			.collect();
+		converting
+			.into_iter()
+			.flat_map(|converted| converted.join().expect("a conversion thread panicked"))
+			.collect()
+	})
+}
+
+// ffmpeg's raw output is read a frame at a time and converted as it arrives, a batch per core,
+// rather than holding the whole decoded video in memory.
+// The frames are 1 / fps apart, or further when max_frames has ffmpeg take fewer.
+fn extract_frames(input: &str, options: &Options) -> Result<Converted, Box<dyn std::error::Error>> {
+	let (frames, seconds) = match options.max_frames {
+		Some(max_frames) if options.fps.saturating_mul(options.duration) > max_frames => (max_frames, options.duration),
+		_ => (options.fps, 1),
+	};
+	let frame_delay = Duration::from_secs_f64(seconds as f64 / frames as f64);
+	let mut child = Command::new("ffmpeg")
+		.args([
+			"-i",
+			input,
+			"-t",
+			&options.duration.to_string(),
+			"-r",
+			&format!("{}/{}", frames, seconds),
+			// Non-square pixels are stretched out to the shape the video is shown with.
+			"-vf",
+			"scale=w=iw*sar:h=ih,setsar=1",
+			"-f",
+			"image2pipe",
+			"-pix_fmt",
+			"rgb24",
+			"-vcodec",
+			"ppm",
+			"-",
+		])
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|error| spawn_error("ffmpeg", error))?;
+
+	// Drained on its own thread so that a chatty ffmpeg can't block on a full pipe.
+	let mut stderr = child.stderr.take().expect("ffmpeg's stderr is piped");
+	let errors = thread::spawn(move || {
+		let mut errors = String::new();
+		let _ = stderr.read_to_string(&mut errors);
+		errors
+	});
+
+	let mut stdout = BufReader::new(child.stdout.take().expect("ffmpeg's stdout is piped"));
+	let batch_size = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+	let mut sizes = vec![Vec::new(); options.widths.len()];
+	let mut batch = Vec::with_capacity(batch_size);
+	let mut read: u32 = 0;
+	loop {
+		// A frame cut short ends the frames like the end of the output does, and ffmpeg's exit
+		// status says why.
+		let frame = read_ppm(&mut stdout).unwrap_or(None);
+		let finished = frame.is_none();
+		// The rate ffmpeg was given can round to a frame over max_frames, which is read and dropped.
+		if !finished
+			&& options
+				.max_frames
+				.is_some_and(|max_frames| u64::from(read) >= max_frames)
+		{
+			continue;
+		}
+		if let Some(frame) = frame {
+			if let Err(error) = check_duration(input, frame_delay * read, options) {
+				let _ = child.kill();
+				let _ = child.wait();
+				return Err(error);
+			}
+			read += 1;
+			batch.push(DynamicImage::ImageRgb8(frame));
+		}
+		if batch.len() == batch_size || (finished && !batch.is_empty()) {
+			for (frames, &width) in sizes.iter_mut().zip(&options.widths) {
+				frames.extend(convert_parallel(&batch, width, options.color, &options.shading));
+			}
+			batch.clear();
+		}
+		if finished {
+			break;
+		}
+	}
+
+	let status = child.wait()?;
+	let errors = errors.join().unwrap_or_default();
+	if !status.success() {
+		return Err(format!("ffmpeg failed ({}): {}", status, errors.trim()).into());
+	}
+	let delays = vec![frame_delay; sizes[0].len()];
+	Ok((sizes, delays))
+}
+
+// GIF, APNG and WebP are decoded in pure Rust so that they work without ffmpeg installed.
+fn decode_native(input: &str, options: &Options) -> Result<Option<TimedFrames>, Box<dyn std::error::Error>> {
+	let duration = options.duration;
+	let frames = match ImageFormat::from_path(input) {
+		Ok(ImageFormat::Gif) => GifDecoder::new(open_file(input)?)?.into_frames(),
+		Ok(ImageFormat::Png) => {
+			let decoder = PngDecoder::new(open_file(input)?)?;
+			if !decoder.is_apng()? {
+				return Ok(Some(vec![(image::open(input)?, Duration::from_secs(duration))]));
+			}
+			decoder.apng()?.into_frames()
+		},
+		Ok(ImageFormat::WebP) => {
+			let decoder = WebPDecoder::new(open_file(input)?)?;
+			if !decoder.has_animation() {
+				return Ok(Some(vec![(image::open(input)?, Duration::from_secs(duration))]));
+			}
+			decoder.into_frames()
+		},
+		_ => return Ok(None),
+	};
+
+	let mut decoded = Vec::new();
+	let mut total_duration = Duration::ZERO;
+
+	for frame in frames {
+		let frame = frame?;
+		check_duration(input, total_duration, options)?;
+		let delay = match Duration::from(frame.delay()) {
+			delay if delay < MIN_FRAME_DELAY => DEFAULT_FRAME_DELAY,
+			delay => delay,
+		};
+
+		decoded.push((DynamicImage::ImageRgba8(frame.into_buffer()), delay));
+		total_duration += delay;
+		if total_duration >= Duration::from_secs(duration) {
+			break;
+		}
+	}
+
+	Ok(Some(decoded))
+}
+
+// Keeps as many of the images as fit in the duration at the frame rate.
+// With more files than max_frames, only every Nth is decoded.
+fn decode_sequence(
+	input: &str,
+	files: &[PathBuf],
+	options: &Options,
+) -> Result<TimedFrames, Box<dyn std::error::Error>> {
+	let count = files.len().min(
+		options
+			.fps
+			.saturating_mul(options.duration)
+			.try_into()
+			.unwrap_or(usize::MAX),
+	);
+	let step = match options.max_frames {
+		Some(max_frames) if count as u64 > max_frames => count.div_ceil(max_frames as usize),
+		_ => 1,
+	};
+	let frame_delay = Duration::from_secs_f64(step as f64 / options.fps as f64);
+	files[..count]
+		.iter()
+		.step_by(step)
+		.enumerate()
+		.map(|(index, file)| {
+			check_duration(input, frame_delay * index as u32, options)?;
+			let frame = image::open(file).map_err(|error| format!("Failed to decode {}: {}", file.display(), error))?;
+			Ok((frame, frame_delay))
+		})
+		.collect()
+}
+
+// Keeps every Nth frame, N as small as gets them down to max_frames, each shown for as long as the
+// ones dropped after it were too.
+fn subsample(frames: TimedFrames, max_frames: Option<u64>) -> TimedFrames {
+	let step = match max_frames {
+		Some(max_frames) if frames.len() as u64 > max_frames => frames.len().div_ceil(max_frames as usize),
+		_ => return frames,
+	};
+	let mut kept: TimedFrames = Vec::with_capacity(frames.len().div_ceil(step));
+	for (index, (frame, delay)) in frames.into_iter().enumerate() {
+		match kept.last_mut() {
+			Some((_, kept_delay)) if index % step != 0 => *kept_delay += delay,
+			_ => kept.push((frame, delay)),
+		}
+	}
+	kept
+}
+
+// Fails as soon as a frame would start past max_duration, before the rest of the input is decoded.
+fn check_duration(input: &str, start: Duration, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+	match options.max_duration {
+		Some(max_duration) if start >= Duration::from_secs(max_duration) => Err(format!(
+			"{} runs longer than max_duration = {} s; lower duration to embed only its start, or raise max_duration",
+			input, max_duration
+		)
+		.into()),
+		_ => Ok(()),
+	}
+}
+
+// The part of the cache key for max_frames and max_duration, empty without them so that entries
+// from before stay valid.
+fn limits(options: &Options) -> String {
+	let mut limits = String::new();
+	if let Some(max_frames) = options.max_frames {
+		limits += &format!(" max_frames={}", max_frames);
+	}
+	if let Some(max_duration) = options.max_duration {
+		limits += &format!(" max_duration={}", max_duration);
+	}
+	limits
+}
+
+fn open_file(input: &str) -> Result<BufReader<File>, Box<dyn std::error::Error>> {
+	Ok(BufReader::new(
+		File::open(input).map_err(|error| format!("Failed to open {}: {}", input, error))?,
+	))
+}
+
+// Reads the next of ffmpeg's PPM frames, or None once its output ends. Each frame has its size in
+// its header, so videos come out the shape ffmpeg made them after rotating and scaling.
+fn read_ppm(input: &mut impl BufRead) -> Result<Option<RgbImage>, Box<dyn std::error::Error>> {
+	if input.fill_buf()?.is_empty() {
+		return Ok(None);
+	}
+	let mut magic = [0; 2];
+	input.read_exact(&mut magic)?;
+	if &magic != b"P6" {
+		return Err("ffmpeg wrote a frame that isn't a PPM image".into());
+	}
+	let width = read_ppm_number(input)?;
+	let height = read_ppm_number(input)?;
+	// The maximum value, which is always 255 for rgb24.
+	read_ppm_number(input)?;
+	let mut pixels = vec![0; width as usize * height as usize * 3];
+	input.read_exact(&mut pixels)?;
+	Ok(Some(
+		RgbImage::from_raw(width, height, pixels).ok_or("Failed to create image from buffer")?,
+	))
+}
+
+// A number in a PPM header along with the whitespace around it, which after the last one is the
+// single byte separating the header from the pixels.
+fn read_ppm_number(input: &mut impl Read) -> Result<u32, Box<dyn std::error::Error>> {
+	let mut number = String::new();
+	for byte in input.bytes() {
+		match byte? {
+			digit if digit.is_ascii_digit() => number.push(digit as char),
+			space if space.is_ascii_whitespace() && number.is_empty() => {},
+			space if space.is_ascii_whitespace() => break,
+			_ => return Err("ffmpeg wrote a malformed PPM header".into()),
+		}
+	}
+	Ok(number.parse()?)
+}
+
+// Returns what the program wrote to stdout, or what it wrote to stderr when it failed.
+fn run(program: &'static str, args: &[&str]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+	let output = Command::new(program)
+		.args(args)
+		.output()
+		.map_err(|error| spawn_error(program, error))?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(format!("{} failed ({}): {}", program, output.status, stderr.trim()).into());
+	}
+	Ok(output.stdout)
+}
+
+// ffmpeg or ffprobe not being installed, which the placeholder fallback gets past.
+#[derive(Debug)]
+pub struct Missing(&'static str);
+
+impl fmt::Display for Missing {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} was not found in PATH; only GIF, PNG and WebP inputs work without it",
+			self.0
+		)
+	}
+}
+
+impl std::error::Error for Missing {}
+
+fn spawn_error(program: &'static str, error: std::io::Error) -> Box<dyn std::error::Error> {
+	match error.kind() {
+		ErrorKind::NotFound => Missing(program).into(),
+		_ => format!("Failed to run {}: {}", program, error).into(),
+	}
+}
+
+fn frame_to_ascii(frame: &DynamicImage, width: u32, color: Color, shading: &Shading) -> String {
+	color.paint(&shading.convert(frame, width), frame)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::Rgb;
+	use std::env;
+	use std::fs;
+
+	fn temporary(name: &str) -> PathBuf {
+		let dir = env::temp_dir()
+			.join(format!("bad_ascii_convert_test_{}", std::process::id()))
+			.join(name);
+		fs::create_dir_all(&dir).expect("the directory can be made");
+		dir
+	}
+
+	#[test]
+	fn patterns_are_told_from_paths() {
+		assert!(is_pattern("frames/*.png"));
+		assert!(is_pattern("frames/frame_?.png"));
+		assert!(is_pattern("frames/[ab].png"));
+		assert!(!is_pattern("frames/clip.mp4"));
+	}
+
+	#[test]
+	fn sequences_are_decoded_in_name_order_at_the_frame_rate() {
+		let dir = temporary("sequence");
+		for (name, value) in [("2.png", 200), ("1.png", 100), ("3.png", 255)] {
+			RgbImage::from_pixel(2, 2, Rgb([value; 3]))
+				.save(dir.join(name))
+				.expect("the frame can be written");
+		}
+		let pattern = dir.join("*.png");
+		let pattern = pattern.to_str().expect("the path is UTF-8");
+		let files = input_files(pattern).expect("the pattern matches");
+		assert_eq!(files, [dir.join("1.png"), dir.join("2.png"), dir.join("3.png")]);
+
+		let options = Options {
+			fps: 2,
+			duration: 1,
+			..Options::default()
+		};
+		let frames = decode_sequence(pattern, &files, &options).expect("the frames decode");
+		let values: Vec<u8> = frames
+			.iter()
+			.map(|(frame, _)| frame.to_rgb8().get_pixel(0, 0).0[0])
+			.collect();
+		assert_eq!(values, [100, 200]);
+		assert!(frames.iter().all(|&(_, delay)| delay == Duration::from_millis(500)));
+	}
+
+	#[test]
+	fn ppm_frames_are_read_one_at_a_time() {
+		let mut output = Vec::new();
+		output.extend_from_slice(b"P6\n2 1\n255\n");
+		output.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+		output.extend_from_slice(b"P6 1 1 255\n");
+		output.extend_from_slice(&[7, 8, 9]);
+		let mut input = &output[..];
+
+		let first = read_ppm(&mut input)
+			.expect("the frame is read")
+			.expect("there is a frame");
+		assert_eq!(first.dimensions(), (2, 1));
+		assert_eq!(first.get_pixel(1, 0), &Rgb([0, 0, 255]));
+		let second = read_ppm(&mut input)
+			.expect("the frame is read")
+			.expect("there is a frame");
+		assert_eq!(second.get_pixel(0, 0), &Rgb([7, 8, 9]));
+		assert!(read_ppm(&mut input).expect("the end is read").is_none());
+	}
+
+	#[test]
+	fn broken_ppm_frames_fail() {
+		assert!(read_ppm(&mut &b"P5\n1 1\n255\n\0"[..]).is_err());
+		assert!(read_ppm(&mut &b"P6\n1 x\n255\n\0\0\0"[..]).is_err());
+		assert!(read_ppm(&mut &b"P6\n2 2\n255\n\0\0\0"[..]).is_err());
+	}
+
+	#[test]
+	fn subsampled_frames_keep_the_time_of_those_dropped() {
+		let frames: TimedFrames = (0..5)
+			.map(|value| {
+				let frame = DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, Rgb([value; 3])));
+				(frame, Duration::from_millis(100))
+			})
+			.collect();
+		assert_eq!(subsample(frames.clone(), Some(5)).len(), 5);
+
+		let kept = subsample(frames, Some(2));
+		let values: Vec<u8> = kept
+			.iter()
+			.map(|(frame, _)| frame.to_rgb8().get_pixel(0, 0).0[0])
+			.collect();
+		assert_eq!(values, [0, 3]);
+		let delays: Vec<Duration> = kept.iter().map(|&(_, delay)| delay).collect();
+		assert_eq!(delays, [Duration::from_millis(300), Duration::from_millis(200)]);
+	}
+
+	#[test]
+	fn frames_starting_past_max_duration_fail() {
+		let options = Options {
+			max_duration: Some(2),
+			..Options::default()
+		};
+		assert!(check_duration("clip.mp4", Duration::from_millis(1999), &options).is_ok());
+		assert!(check_duration("clip.mp4", Duration::from_secs(2), &options).is_err());
+		assert!(check_duration("clip.mp4", Duration::from_secs(60), &Options::default()).is_ok());
+	}
+
+	#[test]
+	fn limits_only_change_the_cache_key_when_set() {
+		assert_eq!(limits(&Options::default()), "");
+		let options = Options {
+			max_frames: Some(5),
+			max_duration: Some(3),
+			..Options::default()
+		};
+		assert_eq!(limits(&options), " max_frames=5 max_duration=3");
+	}
+
+	#[test]
+	fn patterns_matching_nothing_fail() {
+		let pattern = temporary("empty").join("*.png");
+		assert!(input_files(pattern.to_str().expect("the path is UTF-8")).is_err());
+	}
+}
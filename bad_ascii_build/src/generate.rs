@@ -0,0 +1,517 @@
+use crate::convert::{AUDIO_CHANNELS, AUDIO_SAMPLE_RATE, Converted};
+use crate::{Options, visible_width};
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{ToTokens, quote};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::time::Duration;
+
+// What report = true tells about the embedded frames.
+pub struct Stats {
+	frame_count: usize,
+	// Each width and how many distinct frames it has.
+	unique: Vec<(u32, usize)>,
+	text_bytes: usize,
+	embedded: usize,
+}
+
+impl fmt::Display for Stats {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} frames, ", self.frame_count)?;
+		for (index, (width, unique)) in self.unique.iter().enumerate() {
+			if index > 0 {
+				write!(f, ", ")?;
+			}
+			write!(f, "{} unique at width {}", unique, width)?;
+		}
+		// Besides what's embedded, a 64-bit target has a (pointer, length) pair per frame in each
+		// width's ASCII_FRAMES, and a Duration per frame in FRAME_DELAYS.
+		let binary = self.embedded + 16 * self.frame_count * (self.unique.len() + 1);
+		write!(
+			f,
+			"; {} bytes of text, {} embedded with tables and audio, about {} added to a 64-bit binary",
+			self.text_bytes, self.embedded, binary
+		)
+	}
+}
+
+// The items process! puts in its module, and that build scripts write out for include!. Fails when
+// the frames and audio would take more than max_bytes.
+pub fn items(
+	options: &Options,
+	(sizes, frame_delays): Converted,
+	audio: Option<&[u8]>,
+) -> Result<(TokenStream, Stats), String> {
+	let frame_count = frame_delays.len();
+	let text_bytes: usize = sizes.iter().flatten().map(String::len).sum();
+	let frame_delay_array = frame_delays.iter().map(|delay| {
+		let nanos = delay.as_nanos() as u64;
+		quote! { Duration::from_nanos(#nanos) }
+	});
+	let duration_ms = frame_delays.iter().sum::<Duration>().as_millis() as u64;
+	let fps = match duration_ms {
+		0 => 0.0,
+		_ => frame_count as f64 * 1000.0 / duration_ms as f64,
+	};
+
+	// Each size's frames and META, with how wide it came out and how to reach its frames from the
+	// top.
+	let mut embedded = 0;
+	let unique = options
+		.widths
+		.iter()
+		.zip(&sizes)
+		.map(|(&width, ascii_frames)| (width, ascii_frames.iter().collect::<HashSet<_>>().len()))
+		.collect();
+	let mut sizes: Vec<(usize, TokenStream, TokenStream)> = options
+		.widths
+		.iter()
+		.zip(&sizes)
+		.map(|(width, ascii_frames)| {
+			let lines = ascii_frames.iter().flat_map(|frame| frame.lines());
+			let columns = lines.map(visible_width).max().unwrap_or(0);
+			let rows = ascii_frames
+				.iter()
+				.map(|frame| frame.lines().count())
+				.max()
+				.unwrap_or(0);
+			let (frames, bytes) = frames(ascii_frames, options.compress, options.intern_lines);
+			embedded += bytes;
+			let items = quote! {
+				pub const META: AsciiMeta = AsciiMeta {
+					fps: #fps,
+					frame_count: FRAME_COUNT,
+					cols: #columns,
+					rows: #rows,
+					duration_ms: #duration_ms,
+				};
+
+				#frames
+			};
+			let module = if options.widths.len() == 1 {
+				quote! { self }
+			} else {
+				Ident::new(&format!("width_{}", width), Span::call_site()).into_token_stream()
+			};
+			(columns, items, module)
+		})
+		.collect();
+
+	let audio = audio.map(|audio| {
+		embedded += audio.len();
+		let audio = Literal::byte_string(audio);
+		quote! {
+			// 16-bit little endian samples, interleaved, covering the same time as the frames.
+			pub static AUDIO: &[u8] = #audio;
+			pub const AUDIO_SAMPLE_RATE: u32 = #AUDIO_SAMPLE_RATE;
+			pub const AUDIO_CHANNELS: u16 = #AUDIO_CHANNELS;
+		}
+	});
+
+	if let Some(max_bytes) = options.max_bytes {
+		if embedded as u64 > max_bytes {
+			return Err(format!(
+				"The embedded frames and audio take {} bytes, over max_bytes = {}",
+				embedded, max_bytes
+			));
+		}
+	}
+
+	// Without compress and intern_lines the frames are sliced out of the text in const fns, which
+	// const items can use too.
+	let constant = !options.compress && !options.intern_lines;
+	let sized = if sizes.len() == 1 {
+		sizes[0].1.clone()
+	} else {
+		let modules = sizes.iter().map(|(_, items, module)| {
+			quote! {
+				pub mod #module {
+					use super::{AsciiMeta, FRAME_COUNT};
+
+					#items
+				}
+			}
+		});
+		let default = &sizes[0].2;
+		let frame = if constant {
+			quote! { , frame }
+		} else {
+			quote! {}
+		};
+		quote! {
+			#(#modules)*
+			pub use self::#default::{ASCII_FRAMES, META #frame};
+		}
+	};
+
+	// Widest first, so that the first one that fits is picked.
+	sizes.sort_by_key(|(columns, ..)| std::cmp::Reverse(*columns));
+	let deref = if options.compress || options.intern_lines {
+		quote! { * }
+	} else {
+		quote! {}
+	};
+	let (_, _, narrowest) = sizes.pop().expect("there is at least one size");
+	let wider = sizes.iter().map(|(columns, _, module)| {
+		quote! {
+			if cols >= #columns {
+				return &#deref #module::ASCII_FRAMES;
+			}
+		}
+	});
+	let wider_meta = sizes.iter().map(|(columns, _, module)| {
+		quote! {
+			if cols >= #columns {
+				return #module::META;
+			}
+		}
+	});
+	let wider_frame = sizes.iter().map(|(columns, _, module)| {
+		quote! {
+			if cols >= #columns {
+				return #module::frame(index);
+			}
+		}
+	});
+	let frame_for_width = if constant {
+		quote! {
+			pub const fn frame_for_width(cols: usize, index: usize) -> &'static str {
+				#(#wider_frame)*
+				#narrowest::frame(index)
+			}
+		}
+	} else {
+		quote! {}
+	};
+	let select = quote! {
+		pub fn frames_for_width(cols: usize) -> &'static [&'static str; FRAME_COUNT] {
+			#(#wider)*
+			&#deref #narrowest::ASCII_FRAMES
+		}
+
+		pub const fn meta_for_width(cols: usize) -> AsciiMeta {
+			#(#wider_meta)*
+			#narrowest::META
+		}
+
+		#frame_for_width
+	};
+
+	let player = if options.player {
+		player()
+	} else {
+		quote! {}
+	};
+
+	let stats = Stats {
+		frame_count,
+		unique,
+		text_bytes,
+		embedded,
+	};
+	let items = quote! {
+		use ::core::time::Duration;
+
+		const FRAME_COUNT: usize = #frame_count;
+
+		pub const TEXT_BYTES: usize = #text_bytes;
+
+		// What's embedded, for playing it back: the average frame rate, the largest frame's size in
+		// characters and how long all frames take.
+		#[derive(Clone, Copy, Debug)]
+		pub struct AsciiMeta {
+			pub fps: f64,
+			pub frame_count: usize,
+			pub cols: usize,
+			pub rows: usize,
+			pub duration_ms: u64,
+		}
+
+		#sized
+		#select
+		pub static FRAME_DELAYS: [Duration; FRAME_COUNT] = [#(#frame_delay_array),*];
+
+		#audio
+		#player
+	};
+	Ok((items, stats))
+}
+
+// The distinct pieces the frames are made of, put together in one text, and each frame as runs of
+// those pieces: whole frames, or lines when interning lines.
+struct Interned {
+	text: String,
+	// Where each piece starts in the text, and where the last one ends.
+	bounds: Vec<usize>,
+	frames: Vec<Vec<(usize, usize)>>,
+}
+
+fn intern(ascii_frames: &[String], lines: bool) -> Interned {
+	let mut interned = Interned {
+		text: String::new(),
+		bounds: vec![0],
+		frames: Vec::new(),
+	};
+	let mut known = HashMap::new();
+	for frame in ascii_frames {
+		let pieces: Vec<&str> = if lines {
+			frame.split('\n').collect()
+		} else {
+			vec![frame]
+		};
+		let mut runs: Vec<(usize, usize)> = Vec::new();
+		for piece in pieces {
+			let index = *known.entry(piece).or_insert_with(|| {
+				interned.text.push_str(piece);
+				interned.bounds.push(interned.text.len());
+				interned.bounds.len() - 2
+			});
+			match runs.last_mut() {
+				Some((last, repeat)) if *last == index => *repeat += 1,
+				_ => runs.push((index, 1)),
+			}
+		}
+		interned.frames.push(runs);
+	}
+	interned
+}
+
+// Identical frames are always embedded once. Without compress and intern_lines, the frames are
+// sliced out of one string literal at compile time, at the bounds of the pieces; otherwise they
+// are put together on first use. Also returns how many bytes of text and tables that embeds.
+fn frames(ascii_frames: &[String], compress: bool, intern_lines: bool) -> (TokenStream, usize) {
+	let interned = intern(ascii_frames, intern_lines);
+	let mut embedded = interned.text.len();
+	let (text, load_text) = if compress {
+		let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+		encoder
+			.write_all(interned.text.as_bytes())
+			.and_then(|()| encoder.try_finish())
+			.expect("Failed to compress frames");
+		embedded = encoder.get_ref().len();
+		let compressed = Literal::byte_string(encoder.get_ref());
+		let text = quote! { const COMPRESSED: &[u8] = #compressed; };
+		let load_text = quote! {
+			use std::io::Read;
+
+			let mut text = String::new();
+			::flate2::read::DeflateDecoder::new(COMPRESSED)
+				.read_to_string(&mut text)
+				.expect("embedded frames are valid deflate data");
+			let text: &'static str = Box::leak(text.into_boxed_str());
+		};
+		(text, load_text)
+	} else {
+		let text = &interned.text;
+		(quote! { const TEXT: &str = #text; }, quote! { let text = TEXT; })
+	};
+	let bounds = interned.bounds.iter().map(|&bound| offset(bound));
+	let bound_count = interned.bounds.len();
+
+	if !intern_lines {
+		let frame_pieces = interned.frames.iter().map(|runs| offset(runs[0].0));
+		let frames = if compress {
+			quote! {
+				pub static ASCII_FRAMES: std::sync::LazyLock<[&'static str; FRAME_COUNT]> = std::sync::LazyLock::new(|| {
+					#load_text
+					::core::array::from_fn(|i| {
+						let piece = FRAME_PIECES[i] as usize;
+						&text[BOUNDS[piece] as usize..BOUNDS[piece + 1] as usize]
+					})
+				});
+			}
+		} else {
+			quote! {
+				// The same as ASCII_FRAMES[index], for const items, which can't read statics on every
+				// compiler.
+				pub const fn frame(index: usize) -> &'static str {
+					let piece = FRAME_PIECES[index] as usize;
+					let (start, end) = (BOUNDS[piece] as usize, BOUNDS[piece + 1] as usize);
+					TEXT.split_at(end).0.split_at(start).1
+				}
+
+				const fn create_frames() -> [&'static str; FRAME_COUNT] {
+					let mut frames: [&str; FRAME_COUNT] = [""; FRAME_COUNT];
+					let mut i = 0;
+					while i < FRAME_COUNT {
+						frames[i] = frame(i);
+						i += 1;
+					}
+					frames
+				}
+
+				pub static ASCII_FRAMES: [&'static str; FRAME_COUNT] = create_frames();
+			}
+		};
+		embedded += 4 * (bound_count + ascii_frames.len());
+		let frames = quote! {
+			#text
+			const BOUNDS: [u32; #bound_count] = [#(#bounds),*];
+			const FRAME_PIECES: [u32; FRAME_COUNT] = [#(#frame_pieces),*];
+			#frames
+		};
+		return (frames, embedded);
+	}
+
+	let runs = interned.frames.iter().flatten().map(|&(line, repeat)| {
+		let (line, repeat) = (offset(line), offset(repeat));
+		quote! { (#line, #repeat) }
+	});
+	let run_count: usize = interned.frames.iter().map(Vec::len).sum();
+	let frame_runs = std::iter::once(0)
+		.chain(interned.frames.iter().scan(0, |end, runs| {
+			*end += runs.len();
+			Some(*end)
+		}))
+		.map(offset);
+	embedded += 4 * bound_count + 8 * run_count + 4 * (ascii_frames.len() + 1);
+	let frames = quote! {
+		#text
+		const BOUNDS: [u32; #bound_count] = [#(#bounds),*];
+		// Each frame's lines as runs of a line repeated a number of times, and where each frame's
+		// runs start.
+		const RUNS: [(u32, u32); #run_count] = [#(#runs),*];
+		const FRAME_RUNS: [u32; FRAME_COUNT + 1] = [#(#frame_runs),*];
+
+		pub static ASCII_FRAMES: std::sync::LazyLock<[&'static str; FRAME_COUNT]> = std::sync::LazyLock::new(|| {
+			#load_text
+			::core::array::from_fn(|i| {
+				let mut lines = Vec::new();
+				for &(line, repeat) in &RUNS[FRAME_RUNS[i] as usize..FRAME_RUNS[i + 1] as usize] {
+					let line = &text[BOUNDS[line as usize] as usize..BOUNDS[line as usize + 1] as usize];
+					lines.extend(std::iter::repeat_n(line, repeat as usize));
+				}
+				let frame: &'static str = Box::leak(lines.join("\n").into_boxed_str());
+				frame
+			})
+		});
+	};
+	(frames, embedded)
+}
+
+// Offsets and counts are embedded as u32, a quarter of a (usize, usize) pair per piece.
+fn offset(value: usize) -> Literal {
+	Literal::u32_unsuffixed(u32::try_from(value).expect("embedded frames are under 4 GiB"))
+}
+
+fn player() -> TokenStream {
+	quote! {
+		pub fn play() -> std::io::Result<()> {
+			use ::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+			use ::crossterm::{cursor, execute, queue, style, terminal};
+			use std::io::Write;
+
+			let mut out = std::io::stdout();
+			terminal::enable_raw_mode()?;
+			execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+			let mut show = || -> std::io::Result<()> {
+				let mut laid_out = None;
+				for (index, delay) in FRAME_DELAYS.iter().enumerate() {
+					// Picked again for every frame, so that resizing the terminal switches sizes.
+					let (columns, rows) = terminal::size()?;
+					let meta = meta_for_width(columns as usize);
+					let top = rows.saturating_sub(meta.rows as u16) / 2;
+					let left = columns.saturating_sub(meta.cols as u16) / 2;
+					// The screen is only cleared when the size or position changes. Otherwise every line
+					// is drawn over the last frame's, clearing what's left of it to its right and below.
+					if laid_out != Some((columns, rows)) {
+						queue!(out, terminal::Clear(terminal::ClearType::All))?;
+						laid_out = Some((columns, rows));
+					}
+					let mut lines = frames_for_width(columns as usize)[index].lines();
+					for row in 0..meta.rows {
+						queue!(
+							out,
+							cursor::MoveTo(left, top + row as u16),
+							style::Print(lines.next().unwrap_or("")),
+							terminal::Clear(terminal::ClearType::UntilNewLine)
+						)?;
+					}
+					out.flush()?;
+
+					let shown = std::time::Instant::now();
+					while let Some(remaining) = delay.checked_sub(shown.elapsed()) {
+						if !event::poll(remaining)? {
+							break;
+						}
+						if let Event::Key(key) = event::read()? {
+							let ctrl_c = key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+							if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c) {
+								return Ok(());
+							}
+						}
+					}
+				}
+				Ok(())
+			};
+			let shown = show();
+			execute!(out, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
+			terminal::disable_raw_mode()?;
+			shown
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn strings(frames: &[&str]) -> Vec<String> {
+		frames.iter().map(|frame| frame.to_string()).collect()
+	}
+
+	#[test]
+	fn identical_frames_are_embedded_once() {
+		let interned = intern(&strings(&["a\nb", "a\nb", "a\na"]), false);
+		assert_eq!(interned.text, "a\nba\na");
+		assert_eq!(interned.bounds, [0, 3, 6]);
+		assert_eq!(interned.frames, [vec![(0, 1)], vec![(0, 1)], vec![(1, 1)]]);
+	}
+
+	#[test]
+	fn interned_lines_are_runs_of_repeats() {
+		let interned = intern(&strings(&["a\nb", "a\nb", "a\na"]), true);
+		assert_eq!(interned.text, "ab");
+		assert_eq!(interned.bounds, [0, 1, 2]);
+		assert_eq!(
+			interned.frames,
+			[vec![(0, 1), (1, 1)], vec![(0, 1), (1, 1)], vec![(0, 2)]]
+		);
+	}
+
+	#[test]
+	fn the_report_counts_unique_frames() {
+		let options = Options::default();
+		let converted = (
+			vec![strings(&["ab\n", "ab\n", "cd\n"])],
+			vec![Duration::from_millis(100); 3],
+		);
+		let (_, stats) = items(&options, converted, None).expect("the frames fit");
+		assert_eq!(stats.frame_count, 3);
+		assert_eq!(stats.unique, [(options.widths[0], 2)]);
+		assert_eq!(stats.text_bytes, 9);
+		assert!(
+			stats
+				.to_string()
+				.starts_with("3 frames, 2 unique at width 160; 9 bytes of text")
+		);
+	}
+
+	#[test]
+	fn frames_over_max_bytes_are_refused() {
+		let converted = || (vec![strings(&["abcd\n"; 4])], vec![Duration::from_millis(100); 4]);
+		let options = Options {
+			max_bytes: Some(16),
+			..Options::default()
+		};
+		assert!(items(&options, converted(), None).is_err());
+		let options = Options {
+			max_bytes: Some(64),
+			..Options::default()
+		};
+		assert!(items(&options, converted(), None).is_ok());
+	}
+}
@@ -0,0 +1,362 @@
+mod baa;
+mod cache;
+mod color;
+mod convert;
+mod generate;
+mod shade;
+mod trim;
+
+pub use self::color::Color;
+pub use self::shade::{Dither, Shading};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const DEFAULT_FPS: u64 = 24;
+pub const DEFAULT_DURATION: u64 = 10;
+pub const DEFAULT_WIDTH: u32 = 160;
+
+// What convert writes: Rust for `include!`, with the same items process! generates, or a .baa file
+// for `include_bytes!` that bad_ascii plays.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Output {
+	#[default]
+	Rust,
+	Baa,
+}
+
+// What to do when ffmpeg or ffprobe aren't installed, as on docs.rs or some CI builders: fail, or
+// embed a small placeholder animation and warn.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Fallback {
+	#[default]
+	Fail,
+	Placeholder,
+}
+
+impl Fallback {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"fail" => Some(Fallback::Fail),
+			"placeholder" => Some(Fallback::Placeholder),
+			_ => None,
+		}
+	}
+}
+
+// The settings process! takes, see there. A .baa file only holds the first width, and compress,
+// intern_lines, player and audio only apply to Rust. With the placeholder fallback, audio is
+// embedded empty.
+pub struct Options {
+	pub fps: u64,
+	pub duration: u64,
+	// The first is the default.
+	pub widths: Vec<u32>,
+	pub color: Color,
+	pub shading: Shading,
+	pub compress: bool,
+	pub intern_lines: bool,
+	pub player: bool,
+	pub audio: bool,
+	// Drops trailing spaces and the blank border around the frames.
+	pub trim: bool,
+	// In bytes, of the embedded text and tables or of the .baa file.
+	pub max_bytes: Option<u64>,
+	// More frames than this are subsampled down to it.
+	pub max_frames: Option<u64>,
+	// In seconds, an input running longer than this fails.
+	pub max_duration: Option<u64>,
+	// Tells what was embedded and how large it is, as a warning.
+	pub report: bool,
+	pub fallback: Fallback,
+	pub output: Output,
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Self {
+			fps: DEFAULT_FPS,
+			duration: DEFAULT_DURATION,
+			widths: vec![DEFAULT_WIDTH],
+			color: Color::Plain,
+			shading: Shading::default(),
+			compress: false,
+			intern_lines: false,
+			player: false,
+			audio: false,
+			trim: false,
+			max_bytes: None,
+			max_frames: None,
+			max_duration: None,
+			report: false,
+			fallback: Fallback::Fail,
+			output: Output::Rust,
+		}
+	}
+}
+
+// For build scripts: converts the input into OUT_DIR, named after it, and returns where it went.
+// Cargo is told to run the script again only when the input changes, and unchanged inputs are
+// served from the same cache as process! uses.
+//
+//     // build.rs
+//     bad_ascii_build::convert("clip.mp4", &Options { widths: vec![120], ..Options::default() })?;
+//     // src/main.rs
+//     #[allow(dead_code)]
+//     mod clip {
+//         include!(concat!(env!("OUT_DIR"), "/clip.rs"));
+//     }
+//
+// Relative paths are taken from the package's manifest directory, and patterns like
+// "frames/*.png" are image sequences named after their directory.
+pub fn convert(input: &str, options: &Options) -> Result<PathBuf, Box<dyn std::error::Error>> {
+	check(options)?;
+	let out_dir = env::var_os("OUT_DIR").ok_or("OUT_DIR is not set, convert is meant to run in a build script")?;
+	let mut input = PathBuf::from(input);
+	if input.is_relative() {
+		if let Some(manifest_dir) = env::var_os("CARGO_MANIFEST_DIR") {
+			input = PathBuf::from(manifest_dir).join(input);
+		}
+	}
+	let input = input.to_str().ok_or("The input path isn't valid UTF-8")?;
+
+	// The part of the path before any pattern, whose directory picks up added and removed files.
+	let fixed = Path::new(input)
+		.ancestors()
+		.find(|ancestor| !convert::is_pattern(&ancestor.to_string_lossy()))
+		.unwrap_or(Path::new(input));
+	println!("cargo:rerun-if-changed={}", fixed.display());
+	for file in convert::input_files(input)? {
+		println!("cargo:rerun-if-changed={}", file.display());
+	}
+
+	let content = content(input, options)?;
+	if let Some(warning) = &content.warning {
+		println!("cargo:warning={}", warning);
+	}
+	let (sizes, delays) = content.converted;
+	let name = fixed.file_stem().ok_or("The input path has no file name")?;
+	let path = match options.output {
+		Output::Rust => {
+			let path = Path::new(&out_dir).join(name).with_extension("rs");
+			let (items, stats) = generate::items(options, (sizes, delays), content.audio.as_deref())?;
+			fs::write(&path, items.to_string())?;
+			if options.report {
+				println!("cargo:warning={}: {}", input, stats);
+			}
+			path
+		},
+		Output::Baa => {
+			let path = Path::new(&out_dir).join(name).with_extension("baa");
+			baa::write(&sizes[0], &delays, options.color != Color::Plain, File::create(&path)?)?;
+			let bytes = fs::metadata(&path)?.len();
+			if options.max_bytes.is_some_and(|max_bytes| bytes > max_bytes) {
+				fs::remove_file(&path)?;
+				return Err(format!(
+					"The .baa file takes {} bytes, over max_bytes = {}",
+					bytes,
+					options.max_bytes.unwrap_or_default()
+				)
+				.into());
+			}
+			if options.report {
+				println!(
+					"cargo:warning={}: {} frames, {} bytes of .baa file",
+					input,
+					delays.len(),
+					bytes
+				);
+			}
+			path
+		},
+	};
+	Ok(path)
+}
+
+// For process!, which resolves the path and reports errors itself. Warnings and the report are
+// turned into uses of a deprecated constant, the one way a proc macro has to warn on stable.
+pub fn generate(input: &str, options: &Options) -> Result<TokenStream, Box<dyn std::error::Error>> {
+	check(options)?;
+	let content = content(input, options)?;
+	let (mut items, stats) = generate::items(options, content.converted, content.audio.as_deref())?;
+	if let Some(warning) = content.warning {
+		items.extend(warn("PLACEHOLDER", &warning));
+	}
+	if options.report {
+		items.extend(warn("REPORT", &format!("{}: {}", input, stats)));
+	}
+	Ok(items)
+}
+
+fn warn(name: &str, note: &str) -> TokenStream {
+	let name = Ident::new(name, Span::call_site());
+	quote! {
+		#[deprecated(note = #note)]
+		const #name: () = ();
+		const _: () = #name;
+	}
+}
+
+// What's embedded, and a warning when it's the placeholder.
+struct Content {
+	converted: convert::Converted,
+	audio: Option<Vec<u8>>,
+	warning: Option<String>,
+}
+
+fn content(input: &str, options: &Options) -> Result<Content, Box<dyn std::error::Error>> {
+	let converted = convert::cached_frames(input, options).and_then(|converted| {
+		let audio = options
+			.audio
+			.then(|| convert::cached_audio(input, options))
+			.transpose()?;
+		Ok((converted, audio))
+	});
+	let mut content = match converted {
+		Ok((converted, audio)) => Content {
+			converted,
+			audio,
+			warning: None,
+		},
+		Err(error) if options.fallback == Fallback::Placeholder && error.is::<convert::Missing>() => Content {
+			converted: placeholder(options.widths.len()),
+			audio: options.audio.then(Vec::new),
+			warning: Some(format!("{}, so {} is embedded as a placeholder", error, input)),
+		},
+		Err(error) => return Err(error),
+	};
+	if options.trim {
+		for frames in &mut content.converted.0 {
+			trim::trim(frames);
+		}
+	}
+	Ok(content)
+}
+
+// A spinner saying what's missing, the same at every width.
+fn placeholder(widths: usize) -> convert::Converted {
+	let frames: Vec<String> = ['|', '/', '-', '\\']
+		.iter()
+		.map(|spinner| format!("{} video unavailable, ffmpeg was not found\n", spinner))
+		.collect();
+	(vec![frames; widths], vec![Duration::from_millis(250); 4])
+}
+
+fn check(options: &Options) -> Result<(), String> {
+	if options.fps == 0 || options.duration == 0 {
+		return Err("fps and duration have to be above 0".into());
+	}
+	if options.max_frames == Some(0) || options.max_duration == Some(0) {
+		return Err("max_frames and max_duration have to be above 0".into());
+	}
+	if options.widths.is_empty() || options.widths.contains(&0) {
+		return Err("There has to be at least one width, and every width above 0".into());
+	}
+	if let Some(width) = options
+		.widths
+		.iter()
+		.enumerate()
+		.find_map(|(index, width)| options.widths[..index].contains(width).then_some(width))
+	{
+		return Err(format!("{} is among the widths more than once", width));
+	}
+	if !(options.shading.gamma > 0.0 && options.shading.gamma.is_finite()) {
+		return Err("The gamma has to be above 0".into());
+	}
+	if options.shading.charset.as_ref().is_some_and(String::is_empty) {
+		return Err("The charset has to have at least one character".into());
+	}
+	Ok(())
+}
+
+// Characters in the line as a terminal shows it, leaving out color escapes.
+fn visible_width(line: &str) -> usize {
+	let mut width = 0;
+	let mut escaped = false;
+	for character in line.chars() {
+		match character {
+			'\u{1b}' => escaped = true,
+			'm' if escaped => escaped = false,
+			_ if escaped => {},
+			_ => width += 1,
+		}
+	}
+	width
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn the_defaults_pass_the_checks() {
+		assert!(check(&Options::default()).is_ok());
+		let options = Options {
+			widths: vec![80, 120, 40],
+			..Options::default()
+		};
+		assert!(check(&options).is_ok());
+	}
+
+	#[test]
+	fn impossible_options_are_refused() {
+		let refused = |options: Options| check(&options).is_err();
+		assert!(refused(Options {
+			fps: 0,
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			duration: 0,
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			max_frames: Some(0),
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			widths: Vec::new(),
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			widths: vec![80, 0],
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			widths: vec![80, 120, 80],
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			shading: Shading {
+				gamma: f64::NAN,
+				..Shading::default()
+			},
+			..Options::default()
+		}));
+		assert!(refused(Options {
+			shading: Shading {
+				charset: Some(String::new()),
+				..Shading::default()
+			},
+			..Options::default()
+		}));
+	}
+
+	#[test]
+	fn width_leaves_out_color_escapes() {
+		assert_eq!(visible_width("\u{1b}[38;2;255;0;0mab\u{1b}[0mc"), 3);
+		assert_eq!(visible_width("é ü"), 3);
+		assert_eq!(visible_width(""), 0);
+	}
+
+	#[test]
+	fn the_placeholder_has_every_width() {
+		let (sizes, delays) = placeholder(3);
+		assert_eq!(sizes.len(), 3);
+		assert!(sizes.iter().all(|frames| frames.len() == delays.len()));
+		assert!(Fallback::from_name("placeholder") == Some(Fallback::Placeholder));
+		assert!(Fallback::from_name("skip").is_none());
+	}
+}
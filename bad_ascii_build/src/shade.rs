@@ -0,0 +1,227 @@
+use artem::config::{ConfigBuilder, ResizingDimension};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba};
+use std::num::NonZeroU32;
+
+// artem's default, how much wider than high a character cell is.
+const CELL_SCALE: f32 = 0.42;
+
+// A 4x4 Bayer matrix, the thresholds of ordered dithering.
+const BAYER: [[f32; 4]; 4] = [
+	[0.0, 8.0, 2.0, 10.0],
+	[12.0, 4.0, 14.0, 6.0],
+	[3.0, 11.0, 1.0, 9.0],
+	[15.0, 7.0, 13.0, 5.0],
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dither {
+	None,
+	Ordered,
+	FloydSteinberg,
+}
+
+impl Dither {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"none" => Some(Dither::None),
+			"ordered" => Some(Dither::Ordered),
+			"floyd-steinberg" => Some(Dither::FloydSteinberg),
+			_ => None,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Dither::None => "none",
+			Dither::Ordered => "ordered",
+			Dither::FloydSteinberg => "floyd-steinberg",
+		}
+	}
+}
+
+// How brightness is turned into characters. Without dithering artem picks them; with it they are
+// picked here, on the same grid of cells artem would use, so that both come out the same size.
+pub struct Shading {
+	// Densest first, which goes to the brightest parts on a dark terminal. artem's own when unset.
+	pub charset: Option<String>,
+	pub invert: bool,
+	pub gamma: f64,
+	pub dither: Dither,
+}
+
+impl Default for Shading {
+	fn default() -> Self {
+		Self {
+			charset: None,
+			invert: false,
+			gamma: 1.0,
+			dither: Dither::None,
+		}
+	}
+}
+
+impl Shading {
+	pub fn key(&self) -> String {
+		format!(
+			"charset={:?} invert={} gamma={} dither={}",
+			self.charset,
+			self.invert,
+			self.gamma,
+			self.dither.name()
+		)
+	}
+
+	pub fn convert(&self, frame: &DynamicImage, width: u32) -> String {
+		let frame = self.adjust(frame);
+		let mut builder = ConfigBuilder::new();
+		builder
+			.target_size(NonZeroU32::new(width).expect("width is at least 1"))
+			.color(false)
+			.invert(self.invert);
+		if let Some(charset) = &self.charset {
+			builder.characters(charset.clone());
+		}
+		let config = builder.build();
+		if self.dither == Dither::None {
+			return artem::convert(frame, &config);
+		}
+
+		let characters: Vec<char> = config.characters.chars().collect();
+		let (columns, rows, _, _) = ResizingDimension::calculate_dimensions(
+			width,
+			frame.height(),
+			frame.width(),
+			CELL_SCALE,
+			false,
+			ResizingDimension::Width,
+		);
+		let cells = imageops::resize(&frame.to_rgba8(), columns, rows, FilterType::Triangle);
+
+		// Each cell's position between the first and the last character, as a fraction of a step.
+		let last = (characters.len() - 1) as f32;
+		let mut levels: Vec<f32> = cells
+			.pixels()
+			.map(|&Rgba([red, green, blue, _])| {
+				let luminosity = (0.21 * red as f32 + 0.72 * green as f32 + 0.07 * blue as f32) / 255.0;
+				if self.invert {
+					luminosity * last
+				} else {
+					(1.0 - luminosity) * last
+				}
+			})
+			.collect();
+
+		let (columns, rows) = (columns as usize, rows as usize);
+		let mut text = String::with_capacity((columns + 1) * rows);
+		for row in 0..rows {
+			for column in 0..columns {
+				let level = levels[row * columns + column];
+				let index = match self.dither {
+					Dither::Ordered => (level + BAYER[row % 4][column % 4] / 16.0).floor(),
+					_ => level.round(),
+				}
+				.clamp(0.0, last);
+				if self.dither == Dither::FloydSteinberg {
+					let error = level - index;
+					let mut spread = |row: usize, column: usize, share: f32| {
+						if row < rows && column < columns {
+							levels[row * columns + column] += error * share;
+						}
+					};
+					spread(row, column + 1, 7.0 / 16.0);
+					if column > 0 {
+						spread(row + 1, column - 1, 3.0 / 16.0);
+					}
+					spread(row + 1, column, 5.0 / 16.0);
+					spread(row + 1, column + 1, 1.0 / 16.0);
+				}
+				text.push(characters[index as usize]);
+			}
+			text.push('\n');
+		}
+		text
+	}
+
+	// Gamma above 1 brightens the midtones, below 1 darkens them.
+	fn adjust(&self, frame: &DynamicImage) -> DynamicImage {
+		if self.gamma == 1.0 {
+			return frame.clone();
+		}
+		let table: [u8; 256] =
+			std::array::from_fn(|value| (255.0 * (value as f64 / 255.0).powf(1.0 / self.gamma)).round() as u8);
+		let mut adjusted = frame.to_rgba8();
+		for Rgba([red, green, blue, _]) in adjusted.pixels_mut() {
+			for channel in [red, green, blue] {
+				*channel = table[*channel as usize];
+			}
+		}
+		DynamicImage::ImageRgba8(adjusted)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::{Rgb, RgbImage};
+
+	fn gray(value: u8) -> DynamicImage {
+		DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 16, Rgb([value; 3])))
+	}
+
+	fn shading(charset: &str, dither: Dither) -> Shading {
+		Shading {
+			charset: Some(charset.to_string()),
+			dither,
+			..Shading::default()
+		}
+	}
+
+	#[test]
+	fn dither_names_round_trip() {
+		for dither in [Dither::None, Dither::Ordered, Dither::FloydSteinberg] {
+			assert!(Dither::from_name(dither.name()) == Some(dither));
+		}
+		assert!(Dither::from_name("random").is_none());
+	}
+
+	#[test]
+	fn the_densest_character_goes_to_the_brightest_parts() {
+		for dither in [Dither::Ordered, Dither::FloydSteinberg] {
+			let text = shading("#. ", dither).convert(&gray(255), 8);
+			assert!(!text.is_empty());
+			assert!(text.lines().all(|line| line == "########"), "{:?}", text);
+
+			let text = shading("#. ", dither).convert(&gray(0), 8);
+			assert!(text.lines().all(|line| line == "        "), "{:?}", text);
+		}
+	}
+
+	#[test]
+	fn invert_swaps_the_ends_of_the_charset() {
+		let mut shading = shading("#. ", Dither::Ordered);
+		shading.invert = true;
+		let text = shading.convert(&gray(0), 8);
+		assert!(text.lines().all(|line| line == "########"), "{:?}", text);
+	}
+
+	#[test]
+	fn floyd_steinberg_mixes_characters_for_midtones() {
+		let text = shading("# ", Dither::FloydSteinberg).convert(&gray(128), 8);
+		assert!(text.contains('#') && text.contains(' '), "{:?}", text);
+	}
+
+	#[test]
+	fn gamma_brightens_the_midtones() {
+		let shading = Shading {
+			gamma: 2.0,
+			..Shading::default()
+		};
+		let adjusted = shading.adjust(&gray(64)).to_rgb8();
+		assert_eq!(adjusted.get_pixel(0, 0), &Rgb([128; 3]));
+		assert_eq!(
+			Shading::default().adjust(&gray(64)).to_rgb8().get_pixel(0, 0),
+			&Rgb([64; 3])
+		);
+	}
+}
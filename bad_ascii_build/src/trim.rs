@@ -0,0 +1,107 @@
+// Drops the spaces at the end of every line, and the blank rows and columns around the picture.
+// Only the border all frames share is dropped, so that they stay aligned with each other. Color
+// escapes are kept where they are, only the spaces between them go.
+pub fn trim(frames: &mut [String]) {
+	let is_blank = |line: &&str| visible(line).all(|character| character == ' ');
+	let top = frames
+		.iter()
+		.map(|frame| frame.lines().take_while(is_blank).count())
+		.min()
+		.unwrap_or(0);
+	let bottom = frames
+		.iter()
+		.map(|frame| frame.lines().rev().take_while(is_blank).count())
+		.min()
+		.unwrap_or(0);
+	let left = frames
+		.iter()
+		.flat_map(|frame| frame.lines())
+		.filter(|line| !is_blank(line))
+		.map(|line| visible(line).take_while(|&character| character == ' ').count())
+		.min()
+		.unwrap_or(0);
+
+	for frame in frames {
+		let lines: Vec<&str> = frame.lines().collect();
+		let start = top.min(lines.len());
+		let end = lines.len().saturating_sub(bottom).max(start);
+		let mut trimmed = lines[start..end]
+			.iter()
+			.map(|line| trim_line(line, left))
+			.collect::<Vec<_>>()
+			.join("\n");
+		if frame.ends_with('\n') {
+			trimmed.push('\n');
+		}
+		*frame = trimmed;
+	}
+}
+
+// The characters a terminal shows, leaving out escapes.
+fn visible(line: &str) -> impl Iterator<Item = char> + '_ {
+	let mut escaped = false;
+	line.chars().filter(move |&character| match character {
+		'\u{1b}' => {
+			escaped = true;
+			false
+		},
+		'm' if escaped => {
+			escaped = false;
+			false
+		},
+		_ => !escaped,
+	})
+}
+
+// Leaves out the first `left` characters, which are spaces, and the spaces after the last other
+// character.
+fn trim_line(line: &str, left: usize) -> String {
+	let last = visible(line)
+		.collect::<Vec<_>>()
+		.iter()
+		.rposition(|&character| character != ' ');
+	let mut trimmed = String::with_capacity(line.len());
+	let mut escaped = false;
+	let mut column = 0;
+	for character in line.chars() {
+		match character {
+			'\u{1b}' => escaped = true,
+			'm' if escaped => escaped = false,
+			_ if escaped => {},
+			_ => {
+				let keep = column >= left && last.is_some_and(|last| column <= last);
+				column += 1;
+				if !keep {
+					continue;
+				}
+			},
+		}
+		trimmed.push(character);
+	}
+	trimmed
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_the_shared_border_is_dropped() {
+		let mut frames = vec!["\n  ab  \n   c \n\n".to_string(), "\n\n    d\n\n".to_string()];
+		trim(&mut frames);
+		assert_eq!(frames, ["ab\n c\n", "\n  d\n"]);
+	}
+
+	#[test]
+	fn color_escapes_stay_where_they_are() {
+		assert_eq!(trim_line("\u{1b}[31m  a \u{1b}[0m ", 1), "\u{1b}[31m a\u{1b}[0m");
+		assert_eq!(visible("\u{1b}[38;2;1;2;3mab\u{1b}[0m").collect::<String>(), "ab");
+	}
+
+	#[test]
+	fn blank_frames_are_left_empty() {
+		let mut frames = vec!["   \n   \n".to_string()];
+		trim(&mut frames);
+		assert_eq!(frames, ["\n"]);
+	}
+}
@@ -0,0 +1,87 @@
+#ifndef BAD_ASCII_H
+#define BAD_ASCII_H
+
+// Generated by cbindgen from bad_ascii_ffi/src/lib.rs, edits will be overwritten.
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Converted frames and when each of them starts, created with bad_ascii_player_new.
+typedef struct BadAsciiPlayer BadAsciiPlayer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a player whose frames are converted to target_size columns, 0 for bad_ascii's default,
+// with ANSI colors when color is true. Free it with bad_ascii_player_destroy.
+struct BadAsciiPlayer *bad_ascii_player_new(uint32_t target_size, bool color);
+
+// Converts a frame of width * height pixels, 3 bytes each in RGB order with no padding between
+// rows, which stays on screen for delay_ms. Returns 0, or -1 on failure.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new, and rgb points to width * height * 3 readable bytes.
+int32_t bad_ascii_player_push_rgb(struct BadAsciiPlayer *player,
+                                  const uint8_t *rgb,
+                                  uint32_t width,
+                                  uint32_t height,
+                                  uint32_t delay_ms);
+
+// The next converted frame not pulled yet, its lines separated by newlines, or NULL when every
+// frame pushed so far was. Free it with bad_ascii_string_free.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new.
+char *bad_ascii_player_pull(struct BadAsciiPlayer *player);
+
+// How many frames were pushed.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new.
+uintptr_t bad_ascii_player_frame_count(const struct BadAsciiPlayer *player);
+
+// The index of the frame on screen seconds after the first one started, going by the delays the
+// frames were pushed with.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new.
+uintptr_t bad_ascii_player_frame_at(const struct BadAsciiPlayer *player, double seconds);
+
+// A copy of the converted frame at index, or NULL past the last one. Free it with
+// bad_ascii_string_free.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new.
+char *bad_ascii_player_frame(const struct BadAsciiPlayer *player, uintptr_t index);
+
+// Frees a player and every frame it holds. NULL is ignored.
+//
+// # Safety
+//
+// player comes from bad_ascii_player_new and is not used again.
+void bad_ascii_player_destroy(struct BadAsciiPlayer *player);
+
+// Frees a string returned by bad_ascii_player_pull or bad_ascii_player_frame. NULL is ignored.
+//
+// # Safety
+//
+// text comes from one of those and is not used again.
+void bad_ascii_string_free(char *text);
+
+// What the last call on this thread that failed went wrong with, or NULL. The string stays valid
+// until the next failure on the thread.
+const char *bad_ascii_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BAD_ASCII_H */
@@ -0,0 +1,241 @@
+// C functions around bad_ascii's converter and scheduler, for terminal programs in C and C++ that
+// decode video themselves. include/bad_ascii.h is generated from this file and checked in; run
+// `cbindgen --config cbindgen.toml --output include/bad_ascii.h` in this directory after changing it.
+use bad_ascii::DEFAULT_WIDTH;
+use bad_ascii::convert::{ColorMode, Converter};
+use bad_ascii::schedule;
+use bad_ascii::theme::Theme;
+use image::{DynamicImage, RgbImage};
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CString, c_char};
+use std::num::NonZeroU32;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Converted frames and when each of them starts, created with bad_ascii_player_new.
+pub struct BadAsciiPlayer {
+	converter: Converter,
+	frames: Vec<String>,
+	delays: Vec<Duration>,
+	timestamps: Vec<f64>,
+	pulled: usize,
+}
+
+fn set_error(error: impl ToString) {
+	let message = CString::new(error.to_string().replace('\0', " ")).expect("nul bytes were replaced");
+	LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+// A panic can't unwind into C, where it would abort the program, so it fails the call instead.
+fn guarded<T>(failed: T, call: impl FnOnce() -> T) -> T {
+	panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|panic| {
+		set_error(format!("bad_ascii panicked: {}", panic_message(panic.as_ref())));
+		failed
+	})
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+	match panic.downcast_ref::<&str>() {
+		Some(message) => message,
+		None => panic.downcast_ref::<String>().map_or("no message", String::as_str),
+	}
+}
+
+fn to_c_string(text: &str) -> *mut c_char {
+	// Converted text never has nul bytes in it, only what artem and the theme put there.
+	CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Creates a player whose frames are converted to target_size columns, 0 for bad_ascii's default,
+/// with ANSI colors when color is true. Free it with bad_ascii_player_destroy.
+#[no_mangle]
+pub extern "C" fn bad_ascii_player_new(target_size: u32, color: bool) -> *mut BadAsciiPlayer {
+	guarded(ptr::null_mut(), || {
+		let target_size = NonZeroU32::new(target_size).unwrap_or(DEFAULT_WIDTH);
+		let color_mode = if color { ColorMode::Always } else { ColorMode::Never };
+		let player = BadAsciiPlayer {
+			converter: Converter::new(target_size, color_mode, None, Theme::default()),
+			frames: Vec::new(),
+			delays: Vec::new(),
+			timestamps: Vec::new(),
+			pulled: 0,
+		};
+		Box::into_raw(Box::new(player))
+	})
+}
+
+/// Converts a frame of width * height pixels, 3 bytes each in RGB order with no padding between
+/// rows, which stays on screen for delay_ms. Returns 0, or -1 on failure.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new, and rgb points to width * height * 3 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_push_rgb(
+	player: *mut BadAsciiPlayer,
+	rgb: *const u8,
+	width: u32,
+	height: u32,
+	delay_ms: u32,
+) -> i32 {
+	guarded(-1, || {
+		let Some(player) = (unsafe { player.as_mut() }) else {
+			set_error("player is NULL");
+			return -1;
+		};
+		if rgb.is_null() || width == 0 || height == 0 {
+			set_error("rgb is NULL or the frame is empty");
+			return -1;
+		}
+		let length = width as usize * height as usize * 3;
+		let pixels = unsafe { std::slice::from_raw_parts(rgb, length) }.to_vec();
+		let image = RgbImage::from_raw(width, height, pixels).expect("the buffer is width * height * 3 bytes");
+
+		let lines = player.converter.convert(DynamicImage::ImageRgb8(image));
+		let delay = Duration::from_millis(delay_ms as u64);
+		let start = player.timestamps.last().zip(player.delays.last());
+		player
+			.timestamps
+			.push(start.map_or(0.0, |(timestamp, delay)| timestamp + delay.as_secs_f64()));
+		player.frames.push(lines.join("\n"));
+		player.delays.push(delay);
+		0
+	})
+}
+
+/// The next converted frame not pulled yet, its lines separated by newlines, or NULL when every
+/// frame pushed so far was. Free it with bad_ascii_string_free.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_pull(player: *mut BadAsciiPlayer) -> *mut c_char {
+	guarded(ptr::null_mut(), || {
+		let Some(player) = (unsafe { player.as_mut() }) else {
+			return ptr::null_mut();
+		};
+		let Some(frame) = player.frames.get(player.pulled) else {
+			return ptr::null_mut();
+		};
+		player.pulled += 1;
+		to_c_string(frame)
+	})
+}
+
+/// How many frames were pushed.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_frame_count(player: *const BadAsciiPlayer) -> usize {
+	guarded(0, || unsafe { player.as_ref() }.map_or(0, |player| player.frames.len()))
+}
+
+/// The index of the frame on screen seconds after the first one started, going by the delays the
+/// frames were pushed with.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_frame_at(player: *const BadAsciiPlayer, seconds: f64) -> usize {
+	guarded(0, || {
+		unsafe { player.as_ref() }.map_or(0, |player| schedule::frame_at(&player.timestamps, seconds))
+	})
+}
+
+/// A copy of the converted frame at index, or NULL past the last one. Free it with
+/// bad_ascii_string_free.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_frame(player: *const BadAsciiPlayer, index: usize) -> *mut c_char {
+	guarded(ptr::null_mut(), || {
+		unsafe { player.as_ref() }
+			.and_then(|player| player.frames.get(index))
+			.map_or(ptr::null_mut(), |frame| to_c_string(frame))
+	})
+}
+
+/// Frees a player and every frame it holds. NULL is ignored.
+///
+/// # Safety
+///
+/// player comes from bad_ascii_player_new and is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_player_destroy(player: *mut BadAsciiPlayer) {
+	guarded((), || {
+		if !player.is_null() {
+			drop(unsafe { Box::from_raw(player) });
+		}
+	})
+}
+
+/// Frees a string returned by bad_ascii_player_pull or bad_ascii_player_frame. NULL is ignored.
+///
+/// # Safety
+///
+/// text comes from one of those and is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn bad_ascii_string_free(text: *mut c_char) {
+	guarded((), || {
+		if !text.is_null() {
+			drop(unsafe { CString::from_raw(text) });
+		}
+	})
+}
+
+/// What the last call on this thread that failed went wrong with, or NULL. The string stays valid
+/// until the next failure on the thread.
+#[no_mangle]
+pub extern "C" fn bad_ascii_last_error() -> *const c_char {
+	guarded(ptr::null(), || {
+		LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |error| error.as_ptr()))
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::ffi::CStr;
+
+	fn last_error() -> Option<String> {
+		let error = bad_ascii_last_error();
+		(!error.is_null()).then(|| unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned())
+	}
+
+	#[test]
+	fn panics_fail_the_call_instead_of_aborting() {
+		assert_eq!(guarded(-1, || panic!("the converter broke")), -1);
+		assert_eq!(last_error().as_deref(), Some("bad_ascii panicked: the converter broke"));
+	}
+
+	#[test]
+	fn pushed_frames_are_pulled_in_order() {
+		let player = bad_ascii_player_new(4, false);
+		let rgb = [255; 2 * 2 * 3];
+		unsafe {
+			assert_eq!(bad_ascii_player_push_rgb(player, rgb.as_ptr(), 2, 2, 100), 0);
+			assert_eq!(bad_ascii_player_push_rgb(player, ptr::null(), 2, 2, 100), -1);
+			assert_eq!(bad_ascii_player_frame_count(player), 1);
+			assert_eq!(bad_ascii_player_frame_at(player, 0.05), 0);
+
+			let frame = bad_ascii_player_pull(player);
+			assert!(!frame.is_null());
+			bad_ascii_string_free(frame);
+			assert!(bad_ascii_player_pull(player).is_null());
+			bad_ascii_player_destroy(player);
+		}
+		assert_eq!(last_error().as_deref(), Some("rgb is NULL or the frame is empty"));
+	}
+}
@@ -1,130 +1,343 @@
-use artem::convert;
-use image::{DynamicImage, ImageBuffer};
+mod path;
+
+use self::path::parse_path;
+use bad_ascii_build::{Color, Dither, Fallback};
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
-use std::num::NonZeroU32;
-use std::process::Command;
-use syn::{LitStr, parse_macro_input};
+use std::fmt::Display;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::str::FromStr;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Lit, Token, Visibility, parse_macro_input};
 
-const OUTPUT_FPS: u64 = 24;
-const DURATION: u64 = 10;
+// What process! was given, see its documentation.
+struct Options {
+	name: Option<(Visibility, Ident)>,
+	path: String,
+	// Errors about the input point at where the path was given.
+	span: Span,
+	conversion: bad_ascii_build::Options,
+}
 
-#[proc_macro]
-pub fn process(input: TokenStream) -> TokenStream {
-	let input_path = parse_macro_input!(input as LitStr).value();
-	let frames = extract_frames(&input_path).expect("Failed to extract frames");
-	let ascii_frames: Vec<String> = frames.into_iter().map(|frame| frame_to_ascii(frame)).collect();
+impl Parse for Options {
+	fn parse(input: ParseStream) -> syn::Result<Self> {
+		let name = if input.peek(Token![pub]) || (input.peek(Ident) && input.peek2(Token![,])) {
+			let name = (input.parse()?, input.parse()?);
+			input.parse::<Token![,]>()?;
+			Some(name)
+		} else {
+			None
+		};
+		let (path, span) = parse_path(input)?;
+		let mut options = Options {
+			name,
+			path,
+			span,
+			conversion: bad_ascii_build::Options::default(),
+		};
+		let mut seen: Vec<String> = Vec::new();
+		while !input.is_empty() {
+			input.parse::<Token![,]>()?;
+			if input.is_empty() {
+				break;
+			}
+			let name: Ident = input.parse()?;
+			input.parse::<Token![=]>()?;
+			if seen.contains(&name.to_string()) {
+				return Err(syn::Error::new(
+					name.span(),
+					format!("`{}` is given more than once", name),
+				));
+			}
+			match name.to_string().as_str() {
+				"fps" => options.conversion.fps = number::<NonZeroU64>(&input.parse()?)?.get(),
+				"duration" => options.conversion.duration = number::<NonZeroU64>(&input.parse()?)?.get(),
+				"width" | "sizes" if seen.iter().any(|seen| seen == "width" || seen == "sizes") => {
+					return Err(syn::Error::new(name.span(), "`width` and `sizes` can't be combined"));
+				},
+				"width" => options.conversion.widths = vec![number::<NonZeroU32>(&input.parse()?)?.get()],
+				"sizes" => options.conversion.widths = sizes(input)?,
+				"color" => options.conversion.color = color(&input.parse()?)?,
+				"charset" => options.conversion.shading.charset = Some(charset(&input.parse()?)?),
+				"invert" => options.conversion.shading.invert = boolean(&input.parse()?)?,
+				"gamma" => options.conversion.shading.gamma = gamma(&input.parse()?)?,
+				"dither" => options.conversion.shading.dither = dither(&input.parse()?)?,
+				"compress" => options.conversion.compress = boolean(&input.parse()?)?,
+				"intern_lines" => options.conversion.intern_lines = boolean(&input.parse()?)?,
+				"player" => options.conversion.player = boolean(&input.parse()?)?,
+				"audio" => options.conversion.audio = boolean(&input.parse()?)?,
+				"fallback" => options.conversion.fallback = fallback(&input.parse()?)?,
+				"trim" => options.conversion.trim = boolean(&input.parse()?)?,
+				"max_bytes" => options.conversion.max_bytes = Some(number::<NonZeroU64>(&input.parse()?)?.get()),
+				"max_frames" => options.conversion.max_frames = Some(number::<NonZeroU64>(&input.parse()?)?.get()),
+				"report" => options.conversion.report = boolean(&input.parse()?)?,
+				"max_duration" => options.conversion.max_duration = Some(number::<NonZeroU64>(&input.parse()?)?.get()),
+				_ => {
+					return Err(syn::Error::new(
+						name.span(),
+						format!(
+							"unknown option `{}`, expected fps, duration, width, sizes, color, charset, invert, gamma, dither, compress, \
+							 intern_lines, player, audio, trim, max_bytes, max_frames, max_duration, report or fallback",
+							name
+						),
+					));
+				},
+			}
+			seen.push(name.to_string());
+		}
+		Ok(options)
+	}
+}
+
+fn number<N>(value: &Lit) -> syn::Result<N>
+where
+	N: FromStr,
+	N::Err: Display,
+{
+	match value {
+		Lit::Int(value) => value.base10_parse(),
+		_ => Err(syn::Error::new(value.span(), "expected a number")),
+	}
+}
+
+fn sizes(input: ParseStream) -> syn::Result<Vec<u32>> {
+	let list;
+	let brackets = syn::bracketed!(list in input);
+	let widths = list.parse_terminated(|input| input.parse::<Lit>(), Token![,])?;
+	let mut sizes: Vec<u32> = Vec::new();
+	for width in &widths {
+		let width = number::<NonZeroU32>(width)?.get();
+		if sizes.contains(&width) {
+			return Err(syn::Error::new(
+				brackets.span.join(),
+				format!("{} is in sizes more than once", width),
+			));
+		}
+		sizes.push(width);
+	}
+	if sizes.is_empty() {
+		return Err(syn::Error::new(brackets.span.join(), "expected at least one width"));
+	}
+	Ok(sizes)
+}
+
+fn boolean(value: &Lit) -> syn::Result<bool> {
+	match value {
+		Lit::Bool(value) => Ok(value.value),
+		_ => Err(syn::Error::new(value.span(), "expected true or false")),
+	}
+}
+
+fn charset(value: &Lit) -> syn::Result<String> {
+	match value {
+		Lit::Str(charset) if !charset.value().is_empty() => Ok(charset.value()),
+		_ => Err(syn::Error::new(
+			value.span(),
+			"expected a string of at least one character",
+		)),
+	}
+}
+
+fn gamma(value: &Lit) -> syn::Result<f64> {
+	let gamma = match value {
+		Lit::Float(value) => value.base10_parse()?,
+		Lit::Int(value) => value.base10_parse()?,
+		_ => return Err(syn::Error::new(value.span(), "expected a number")),
+	};
+	if gamma > 0.0 && f64::is_finite(gamma) {
+		Ok(gamma)
+	} else {
+		Err(syn::Error::new(value.span(), "expected a gamma above 0"))
+	}
+}
 
-	let frame_count = ascii_frames.len();
-	let total_chars: usize = ascii_frames.iter().map(|s| s.len()).sum();
+fn dither(value: &Lit) -> syn::Result<Dither> {
+	match value {
+		Lit::Str(name) => Dither::from_name(&name.value()),
+		_ => None,
+	}
+	.ok_or_else(|| syn::Error::new(value.span(), "expected \"none\", \"ordered\" or \"floyd-steinberg\""))
+}
 
-	let frame_lengths: Vec<usize> = ascii_frames.iter().map(|s| s.len()).collect();
-	let frame_length_array = frame_lengths.iter().map(|&len| quote! { #len });
+fn fallback(value: &Lit) -> syn::Result<Fallback> {
+	match value {
+		Lit::Str(name) => Fallback::from_name(&name.value()),
+		_ => None,
+	}
+	.ok_or_else(|| syn::Error::new(value.span(), "expected \"fail\" or \"placeholder\""))
+}
 
-	let all_chars: String = ascii_frames.join("");
-	let char_array = all_chars.chars().map(|c| quote! { #c });
+fn color(value: &Lit) -> syn::Result<Color> {
+	match value {
+		Lit::Bool(value) if value.value => Ok(Color::Rgb),
+		Lit::Bool(_) => Ok(Color::Plain),
+		Lit::Str(name) => Color::from_name(&name.value())
+			.ok_or_else(|| syn::Error::new(name.span(), "expected \"16\", \"256\" or \"truecolor\"")),
+		_ => Err(syn::Error::new(
+			value.span(),
+			"expected true, false, \"16\", \"256\" or \"truecolor\"",
+		)),
+	}
+}
+
+/// Optionally a name, as in `process!(pub INTRO, "intro.mp4")`, which puts the generated items in a
+/// module of that name so that several inputs can be embedded side by side. Without one, the items
+/// are brought into the calling scope.
+///
+/// The path is a string literal, where $VAR and ${VAR} are replaced by environment variables, or
+/// `concat!` and `env!` calls like `concat!(env!("ASSETS"), "/clip.mp4")`, taken from the calling
+/// crate's manifest directory when relative.
+///
+/// The path is followed by any of `fps = N` (frames extracted per second by ffmpeg),
+/// `duration = SECONDS` (how much of the input is kept) and `width = CHARACTERS`, as in
+/// `process!("video.mp4", fps = 12, duration = 30, width = 120)`. META describes what came out.
+/// `sizes = [80, 120, 200]` embeds the frames at several widths instead, each with its ASCII_FRAMES
+/// and META in a module like `width_120`; the first is the default also found at the top.
+/// `frames_for_width(cols)` picks the widest that fits in cols columns, or the narrowest when none
+/// does.
+/// A path with `*`, `?` or `[` in it is a pattern for an image sequence like "frames/*.png", played in
+/// the order of the file names at fps.
+///
+/// `color = true` embeds the frames with truecolor escapes, or with the nearest of the 256 or 16
+/// standard colors with `color = "256"` or `color = "16"`. TEXT_BYTES says how much text that made.
+///
+/// With `compress = true` the frames are embedded deflated and inflated on first use, which keeps
+/// long or wide inputs from bloating the executable. The calling crate has to depend on flate2 for
+/// that.
+///
+/// `charset = "@%#*+=-:. "` replaces the characters brightness is drawn with, densest first,
+/// `invert = true` draws for light terminals, `gamma = 2.2` brightens midtones (below 1 darkens
+/// them), and `dither = "ordered"` or `dither = "floyd-steinberg"` trades flat areas for smoother
+/// gradients with few characters.
+///
+/// Identical frames are embedded once. With `intern_lines = true` so are identical lines, for
+/// inputs that only change in places, and the frames are put together on first use.
+///
+/// `player = true` also generates a `play()` that shows the frames once, centered in the terminal
+/// and each for its delay, until they run out or q is pressed. The calling crate has to depend on
+/// crossterm for that.
+///
+/// `audio = true` also embeds the input's sound as AUDIO, 16-bit PCM at AUDIO_SAMPLE_RATE with
+/// AUDIO_CHANNELS interleaved, for the calling crate to play alongside the frames. That takes
+/// ffmpeg, and inputs without a sound track fail.
+///
+/// `trim = true` drops the spaces at the ends of lines and the blank border all frames share, and
+/// `max_bytes = N` fails the build, saying how much it took, when the embedded text and tables
+/// would take more than N bytes.
+///
+/// `max_frames = N` keeps every second, third or Nth frame when there would be more than N, each
+/// shown for as long as the ones dropped, and `max_duration = SECONDS` fails the build as soon as
+/// the input turns out to run longer, so that a long video given a large duration by mistake is
+/// caught before it is all decoded.
+///
+/// `report = true` has the build warn with how many frames were embedded, how many of them are
+/// distinct at each width, and how many bytes they take in the text, embedded and in the binary,
+/// for tuning the options above without comparing binaries.
+///
+/// `fallback = "placeholder"` embeds a small animation saying ffmpeg is missing, with a warning,
+/// instead of failing the build where ffmpeg or ffprobe aren't installed, like docs.rs. AUDIO is
+/// then empty.
+///
+/// Without compress, intern_lines and player, the generated items only use core, so that no_std
+/// crates like microcontroller firmware can embed animations too. The frames can then also be taken
+/// in const items, as in `const BANNER: &str = frame(0);`, with `frame_for_width(cols, index)` to
+/// pick among sizes.
+///
+/// The conversion itself is in bad_ascii_build, which build scripts can also call to write the same
+/// items into OUT_DIR instead.
+#[proc_macro]
+pub fn process(input: TokenStream) -> TokenStream {
+	let options = parse_macro_input!(input as Options);
+	let items = match bad_ascii_build::generate(&options.path, &options.conversion) {
+		Ok(items) => items,
+		Err(error) => return syn::Error::new(options.span, error).to_compile_error().into(),
+	};
+
+	let (module, import) = match &options.name {
+		Some((visibility, name)) => (quote! { #[allow(non_snake_case)] #visibility mod #name }, quote! {}),
+		None => (
+			quote! { mod ascii_frames },
+			quote! {
+				#[allow(unused_imports)]
+				use self::ascii_frames::*;
+			},
+		),
+	};
 
 	let expanded = quote! {
 		#[allow(clippy::all)]
-		mod ascii_frames {
-			use std::mem::MaybeUninit;
-
-			const FRAME_COUNT: usize = #frame_count;
-			const TOTAL_CHARS: usize = #total_chars;
-
-			const FRAME_LENGTHS: [usize; FRAME_COUNT] = [#(#frame_length_array),*];
-			const CHAR_ARRAY: [char; TOTAL_CHARS] = [#(#char_array),*];
-
-			const fn create_frames() -> [&'static str; FRAME_COUNT] {
-				let mut frames: [&str; FRAME_COUNT] = [""; FRAME_COUNT];
-				let mut char_index = 0;
-				let mut i = 0;
-				while i < FRAME_COUNT {
-					let length = FRAME_LENGTHS[i];
-					// SAFETY: We ensure that char_index and length are within bounds
-					frames[i] = unsafe {
-						std::str::from_utf8_unchecked(
-							std::slice::from_raw_parts(
-								CHAR_ARRAY.as_ptr().add(char_index) as *const u8,
-								length
-							)
-						)
-					};
-					char_index += length;
-					i += 1;
-				}
-				frames
-			}
-
-			pub static ASCII_FRAMES: [&'static str; FRAME_COUNT] = create_frames();
+		#module {
+			#items
 		}
 
-		use self::ascii_frames::ASCII_FRAMES;
+		#import
 	};
 
 	expanded.into()
 }
 
-fn extract_frames(input: &str) -> Result<Vec<DynamicImage>, Box<dyn std::error::Error>> {
-	let (width, height) = get_video_dimensions(input)?;
-	let mut frames = Vec::new();
-	let output = Command::new("ffmpeg")
-		.args(&[
-			"-i",
-			input,
-			"-t",
-			&DURATION.to_string(),
-			"-r",
-			&OUTPUT_FPS.to_string(),
-			"-f",
-			"image2pipe",
-			"-pix_fmt",
-			"rgb24",
-			"-vcodec",
-			"rawvideo",
-			"-",
-		])
-		.output()?;
-
-	let buffer = output.stdout;
-	let chunk_size = (width * height * 3) as usize;
-
-	for chunk in buffer.chunks(chunk_size) {
-		if chunk.len() == chunk_size {
-			let image_buffer =
-				ImageBuffer::from_raw(width, height, chunk.to_vec()).ok_or("Failed to create image from buffer")?;
-			frames.push(DynamicImage::ImageRgb8(image_buffer));
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parsed(input: &str) -> Options {
+		match syn::parse_str::<Options>(input) {
+			Ok(options) => options,
+			Err(error) => panic!("{} didn't parse: {}", input, error),
 		}
 	}
 
-	Ok(frames)
-}
+	fn error(input: &str) -> String {
+		match syn::parse_str::<Options>(input) {
+			Ok(_) => panic!("{} parsed", input),
+			Err(error) => error.to_string(),
+		}
+	}
 
-fn get_video_dimensions(input: &str) -> Result<(u32, u32), Box<dyn std::error::Error>> {
-	let output = Command::new("ffprobe")
-		.args(&[
-			"-v",
-			"error",
-			"-select_streams",
-			"v:0",
-			"-count_packets",
-			"-show_entries",
-			"stream=width,height",
-			"-of",
-			"csv=p=0",
-			input,
-		])
-		.output()?;
-
-	let output_str = String::from_utf8(output.stdout)?;
-	let dimensions: Vec<u32> = output_str.trim().split(',').map(|s| s.parse().unwrap()).collect();
-
-	Ok((dimensions[0], dimensions[1]))
-}
+	#[test]
+	fn options_set_the_conversion() {
+		let options = parsed("\"/clip.mp4\", fps = 12, duration = 30, width = 120, gamma = 2, dither = \"ordered\",");
+		assert_eq!(options.path, "/clip.mp4");
+		assert!(options.name.is_none());
+		assert_eq!(options.conversion.fps, 12);
+		assert_eq!(options.conversion.duration, 30);
+		assert_eq!(options.conversion.widths, [120]);
+		assert_eq!(options.conversion.shading.gamma, 2.0);
+		assert!(options.conversion.shading.dither == Dither::Ordered);
 
-fn frame_to_ascii(frame: DynamicImage) -> String {
-	let config = artem::config::ConfigBuilder::new()
-		.target_size(NonZeroU32::new(160).unwrap())
-		.build();
-	convert(frame, &config)
+		let options = parsed("pub INTRO, \"/intro.gif\", sizes = [80, 120], color = \"256\"");
+		assert_eq!(options.name.expect("there is a name").1, "INTRO");
+		assert_eq!(options.conversion.widths, [80, 120]);
+		assert!(options.conversion.color == Color::Indexed);
+	}
+
+	#[test]
+	fn mistakes_are_explained() {
+		assert_eq!(
+			error("\"/clip.mp4\", fps = 12, fps = 24"),
+			"`fps` is given more than once"
+		);
+		assert_eq!(
+			error("\"/clip.mp4\", width = 80, sizes = [120]"),
+			"`width` and `sizes` can't be combined"
+		);
+		assert_eq!(
+			error("\"/clip.mp4\", sizes = [80, 80]"),
+			"80 is in sizes more than once"
+		);
+		assert_eq!(error("\"/clip.mp4\", sizes = []"), "expected at least one width");
+		assert_eq!(error("\"/clip.mp4\", fps = \"12\""), "expected a number");
+		assert_eq!(error("\"/clip.mp4\", gamma = 0"), "expected a gamma above 0");
+		assert_eq!(
+			error("\"/clip.mp4\", charset = \"\""),
+			"expected a string of at least one character"
+		);
+		assert_eq!(
+			error("\"/clip.mp4\", color = \"512\""),
+			"expected \"16\", \"256\" or \"truecolor\""
+		);
+		assert!(error("\"/clip.mp4\", speed = 2").starts_with("unknown option `speed`"));
+		assert!(error("\"/clip.mp4\", fps = 0").contains("zero"));
+	}
 }
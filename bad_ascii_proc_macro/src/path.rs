@@ -0,0 +1,140 @@
+use proc_macro2::Span;
+use std::env;
+use std::path::PathBuf;
+use syn::parse::{ParseStream, Parser};
+use syn::spanned::Spanned;
+use syn::{LitStr, Macro, Token};
+
+// The input path, as a string literal where $VAR and ${VAR} are replaced by environment variables,
+// or as `concat!` and `env!` calls like `concat!(env!("ASSETS"), "/clip.mp4")`, which a proc macro
+// only receives unexpanded and so evaluates itself. Relative paths are taken from the calling
+// crate's manifest directory rather than wherever the compiler runs.
+pub fn parse_path(input: ParseStream) -> syn::Result<(String, Span)> {
+	let (path, span) = if input.peek(LitStr) {
+		let literal: LitStr = input.parse()?;
+		(expand(&literal.value(), literal.span())?, literal.span())
+	} else {
+		let call: Macro = input.parse()?;
+		(evaluate(&call)?, call.span())
+	};
+
+	let mut resolved = PathBuf::from(path);
+	if resolved.is_relative() {
+		if let Some(manifest_dir) = env::var_os("CARGO_MANIFEST_DIR") {
+			resolved = PathBuf::from(manifest_dir).join(resolved);
+		}
+	}
+	let resolved = resolved
+		.into_os_string()
+		.into_string()
+		.map_err(|_| syn::Error::new(span, "the path isn't valid UTF-8"))?;
+	Ok((resolved, span))
+}
+
+fn expand(text: &str, span: Span) -> syn::Result<String> {
+	let mut expanded = String::new();
+	let mut rest = text;
+	while let Some(start) = rest.find('$') {
+		expanded.push_str(&rest[..start]);
+		rest = &rest[start + 1..];
+		let (name, after) = match rest.strip_prefix('{') {
+			Some(braced) => match braced.find('}') {
+				Some(end) => (&braced[..end], &braced[end + 1..]),
+				None => return Err(syn::Error::new(span, "unclosed ${ in the path")),
+			},
+			None => {
+				let end = rest
+					.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+					.unwrap_or(rest.len());
+				(&rest[..end], &rest[end..])
+			},
+		};
+		// A $ that doesn't start a name is kept as it is.
+		if name.is_empty() {
+			expanded.push('$');
+			continue;
+		}
+		expanded.push_str(&variable(name, span)?);
+		rest = after;
+	}
+	expanded.push_str(rest);
+	Ok(expanded)
+}
+
+fn evaluate(call: &Macro) -> syn::Result<String> {
+	if call.path.is_ident("env") {
+		let name: LitStr = call.parse_body()?;
+		variable(&name.value(), name.span())
+	} else if call.path.is_ident("concat") {
+		let parts = |input: ParseStream| {
+			let mut text = String::new();
+			while !input.is_empty() {
+				if input.peek(LitStr) {
+					text.push_str(&input.parse::<LitStr>()?.value());
+				} else {
+					text.push_str(&evaluate(&input.parse()?)?);
+				}
+				if !input.is_empty() {
+					input.parse::<Token![,]>()?;
+				}
+			}
+			Ok(text)
+		};
+		parts.parse2(call.tokens.clone())
+	} else {
+		Err(syn::Error::new(
+			call.path.span(),
+			"expected a string literal, concat! or env!",
+		))
+	}
+}
+
+fn variable(name: &str, span: Span) -> syn::Result<String> {
+	env::var(name).map_err(|_| syn::Error::new(span, format!("environment variable `{}` is not set", name)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expanded(text: &str) -> syn::Result<String> {
+		expand(text, Span::call_site())
+	}
+
+	#[test]
+	fn variables_are_replaced() {
+		assert_eq!(
+			expanded("assets/$CARGO_PKG_NAME/clip.mp4").expect("the variable is set"),
+			"assets/bad_ascii_proc_macro/clip.mp4"
+		);
+		assert_eq!(
+			expanded("${CARGO_PKG_NAME}_intro.mp4").expect("the variable is set"),
+			"bad_ascii_proc_macro_intro.mp4"
+		);
+		assert_eq!(expanded("a$/b$").expect("nothing is replaced"), "a$/b$");
+	}
+
+	#[test]
+	fn missing_variables_are_errors() {
+		assert!(expanded("$BAD_ASCII_UNSET_VARIABLE/clip.mp4").is_err());
+		assert!(expanded("${CARGO_PKG_NAME/clip.mp4").is_err());
+	}
+
+	#[test]
+	fn macros_are_evaluated_from_the_manifest_directory() {
+		let (path, _) = parse_path
+			.parse_str("concat!(env!(\"CARGO_PKG_NAME\"), \"/clip.mp4\")")
+			.expect("the path is evaluated");
+		let expected = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("bad_ascii_proc_macro/clip.mp4");
+		assert_eq!(PathBuf::from(path), expected);
+		assert!(parse_path.parse_str("include_str!(\"clip.mp4\")").is_err());
+	}
+
+	#[test]
+	fn absolute_paths_are_kept() {
+		let absolute = env::temp_dir().join("clip.mp4");
+		let literal = format!("{:?}", absolute.to_str().expect("the path is UTF-8"));
+		let (path, _) = parse_path.parse_str(&literal).expect("the path is parsed");
+		assert_eq!(PathBuf::from(path), absolute);
+	}
+}
@@ -0,0 +1,143 @@
+// The bad_ascii Python module, for ASCII previews of arrays and clips from notebooks and scripts.
+// Build it with maturin, which installs it under the name in pyproject.toml.
+use bad_ascii::convert::{self, ColorMode, Converter};
+use bad_ascii::player::Player;
+use bad_ascii::render::AnsiRenderer;
+use bad_ascii::source::{self, Backend, DecodeOptions, SourceOptions, Transition};
+use bad_ascii::theme::Theme;
+use bad_ascii::{DEFAULT_WIDTH, Error};
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use numpy::PyReadonlyArrayDyn;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+// Slideshows of image directories and how much of a clip is converted go by the CLI's defaults.
+const SLIDE_DURATION: Duration = Duration::from_secs(5);
+const DURATION: Duration = Duration::from_secs(90);
+
+fn converter(width: Option<u32>, color: bool) -> PyResult<Converter> {
+	let target_size = match width {
+		Some(width) => NonZeroU32::new(width).ok_or_else(|| PyValueError::new_err("width must be positive"))?,
+		None => DEFAULT_WIDTH,
+	};
+	let color_mode = if color { ColorMode::Always } else { ColorMode::Never };
+	Ok(Converter::new(target_size, color_mode, None, Theme::default()))
+}
+
+fn open(path: &str, converter: &Converter, full_length: bool) -> PyResult<Box<dyn source::FrameSource>> {
+	let options = SourceOptions {
+		slide_duration: SLIDE_DURATION,
+		transition: Transition::None,
+		backend: Backend::default(),
+		decode: DecodeOptions {
+			columns: Some(converter.target_size()),
+			limit: (!full_length).then_some(DURATION),
+			..DecodeOptions::default()
+		},
+	};
+	source::open(path, &options).map_err(py_error)
+}
+
+// Bad arguments are a ValueError and failed reads and writes an OSError, as Python's own are.
+fn py_error(error: Error) -> PyErr {
+	match error {
+		Error::BadOptions(message) => PyValueError::new_err(message),
+		Error::Io(error) => error.into(),
+		error => PyRuntimeError::new_err(error.to_string()),
+	}
+}
+
+// Arrays are height x width grayscale, or height x width x 3 or 4 for RGB and RGBA, of uint8.
+fn to_image(array: &PyReadonlyArrayDyn<u8>) -> PyResult<DynamicImage> {
+	let array = array.as_array();
+	let pixels: Vec<u8> = array.iter().copied().collect();
+	let image = match *array.shape() {
+		[height, width] => GrayImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageLuma8),
+		[height, width, 3] => RgbImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageRgb8),
+		[height, width, 4] => RgbaImage::from_raw(width as u32, height as u32, pixels).map(DynamicImage::ImageRgba8),
+		_ => None,
+	};
+	image.ok_or_else(|| {
+		PyValueError::new_err("expected an array of shape (height, width), (height, width, 3) or (height, width, 4)")
+	})
+}
+
+/// Converts an image array to text, its lines separated by newlines.
+#[pyfunction]
+#[pyo3(signature = (array, width = None, color = false))]
+fn convert_frame(array: PyReadonlyArrayDyn<u8>, width: Option<u32>, color: bool) -> PyResult<String> {
+	let image = to_image(&array)?;
+	Ok(converter(width, color)?.convert(image).join("\n"))
+}
+
+/// Converts a video, GIF, image or directory of images, one string a frame. Only the first 90
+/// seconds are converted unless full_length is true.
+#[pyfunction]
+#[pyo3(signature = (path, width = None, color = false, full_length = false))]
+fn convert_video(path: &str, width: Option<u32>, color: bool, full_length: bool) -> PyResult<Vec<String>> {
+	let converter = converter(width, color)?;
+	let source = open(path, &converter, full_length)?;
+	let (frames, _) = convert::convert_frames(source, &converter).map_err(py_error)?;
+	Ok(frames.into_iter().map(|lines| lines.join("\n")).collect())
+}
+
+/// Plays a video, GIF, image or directory of images in the terminal, from its top left corner.
+/// Ctrl-C stops it, raising KeyboardInterrupt.
+#[pyfunction]
+#[pyo3(signature = (path, width = None, color = true, r#loop = false, fps_cap = None))]
+fn play(
+	py: Python<'_>,
+	path: &str,
+	width: Option<u32>,
+	color: bool,
+	r#loop: bool,
+	fps_cap: Option<f64>,
+) -> PyResult<()> {
+	let converter = converter(width, color)?;
+	// What Python raised for a signal, which stops playback.
+	let interrupted = Arc::new(Mutex::new(None));
+	let signaled = Arc::clone(&interrupted);
+	// Other Python threads go on while playing, which only takes the GIL back for a moment every
+	// frame to let Python handle signals.
+	let played = py.detach(move || {
+		let source = open(path, &converter, false)?;
+		let mut builder = Player::builder()
+			.source(source)
+			.converter(converter)
+			.renderer(Box::new(AnsiRenderer::new(io::stdout(), 1, 1)))
+			.loop_(r#loop);
+		if let Some(fps) = fps_cap {
+			builder = builder.fps_cap(fps);
+		}
+		let stop = builder.stop_handle();
+		builder = builder.on_frame(move |_, _| {
+			if let Err(error) = Python::attach(|py| py.check_signals()) {
+				*signaled.lock().unwrap_or_else(PoisonError::into_inner) = Some(error);
+				stop.stop();
+			}
+		});
+
+		print!("\x1B[2J");
+		let played = builder.build().and_then(Player::play);
+		println!();
+		io::stdout().flush()?;
+		played.map_err(py_error)
+	});
+	match interrupted.lock().unwrap_or_else(PoisonError::into_inner).take() {
+		Some(error) => Err(error),
+		None => played,
+	}
+}
+
+#[pymodule]
+#[pyo3(name = "bad_ascii")]
+fn module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+	m.add_function(wrap_pyfunction!(convert_frame, m)?)?;
+	m.add_function(wrap_pyfunction!(convert_video, m)?)?;
+	m.add_function(wrap_pyfunction!(play, m)?)?;
+	Ok(())
+}
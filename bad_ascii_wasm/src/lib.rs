@@ -0,0 +1,135 @@
+// bad_ascii's converter for web pages, built with `wasm-pack build --target web`. The page decodes
+// frames itself, drawing a <video> or a WebCodecs VideoFrame to a canvas and reading its RGBA back,
+// and gets text to put in a <pre>:
+//
+//     import init, { Converter } from "./pkg/bad_ascii_wasm.js";
+//     await init();
+//     const converter = new Converter(120, "truecolor");
+//     const { data, width, height } = context.getImageData(0, 0, canvas.width, canvas.height);
+//     pre.innerHTML = converter.convertHtml(data, width, height);
+//
+// Conversion goes through the same bad_ascii::convert the player uses, so frames look the same as
+// in the terminal. The color is picked here, as there is no terminal or environment to go by.
+use bad_ascii::DEFAULT_WIDTH;
+use bad_ascii::convert::{self, ColorMode};
+use bad_ascii::export::runs;
+use bad_ascii::theme::Theme;
+use image::{DynamicImage, RgbaImage};
+use std::fmt::Write;
+use std::num::NonZeroU32;
+use wasm_bindgen::prelude::*;
+
+// Turns RGBA frames into lines of text, set up once from the style options.
+#[wasm_bindgen]
+pub struct Converter {
+	width: NonZeroU32,
+	// None for plain text, otherwise whether to use truecolor rather than the 16 basic colors.
+	truecolor: Option<bool>,
+	charset: Option<String>,
+	theme: Theme,
+	converter: convert::Converter,
+}
+
+#[wasm_bindgen]
+impl Converter {
+	// Width is in characters, 0 for bad_ascii's default, and color is "plain", "16" or "truecolor".
+	#[wasm_bindgen(constructor)]
+	pub fn new(width: u32, color: &str) -> Result<Converter, JsError> {
+		let truecolor = match color {
+			"plain" => None,
+			"16" => Some(false),
+			"truecolor" => Some(true),
+			name => return Err(JsError::new(&format!("Unknown color {:?}", name))),
+		};
+		let width = NonZeroU32::new(width).unwrap_or(DEFAULT_WIDTH);
+		let theme = Theme::default();
+		Ok(Self {
+			width,
+			truecolor,
+			charset: None,
+			converter: build(width, truecolor, None, theme.clone()),
+			theme,
+		})
+	}
+
+	// Densest first, which goes to the brightest parts on a dark background.
+	#[wasm_bindgen(js_name = setCharset)]
+	pub fn set_charset(&mut self, charset: &str) -> Result<(), JsError> {
+		if charset.chars().count() < 2 {
+			return Err(JsError::new("The charset needs at least two characters"));
+		}
+		self.charset = Some(charset.to_string());
+		self.rebuild();
+		Ok(())
+	}
+
+	// One of the player's built-in themes: "default", "matrix", "amber" or "mono". Themes with a
+	// palette snap colors to it.
+	#[wasm_bindgen(js_name = setTheme)]
+	pub fn set_theme(&mut self, name: &str) -> Result<(), JsError> {
+		self.theme = Theme::built_in(name).ok_or_else(|| JsError::new(&format!("Unknown theme {:?}", name)))?;
+		self.rebuild();
+		Ok(())
+	}
+
+	// The frame as text colored with ANSI escapes, for terminal emulators like xterm.js.
+	pub fn convert(&self, rgba: &[u8], width: u32, height: u32) -> Result<String, JsError> {
+		Ok(self.converter.convert(to_image(rgba, width, height)?).join("\n"))
+	}
+
+	// The frame as HTML for a <pre>, each run of a color in a styled span.
+	#[wasm_bindgen(js_name = convertHtml)]
+	pub fn convert_html(&self, rgba: &[u8], width: u32, height: u32) -> Result<String, JsError> {
+		let lines = self.converter.convert(to_image(rgba, width, height)?);
+		let mut html = String::new();
+		for (row, line) in lines.iter().enumerate() {
+			if row > 0 {
+				html.push('\n');
+			}
+			if self.truecolor.is_none() {
+				html.push_str(&escape(line));
+				continue;
+			}
+			for (color, text) in runs(line) {
+				let [red, green, blue] = color.0;
+				write!(
+					html,
+					"<span style=\"color:#{:02x}{:02x}{:02x}\">{}</span>",
+					red,
+					green,
+					blue,
+					escape(&text)
+				)
+				.expect("writing to a String can't fail");
+			}
+		}
+		Ok(html)
+	}
+
+	fn rebuild(&mut self) {
+		self.converter = build(self.width, self.truecolor, self.charset.as_deref(), self.theme.clone());
+	}
+}
+
+fn build(width: NonZeroU32, truecolor: Option<bool>, charset: Option<&str>, theme: Theme) -> convert::Converter {
+	let color_mode = if truecolor.is_some() {
+		ColorMode::Always
+	} else {
+		ColorMode::Never
+	};
+	convert::Converter::new(width, color_mode, charset, theme).with_truecolor(truecolor.unwrap_or(false))
+}
+
+fn escape(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// ImageData and VideoFrame.copyTo with the RGBA format both lay pixels out as 4 bytes each, with no
+// padding between rows.
+fn to_image(rgba: &[u8], width: u32, height: u32) -> Result<DynamicImage, JsError> {
+	if width == 0 || height == 0 || rgba.len() != width as usize * height as usize * 4 {
+		return Err(JsError::new("Expected width * height * 4 bytes of RGBA"));
+	}
+	let image = RgbaImage::from_raw(width, height, rgba.to_vec()).expect("the buffer is width * height * 4 bytes");
+	Ok(DynamicImage::ImageRgba8(image))
+}
@@ -0,0 +1,249 @@
+mod mapped;
+mod reader;
+mod writer;
+
+pub use self::mapped::Mapped;
+pub use self::reader::{BaaReader, decode_record};
+pub use self::writer::{BaaWriter, encode_record, write, write_source};
+
+use std::path::Path;
+
+// Files start with this, followed by the version and the rest of the header, all little endian.
+const MAGIC: &[u8; 8] = b"BADASCII";
+const VERSION: u16 = 1;
+const HEADER_LENGTH: u64 = 24;
+
+const COLOR_PLAIN: u8 = 0;
+const COLOR_ANSI: u8 = 1;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+
+// Frames are compressed in blocks of this many, which lets zstd find the repetition between
+// neighbouring frames without having to hold the whole file in memory.
+const FRAMES_PER_BLOCK: usize = 64;
+
+// Every block starts with a full frame, the rest only store the cells that changed since the frame
+// before them.
+const FRAME_KEY: u8 = 0;
+const FRAME_DELTA: u8 = 1;
+// A run of identical frames is stored as one, held for all of their delays, along with how many
+// frames it stands for.
+const FRAME_REPEAT: u8 = 2;
+
+// The blocks end with an empty one and are followed by an index of every frame and block, then the
+// source's chapters, each its start in microseconds and its title. The file ends with the index's
+// offset, the number of blocks, the chapters' offset and this. The header's frame count may be 0
+// if it wasn't known when the file was started.
+const INDEX_MAGIC: &[u8; 8] = b"BAAINDEX";
+const INDEX_FLAG_KEY: u8 = 1;
+const FOOTER_LENGTH: usize = 28;
+
+pub const DEFAULT_LEVEL: i32 = 3;
+
+pub struct Header {
+	pub fps: f32,
+	pub columns: u16,
+	pub rows: u16,
+	// Whether the frames carry ANSI color codes.
+	pub color: bool,
+	pub compressed: bool,
+	pub frame_count: u32,
+}
+
+pub fn is_container(input: &str) -> bool {
+	Path::new(input)
+		.extension()
+		.is_some_and(|extension| extension.eq_ignore_ascii_case("baa"))
+}
+
+// Splits a line into cells, each being one visible character along with the escape sequences in
+// front of it. Anything after the last character stays with it, so joining the cells gives back
+// the original line.
+fn cells(line: &str) -> Vec<&str> {
+	let mut cells = Vec::new();
+	let mut start = 0;
+	let mut in_escape = false;
+	for (index, c) in line.char_indices() {
+		if in_escape {
+			// CSI sequences end with a letter.
+			in_escape = !c.is_ascii_alphabetic();
+		} else if c == '\u{1b}' {
+			in_escape = true;
+		} else {
+			let end = index + c.len_utf8();
+			cells.push(&line[start..end]);
+			start = end;
+		}
+	}
+
+	if start < line.len() {
+		match cells.pop() {
+			Some(last) => {
+				let last_start = start - last.len();
+				cells.push(&line[last_start..]);
+			},
+			None => cells.push(line),
+		}
+	}
+	cells
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Error;
+	use crate::source::Chapter;
+	use std::env;
+	use std::fs;
+	use std::process;
+	use std::time::Duration;
+
+	// Runs of identical frames, each run changing one cell of the one before it, and enough of them
+	// to fill more than one block. The first run is three long, the rest two so that none of them
+	// crosses into the next block, where the repeats would start over.
+	fn frames() -> (Vec<Vec<String>>, Vec<Duration>) {
+		let frames: Vec<Vec<String>> = (0..100)
+			.map(|index| {
+				let run = if index < 3 { 0 } else { (index - 3) / 2 + 1 };
+				vec![format!("{}{}d", "-".repeat(40), run % 10), "xyz".to_string()]
+			})
+			.collect();
+		let delays = vec![Duration::from_millis(10); frames.len()];
+		(frames, delays)
+	}
+
+	// Frames as they come back, where a run's repeats are one frame held for all of their delays.
+	fn merged(frames: &[Vec<String>], delays: &[Duration]) -> Vec<(Duration, Vec<String>)> {
+		let mut merged: Vec<(Duration, Vec<String>)> = Vec::new();
+		for (index, (frame, &delay)) in frames.iter().zip(delays).enumerate() {
+			let repeated = index >= 2 && frames[index - 1] == *frame && frames[index - 2] == *frame;
+			match merged.last_mut() {
+				Some((held, _)) if repeated => *held += delay,
+				_ => merged.push((delay, frame.clone())),
+			}
+		}
+		merged
+	}
+
+	fn chapters() -> Vec<Chapter> {
+		vec![
+			Chapter {
+				start: Duration::ZERO,
+				title: "Intro".to_string(),
+			},
+			Chapter {
+				start: Duration::from_millis(500),
+				title: "Middle".to_string(),
+			},
+		]
+	}
+
+	#[test]
+	fn cells_keep_escapes_with_their_character() {
+		assert_eq!(cells("\x1B[31ma\x1B[0mb"), ["\x1B[31ma", "\x1B[0mb"]);
+		assert_eq!(cells("ab\x1B[0m"), ["a", "b\x1B[0m"]);
+		assert_eq!(cells("\x1B[0m"), ["\x1B[0m"]);
+	}
+
+	#[test]
+	fn records_round_trip() {
+		// Long enough rows that storing the changed cells takes less than the whole frame.
+		let previous = vec!["\x1B[31ma\x1B[0mbc".to_string(), "d".repeat(40)];
+		let frame = vec!["\x1B[32ma\x1B[0mbc".to_string(), format!("dX{}", "d".repeat(38))];
+
+		let (key, is_key) = encode_record(None, &previous, 1000).unwrap();
+		assert!(is_key);
+		let (delay, decoded) = decode_record(&mut key.as_slice(), None).unwrap();
+		assert_eq!(delay, Duration::from_millis(1));
+		assert_eq!(decoded, previous);
+
+		let (delta, is_key) = encode_record(Some(&previous), &frame, 2000).unwrap();
+		assert!(!is_key);
+		assert_eq!(delta[0], FRAME_DELTA);
+		let (delay, decoded) = decode_record(&mut delta.as_slice(), Some(&previous)).unwrap();
+		assert_eq!(delay, Duration::from_millis(2));
+		assert_eq!(decoded, frame);
+
+		// A delta can't be decoded without the frame it was made from.
+		assert!(decode_record(&mut delta.as_slice(), None).is_err());
+	}
+
+	#[test]
+	fn reader_gets_back_what_was_written() {
+		let (frames, delays) = frames();
+		for level in [0, DEFAULT_LEVEL] {
+			let mut file = Vec::new();
+			let header = write(&frames, &delays, &chapters(), false, level, &mut file).unwrap();
+			assert_eq!((header.columns, header.rows), (42, 2));
+			assert_eq!(header.compressed, level > 0);
+
+			let mut reader = BaaReader::new(file.as_slice()).unwrap();
+			let mut read = Vec::new();
+			while let Some(frame) = reader.next_frame().unwrap() {
+				read.push(frame);
+			}
+			assert_eq!(read, merged(&frames, &delays));
+			assert_eq!(read.len(), header.frame_count as usize);
+		}
+	}
+
+	#[test]
+	fn mapped_seeks_through_the_index() {
+		let (frames, delays) = frames();
+		let path = env::temp_dir().join(format!("bad_ascii-test-{}.baa", process::id()));
+		let mut file = Vec::new();
+		write(&frames, &delays, &chapters(), false, DEFAULT_LEVEL, &mut file).unwrap();
+		fs::write(&path, &file).unwrap();
+		let mapped = Mapped::open(&path);
+		fs::remove_file(&path).unwrap();
+		let mut mapped = mapped.unwrap();
+
+		let expected = merged(&frames, &delays);
+		assert!(expected.len() > FRAMES_PER_BLOCK);
+		assert_eq!(
+			mapped.delays(),
+			expected.iter().map(|(delay, _)| *delay).collect::<Vec<_>>()
+		);
+		let titles: Vec<&str> = mapped.chapters().iter().map(|chapter| chapter.title.as_str()).collect();
+		assert_eq!(titles, ["Intro", "Middle"]);
+		assert_eq!(mapped.chapters()[1].start, Duration::from_millis(500));
+
+		// Out of order, across blocks and back, the way seeking asks for them.
+		for index in [expected.len() - 1, 3, 4, FRAMES_PER_BLOCK, FRAMES_PER_BLOCK - 1, 0] {
+			assert_eq!(mapped.frame(index).unwrap(), expected[index].1, "frame {}", index);
+		}
+		assert!(mapped.frame(expected.len()).is_err());
+	}
+
+	#[test]
+	fn lengths_past_the_end_of_the_input_are_refused() {
+		let (frames, delays) = frames();
+		let mut file = Vec::new();
+		write(&frames, &delays, &[], false, 0, &mut file).unwrap();
+		file.truncate(HEADER_LENGTH as usize);
+		file.extend_from_slice(&1u32.to_le_bytes());
+		file.extend_from_slice(&u32::MAX.to_le_bytes());
+		file.extend_from_slice(b"too short");
+		let mut reader = BaaReader::new(file.as_slice()).unwrap();
+		assert!(matches!(reader.next_frame(), Err(Error::BadInput(_))));
+
+		let mut record = vec![FRAME_KEY];
+		record.extend_from_slice(&0u32.to_le_bytes());
+		record.extend_from_slice(&u32::MAX.to_le_bytes());
+		assert!(matches!(
+			decode_record(&mut record.as_slice(), None),
+			Err(Error::BadInput(_))
+		));
+	}
+
+	#[test]
+	fn other_versions_are_refused() {
+		let (frames, delays) = frames();
+		let mut file = Vec::new();
+		write(&frames, &delays, &[], false, 0, &mut file).unwrap();
+		file[8..10].copy_from_slice(&(VERSION + 1).to_le_bytes());
+		assert!(BaaReader::new(file.as_slice()).is_err());
+		assert!(BaaReader::new(&b"NOTABAA!"[..]).is_err());
+	}
+}
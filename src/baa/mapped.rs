@@ -0,0 +1,192 @@
+use super::reader::{corrupt, read_array, read_frame, read_header, read_string};
+use super::{FOOTER_LENGTH, Header, INDEX_FLAG_KEY, INDEX_MAGIC};
+use crate::Error;
+use crate::source::Chapter;
+use memmap2::Mmap;
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+struct IndexEntry {
+	block: usize,
+	// Where the frame starts inside its block, once the block is decompressed.
+	offset: usize,
+}
+
+// The block being played and the last frame decoded from it, which the next frame is usually a
+// delta against.
+struct Cursor {
+	block: usize,
+	// The decompressed block, left empty when the file isn't compressed and is read from the map.
+	data: Vec<u8>,
+	decoded: Option<usize>,
+	next_offset: usize,
+	lines: Vec<String>,
+}
+
+// Plays a .baa file straight from a memory map, decoding only the frames that are asked for, so
+// that memory use doesn't grow with the length of the file.
+pub struct Mapped {
+	map: Mmap,
+	header: Header,
+	// Where each block's frames are in the file, still compressed if the file is.
+	blocks: Vec<Range<usize>>,
+	frames: Vec<IndexEntry>,
+	delays: Vec<Duration>,
+	keyframes: Vec<usize>,
+	chapters: Vec<Chapter>,
+	cursor: Option<Cursor>,
+}
+
+impl Mapped {
+	pub fn open(path: &Path) -> Result<Self, Error> {
+		let file = File::open(path)?;
+		// SAFETY: the map is only read, and a file truncated by someone else while it's playing is
+		// no different from the other ways a user can pull a file out from under a program.
+		let map = unsafe { Mmap::map(&file)? };
+
+		let header = read_header(&mut &map[..], &path.display().to_string())?;
+		let mut mapped = Self {
+			header,
+			blocks: Vec::new(),
+			frames: Vec::new(),
+			delays: Vec::new(),
+			keyframes: Vec::new(),
+			chapters: Vec::new(),
+			cursor: None,
+			map,
+		};
+
+		mapped.read_index()?;
+		Ok(mapped)
+	}
+
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+
+	pub fn delays(&self) -> &[Duration] {
+		&self.delays
+	}
+
+	pub fn chapters(&self) -> &[Chapter] {
+		&self.chapters
+	}
+
+	pub fn frame(&mut self, index: usize) -> Result<&[String], Error> {
+		let block = self
+			.frames
+			.get(index)
+			.ok_or_else(|| Error::BadOptions("Frame is past the end of the .baa file".to_string()))?
+			.block;
+		let keyframe = self.keyframes[self
+			.keyframes
+			.partition_point(|&keyframe| keyframe <= index)
+			.checked_sub(1)
+			.ok_or_else(|| corrupt("A .baa file doesn't start with a keyframe"))?];
+
+		// Carry on from the last frame when it's on the way, otherwise start over from the nearest
+		// keyframe.
+		let mut cursor = match self.cursor.take() {
+			Some(cursor) if cursor.block == block => cursor,
+			_ => Cursor {
+				block,
+				data: if self.header.compressed {
+					zstd::decode_all(&self.map[self.blocks[block].clone()])?
+				} else {
+					Vec::new()
+				},
+				decoded: None,
+				next_offset: 0,
+				lines: Vec::new(),
+			},
+		};
+		if cursor
+			.decoded
+			.is_none_or(|decoded| decoded < keyframe || decoded > index)
+		{
+			cursor.decoded = None;
+			cursor.next_offset = self.frames[keyframe].offset;
+		}
+
+		let data = if self.header.compressed {
+			&cursor.data[..]
+		} else {
+			&self.map[self.blocks[block].clone()]
+		};
+		while cursor.decoded != Some(index) {
+			let mut input = data
+				.get(cursor.next_offset..)
+				.ok_or_else(|| corrupt("A .baa index points past its block"))?;
+			let remaining = input.len();
+			let previous = cursor.decoded.map(|_| cursor.lines.as_slice());
+			let (_, lines) = read_frame(&mut input, previous)?;
+
+			cursor.next_offset += remaining - input.len();
+			cursor.lines = lines;
+			cursor.decoded = Some(cursor.decoded.map_or(keyframe, |decoded| decoded + 1));
+		}
+
+		Ok(&self.cursor.insert(cursor).lines)
+	}
+
+	fn read_index(&mut self) -> Result<(), Error> {
+		let footer_start = self
+			.map
+			.len()
+			.checked_sub(FOOTER_LENGTH)
+			.filter(|&start| self.map[start..].ends_with(INDEX_MAGIC))
+			.ok_or_else(|| corrupt("A .baa file is missing its index"))?;
+		let footer = &self.map[footer_start..];
+		let index_offset = u64::from_le_bytes(footer[..8].try_into().expect("the footer is long enough")) as usize;
+		let block_count = u32::from_le_bytes(footer[8..12].try_into().expect("the footer is long enough")) as usize;
+		let index_end = u64::from_le_bytes(footer[12..20].try_into().expect("the footer is long enough")) as usize;
+		if index_end > footer_start {
+			return Err(corrupt("A .baa index is out of bounds"));
+		}
+
+		// The header may not have the frame count, but it follows from the size of the index.
+		let frame_count = index_end
+			.checked_sub(block_count * 16)
+			.and_then(|blocks_start| blocks_start.checked_sub(index_offset))
+			.ok_or_else(|| corrupt("A .baa index is out of bounds"))?
+			/ 13;
+		self.header.frame_count = frame_count
+			.try_into()
+			.map_err(|_| corrupt("A .baa file has too many frames"))?;
+
+		let mut index = &self.map[index_offset..];
+		for frame in 0..frame_count {
+			self.delays
+				.push(Duration::from_micros(u32::from_le_bytes(read_array(&mut index)?) as u64));
+			let block = u32::from_le_bytes(read_array(&mut index)?) as usize;
+			let offset = u32::from_le_bytes(read_array(&mut index)?) as usize;
+			if read_array::<1>(&mut index)?[0] & INDEX_FLAG_KEY != 0 {
+				self.keyframes.push(frame);
+			}
+			self.frames.push(IndexEntry { block, offset });
+		}
+		for _ in 0..block_count {
+			let offset = u64::from_le_bytes(read_array(&mut index)?) as usize;
+			let length = u32::from_le_bytes(read_array(&mut index)?) as usize;
+			read_array::<4>(&mut index)?;
+			if offset + length > self.map.len() {
+				return Err(corrupt("A .baa block is out of bounds"));
+			}
+			self.blocks.push(offset..offset + length);
+		}
+		if self.frames.iter().any(|frame| frame.block >= self.blocks.len()) {
+			return Err(corrupt("A .baa index refers to a block that doesn't exist"));
+		}
+
+		let mut chapters = &self.map[index_end..footer_start];
+		let count = u32::from_le_bytes(read_array(&mut chapters)?);
+		for _ in 0..count {
+			let start = Duration::from_micros(u64::from_le_bytes(read_array(&mut chapters)?));
+			let title = read_string(&mut chapters)?;
+			self.chapters.push(Chapter { start, title });
+		}
+		Ok(())
+	}
+}
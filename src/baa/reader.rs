@@ -0,0 +1,187 @@
+use super::{
+	COLOR_ANSI, COLOR_PLAIN, COMPRESSION_NONE, COMPRESSION_ZSTD, FRAME_DELTA, FRAME_KEY, FRAME_REPEAT, Header, MAGIC,
+	VERSION, cells,
+};
+use crate::Error;
+use std::io::Read;
+use std::time::Duration;
+
+// A frame's lines and how long it stays on screen.
+type TimedFrame = (Duration, Vec<String>);
+
+// Reads frames in order as they arrive, without needing to seek, so it works on pipes and sockets
+// as well as files.
+pub struct BaaReader<R: Read> {
+	input: R,
+	header: Header,
+	block: Vec<u8>,
+	offset: usize,
+	left_in_block: u32,
+	previous: Option<Vec<String>>,
+	finished: bool,
+}
+
+impl<R: Read> BaaReader<R> {
+	pub fn new(mut input: R) -> Result<Self, Error> {
+		let header = read_header(&mut input, "The input")?;
+		Ok(Self {
+			input,
+			header,
+			block: Vec::new(),
+			offset: 0,
+			left_in_block: 0,
+			previous: None,
+			finished: false,
+		})
+	}
+
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+
+	pub fn next_frame(&mut self) -> Result<Option<TimedFrame>, Error> {
+		if self.finished {
+			return Ok(None);
+		}
+
+		if self.left_in_block == 0 {
+			let frame_count = u32::from_le_bytes(read_array(&mut self.input)?);
+			let length = u32::from_le_bytes(read_array(&mut self.input)?);
+			if frame_count == 0 {
+				self.finished = true;
+				return Ok(None);
+			}
+
+			let block = read_bytes(&mut self.input, length)?;
+			self.block = if self.header.compressed {
+				zstd::decode_all(block.as_slice())?
+			} else {
+				block
+			};
+			self.offset = 0;
+			self.left_in_block = frame_count;
+		}
+
+		let mut input = self
+			.block
+			.get(self.offset..)
+			.ok_or_else(|| corrupt("A .baa block ended early"))?;
+		let remaining = input.len();
+		let (delay, frame) = read_frame(&mut input, self.previous.as_deref())?;
+		self.offset += remaining - input.len();
+		self.left_in_block -= 1;
+
+		self.previous = Some(frame.clone());
+		Ok(Some((delay, frame)))
+	}
+}
+
+pub(super) fn read_header(input: &mut impl Read, name: &str) -> Result<Header, Error> {
+	let mut magic = [0; 8];
+	input.read_exact(&mut magic)?;
+	if &magic != MAGIC {
+		return Err(Error::BadInput(format!("{} is not a .baa file", name)));
+	}
+	let version = u16::from_le_bytes(read_array(input)?);
+	if version != VERSION {
+		return Err(Error::BadInput(format!("Unsupported .baa version {}", version)));
+	}
+
+	let fps = f32::from_le_bytes(read_array(input)?);
+	let columns = u16::from_le_bytes(read_array(input)?);
+	let rows = u16::from_le_bytes(read_array(input)?);
+	let color = match read_array::<1>(input)?[0] {
+		COLOR_PLAIN => false,
+		COLOR_ANSI => true,
+		mode => return Err(Error::BadInput(format!("Unknown .baa color mode {}", mode))),
+	};
+	let compressed = match read_array::<1>(input)?[0] {
+		COMPRESSION_NONE => false,
+		COMPRESSION_ZSTD => true,
+		compression => return Err(Error::BadInput(format!("Unknown .baa compression {}", compression))),
+	};
+	let frame_count = u32::from_le_bytes(read_array(input)?);
+
+	Ok(Header {
+		fps,
+		columns,
+		rows,
+		color,
+		compressed,
+		frame_count,
+	})
+}
+
+// Reads a record written by encode_record.
+pub fn decode_record(input: &mut impl Read, previous: Option<&[String]>) -> Result<TimedFrame, Error> {
+	read_frame(input, previous)
+}
+
+// Reads one frame record, applying it to the frame before it when it's a delta.
+pub(super) fn read_frame(input: &mut impl Read, previous: Option<&[String]>) -> Result<TimedFrame, Error> {
+	let kind = read_array::<1>(input)?[0];
+	let delay = Duration::from_micros(u32::from_le_bytes(read_array(input)?) as u64);
+
+	let frame = match kind {
+		FRAME_KEY => read_string(input)?.split('\n').map(String::from).collect(),
+		FRAME_DELTA => {
+			let mut frame = previous
+				.ok_or_else(|| corrupt("A .baa delta frame has no frame before it"))?
+				.to_vec();
+			let changes = u32::from_le_bytes(read_array(input)?);
+			for _ in 0..changes {
+				let row = u16::from_le_bytes(read_array(input)?) as usize;
+				let column = u16::from_le_bytes(read_array(input)?) as usize;
+				let replaced = u16::from_le_bytes(read_array(input)?) as usize;
+				let text = read_string(input)?;
+
+				let line = frame
+					.get_mut(row)
+					.ok_or_else(|| corrupt("A .baa delta frame changes a row that doesn't exist"))?;
+				let mut line_cells = cells(line);
+				if column + replaced > line_cells.len() {
+					return Err(corrupt("A .baa delta frame changes cells that don't exist"));
+				}
+				line_cells.splice(column..column + replaced, cells(&text));
+				*line = line_cells.concat();
+			}
+			frame
+		},
+		FRAME_REPEAT => {
+			// Only the hold matters for playback, not how many frames were merged into it.
+			read_array::<4>(input)?;
+			previous
+				.ok_or_else(|| corrupt("A .baa repeat has no frame before it"))?
+				.to_vec()
+		},
+		kind => return Err(Error::BadInput(format!("Unknown .baa frame type {}", kind))),
+	};
+	Ok((delay, frame))
+}
+
+pub(super) fn read_string(input: &mut impl Read) -> Result<String, Error> {
+	let length = u32::from_le_bytes(read_array(input)?);
+	String::from_utf8(read_bytes(input, length)?).map_err(|_| corrupt("A .baa frame isn't valid UTF-8"))
+}
+
+// Lengths come from the input, so what they promise is read as it arrives rather than allocated up
+// front, and a stream can't claim gigabytes it doesn't have.
+fn read_bytes(input: &mut impl Read, length: u32) -> Result<Vec<u8>, Error> {
+	let mut bytes = Vec::new();
+	input.by_ref().take(length.into()).read_to_end(&mut bytes)?;
+	if bytes.len() != length as usize {
+		return Err(corrupt("A .baa file ended early"));
+	}
+	Ok(bytes)
+}
+
+pub(super) fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N], Error> {
+	let mut bytes = [0; N];
+	input.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+// Files that were cut short or written wrong.
+pub(super) fn corrupt(message: &str) -> Error {
+	Error::BadInput(message.to_string())
+}
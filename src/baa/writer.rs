@@ -0,0 +1,355 @@
+use super::{
+	COLOR_ANSI, COLOR_PLAIN, COMPRESSION_NONE, COMPRESSION_ZSTD, FRAME_DELTA, FRAME_KEY, FRAME_REPEAT,
+	FRAMES_PER_BLOCK, HEADER_LENGTH, Header, INDEX_FLAG_KEY, INDEX_MAGIC, MAGIC, VERSION, cells,
+};
+use crate::convert::Converter;
+use crate::render::remove_ansi_escape_sequences;
+use crate::source::{Chapter, FrameSource};
+use crate::{Error, export};
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+// Writes frames as they are produced. Every FRAMES_PER_BLOCK frames a block is written out and
+// the output flushed, so that a reader on the other end of a pipe or socket can play them as they
+// arrive. The index goes at the end, once every frame is known.
+pub struct BaaWriter<W: Write> {
+	out: W,
+	color: bool,
+	compressed: bool,
+	level: i32,
+	// Bytes written so far, which is where the next block goes.
+	position: u64,
+	block: Vec<u8>,
+	block_frames: u32,
+	previous: Option<Vec<String>>,
+	// Where the repeat record for the current run of identical frames is in the block.
+	repeat: Option<usize>,
+	// Each frame's delay, block, offset inside its decompressed block and whether it's a keyframe.
+	frame_index: Vec<(u32, u32, u32, u8)>,
+	// Each block's offset in the file, compressed length and frame count.
+	block_index: Vec<(u64, u32, u32)>,
+	chapters: Vec<Chapter>,
+}
+
+impl<W: Write> BaaWriter<W> {
+	// The header's frame count can be left at 0 when it isn't known up front. The level is only
+	// used when the header asks for compression.
+	pub fn new(mut out: W, header: &Header, level: i32) -> Result<Self, Error> {
+		out.write_all(MAGIC)?;
+		out.write_all(&VERSION.to_le_bytes())?;
+		out.write_all(&header.fps.to_le_bytes())?;
+		out.write_all(&header.columns.to_le_bytes())?;
+		out.write_all(&header.rows.to_le_bytes())?;
+		out.write_all(&[if header.color { COLOR_ANSI } else { COLOR_PLAIN }])?;
+		out.write_all(&[if header.compressed {
+			COMPRESSION_ZSTD
+		} else {
+			COMPRESSION_NONE
+		}])?;
+		out.write_all(&header.frame_count.to_le_bytes())?;
+
+		Ok(Self {
+			out,
+			color: header.color,
+			compressed: header.compressed,
+			level,
+			position: HEADER_LENGTH,
+			block: Vec::new(),
+			block_frames: 0,
+			previous: None,
+			repeat: None,
+			frame_index: Vec::new(),
+			block_index: Vec::new(),
+			chapters: Vec::new(),
+		})
+	}
+
+	// Chapters are written along with the index, so they can be set any time before finishing.
+	pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+		self.chapters = chapters;
+	}
+
+	pub fn write_frame(&mut self, frame: &[String], delay: Duration) -> Result<(), Error> {
+		let lines: Vec<String> = if self.color {
+			frame.to_vec()
+		} else {
+			frame.iter().map(|line| remove_ansi_escape_sequences(line)).collect()
+		};
+		let delay =
+			u32::try_from(delay.as_micros()).map_err(|_| too_large("Frame delay is too long for a .baa file"))?;
+
+		if self.previous.as_ref() == Some(&lines) {
+			return self.write_repeat(delay);
+		}
+		self.repeat = None;
+
+		let (record, is_key) = encode_record(self.previous.as_deref(), &lines, delay)?;
+		self.frame_index.push((
+			delay,
+			self.block_index.len() as u32,
+			self.block.len() as u32,
+			if is_key { INDEX_FLAG_KEY } else { 0 },
+		));
+		self.block.extend_from_slice(&record);
+		self.previous = Some(lines);
+		self.block_frames += 1;
+
+		if self.block_frames as usize == FRAMES_PER_BLOCK {
+			self.write_block()?;
+		}
+		Ok(())
+	}
+
+	// A frame identical to the one before it only extends how long that one is held. The first
+	// repeat adds a record, later ones add to its delay and count in place.
+	fn write_repeat(&mut self, delay: u32) -> Result<(), Error> {
+		if let Some(offset) = self.repeat {
+			let (total, _, _, _) = self.frame_index.last_mut().expect("a repeat follows a frame");
+			if let Some(sum) = total.checked_add(delay) {
+				*total = sum;
+				let count = self.block[offset + 5..offset + 9]
+					.try_into()
+					.expect("a repeat record is 9 bytes");
+				let count = u32::from_le_bytes(count) + 1;
+				self.block[offset + 1..offset + 5].copy_from_slice(&sum.to_le_bytes());
+				self.block[offset + 5..offset + 9].copy_from_slice(&count.to_le_bytes());
+				return Ok(());
+			}
+		}
+
+		let offset = self.block.len();
+		self.frame_index
+			.push((delay, self.block_index.len() as u32, offset as u32, 0));
+		self.block.push(FRAME_REPEAT);
+		self.block.extend_from_slice(&delay.to_le_bytes());
+		self.block.extend_from_slice(&1u32.to_le_bytes());
+		self.repeat = Some(offset);
+		self.block_frames += 1;
+
+		if self.block_frames as usize == FRAMES_PER_BLOCK {
+			self.write_block()?;
+		}
+		Ok(())
+	}
+
+	// Writes whatever is left along with the index, and returns the output and the number of frames.
+	pub fn finish(mut self) -> Result<(W, u32), Error> {
+		if self.block_frames > 0 {
+			self.write_block()?;
+		}
+		// A block without frames marks the end of them, for readers that aren't using the index.
+		self.out.write_all(&0u32.to_le_bytes())?;
+		self.out.write_all(&0u32.to_le_bytes())?;
+		let index_offset = self.position + 8;
+
+		for (delay, block, offset, flags) in &self.frame_index {
+			self.out.write_all(&delay.to_le_bytes())?;
+			self.out.write_all(&block.to_le_bytes())?;
+			self.out.write_all(&offset.to_le_bytes())?;
+			self.out.write_all(&[*flags])?;
+		}
+		for (offset, length, frame_count) in &self.block_index {
+			self.out.write_all(&offset.to_le_bytes())?;
+			self.out.write_all(&length.to_le_bytes())?;
+			self.out.write_all(&frame_count.to_le_bytes())?;
+		}
+
+		let chapters_offset = index_offset + self.frame_index.len() as u64 * 13 + self.block_index.len() as u64 * 16;
+		let chapter_count =
+			u32::try_from(self.chapters.len()).map_err(|_| too_large("Too many chapters for a .baa file"))?;
+		self.out.write_all(&chapter_count.to_le_bytes())?;
+		for chapter in &self.chapters {
+			let start = u64::try_from(chapter.start.as_micros())
+				.map_err(|_| too_large("Chapter starts too late for a .baa file"))?;
+			self.out.write_all(&start.to_le_bytes())?;
+			self.out.write_all(&encode_string(&chapter.title)?)?;
+		}
+
+		self.out.write_all(&index_offset.to_le_bytes())?;
+		self.out.write_all(&(self.block_index.len() as u32).to_le_bytes())?;
+		self.out.write_all(&chapters_offset.to_le_bytes())?;
+		self.out.write_all(INDEX_MAGIC)?;
+		self.out.flush()?;
+
+		let frame_count =
+			u32::try_from(self.frame_index.len()).map_err(|_| too_large("Too many frames for a .baa file"))?;
+		Ok((self.out, frame_count))
+	}
+
+	fn write_block(&mut self) -> Result<(), Error> {
+		let block = std::mem::take(&mut self.block);
+		let block = if self.compressed {
+			zstd::bulk::compress(&block, self.level)?
+		} else {
+			block
+		};
+		let length = u32::try_from(block.len()).map_err(|_| too_large("Frame block is too large for a .baa file"))?;
+
+		self.out.write_all(&self.block_frames.to_le_bytes())?;
+		self.out.write_all(&length.to_le_bytes())?;
+		self.block_index.push((self.position + 8, length, self.block_frames));
+		self.out.write_all(&block)?;
+		self.out.flush()?;
+
+		self.position += 8 + block.len() as u64;
+		self.block_frames = 0;
+		// Blocks start with a keyframe, so that each of them can be decoded on its own.
+		self.previous = None;
+		self.repeat = None;
+		Ok(())
+	}
+}
+
+// Writes a whole conversion at once. A level of 0 stores the frames uncompressed.
+pub fn write(
+	frames: &[Vec<String>],
+	delays: &[Duration],
+	chapters: &[Chapter],
+	color: bool,
+	level: i32,
+	out: impl Write,
+) -> Result<Header, Error> {
+	let (columns, rows) = export::grid_size(frames);
+	let total: f64 = delays.iter().map(Duration::as_secs_f64).sum();
+	let header = Header {
+		fps: if total > 0.0 {
+			(frames.len() as f64 / total) as f32
+		} else {
+			0.0
+		},
+		columns: columns
+			.try_into()
+			.map_err(|_| too_large("Frames are too wide for a .baa file"))?,
+		rows: rows
+			.try_into()
+			.map_err(|_| too_large("Frames are too tall for a .baa file"))?,
+		color,
+		compressed: level > 0,
+		// Repeated frames are merged, so how many are stored is only known at the end.
+		frame_count: 0,
+	};
+
+	let mut writer = BaaWriter::new(BufWriter::new(out), &header, level)?;
+	writer.set_chapters(chapters.to_vec());
+	for (frame, delay) in frames.iter().zip(delays) {
+		writer.write_frame(frame, *delay)?;
+	}
+	let (_, frame_count) = writer.finish()?;
+	Ok(Header { frame_count, ..header })
+}
+
+// Converts a source's frames and writes each as soon as it's converted, so that only one is held at
+// a time however long the source is. The header goes by the first frame, and is returned along with
+// how many frames were converted.
+pub fn write_source(
+	source: &mut dyn FrameSource,
+	converter: &Converter,
+	chapters: Vec<Chapter>,
+	color: bool,
+	level: i32,
+	out: impl Write,
+) -> Result<(Header, usize), Error> {
+	let first = source.next_frame()?.ok_or(Error::NoFrames)?;
+	let first_lines = converter.convert_picture(first.picture);
+	let (columns, rows) = export::grid_size(std::slice::from_ref(&first_lines));
+	let header = Header {
+		fps: source.frame_rate() as f32,
+		columns: columns
+			.try_into()
+			.map_err(|_| too_large("Frames are too wide for a .baa file"))?,
+		rows: rows
+			.try_into()
+			.map_err(|_| too_large("Frames are too tall for a .baa file"))?,
+		color,
+		compressed: level > 0,
+		frame_count: 0,
+	};
+
+	let mut writer = BaaWriter::new(BufWriter::new(out), &header, level)?;
+	writer.set_chapters(chapters);
+	writer.write_frame(&first_lines, first.delay)?;
+	let mut converted = 1;
+	while let Some(frame) = source.next_frame()? {
+		writer.write_frame(&converter.convert_picture(frame.picture), frame.delay)?;
+		converted += 1;
+	}
+	let (_, frame_count) = writer.finish()?;
+	Ok((Header { frame_count, ..header }, converted))
+}
+
+// One frame record as it's stored in a block, and whether it's a keyframe. Frames are stored as the
+// cells that changed since the one before them when that takes less room.
+pub fn encode_record(previous: Option<&[String]>, lines: &[String], delay: u32) -> Result<(Vec<u8>, bool), Error> {
+	let key = encode_key(lines)?;
+	let delta = match previous {
+		Some(previous) if previous.len() == lines.len() => Some(encode_delta(previous, lines)?),
+		_ => None,
+	};
+	let (kind, data) = match delta {
+		Some(delta) if delta.len() < key.len() => (FRAME_DELTA, delta),
+		_ => (FRAME_KEY, key),
+	};
+	let mut record = vec![kind];
+	record.extend_from_slice(&delay.to_le_bytes());
+	record.extend_from_slice(&data);
+	Ok((record, kind == FRAME_KEY))
+}
+
+fn encode_key(lines: &[String]) -> Result<Vec<u8>, Error> {
+	encode_string(&lines.join("\n"))
+}
+
+fn encode_string(text: &str) -> Result<Vec<u8>, Error> {
+	let length = u32::try_from(text.len()).map_err(|_| too_large("Frame is too large for a .baa file"))?;
+
+	let mut encoded = length.to_le_bytes().to_vec();
+	encoded.extend_from_slice(text.as_bytes());
+	Ok(encoded)
+}
+
+// Each change replaces a run of cells on one row. Rows whose width changed are replaced whole.
+fn encode_delta(previous: &[String], lines: &[String]) -> Result<Vec<u8>, Error> {
+	let mut changes = Vec::new();
+	for (row, (old, new)) in previous.iter().zip(lines).enumerate() {
+		if old == new {
+			continue;
+		}
+		let (old_cells, new_cells) = (cells(old), cells(new));
+		if old_cells.len() != new_cells.len() {
+			changes.push((row, 0, old_cells.len(), new.clone()));
+			continue;
+		}
+
+		let mut column = 0;
+		while column < new_cells.len() {
+			if old_cells[column] == new_cells[column] {
+				column += 1;
+				continue;
+			}
+			let start = column;
+			while column < new_cells.len() && old_cells[column] != new_cells[column] {
+				column += 1;
+			}
+			changes.push((row, start, column - start, new_cells[start..column].concat()));
+		}
+	}
+
+	let overflow = || too_large("Frame is too large for a .baa file");
+	let mut encoded = u32::try_from(changes.len())
+		.map_err(|_| overflow())?
+		.to_le_bytes()
+		.to_vec();
+	for (row, column, replaced, text) in changes {
+		encoded.extend_from_slice(&u16::try_from(row).map_err(|_| overflow())?.to_le_bytes());
+		encoded.extend_from_slice(&u16::try_from(column).map_err(|_| overflow())?.to_le_bytes());
+		encoded.extend_from_slice(&u16::try_from(replaced).map_err(|_| overflow())?.to_le_bytes());
+		encoded.extend_from_slice(&u32::try_from(text.len()).map_err(|_| overflow())?.to_le_bytes());
+		encoded.extend_from_slice(text.as_bytes());
+	}
+	Ok(encoded)
+}
+
+// What doesn't fit in the fields of the format.
+fn too_large(message: &str) -> Error {
+	Error::EncodeError(message.to_string())
+}
@@ -0,0 +1,151 @@
+use crate::convert::Converter;
+use crate::source::{Chapter, FrameSource};
+use crate::{Error, baa};
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_MAX_SIZE: u64 = 1 << 30;
+
+// A conversion stored in the cache, named after a hash of the source file's contents and of the
+// settings it was converted with. Next to it a .meta file records the source path and settings.
+pub struct Entry {
+	path: PathBuf,
+	source: PathBuf,
+	settings: String,
+}
+
+impl Entry {
+	// Returns None for inputs that can't be cached, like streams and directories.
+	pub fn find(dir: &Path, input: &str, settings: &str) -> io::Result<Option<Self>> {
+		if !Path::new(input).is_file() {
+			return Ok(None);
+		}
+
+		let mut hasher = blake3::Hasher::new();
+		io::copy(&mut File::open(input)?, &mut hasher)?;
+		hasher.update(settings.as_bytes());
+		Ok(Some(Self {
+			path: dir.join(format!("{}.baa", hasher.finalize().to_hex())),
+			source: fs::canonicalize(input)?,
+			settings: settings.to_string(),
+		}))
+	}
+
+	pub fn load(&self) -> Option<baa::Mapped> {
+		let mapped = baa::Mapped::open(&self.path).ok()?;
+		// Eviction goes by modification time, so a hit marks the entry as recently used.
+		let _ = File::options()
+			.write(true)
+			.open(&self.path)
+			.and_then(|file| file.set_modified(SystemTime::now()));
+		Some(mapped)
+	}
+
+	// Converts straight into the cache, one frame at a time, and opens the result like a hit.
+	pub fn convert(
+		&self,
+		source: &mut dyn FrameSource,
+		converter: &Converter,
+		chapters: Vec<Chapter>,
+	) -> Result<baa::Mapped, Error> {
+		let dir = self
+			.path
+			.parent()
+			.expect("cache entries are inside the cache directory");
+		fs::create_dir_all(dir)?;
+
+		// Written under another name first, so that an interrupted run doesn't leave half a file behind.
+		let partial = self.path.with_extension("partial");
+		let color = converter.color_mode() != "plain";
+		let written = File::create(&partial)
+			.map_err(Error::from)
+			.and_then(|out| baa::write_source(source, converter, chapters, color, baa::DEFAULT_LEVEL, out));
+		if let Err(error) = written {
+			let _ = fs::remove_file(&partial);
+			return Err(error);
+		}
+		fs::write(
+			self.path.with_extension("meta"),
+			format!("{}\n{}\n", self.source.display(), self.settings),
+		)?;
+		fs::rename(partial, &self.path)?;
+		baa::Mapped::open(&self.path)
+	}
+}
+
+pub struct Listing {
+	pub key: String,
+	pub path: PathBuf,
+	pub size: u64,
+	pub age: Duration,
+	pub source: String,
+	pub settings: String,
+}
+
+// Every cached conversion, most recently used first.
+pub fn list(dir: &Path) -> io::Result<Vec<Listing>> {
+	let read_dir = match fs::read_dir(dir) {
+		Ok(read_dir) => read_dir,
+		Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(error) => return Err(error),
+	};
+
+	let mut listings = Vec::new();
+	for dir_entry in read_dir {
+		let path = dir_entry?.path();
+		if path.extension().is_none_or(|extension| extension != "baa") {
+			continue;
+		}
+		let metadata = fs::metadata(&path)?;
+		let meta = fs::read_to_string(path.with_extension("meta")).unwrap_or_default();
+		let mut meta = meta.lines();
+
+		listings.push(Listing {
+			key: path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+			size: metadata.len(),
+			age: metadata.modified()?.elapsed().unwrap_or_default(),
+			source: meta.next().unwrap_or("?").to_string(),
+			settings: meta.next().unwrap_or("?").to_string(),
+			path,
+		});
+	}
+	listings.sort_by_key(|listing| listing.age);
+	Ok(listings)
+}
+
+pub fn remove(listing: &Listing) -> io::Result<()> {
+	fs::remove_file(&listing.path)?;
+	match fs::remove_file(listing.path.with_extension("meta")) {
+		Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+		_ => Ok(()),
+	}
+}
+
+// Removes the least recently used conversions until the cache fits in `max_size` bytes, returning
+// how many were removed.
+pub fn evict(dir: &Path, max_size: u64) -> io::Result<usize> {
+	let listings = list(dir)?;
+	let mut total: u64 = listings.iter().map(|listing| listing.size).sum();
+	let mut removed = 0;
+	for listing in listings.iter().rev() {
+		if total <= max_size {
+			break;
+		}
+		remove(listing)?;
+		total -= listing.size;
+		removed += 1;
+	}
+	Ok(removed)
+}
+
+// Where the cache goes unless --cache-dir says otherwise.
+pub fn default_dir() -> Option<PathBuf> {
+	let base = env::var_os("XDG_CACHE_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+	Some(base.join("bad_ascii"))
+}
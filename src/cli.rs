@@ -0,0 +1,505 @@
+use bad_ascii::convert::ColorMode;
+use bad_ascii::render::Graphics;
+use bad_ascii::source::{Backend, Hwaccel, PixelFormat, Transition};
+use clap::{ArgGroup, Parser, Subcommand};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const INPUT: &str = "input.mp4";
+
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Args {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+
+	// Without a subcommand the arguments are taken as those of `play`.
+	#[command(flatten)]
+	pub play: PlayArgs,
+}
+
+// Where frames come from and how they are decoded, shared by every subcommand that converts.
+#[derive(clap::Args, Clone)]
+pub struct SourceArgs {
+	/// Video, image, image directory, .baa file or URL to play
+	#[arg(default_value = INPUT)]
+	pub input: String,
+
+	/// Resolve the input through yt-dlp even when its site isn't one known to need it
+	#[arg(long)]
+	pub ytdl: bool,
+
+	/// yt-dlp format to ask for when resolving the input
+	#[arg(long, value_name = "FORMAT", default_value = "bv*[height<=480]/b[height<=480]/bv*/b")]
+	pub ytdl_format: String,
+
+	/// How long each image is shown when the input is a directory
+	#[arg(long, value_name = "SECONDS", default_value_t = 5.0, value_parser = parse_seconds)]
+	pub slide_duration: f64,
+
+	/// How one image of a directory gives way to the next
+	#[arg(long, value_enum, default_value_t = Transition::None)]
+	pub transition: Transition,
+
+	/// What decodes the input; native needs no ffmpeg but only reads Y4M and images
+	#[arg(long, value_enum, default_value_t = Backend::default())]
+	pub backend: Backend,
+
+	/// Decode on the GPU through this ffmpeg hardware acceleration
+	#[arg(long, value_enum)]
+	pub hwaccel: Option<Hwaccel>,
+
+	/// ffmpeg filters to run on the video before it is converted, like crop=iw/2:ih
+	#[arg(long, value_name = "FILTERS")]
+	pub vf: Option<String>,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct CacheOptions {
+	/// Convert from scratch instead of reusing or storing a cached conversion
+	#[arg(long)]
+	pub no_cache: bool,
+
+	/// Size the conversion cache is trimmed to after adding to it, like 500M or 2G
+	#[arg(long, value_name = "SIZE", default_value_t = bad_ascii::cache::DEFAULT_MAX_SIZE, value_parser = parse_bytes)]
+	pub cache_max_size: u64,
+
+	/// Where to keep cached conversions [default: $XDG_CACHE_HOME/bad_ascii]
+	#[arg(long, value_name = "DIR")]
+	pub cache_dir: Option<PathBuf>,
+}
+
+impl CacheOptions {
+	pub fn dir(&self) -> Option<PathBuf> {
+		self.cache_dir.clone().or_else(bad_ascii::cache::default_dir)
+	}
+}
+
+// How pictures are turned into characters.
+#[derive(clap::Args, Clone)]
+pub struct StyleArgs {
+	/// Characters to draw with, from the densest to the lightest
+	#[arg(long, value_name = "CHARS")]
+	pub charset: Option<String>,
+
+	/// Theme setting the charset, OSD colors, progress bar and palette; built in are default, matrix,
+	/// amber and mono, and more can be added under [themes.NAME] in config.toml
+	#[arg(long, value_name = "NAME", default_value = "default")]
+	pub theme: String,
+
+	/// Whether to color the output; auto leaves it to the terminal and environment
+	#[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+	pub color_mode: ColorMode,
+}
+
+#[derive(clap::Args)]
+pub struct PlayArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	#[command(flatten)]
+	pub cache: CacheOptions,
+
+	/// Play from a camera instead, like /dev/video0 or the name of a dshow device
+	#[arg(long, value_name = "DEVICE")]
+	pub capture: Option<String>,
+
+	/// Play what is on the screen instead
+	#[arg(long, conflicts_with = "capture")]
+	pub capture_screen: bool,
+
+	/// Play raw frames of --size and --pix-fmt read from stdin instead
+	#[arg(long, requires = "size", conflicts_with_all = ["capture", "capture_screen"])]
+	pub stdin: bool,
+
+	/// Size of the frames read with --stdin
+	#[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+	pub size: Option<(u32, u32)>,
+
+	/// Pixel layout of the frames read with --stdin
+	#[arg(long, value_enum, default_value_t = PixelFormat::Rgb24)]
+	pub pix_fmt: PixelFormat,
+
+	/// LRC file to show under the video, highlighting each word as it is sung
+	#[arg(long, value_name = "FILE")]
+	pub lyrics: Option<PathBuf>,
+
+	/// Close a still image after this long instead of waiting for a key
+	#[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+	pub hold: Option<f64>,
+
+	/// Directory that frames saved with the `s` key are written to
+	#[arg(long, value_name = "DIR", default_value = ".")]
+	pub screenshot_dir: PathBuf,
+
+	/// Keep the ANSI color codes in saved frames
+	#[arg(long)]
+	pub screenshot_color: bool,
+
+	/// Save everything drawn while playing, overlays and seeks included, as an asciinema recording or
+	/// a .baa file, going by the extension
+	#[arg(long, value_name = "FILE")]
+	pub record: Option<PathBuf>,
+
+	/// Print the keys used while playing, after config.toml's [keys], and exit
+	#[arg(long)]
+	pub dump_keys: bool,
+
+	/// Draw at most this many frames a second, skipping the rest
+	#[arg(long, value_name = "FPS", value_parser = parse_fps)]
+	pub max_fps: Option<f64>,
+
+	/// Draw frames as pictures with the sixel or kitty graphics protocol instead of text
+	#[arg(long, value_enum)]
+	pub graphics: Option<Graphics>,
+
+	/// Accept newline-delimited JSON commands like {"command": ["seek", 10]} on this Unix socket
+	#[cfg(unix)]
+	#[arg(long, value_name = "PATH")]
+	pub ipc_socket: Option<PathBuf>,
+
+	/// Read commands like `pause`, `seek +10` or `quit` a line at a time from this named pipe
+	#[cfg(unix)]
+	#[arg(long, value_name = "PATH")]
+	pub control_fifo: Option<PathBuf>,
+
+	/// Take speed, brightness, seek and pause changes as OSC messages under /bad_ascii/ on this UDP port
+	#[arg(long, value_name = "PORT")]
+	pub osc_port: Option<u16>,
+
+	/// Address to take OSC messages on; anyone who can reach it controls playback, so only set this
+	/// to a network interface for controllers on other machines
+	#[arg(long, value_name = "ADDR", default_value = "127.0.0.1", requires = "osc_port")]
+	pub osc_bind: IpAddr,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Play in the terminal; also what runs when no subcommand is given
+	Play(PlayArgs),
+	/// Convert once into a .baa file that plays back without decoding again
+	Convert(ConvertArgs),
+	/// Write the converted frames out as text files, GIF, video, HTML, SVG, asciicast or ANSI art
+	Export(ExportArgs),
+	/// Show what the input holds and how it would be converted, without playing it
+	Info(InfoArgs),
+	/// Sample frames evenly across the whole input and tile them into a contact sheet
+	Thumbnails(ThumbnailsArgs),
+	/// Inspect and prune cached conversions
+	Cache(CacheArgs),
+	/// Decode and convert without drawing anything, and report how fast each step went
+	Bench(BenchArgs),
+	/// Convert once and play to everyone who connects over the network
+	Serve(ServeArgs),
+	/// Play what a `serve --stream` server sends, reconnecting whenever the connection drops
+	Connect(ConnectArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ConvertArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	/// Where to write the .baa file, or - to stream it to stdout
+	#[arg(short, long, value_name = "FILE.baa")]
+	pub output: PathBuf,
+
+	/// Strip the ANSI color codes from the stored frames
+	#[arg(long)]
+	pub plain: bool,
+
+	/// zstd compression level for the stored frames, or 0 to store them uncompressed
+	#[arg(long, default_value_t = bad_ascii::baa::DEFAULT_LEVEL, value_parser = clap::value_parser!(i32).range(0..=22))]
+	pub level: i32,
+}
+
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("outputs").required(true).multiple(true)))]
+pub struct ExportArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	#[command(flatten)]
+	pub cache: CacheOptions,
+
+	/// Write every frame to its own text file in DIR, plus a manifest.json describing them
+	#[arg(short, long, value_name = "DIR", group = "outputs")]
+	pub output: Option<PathBuf>,
+
+	/// Keep the ANSI color codes in the text files instead of writing plain text
+	#[arg(long, requires = "output")]
+	pub color: bool,
+
+	/// Write an animated GIF of the frames drawn as text
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub gif: Option<PathBuf>,
+
+	/// Write a video of the frames drawn as text, with the input's audio, through ffmpeg
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub video: Option<PathBuf>,
+
+	/// Write a standalone HTML page that plays the frames
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub html: Option<PathBuf>,
+
+	/// Write an animated SVG of the frames
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub svg: Option<PathBuf>,
+
+	/// Write an asciinema recording of the frames
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub cast: Option<PathBuf>,
+
+	/// Write frames as .ans art with a SAUCE record, a numbered file for each when there are several
+	#[arg(long, value_name = "FILE", group = "outputs")]
+	pub ans: Option<PathBuf>,
+
+	/// Frame to write with --ans, counting from 1; can be repeated, defaults to every frame
+	#[arg(long = "frame", value_name = "N", requires = "ans")]
+	pub frames: Vec<usize>,
+}
+
+#[derive(clap::Args)]
+pub struct InfoArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+}
+
+#[derive(clap::Args)]
+pub struct ThumbnailsArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	/// Where to write the sheet, rendered as an image when it ends in .png [default: stdout]
+	#[arg(short, long, value_name = "FILE")]
+	pub output: Option<PathBuf>,
+
+	/// Number of thumbnails to sample
+	#[arg(short = 'n', long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+	pub count: u32,
+
+	/// Thumbnails per row of the sheet
+	#[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+	pub per_row: u32,
+
+	/// Width of each thumbnail in characters
+	#[arg(long, default_value_t = 40, value_parser = clap::value_parser!(u32).range(1..))]
+	pub width: u32,
+
+	/// Keep the ANSI color codes instead of writing plain text
+	#[arg(long)]
+	pub color: bool,
+}
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	/// How much of the input to decode and convert
+	#[arg(long, value_name = "SECONDS", default_value_t = 10.0, value_parser = parse_seconds)]
+	pub seconds: f64,
+
+	/// Width to convert to in characters [default: 160]
+	#[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+	pub width: Option<u32>,
+}
+
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("listeners").required(true).multiple(true)))]
+#[command(group(ArgGroup::new("web").multiple(true)))]
+pub struct ServeArgs {
+	#[command(flatten)]
+	pub source: SourceArgs,
+
+	#[command(flatten)]
+	pub style: StyleArgs,
+
+	#[command(flatten)]
+	pub cache: CacheOptions,
+
+	/// Accept telnet and plain TCP clients on this address, like :2323 or 127.0.0.1:2323
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address, group = "listeners")]
+	pub telnet: Option<SocketAddr>,
+
+	/// Accept SSH clients on this address, whatever user or key they log in with
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address, group = "listeners")]
+	pub ssh: Option<SocketAddr>,
+
+	/// OpenSSH private key the SSH server identifies itself with [default: a new one every start]
+	#[arg(long, value_name = "FILE", requires = "ssh")]
+	pub ssh_host_key: Option<PathBuf>,
+
+	/// Serve a browser player on this address, and frames over WebSockets on its /ws path
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address, groups = ["listeners", "web"])]
+	pub ws: Option<SocketAddr>,
+
+	/// Stream frames over HTTP on this address, to `curl http://HOST:PORT/play`
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address, groups = ["listeners", "web"])]
+	pub http: Option<SocketAddr>,
+
+	/// Certificate chain, in PEM, to serve --ws and --http over TLS with
+	#[arg(long, value_name = "FILE", requires_all = ["key", "web"])]
+	pub cert: Option<PathBuf>,
+
+	/// Private key of the --cert certificate, in PEM
+	#[arg(long, value_name = "FILE", requires = "cert")]
+	pub key: Option<PathBuf>,
+
+	/// Stream frames to `bad_ascii connect` on this address, each client from the start at its own
+	/// pace
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address, group = "listeners")]
+	pub stream: Option<SocketAddr>,
+
+	/// Serve Prometheus metrics on /metrics at this address: viewers connected, frames and bytes
+	/// sent, frames dropped and how long conversions took
+	#[arg(long, value_name = "[HOST]:PORT", value_parser = parse_address)]
+	pub metrics: Option<SocketAddr>,
+
+	/// Turn connections away once this many are open, across all listeners
+	#[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+	pub max_clients: Option<u32>,
+
+	/// Turn connections away from an address that already has this many open
+	#[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+	pub max_clients_per_address: Option<u32>,
+
+	/// Send each viewer at most this many KiB per second. Frames that don't fit are shown late, or
+	/// skipped in a broadcast
+	#[arg(long, value_name = "KIB", value_parser = clap::value_parser!(u32).range(1..))]
+	pub max_bandwidth: Option<u32>,
+
+	/// Play one shared stream that viewers join in progress, rather than from the start for each at
+	/// its own pace. Viewers with the same terminal size are sent the same drawn frames, and can't
+	/// pause
+	#[arg(long)]
+	pub broadcast: bool,
+
+	/// Width to convert to in characters, for clients that don't report their terminal size
+	#[arg(long, default_value_t = 80, value_parser = clap::value_parser!(u32).range(1..))]
+	pub width: u32,
+}
+
+#[derive(clap::Args)]
+pub struct ConnectArgs {
+	/// Server to connect to, like example.com:2324
+	#[arg(value_name = "HOST:PORT")]
+	pub address: String,
+
+	/// Theme for the OSD colors; the frames come colored by the server
+	#[arg(long, value_name = "NAME", default_value = "default")]
+	pub theme: String,
+
+	/// Directory that frames saved with the `s` key are written to
+	#[arg(long, value_name = "DIR", default_value = ".")]
+	pub screenshot_dir: PathBuf,
+
+	/// Keep the ANSI color codes in saved frames
+	#[arg(long)]
+	pub screenshot_color: bool,
+
+	/// Draw at most this many frames a second, skipping the rest
+	#[arg(long, value_name = "FPS", value_parser = parse_fps)]
+	pub max_fps: Option<f64>,
+}
+
+#[derive(clap::Args)]
+pub struct CacheArgs {
+	#[command(subcommand)]
+	pub action: CacheAction,
+
+	/// Where cached conversions are kept [default: $XDG_CACHE_HOME/bad_ascii]
+	#[arg(long, value_name = "DIR", global = true)]
+	pub cache_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+	/// List cached conversions, most recently used first
+	Ls,
+	/// Show where the cache is and how big it is, or the details of one conversion
+	Info {
+		/// Start of the key of a cached conversion, as shown by `cache ls`
+		key: Option<String>,
+	},
+	/// Remove cached conversions
+	Clear {
+		/// Only remove the least recently used conversions until the cache fits in SIZE
+		#[arg(long, value_name = "SIZE", value_parser = parse_bytes)]
+		max_size: Option<u64>,
+	},
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+	let (width, height) = size.split_once('x').ok_or("expected WIDTHxHEIGHT")?;
+	let width = width
+		.parse::<u32>()
+		.ok()
+		.filter(|&width| width > 0)
+		.ok_or_else(|| format!("invalid width `{}`", width))?;
+	let height = height
+		.parse::<u32>()
+		.ok()
+		.filter(|&height| height > 0)
+		.ok_or_else(|| format!("invalid height `{}`", height))?;
+	Ok((width, height))
+}
+
+fn parse_fps(fps: &str) -> Result<f64, String> {
+	match fps.parse() {
+		Ok(fps) if fps > 0.0 => Ok(fps),
+		_ => Err(format!("invalid frame rate `{}`", fps)),
+	}
+}
+
+// Lengths of time that go on to be a Duration, which can't be negative or endless.
+fn parse_seconds(seconds: &str) -> Result<f64, String> {
+	match seconds.parse() {
+		Ok(value) if value > 0.0 && Duration::try_from_secs_f64(value).is_ok() => Ok(value),
+		_ => Err(format!("invalid number of seconds `{}`", seconds)),
+	}
+}
+
+// A missing host means every interface, as in `:2323`.
+fn parse_address(address: &str) -> Result<SocketAddr, String> {
+	let full = match address.strip_prefix(':') {
+		Some(port) => format!("0.0.0.0:{}", port),
+		None => address.to_string(),
+	};
+	full.to_socket_addrs()
+		.ok()
+		.and_then(|mut addresses| addresses.next())
+		.ok_or_else(|| format!("invalid address `{}`, expected [HOST]:PORT", address))
+}
+
+fn parse_bytes(size: &str) -> Result<u64, String> {
+	let (number, multiplier) = match size.char_indices().last() {
+		Some((index, 'K' | 'k')) => (&size[..index], 1 << 10),
+		Some((index, 'M' | 'm')) => (&size[..index], 1 << 20),
+		Some((index, 'G' | 'g')) => (&size[..index], 1 << 30),
+		_ => (size, 1),
+	};
+	let number: u64 = number.parse().map_err(|_| format!("invalid size `{}`", size))?;
+	number
+		.checked_mul(multiplier)
+		.ok_or_else(|| format!("size `{}` is too large", size))
+}
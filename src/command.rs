@@ -0,0 +1,8 @@
+pub mod bench;
+pub mod cache;
+pub mod convert;
+pub mod export;
+pub mod info;
+pub mod play;
+pub mod serve;
+pub mod thumbnails;
@@ -0,0 +1,74 @@
+use crate::cli::BenchArgs;
+use crate::config::Config;
+use crate::pipeline;
+use bad_ascii::source::{self, DecodeOptions};
+use bad_ascii::{DEFAULT_WIDTH, Error};
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+
+pub fn run(args: &BenchArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	let target_size = match args.width {
+		Some(width) => NonZeroU32::new(width).expect("width is at least 1"),
+		None => DEFAULT_WIDTH,
+	};
+	let decode = DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(target_size.get()),
+		filters: args.source.vf.clone(),
+		limit: None,
+	};
+	let converter = pipeline::converter(target_size, &args.style, config.theme(&args.style.theme)?);
+	let limit = Duration::from_secs_f64(args.seconds);
+
+	// Opening is timed along with decoding, since spawning and probing is part of what a user waits for.
+	let started = Instant::now();
+	let mut source = source::open(&input, &pipeline::source_options(&args.source, decode))?;
+	let mut decoding = started.elapsed();
+	let mut converting = Duration::ZERO;
+	let mut frames = 0;
+	let mut media_time = Duration::ZERO;
+
+	while media_time < limit {
+		let decode_start = Instant::now();
+		let Some(frame) = source.next_frame()? else {
+			break;
+		};
+		let convert_start = Instant::now();
+		decoding += convert_start - decode_start;
+
+		let lines = converter.convert_picture(frame.picture);
+		converting += convert_start.elapsed();
+		std::hint::black_box(lines);
+
+		frames += 1;
+		media_time += frame.delay;
+	}
+	if frames == 0 {
+		return Err(Error::NoFrames);
+	}
+
+	println!(
+		"{} frames, {:.2}s of {} at {} columns",
+		frames,
+		media_time.as_secs_f64(),
+		input,
+		target_size
+	);
+	println!("Decode:        {}", rate(frames, decoding));
+	println!("Convert:       {}", rate(frames, converting));
+	println!("Total:         {}", rate(frames, decoding + converting));
+	println!(
+		"Realtime:      {:.2}x",
+		media_time.as_secs_f64() / (decoding + converting).as_secs_f64()
+	);
+	Ok(())
+}
+
+fn rate(frames: u32, elapsed: Duration) -> String {
+	format!(
+		"{:>9.1} fps  ({:.3}s)",
+		frames as f64 / elapsed.as_secs_f64(),
+		elapsed.as_secs_f64()
+	)
+}
@@ -0,0 +1,86 @@
+use crate::cli::{CacheAction, CacheArgs};
+use bad_ascii::{Error, baa, cache};
+use std::time::Duration;
+
+pub fn run(args: &CacheArgs) -> Result<(), Error> {
+	let dir =
+		args.cache_dir.clone().or_else(cache::default_dir).ok_or_else(|| {
+			Error::BadOptions("No cache directory; neither XDG_CACHE_HOME nor HOME is set".to_string())
+		})?;
+	match &args.action {
+		CacheAction::Ls => {
+			for listing in cache::list(&dir)? {
+				println!(
+					"{}  {:>10}  {:>4}  {}",
+					&listing.key[..listing.key.len().min(12)],
+					format_size(listing.size),
+					format_age(listing.age),
+					listing.source
+				);
+			}
+		},
+		CacheAction::Info { key: None } => {
+			let listings = cache::list(&dir)?;
+			println!("Directory:     {}", dir.display());
+			println!("Conversions:   {}", listings.len());
+			println!(
+				"Size:          {}",
+				format_size(listings.iter().map(|listing| listing.size).sum())
+			);
+		},
+		CacheAction::Info { key: Some(key) } => {
+			let listings = cache::list(&dir)?;
+			let mut matches = listings.iter().filter(|listing| listing.key.starts_with(key.as_str()));
+			let listing = matches
+				.next()
+				.ok_or_else(|| Error::BadOptions(format!("No cached conversion matches {}", key)))?;
+			if matches.next().is_some() {
+				return Err(Error::BadOptions(format!(
+					"{} matches more than one cached conversion",
+					key
+				)));
+			}
+			let mapped = baa::Mapped::open(&listing.path)?;
+			let header = mapped.header();
+
+			println!("Key:           {}", listing.key);
+			println!("Source:        {}", listing.source);
+			println!("Settings:      {}", listing.settings);
+			println!("Size:          {}", format_size(listing.size));
+			println!("Last used:     {} ago", format_age(listing.age));
+			println!("Frames:        {} at {:.2} fps", header.frame_count, header.fps);
+			println!("Dimensions:    {}x{}", header.columns, header.rows);
+			println!("Color:         {}", if header.color { "yes" } else { "no" });
+		},
+		CacheAction::Clear { max_size } => {
+			let removed = match max_size {
+				Some(max_size) => cache::evict(&dir, *max_size)?,
+				None => {
+					let listings = cache::list(&dir)?;
+					listings.iter().try_for_each(cache::remove)?;
+					listings.len()
+				},
+			};
+			println!("Removed {} cached conversions", removed);
+		},
+	}
+	Ok(())
+}
+
+pub(super) fn format_size(bytes: u64) -> String {
+	match bytes {
+		bytes if bytes >= 1 << 30 => format!("{:.1} GiB", bytes as f64 / (1 << 30) as f64),
+		bytes if bytes >= 1 << 20 => format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64),
+		bytes if bytes >= 1 << 10 => format!("{:.1} KiB", bytes as f64 / (1 << 10) as f64),
+		bytes => format!("{} B", bytes),
+	}
+}
+
+fn format_age(age: Duration) -> String {
+	match age.as_secs() {
+		seconds if seconds >= 86400 => format!("{}d", seconds / 86400),
+		seconds if seconds >= 3600 => format!("{}h", seconds / 3600),
+		seconds if seconds >= 60 => format!("{}m", seconds / 60),
+		seconds => format!("{}s", seconds),
+	}
+}
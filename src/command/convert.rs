@@ -0,0 +1,59 @@
+use crate::cli::ConvertArgs;
+use crate::config::Config;
+use crate::pipeline;
+use bad_ascii::source::{self, DecodeOptions};
+use bad_ascii::{DEFAULT_WIDTH, Error, baa, probe};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn run(args: &ConvertArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	let target_size = DEFAULT_WIDTH;
+	let converter = pipeline::converter(target_size, &args.style, config.theme(&args.style.theme)?);
+	let decode = DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(target_size.get()),
+		filters: args.source.vf.clone(),
+		// A .baa file is played later in place of the input, so it holds all of it.
+		limit: None,
+	};
+	let mut source = source::open(&input, &pipeline::source_options(&args.source, decode))?;
+
+	let to_stdout = args.output == Path::new("-");
+	let out: Box<dyn Write> = if to_stdout {
+		Box::new(io::stdout().lock())
+	} else {
+		Box::new(File::create(&args.output)?)
+	};
+	// Frames are written as they are converted, so the header describes the first one.
+	let (header, converted) = baa::write_source(
+		source.as_mut(),
+		&converter,
+		probe::probe_chapters(&input),
+		!args.plain,
+		args.level,
+		out,
+	)?;
+
+	let mut summary = format!(
+		"Converted {} {} frames of {}x{} at {:.2} fps to {}",
+		converted,
+		if header.color { "color" } else { "plain" },
+		header.columns,
+		header.rows,
+		header.fps,
+		args.output.display()
+	);
+	// Runs of identical frames are each stored once, so fewer records than frames means some merged.
+	if (header.frame_count as usize) < converted {
+		summary += &format!(" ({} repeated frames merged)", converted - header.frame_count as usize);
+	}
+	// The frames themselves went to stdout.
+	if to_stdout {
+		eprintln!("{}", summary);
+	} else {
+		println!("{}", summary);
+	}
+	Ok(())
+}
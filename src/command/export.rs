@@ -0,0 +1,60 @@
+use crate::cli::ExportArgs;
+use crate::config::Config;
+use crate::pipeline;
+use bad_ascii::source::DecodeOptions;
+use bad_ascii::{DEFAULT_WIDTH, Error, baa, export};
+use std::path::Path;
+
+pub fn run(args: &ExportArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	// Exports aren't shown in this terminal, so they are converted at the full size.
+	let target_size = DEFAULT_WIDTH;
+	let decode = DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(target_size.get()),
+		filters: args.source.vf.clone(),
+		// Unlike playing, exports hold the whole input.
+		limit: None,
+	};
+	let (frames, _) = pipeline::load(
+		&input,
+		&args.source,
+		&args.cache,
+		&pipeline::converter(target_size, &args.style, config.theme(&args.style.theme)?),
+		decode,
+	)?;
+	let (ascii_frames, frame_delays) = frames.into_converted()?;
+
+	if let Some(dir) = &args.output {
+		export::write_text(&ascii_frames, &frame_delays, dir, args.color)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), dir.display());
+	}
+	if let Some(path) = &args.gif {
+		export::write_gif(&ascii_frames, &frame_delays, path)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), path.display());
+	}
+	if let Some(path) = &args.html {
+		export::write_html(&ascii_frames, &frame_delays, path)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), path.display());
+	}
+	if let Some(path) = &args.svg {
+		export::write_svg(&ascii_frames, &frame_delays, path)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), path.display());
+	}
+	if let Some(path) = &args.cast {
+		export::write_cast(&ascii_frames, &frame_delays, path)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), path.display());
+	}
+	if let Some(path) = &args.ans {
+		let written = export::write_ans(&ascii_frames, &args.frames, path)?;
+		println!("Exported {} frames to {}", written, path.display());
+	}
+	if let Some(path) = &args.video {
+		// Slideshows and animated images have no audio track to carry over, and ffmpeg can't read
+		// .baa files at all.
+		let audio_source = Some(input.as_str()).filter(|input| !Path::new(input).is_dir() && !baa::is_container(input));
+		export::write_video(&ascii_frames, &frame_delays, audio_source, path)?;
+		println!("Exported {} frames to {}", ascii_frames.len(), path.display());
+	}
+	Ok(())
+}
@@ -0,0 +1,128 @@
+use super::cache::format_size;
+use super::play::target_size;
+use crate::DURATION;
+use crate::cli::InfoArgs;
+use crate::config::Config;
+use crate::pipeline;
+use bad_ascii::source::{self, DecodeOptions};
+use bad_ascii::{Error, baa, export, probe};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+pub fn run(args: &InfoArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	if baa::is_container(&input) {
+		return container_info(&input);
+	}
+
+	println!("Input:         {}", input);
+	let media = probe::probe(&input);
+	match &media {
+		Ok(media) => {
+			if let Some(duration) = media.duration {
+				println!("Duration:      {}", format_duration(duration));
+			}
+			for (index, stream) in media.streams.iter().enumerate() {
+				let mut description = format!("{} {}", stream.kind, stream.codec);
+				if let (Some(width), Some(height)) = (stream.width, stream.height) {
+					description += &format!(" {}x{}", width, height);
+				}
+				if let Some(fps) = stream.fps.filter(|_| stream.kind == "video") {
+					description += &format!(" at {:.2} fps", fps);
+				}
+				if let Some(rotation) = stream.rotation.filter(|&rotation| rotation != 0) {
+					description += &format!(", rotated {}°", rotation);
+				}
+				println!("Stream {:<8}{}", format!("#{}:", index), description);
+			}
+			let chapters = probe::probe_chapters(&input);
+			if !chapters.is_empty() {
+				println!("Chapters:      {}", chapters.len());
+			}
+		},
+		Err(error) => println!("Streams:       unknown, {}", error),
+	}
+
+	// The first frame is converted just like playing would, to see what comes out.
+	let target_size = target_size();
+	let converter = pipeline::converter(target_size, &args.style, config.theme(&args.style.theme)?);
+	let decode = DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(target_size.get()),
+		filters: args.source.vf.clone(),
+		limit: Some(Duration::from_secs(DURATION)),
+	};
+	let mut source = source::open(&input, &pipeline::source_options(&args.source, decode))?;
+	let first = source.next_frame()?.ok_or(Error::NoFrames)?;
+	let lines = converter.convert_picture(first.picture);
+	let (columns, rows) = export::grid_size(std::slice::from_ref(&lines));
+
+	// Without a duration from ffprobe the rest of the frames have to be decoded to count them.
+	let limit = Duration::from_secs(DURATION);
+	let frame_count = match media.ok().and_then(|media| media.duration) {
+		Some(duration) if !first.delay.is_zero() => {
+			(duration.min(limit.as_secs_f64()) / first.delay.as_secs_f64()).ceil() as u64
+		},
+		_ => {
+			let mut count = 1;
+			let mut total = first.delay;
+			while total < limit {
+				let Some(frame) = source.next_frame()? else {
+					break;
+				};
+				total += frame.delay;
+				count += 1;
+			}
+			count
+		},
+	};
+	let frame_bytes: usize = lines.iter().map(String::len).sum();
+
+	println!(
+		"Conversion:    {}x{} characters, {} output",
+		columns,
+		rows,
+		converter.color_mode()
+	);
+	if !first.delay.is_zero() {
+		println!(
+			"Frames:        about {} at {:.2} fps",
+			frame_count,
+			1.0 / first.delay.as_secs_f64()
+		);
+	}
+	println!(
+		"Memory:        about {} to hold every converted frame",
+		format_size(frame_count * frame_bytes as u64)
+	);
+	Ok(())
+}
+
+fn container_info(input: &str) -> Result<(), Error> {
+	let mapped = baa::Mapped::open(Path::new(input))?;
+	let header = mapped.header();
+	let duration: Duration = mapped.delays().iter().sum();
+
+	println!("Input:         {}", input);
+	println!("Size:          {}", format_size(fs::metadata(input)?.len()));
+	println!("Duration:      {}", format_duration(duration.as_secs_f64()));
+	println!("Frames:        {} at {:.2} fps", header.frame_count, header.fps);
+	println!("Dimensions:    {}x{}", header.columns, header.rows);
+	println!("Color:         {}", if header.color { "yes" } else { "no" });
+	println!("Compressed:    {}", if header.compressed { "yes" } else { "no" });
+	if !mapped.chapters().is_empty() {
+		println!("Chapters:      {}", mapped.chapters().len());
+	}
+	Ok(())
+}
+
+fn format_duration(seconds: f64) -> String {
+	let whole = seconds as u64;
+	format!(
+		"{}:{:02}:{:05.2}",
+		whole / 3600,
+		whole / 60 % 60,
+		seconds - (whole / 60 * 60) as f64
+	)
+}
@@ -0,0 +1,754 @@
+use crate::DURATION;
+use crate::cli::{ConnectArgs, PlayArgs};
+use crate::config::Config;
+use crate::control::{self, Command, Reply, Request};
+#[cfg(unix)]
+use crate::fifo;
+#[cfg(unix)]
+use crate::ipc::IpcServer;
+use crate::keys::{Action, KeyMap};
+use crate::lyrics::Lyrics;
+#[cfg(target_os = "linux")]
+use crate::mpris;
+use crate::osc;
+use crate::osd::Osd;
+use crate::pipeline;
+use crate::record::Recorder;
+use crate::remote::Remote;
+use crate::screenshot::Screenshots;
+use bad_ascii::convert::{Converter, Frames};
+use bad_ascii::frame::AsciiFrame;
+use bad_ascii::render::{
+	Graphics, adjust_brightness, draw_frame, get_horizontal_padding, get_vertical_padding,
+	remove_ansi_escape_sequences, render,
+};
+use bad_ascii::schedule;
+use bad_ascii::source::{self, BaaSource, Chapter, DecodeOptions, FfmpegSource, FrameSource, RawSource};
+use bad_ascii::theme::Theme;
+use bad_ascii::{DEFAULT_WIDTH, Error};
+use crossterm::QueueableCommand;
+use crossterm::cursor::{self};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal::{self, Clear, ClearType};
+use image::DynamicImage;
+use serde_json::{Value, json};
+use std::fmt::Display;
+use std::io::{self, BufReader, Stdout, Write, stdout};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+const SEEK_STEP: f64 = 5.0;
+const SPEED_STEP: f64 = 0.25;
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 4.0;
+const MAX_BRIGHTNESS: f64 = 4.0;
+const LYRIC_ROWS: usize = 3;
+const CHAPTER_RESTART: f64 = 2.0;
+const PROGRESS_WIDTH: usize = 20;
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const STALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+// What the player needs besides the frames themselves.
+struct Controls<'a> {
+	keys: &'a KeyMap,
+	theme: &'a Theme,
+	screenshots: &'a Screenshots,
+	max_fps: Option<f64>,
+	graphics: Option<Graphics>,
+	requests: &'a Receiver<Request>,
+	// Desktop media controls, kept up to date with what is playing.
+	#[cfg(target_os = "linux")]
+	mpris: Option<&'a mpris::Mpris>,
+}
+
+impl Controls<'_> {
+	#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+	fn publish(&self, status: impl FnOnce() -> Value) {
+		#[cfg(target_os = "linux")]
+		if let Some(mpris) = self.mpris {
+			mpris.update(&status());
+		}
+	}
+}
+
+pub fn run(args: &PlayArgs, config: &Config) -> Result<(), Error> {
+	let keys = config.keys()?;
+	if args.dump_keys {
+		keys.dump().iter().for_each(|line| println!("{}", line));
+		return Ok(());
+	}
+	let lyrics = args.lyrics.as_deref().map(Lyrics::load).transpose()?;
+	let mut input = pipeline::resolve_input(&args.source)?;
+
+	let target_size = target_size();
+	let converter = Arc::new(pipeline::converter(
+		target_size,
+		&args.style,
+		config.theme(&args.style.theme)?,
+	));
+	let screenshots = Screenshots::new(args.screenshot_dir.clone(), args.screenshot_color);
+	let theme = config.theme(&args.style.theme)?;
+	let (sender, requests) = control::channel();
+	#[cfg(unix)]
+	let _ipc = match &args.ipc_socket {
+		Some(path) => Some(IpcServer::start(path, sender.clone()).map_err(|error| unavailable(path.display(), error))?),
+		None => None,
+	};
+	// Media keys are a nicety, so playback goes on without them when there is no session bus.
+	#[cfg(target_os = "linux")]
+	let mpris = mpris::start(sender.clone()).ok();
+	if let Some(port) = args.osc_port {
+		osc::listen(args.osc_bind, port, sender.clone())
+			.map_err(|error| unavailable(format!("OSC port {}", port), error))?;
+	}
+	#[cfg(unix)]
+	if let Some(path) = &args.control_fifo {
+		fifo::listen(path, sender).map_err(|error| unavailable(path.display(), error))?;
+	}
+	let controls = Controls {
+		keys: &keys,
+		theme: &theme,
+		screenshots: &screenshots,
+		max_fps: args.max_fps,
+		graphics: args.graphics,
+		requests: &requests,
+		#[cfg(target_os = "linux")]
+		mpris: mpris.as_ref(),
+	};
+	let decode = decode_options(args, &converter);
+
+	let live: Option<Box<dyn FrameSource + Send>> = match &args.capture {
+		Some(device) => Some(Box::new(FfmpegSource::capture(device, &decode)?)),
+		None if args.capture_screen => Some(Box::new(FfmpegSource::capture_screen(&decode)?)),
+		None if args.stdin => {
+			let (width, height) = args.size.expect("--stdin requires --size");
+			Some(Box::new(RawSource::new(io::stdin(), width, height, args.pix_fmt)))
+		},
+		None => None,
+	};
+
+	with_terminal(args.record.as_deref(), |stdout| {
+		// Devices and lyrics belong to what was asked for on the command line, not to files loaded
+		// over a remote control afterwards.
+		let mut live = live;
+		let mut lyrics = lyrics.as_ref();
+		loop {
+			match play_input(&input, live.take(), lyrics.take(), args, &converter, &controls, stdout)? {
+				Finished::Quit => return Ok(()),
+				Finished::Load(next) => input = next,
+			}
+		}
+	})
+}
+
+pub fn connect(args: &ConnectArgs, config: &Config) -> Result<(), Error> {
+	let keys = config.keys()?;
+	let theme = config.theme(&args.theme)?;
+	let screenshots = Screenshots::new(args.screenshot_dir.clone(), args.screenshot_color);
+	// Remote controls belong to playing files, a connection only takes keys.
+	let (_sender, requests) = control::channel();
+	let controls = Controls {
+		keys: &keys,
+		theme: &theme,
+		screenshots: &screenshots,
+		max_fps: args.max_fps,
+		graphics: None,
+		requests: &requests,
+		#[cfg(target_os = "linux")]
+		mpris: None,
+	};
+	let mut remote = Remote::connect(&args.address)?;
+
+	with_terminal(None, |stdout| {
+		play_stream(move || remote.next_frame(), &args.address, &controls, stdout)?;
+		Ok(())
+	})
+}
+
+fn play_input(
+	input: &str,
+	live: Option<Box<dyn FrameSource + Send>>,
+	lyrics: Option<&Lyrics>,
+	args: &PlayArgs,
+	converter: &Arc<Converter>,
+	controls: &Controls,
+	stdout: &mut impl Write,
+) -> Result<Finished, Error> {
+	stdout.queue(Clear(ClearType::All))?;
+	let decode = decode_options(args, converter);
+	let title = Path::new(input)
+		.file_name()
+		.map_or(input.into(), |name| name.to_string_lossy());
+
+	let stream: Option<Box<dyn FrameSource + Send>> = match live {
+		Some(live) => Some(live),
+		None if source::is_url(input) => Some(Box::new(FfmpegSource::stream(input, &decode)?)),
+		None if source::is_live_url(input) => Some(Box::new(FfmpegSource::live_stream(input, &decode)?)),
+		None => None,
+	};
+
+	if let Some(mut stream) = stream {
+		let converter = Arc::clone(converter);
+		let next_frame = move || {
+			Ok(stream
+				.next_frame()?
+				.map(|frame| converter.convert_picture(frame.picture)))
+		};
+		return play_stream(next_frame, &title, controls, stdout);
+	}
+
+	if input == "-" {
+		// A .baa stream, like the one from `convert -o -`, played at its own pace as it arrives.
+		let mut source = BaaSource::new(BufReader::new(io::stdin()))?;
+		let converter = Arc::clone(converter);
+		let mut delay = Duration::ZERO;
+		let next_frame = move || {
+			sleep(delay);
+			Ok(source.next_frame()?.map(|frame| {
+				delay = frame.delay;
+				converter.convert_picture(frame.picture)
+			}))
+		};
+		return play_stream(next_frame, &title, controls, stdout);
+	}
+
+	if let Some(image) = source::load_still(input)? {
+		return show_still(image, &title, converter, args.hold, controls, stdout);
+	}
+
+	let (mut frames, chapters) = pipeline::load(input, &args.source, &args.cache, converter, decode)?;
+
+	let lyric_rows = if lyrics.is_some() { LYRIC_ROWS + 1 } else { 0 };
+	let first_frame = frames.frame(0)?;
+	let top = get_vertical_padding(first_frame.len(), lyric_rows);
+	let left = get_horizontal_padding(first_frame);
+
+	play(
+		frames.as_mut(),
+		&title,
+		&chapters,
+		(top, left),
+		lyrics,
+		controls,
+		stdout,
+	)
+}
+
+fn decode_options(args: &PlayArgs, converter: &Converter) -> DecodeOptions {
+	DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(converter.target_size()),
+		filters: args.source.vf.clone(),
+		limit: Some(Duration::from_secs(DURATION)),
+	}
+}
+
+fn with_terminal(
+	record: Option<&Path>,
+	run: impl FnOnce(&mut Recorder<Stdout>) -> Result<(), Error>,
+) -> Result<(), Error> {
+	let mut stdout = Recorder::new(stdout(), record)?;
+	stdout.queue(Clear(ClearType::All))?.queue(cursor::Hide)?;
+
+	terminal::enable_raw_mode().map_err(Error::TerminalError)?;
+	let result = run(&mut stdout);
+	terminal::disable_raw_mode().map_err(Error::TerminalError)?;
+
+	stdout.queue(cursor::Show)?;
+	stdout.flush()?;
+	let finished = stdout.finish();
+	result.and(finished)
+}
+
+// Graphics are drawn whole every time, there is nothing of the previous frame to keep.
+fn draw(
+	stdout: &mut impl Write,
+	frame: &[String],
+	previous: Option<&[String]>,
+	(top, left): (u16, u16),
+	graphics: Option<Graphics>,
+) -> Result<(), Error> {
+	match graphics {
+		None => Ok(draw_frame(stdout, frame, previous, top, left)?),
+		Some(graphics) => {
+			let frame = AsciiFrame::from_lines(frame);
+			render(
+				graphics.renderer(&mut *stdout, top, left).as_mut(),
+				&frame,
+				Duration::ZERO,
+			)
+		},
+	}
+}
+
+fn show_still(
+	image: DynamicImage,
+	title: &str,
+	converter: &Converter,
+	hold: Option<f64>,
+	controls: &Controls,
+	stdout: &mut impl Write,
+) -> Result<Finished, Error> {
+	let lines = converter.convert(image);
+	let top = get_vertical_padding(lines.len(), 0);
+	let left = get_horizontal_padding(&lines);
+	draw(stdout, &lines, None, (top, left), controls.graphics)?;
+	stdout.flush()?;
+	controls.publish(|| json!({"title": title}));
+
+	// Any key closes it, bound or not.
+	let deadline = hold.map(|hold| Instant::now() + Duration::from_secs_f64(hold));
+	while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+		if event::poll(STREAM_POLL_INTERVAL)? {
+			if let Event::Key(key) = event::read()? {
+				if key.kind == KeyEventKind::Press {
+					break;
+				}
+			}
+		}
+		for request in controls.requests.try_iter() {
+			let finished = dispatch(request.command, Some(request.reply), |command| match command {
+				Command::GetStatus => Ok(json!({"title": title})),
+				_ => Err("a still image can only be quit or replaced".into()),
+			});
+			if let Some(finished) = finished {
+				return Ok(finished);
+			}
+		}
+	}
+	Ok(Finished::Quit)
+}
+
+fn play(
+	frames: &mut dyn Frames,
+	title: &str,
+	chapters: &[Chapter],
+	(top, left): (u16, u16),
+	lyrics: Option<&Lyrics>,
+	controls: &Controls,
+	stdout: &mut impl Write,
+) -> Result<Finished, Error> {
+	let frame_delays = frames.delays().to_vec();
+	// Frames that come quicker than the cap are skipped, since the playback position keeps moving.
+	let frame_duration = frame_delays
+		.iter()
+		.min()
+		.copied()
+		.unwrap_or_default()
+		.max(schedule::min_frame_time(controls.max_fps));
+	let start_time = Instant::now();
+
+	let timestamps = schedule::timestamps(&frame_delays);
+	let (term_width, _) = terminal::size().map_err(Error::TerminalError)?;
+	let lyrics_top = top + frames.frame(0)?.len() as u16 + 1;
+
+	let mut playback = Playback {
+		title,
+		chapters,
+		total_duration: frame_delays.iter().map(Duration::as_secs_f64).sum(),
+		position: 0.0,
+		speed: 1.0,
+		brightness: 1.0,
+		paused: false,
+		osd: Osd::new(controls.theme.osd_style()),
+	};
+	let mut last_tick = start_time;
+	// Goes by the playback position, so that time spent paused or slowed down doesn't count. Short
+	// clips loop until DURATION seconds have been played, longer inputs play to their end.
+	let end = playback.total_duration.max(DURATION as f64);
+
+	let mut previous_frame: Option<Vec<String>> = None;
+	let mut shown_index = 0;
+	let mut shown_brightness = 1.0;
+	let mut previous_lyrics = Vec::new();
+
+	loop {
+		let frame_start = Instant::now();
+
+		for (command, reply) in pending_commands(controls)? {
+			let finished = dispatch(command, reply, |command| {
+				playback.execute(command, previous_frame.as_deref(), controls)
+			});
+			if let Some(finished) = finished {
+				return Ok(finished);
+			}
+		}
+
+		if !playback.paused {
+			playback.position += frame_start.duration_since(last_tick).as_secs_f64() * playback.speed;
+		}
+		last_tick = frame_start;
+		if playback.position >= end {
+			break;
+		}
+		controls.publish(|| playback.status());
+
+		let frame_index = schedule::frame_at(&timestamps, playback.current());
+		if playback.osd.tick() {
+			previous_frame = None;
+		}
+
+		// Frames may be decoded on demand, so they are only fetched when a different one is due.
+		if previous_frame.is_none() || shown_index != frame_index || shown_brightness != playback.brightness {
+			let current_frame = adjust_brightness(frames.frame(frame_index)?, playback.brightness);
+			draw(
+				stdout,
+				&current_frame,
+				previous_frame.as_deref(),
+				(top, left),
+				controls.graphics,
+			)?;
+			previous_frame = Some(current_frame);
+			shown_index = frame_index;
+			shown_brightness = playback.brightness;
+		}
+
+		if let Some(lyrics) = lyrics {
+			let current_lyrics = lyrics.render(playback.current(), LYRIC_ROWS, term_width);
+			if current_lyrics != previous_lyrics {
+				for (row, line) in current_lyrics.iter().enumerate() {
+					let cursor_move = format!("\x1B[{};1H\x1B[2K", lyrics_top + row as u16);
+					stdout.write_all(format!("{}{}", cursor_move, line).as_bytes())?;
+				}
+				previous_lyrics = current_lyrics;
+			}
+		}
+
+		playback.osd.draw(stdout, top, left)?;
+		stdout.flush()?;
+
+		let frame_end = Instant::now();
+		let frame_processing_time = frame_end - frame_start;
+		if frame_processing_time < frame_duration {
+			sleep(frame_duration - frame_processing_time);
+		}
+	}
+
+	Ok(Finished::Quit)
+}
+
+// Where a file is in its playback, which everything but quitting and loading acts on.
+struct Playback<'a> {
+	title: &'a str,
+	chapters: &'a [Chapter],
+	total_duration: f64,
+	// Keeps growing as the file loops, see current().
+	position: f64,
+	speed: f64,
+	brightness: f64,
+	paused: bool,
+	osd: Osd,
+}
+
+impl Playback<'_> {
+	fn current(&self) -> f64 {
+		self.position % self.total_duration
+	}
+
+	fn status(&self) -> Value {
+		json!({
+			"title": self.title,
+			"position": self.current(),
+			"duration": self.total_duration,
+			"speed": self.speed,
+			"paused": self.paused,
+		})
+	}
+
+	fn execute(&mut self, command: Command, frame: Option<&[String]>, controls: &Controls) -> Reply {
+		match command {
+			Command::Pause(paused) => {
+				self.paused = paused.unwrap_or(!self.paused);
+				self.osd.show(if self.paused { "paused" } else { "playing" });
+			},
+			Command::Seek { seconds, absolute } => {
+				self.position = if absolute { seconds } else { self.position + seconds }.max(0.0);
+				let progress = progress(controls.theme, self.current(), self.total_duration);
+				self.osd.show(if absolute {
+					format!("seek  {}", progress)
+				} else {
+					format!("seek {:+}s  {}", seconds, progress)
+				});
+			},
+			Command::Speed { value, absolute } => {
+				self.speed = if absolute { value } else { self.speed + value }.clamp(MIN_SPEED, MAX_SPEED);
+				self.osd.show(format!("speed {}x", self.speed));
+			},
+			Command::Brightness(brightness) => {
+				self.brightness = brightness.clamp(0.0, MAX_BRIGHTNESS);
+				self.osd.show(format!("brightness {:.0}%", self.brightness * 100.0));
+			},
+			Command::Screenshot => return screenshot(frame, controls, &mut self.osd),
+			Command::NextChapter | Command::PreviousChapter => {
+				let current = self.current();
+				let target = if matches!(command, Command::NextChapter) {
+					self.chapters
+						.iter()
+						.position(|chapter| chapter.start.as_secs_f64() > current)
+				} else {
+					// Like other players, go back to the start of the current chapter unless that's
+					// where playback already is.
+					self.chapters
+						.iter()
+						.rposition(|chapter| chapter.start.as_secs_f64() < current - CHAPTER_RESTART)
+				};
+				let index = target
+					.filter(|&index| self.chapters[index].start.as_secs_f64() < self.total_duration)
+					.ok_or("there is no chapter to go to")?;
+				self.position = self.chapters[index].start.as_secs_f64();
+				self.osd.show(format!(
+					"chapter {}/{}: {}",
+					index + 1,
+					self.chapters.len(),
+					self.chapters[index].title
+				));
+			},
+			Command::GetPosition => return Ok(Value::from(self.current())),
+			Command::GetStatus => return Ok(self.status()),
+			Command::Quit | Command::Load(_) => unreachable!("dispatch handles quitting and loading"),
+		}
+		Ok(Value::Null)
+	}
+}
+
+// Streamed sources are decoded on their own thread, so that a stalled network or device doesn't
+// freeze the terminal.
+fn play_stream(
+	mut next_frame: impl FnMut() -> Result<Option<Vec<String>>, Error> + Send + 'static,
+	title: &str,
+	controls: &Controls,
+	stdout: &mut impl Write,
+) -> Result<Finished, Error> {
+	let min_frame_time = schedule::min_frame_time(controls.max_fps);
+	let (sender, receiver) = mpsc::sync_channel(1);
+	thread::spawn(move || {
+		loop {
+			let Some(frame) = next_frame().transpose() else {
+				break;
+			};
+			let failed = frame.is_err();
+			if sender.send(frame).is_err() || failed {
+				break;
+			}
+		}
+	});
+
+	let mut padding = None;
+	let mut shape_drawn = None;
+	let mut previous_frame: Option<Vec<String>> = None;
+	let mut last_frame_at = Instant::now();
+	let mut brightness = 1.0;
+	let mut osd = Osd::new(controls.theme.osd_style());
+	controls.publish(|| json!({"title": title}));
+
+	loop {
+		for (command, reply) in pending_commands(controls)? {
+			// Streams can't be paused or seeked, they carry on whether they are watched or not.
+			let finished = dispatch(command, reply, |command| match command {
+				Command::Screenshot => screenshot(previous_frame.as_deref(), controls, &mut osd),
+				// Applied from the next frame on.
+				Command::Brightness(value) => {
+					brightness = value.clamp(0.0, MAX_BRIGHTNESS);
+					osd.show(format!("brightness {:.0}%", brightness * 100.0));
+					Ok(Value::Null)
+				},
+				Command::GetStatus => Ok(json!({"title": title})),
+				_ => Err("streams can't be paused, seeked or sped up".into()),
+			});
+			if let Some(finished) = finished {
+				return Ok(finished);
+			}
+		}
+
+		// Skip straight to the newest frame when conversion falls behind, to keep up with live sources.
+		let mut received = receiver.recv_timeout(STREAM_POLL_INTERVAL);
+		while let Ok(Ok(_)) = received {
+			match receiver.try_recv() {
+				Ok(newer) => received = Ok(newer),
+				Err(_) => break,
+			}
+		}
+
+		match received {
+			// Dropped rather than waited for, so that the decoder never falls behind a live source.
+			Ok(Ok(_)) if last_frame_at.elapsed() < min_frame_time => {},
+			Ok(Ok(current_frame)) => {
+				// Remote streams change size when the terminal does, and are then centered anew.
+				// Colored lines vary in bytes from frame to frame, so the width is counted in characters.
+				let width = current_frame
+					.first()
+					.map(|line| remove_ansi_escape_sequences(line).chars().count());
+				let shape = (current_frame.len(), width);
+				if shape_drawn.is_some_and(|drawn| drawn != shape) {
+					stdout.queue(Clear(ClearType::All))?;
+					padding = None;
+					previous_frame = None;
+				}
+				shape_drawn = Some(shape);
+				let (top, left) = *padding.get_or_insert_with(|| {
+					(
+						get_vertical_padding(current_frame.len(), 0),
+						get_horizontal_padding(&current_frame),
+					)
+				});
+				if osd.tick() {
+					previous_frame = None;
+				}
+
+				let current_frame = adjust_brightness(&current_frame, brightness);
+				draw(
+					stdout,
+					&current_frame,
+					previous_frame.as_deref(),
+					(top, left),
+					controls.graphics,
+				)?;
+				previous_frame = Some(current_frame);
+				last_frame_at = Instant::now();
+			},
+			Ok(Err(error)) => return Err(error),
+			Err(RecvTimeoutError::Timeout) => {
+				if last_frame_at.elapsed() >= STALL_TIMEOUT {
+					osd.show("buffering...");
+				}
+				if osd.tick() {
+					previous_frame = None;
+				}
+			},
+			Err(RecvTimeoutError::Disconnected) => return Ok(Finished::Quit),
+		}
+
+		let (top, left) = padding.unwrap_or((1, 1));
+		osd.draw(stdout, top, left)?;
+		stdout.flush()?;
+	}
+}
+
+// What to do once the current input stops playing.
+enum Finished {
+	Quit,
+	Load(String),
+}
+
+type Pending = (Command, Option<Sender<Reply>>);
+
+// Key presses waiting to be handled, followed by requests from remote controls, which are the
+// only ones expecting a reply.
+fn pending_commands(controls: &Controls) -> Result<Vec<Pending>, Error> {
+	let mut pending = Vec::new();
+	while event::poll(Duration::ZERO)? {
+		let Event::Key(key) = event::read()? else {
+			continue;
+		};
+		if key.kind != KeyEventKind::Press {
+			continue;
+		}
+		if let Some(action) = controls.keys.action(&key) {
+			pending.push((key_command(action), None));
+		}
+	}
+	pending.extend(
+		controls
+			.requests
+			.try_iter()
+			.map(|request| (request.command, Some(request.reply))),
+	);
+	Ok(pending)
+}
+
+fn key_command(action: Action) -> Command {
+	match action {
+		Action::Quit => Command::Quit,
+		Action::Pause => Command::Pause(None),
+		Action::SeekForward => Command::Seek {
+			seconds: SEEK_STEP,
+			absolute: false,
+		},
+		Action::SeekBackward => Command::Seek {
+			seconds: -SEEK_STEP,
+			absolute: false,
+		},
+		Action::SpeedUp => Command::Speed {
+			value: SPEED_STEP,
+			absolute: false,
+		},
+		Action::SlowDown => Command::Speed {
+			value: -SPEED_STEP,
+			absolute: false,
+		},
+		Action::Screenshot => Command::Screenshot,
+		Action::NextChapter => Command::NextChapter,
+		Action::PreviousChapter => Command::PreviousChapter,
+	}
+}
+
+// Quitting and loading end playback the same way whatever is playing, everything else is left to
+// the player. Returns how playback finished if the command ended it.
+fn dispatch(
+	command: Command,
+	reply: Option<Sender<Reply>>,
+	execute: impl FnOnce(Command) -> Reply,
+) -> Option<Finished> {
+	let (result, finished) = match command {
+		Command::Quit => (Ok(Value::Null), Some(Finished::Quit)),
+		// Checked before anything stops, so that a typo doesn't end playback.
+		Command::Load(input)
+			if !source::is_url(&input) && !source::is_live_url(&input) && !Path::new(&input).exists() =>
+		{
+			(Err(format!("{} doesn't exist", input)), None)
+		},
+		Command::Load(input) => (Ok(Value::Null), Some(Finished::Load(input))),
+		command => (execute(command), None),
+	};
+	if let Some(reply) = reply {
+		// Whoever asked may have hung up already.
+		let _ = reply.send(result);
+	}
+	finished
+}
+
+fn screenshot(frame: Option<&[String]>, controls: &Controls, osd: &mut Osd) -> Reply {
+	let frame = frame.ok_or("nothing has been drawn yet")?;
+	match controls.screenshots.save(frame) {
+		Ok(path) => {
+			osd.show(format!("saved {}", path.display()));
+			Ok(Value::from(path.display().to_string()))
+		},
+		Err(error) => {
+			let message = format!("screenshot failed: {}", error);
+			osd.show(message.clone());
+			Err(message)
+		},
+	}
+}
+
+// A remote control that couldn't be set up, keeping the kind of error so that the exit code matches.
+fn unavailable(what: impl Display, error: io::Error) -> Error {
+	Error::Io(io::Error::new(error.kind(), format!("{}: {}", what, error)))
+}
+
+fn progress(theme: &Theme, position: f64, total: f64) -> String {
+	format!(
+		"{} {} / {}",
+		theme.progress_bar(position / total, PROGRESS_WIDTH),
+		format_time(position),
+		format_time(total)
+	)
+}
+
+fn format_time(seconds: f64) -> String {
+	let seconds = seconds as u64;
+	format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Frames are never converted wider than the terminal, which would wrap every line.
+pub(super) fn target_size() -> NonZeroU32 {
+	terminal::size()
+		.ok()
+		.and_then(|(columns, _)| NonZeroU32::new(columns as u32))
+		.map_or(DEFAULT_WIDTH, |columns| DEFAULT_WIDTH.min(columns))
+}
@@ -0,0 +1,84 @@
+use crate::cli::ServeArgs;
+use crate::config::Config;
+use crate::pipeline;
+use crate::serve::{self, Library, Limits};
+use bad_ascii::Error;
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+pub fn run(args: &ServeArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	let default_width = NonZeroU32::new(args.width).expect("width is at least 1");
+	let library = Arc::new(Library::new(
+		input,
+		(args.source.clone(), args.style.clone(), args.cache.clone()),
+		config.theme(&args.style.theme)?,
+		default_width,
+		args.broadcast,
+		Limits::new(
+			args.max_clients,
+			args.max_clients_per_address,
+			args.max_bandwidth.map(|kib| kib.saturating_mul(1024)),
+		),
+	));
+	// Convert up front, so that a broken input is reported here rather than to the first viewer.
+	library.default_video()?;
+
+	let tls = match (&args.cert, &args.key) {
+		(Some(cert), Some(key)) => Some(serve::load_tls(cert, key)?),
+		_ => None,
+	};
+	let scheme = if tls.is_some() { "https" } else { "http" };
+
+	let mut listeners = Vec::new();
+	if let Some(address) = args.telnet {
+		listeners
+			.push(serve::listen_telnet(address, Arc::clone(&library)).map_err(|error| unavailable(address, error))?);
+		println!("Serving telnet on {}", address);
+	}
+	if let Some(address) = args.ssh {
+		listeners.push(
+			serve::listen_ssh(address, Arc::clone(&library), args.ssh_host_key.as_deref())
+				.map_err(|error| unavailable(address, error))?,
+		);
+		println!("Serving SSH on {}", address);
+	}
+	if let Some(address) = args.ws {
+		listeners.push(
+			serve::listen_ws(address, Arc::clone(&library), tls.clone())
+				.map_err(|error| unavailable(address, error))?,
+		);
+		println!("Serving the browser player on {}://{}/", scheme, address);
+	}
+	if let Some(address) = args.http {
+		listeners.push(
+			serve::listen_http(address, Arc::clone(&library), tls.clone())
+				.map_err(|error| unavailable(address, error))?,
+		);
+		println!("Serving HTTP on {}://{}/play", scheme, address);
+	}
+	if let Some(address) = args.stream {
+		listeners
+			.push(serve::listen_stream(address, Arc::clone(&library)).map_err(|error| unavailable(address, error))?);
+		println!("Serving streams for `bad_ascii connect` on {}", address);
+	}
+	if let Some(address) = args.metrics {
+		listeners
+			.push(serve::listen_metrics(address, Arc::clone(&library)).map_err(|error| unavailable(address, error))?);
+		println!("Serving metrics on http://{}/metrics", address);
+	}
+
+	for listener in listeners {
+		listener
+			.join()
+			.map_err(|_| Error::Network("a listener panicked".to_string()))?;
+	}
+	Ok(())
+}
+
+// A listener that couldn't be started, usually because its address is taken.
+fn unavailable(address: SocketAddr, error: impl Display) -> Error {
+	Error::Network(format!("{}: {}", address, error))
+}
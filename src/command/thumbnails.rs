@@ -0,0 +1,86 @@
+use crate::cli::ThumbnailsArgs;
+use crate::config::Config;
+use crate::pipeline;
+use bad_ascii::source::{self, DecodeOptions, FrameSource, Picture};
+use bad_ascii::{Error, export};
+use image::imageops::FilterType;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+pub fn run(args: &ThumbnailsArgs, config: &Config) -> Result<(), Error> {
+	let input = pipeline::resolve_input(&args.source)?;
+	let decode = DecodeOptions {
+		hwaccel: args.source.hwaccel,
+		columns: Some(args.width),
+		filters: args.source.vf.clone(),
+		limit: None,
+	};
+	let options = pipeline::source_options(&args.source, decode);
+	let converter = pipeline::converter(
+		NonZeroU32::new(args.width).expect("width is at least 1"),
+		&args.style,
+		config.theme(&args.style.theme)?,
+	);
+	let sampled = sample_frames(source::open(&input, &options)?, args.count as usize, args.width)?;
+	let thumbnails: Vec<(Duration, Vec<String>)> = sampled
+		.into_iter()
+		.map(|(timestamp, picture)| (timestamp, converter.convert_picture(picture)))
+		.collect();
+
+	let sheet = export::contact_sheet(&thumbnails, args.per_row as usize, args.color);
+	match &args.output {
+		Some(path) => {
+			export::write_sheet(&sheet, path)?;
+			println!("Wrote {} thumbnails to {}", thumbnails.len(), path.display());
+		},
+		None => sheet.iter().for_each(|line| println!("{}", line)),
+	}
+	Ok(())
+}
+
+// Picks `count` frames spread evenly over a source of unknown length. Every `stride`th frame is
+// kept, and once twice as many as needed have piled up every other one is dropped and the stride
+// doubles, so only a handful of frames are ever held at once.
+fn sample_frames(
+	mut source: Box<dyn FrameSource>,
+	count: usize,
+	columns: u32,
+) -> Result<Vec<(Duration, Picture)>, Error> {
+	// Frames that don't come from ffmpeg are full size; shrink them since they are only needed as thumbnails.
+	let max_width = columns * 4;
+	let mut kept = Vec::new();
+	let mut stride = 1;
+	let mut index = 0;
+	let mut timestamp = Duration::ZERO;
+	while let Some(frame) = source.next_frame()? {
+		if index % stride == 0 {
+			let picture = match frame.picture {
+				Picture::Image(image) if image.width() > max_width => {
+					Picture::Image(image.resize(max_width, u32::MAX, FilterType::Triangle))
+				},
+				picture => picture,
+			};
+			kept.push((timestamp, picture));
+			if kept.len() >= count * 2 {
+				kept = kept.into_iter().step_by(2).collect();
+				stride *= 2;
+			}
+		}
+		index += 1;
+		timestamp += frame.delay;
+	}
+
+	if kept.is_empty() {
+		return Err(Error::NoFrames);
+	}
+	if kept.len() <= count {
+		return Ok(kept);
+	}
+	let total = kept.len();
+	Ok(kept
+		.into_iter()
+		.enumerate()
+		.filter(|(position, _)| position * count / total != (position + 1) * count / total)
+		.map(|(_, frame)| frame)
+		.collect())
+}
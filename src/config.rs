@@ -0,0 +1,230 @@
+use crate::keys::KeyMap;
+use bad_ascii::Error;
+use bad_ascii::theme::Theme;
+use clap::Command;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+// Tables in config.toml that hold something other than a subcommand's flags.
+const KEYS_TABLE: &str = "keys";
+const THEMES_TABLE: &str = "themes";
+
+pub struct Config {
+	path: PathBuf,
+	table: Table,
+}
+
+impl Config {
+	// A missing file is the same as an empty one.
+	pub fn load() -> Result<Self, Error> {
+		let path = path().unwrap_or_default();
+		let table = if path.is_file() {
+			fs::read_to_string(&path)?
+				.parse::<Table>()
+				.map_err(|error| config_error(&path, error.to_string()))?
+		} else {
+			Table::new()
+		};
+		Ok(Self { path, table })
+	}
+
+	// Settings in config.toml are the defaults of the flags of the same name, so that the command
+	// line still overrides them. Top-level settings apply to every subcommand that has the flag, and
+	// those under a [subcommand] table only to that one, like [convert] level = 9.
+	pub fn apply(&self, command: Command) -> Result<Command, Error> {
+		let path = &self.path;
+		let mut command = command;
+		for (key, value) in &self.table {
+			match value {
+				Value::Table(_) if key == KEYS_TABLE || key == THEMES_TABLE => {},
+				Value::Table(settings) => {
+					if command.find_subcommand(key).is_none() {
+						return Err(config_error(path, format!("there is no `{}` subcommand", key)));
+					}
+					for (setting, value) in settings {
+						let values = setting_values(path, setting, value)?;
+						let mut found = false;
+						command = command
+							.mut_subcommand(key, |subcommand| set_default(subcommand, setting, &values, &mut found));
+						if !found {
+							return Err(config_error(path, format!("`{}` has no --{} flag", key, setting)));
+						}
+					}
+				},
+				value => {
+					let values = setting_values(path, key, value)?;
+					let mut found = false;
+					command = set_default(command, key, &values, &mut found);
+					if !found {
+						return Err(config_error(path, format!("there is no --{} flag", key)));
+					}
+				},
+			}
+		}
+		Ok(command)
+	}
+
+	pub fn keys(&self) -> Result<KeyMap, Error> {
+		let keys = match self.table.get(KEYS_TABLE) {
+			Some(Value::Table(keys)) => Some(keys),
+			Some(_) => return Err(config_error(&self.path, format!("[{}] has to be a table", KEYS_TABLE))),
+			None => None,
+		};
+		KeyMap::new(keys).map_err(|error| config_error(&self.path, error))
+	}
+
+	pub fn theme(&self, name: &str) -> Result<Theme, Error> {
+		let themes = match self.table.get(THEMES_TABLE) {
+			Some(Value::Table(themes)) => Some(themes),
+			Some(_) => {
+				return Err(config_error(
+					&self.path,
+					format!("[{}] has to be a table", THEMES_TABLE),
+				));
+			},
+			None => None,
+		};
+		Theme::find(name, themes).map_err(|error| {
+			// Only custom themes come from the file, the built-in lookup failing isn't its fault.
+			if themes.is_some_and(|themes| themes.contains_key(name)) {
+				config_error(&self.path, error)
+			} else {
+				Error::BadOptions(error)
+			}
+		})
+	}
+}
+
+pub fn path() -> Option<PathBuf> {
+	let base = env::var_os("XDG_CONFIG_HOME")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+	Some(base.join("bad_ascii").join("config.toml"))
+}
+
+// Sets the default on the command and all of its subcommands, since flags like --backend are
+// repeated on each of them.
+fn set_default(mut command: Command, long: &str, values: &[String], found: &mut bool) -> Command {
+	let id = command
+		.get_arguments()
+		.find(|arg| arg.get_long() == Some(long))
+		.map(|arg| arg.get_id().clone());
+	if let Some(id) = id {
+		*found = true;
+		command = command.mut_arg(id, |arg| arg.default_values(values));
+	}
+
+	let names: Vec<String> = command
+		.get_subcommands()
+		.map(|subcommand| subcommand.get_name().to_string())
+		.collect();
+	for name in names {
+		command = command.mut_subcommand(name, |subcommand| set_default(subcommand, long, values, found));
+	}
+	command
+}
+
+fn setting_values(path: &Path, key: &str, value: &Value) -> Result<Vec<String>, Error> {
+	let invalid = || {
+		config_error(
+			path,
+			format!("`{}` has to be a string, number, boolean or a list of them", key),
+		)
+	};
+	let to_string = |value: &Value| match value {
+		Value::String(text) => Some(text.clone()),
+		Value::Integer(number) => Some(number.to_string()),
+		Value::Float(number) => Some(number.to_string()),
+		Value::Boolean(flag) => Some(flag.to_string()),
+		_ => None,
+	};
+	match value {
+		Value::Array(values) => values
+			.iter()
+			.map(|value| to_string(value).ok_or_else(invalid))
+			.collect(),
+		value => Ok(vec![to_string(value).ok_or_else(invalid)?]),
+	}
+}
+
+fn config_error(path: &Path, message: String) -> Error {
+	Error::Config {
+		path: path.to_path_buf(),
+		message,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::cli::{Args, Command as Subcommand};
+	use clap::{CommandFactory, FromArgMatches};
+
+	fn config(text: &str) -> Config {
+		Config {
+			path: PathBuf::from("config.toml"),
+			table: text.parse().unwrap(),
+		}
+	}
+
+	fn parse(config: &Config, args: &[&str]) -> Result<Args, Error> {
+		let matches = config
+			.apply(Args::command())?
+			.try_get_matches_from(args)
+			.map_err(|error| Error::BadOptions(error.to_string()))?;
+		Ok(Args::from_arg_matches(&matches).unwrap())
+	}
+
+	#[test]
+	fn settings_become_flag_defaults() {
+		let config = config("theme = \"matrix\"\n[convert]\nlevel = 9");
+		let args = parse(&config, &["bad_ascii", "convert", "-o", "out.baa"]).unwrap();
+		let Some(Subcommand::Convert(convert)) = args.command else {
+			panic!("not parsed as convert");
+		};
+		assert_eq!(convert.level, 9);
+		assert_eq!(convert.style.theme, "matrix");
+
+		// The command line still wins, and settings reach play without its subcommand too.
+		let args = parse(&config, &["bad_ascii", "--theme", "amber"]).unwrap();
+		assert_eq!(args.play.style.theme, "amber");
+	}
+
+	#[test]
+	fn lists_set_every_value() {
+		let config = config("[export]\nframe = [1, 3]");
+		let args = parse(&config, &["bad_ascii", "export", "--ans", "out.ans"]).unwrap();
+		let Some(Subcommand::Export(export)) = args.command else {
+			panic!("not parsed as export");
+		};
+		assert_eq!(export.frames, [1, 3]);
+	}
+
+	#[test]
+	fn unknown_settings_are_refused() {
+		for text in [
+			"no-such-flag = 1",
+			"[convert]\nno-such-flag = 1",
+			"[nothing]\nlevel = 1",
+			"theme = { name = \"matrix\" }",
+		] {
+			assert!(
+				matches!(config(text).apply(Args::command()), Err(Error::Config { .. })),
+				"{}",
+				text
+			);
+		}
+	}
+
+	#[test]
+	fn keys_and_themes_are_not_flags() {
+		let config = config("[keys]\npause = \"p\"\n[themes.mine]\ncharset = \"@. \"");
+		assert!(config.apply(Args::command()).is_ok());
+		assert!(config.keys().is_ok());
+		assert!(config.theme("mine").is_ok());
+		assert!(matches!(self::config("keys = 1").keys(), Err(Error::Config { .. })));
+	}
+}
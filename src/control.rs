@@ -0,0 +1,81 @@
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// Something playback is asked to do, whether it comes from a key press or another program.
+pub enum Command {
+	Quit,
+	// Toggles when no state is given.
+	Pause(Option<bool>),
+	Seek { seconds: f64, absolute: bool },
+	Speed { value: f64, absolute: bool },
+	// Scales the colors frames are drawn with, 1 leaves them alone.
+	Brightness(f64),
+	Screenshot,
+	NextChapter,
+	PreviousChapter,
+	Load(String),
+	GetPosition,
+	// What's playing and how, as an object with the title and, for files, position, duration,
+	// speed and whether it's paused.
+	GetStatus,
+}
+
+impl Command {
+	// Parses the one-line form, e.g. `pause`, `seek +10`, `seek 90` or `load clip.mp4`. Seeks with a
+	// sign are relative, others go to that many seconds in, and the same goes for speed.
+	pub fn parse(line: &str) -> Result<Self, String> {
+		let line = line.trim();
+		let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+		let argument = argument.trim();
+		let number = || {
+			argument
+				.parse::<f64>()
+				.map_err(|_| format!("{} takes a number, not `{}`", name, argument))
+		};
+
+		match (name, argument) {
+			("quit", "") => Ok(Command::Quit),
+			("pause", "") => Ok(Command::Pause(None)),
+			("pause", "on") => Ok(Command::Pause(Some(true))),
+			("pause", "off") => Ok(Command::Pause(Some(false))),
+			("seek", _) => Ok(Command::Seek {
+				seconds: number()?,
+				absolute: !argument.starts_with(['+', '-']),
+			}),
+			("speed", _) => Ok(Command::Speed {
+				value: number()?,
+				absolute: !argument.starts_with(['+', '-']),
+			}),
+			("brightness", _) => Ok(Command::Brightness(number()?)),
+			("screenshot", "") => Ok(Command::Screenshot),
+			("next-chapter", "") => Ok(Command::NextChapter),
+			("previous-chapter", "") => Ok(Command::PreviousChapter),
+			("load", path) if !path.is_empty() => Ok(Command::Load(path.to_string())),
+			("position", "") => Ok(Command::GetPosition),
+			("quit" | "pause" | "screenshot" | "next-chapter" | "previous-chapter" | "load" | "position", _) => {
+				Err(format!("wrong arguments for {}", name))
+			},
+			_ => Err(format!("unknown command `{}`", name)),
+		}
+	}
+}
+
+// What the command returned, sent back to whoever asked for it.
+pub type Reply = Result<Value, String>;
+
+pub struct Request {
+	pub command: Command,
+	pub reply: Sender<Reply>,
+}
+
+// Remote controls each get a sender, and the player drains the receiver between frames.
+pub fn channel() -> (Sender<Request>, Receiver<Request>) {
+	mpsc::channel()
+}
+
+// Sends the command to the player and waits for it to be carried out, or None once the player is gone.
+pub fn send(requests: &Sender<Request>, command: Command) -> Option<Reply> {
+	let (reply, replied) = mpsc::channel();
+	requests.send(Request { command, reply }).ok()?;
+	replied.recv().ok()
+}
@@ -0,0 +1,338 @@
+use crate::export::indexed_color;
+use crate::frame::AsciiFrame;
+use crate::source::{FrameSource, Picture};
+use crate::theme::Theme;
+use crate::{Error, baa};
+use artem::config::{Config, ConfigBuilder};
+use artem::convert;
+use image::DynamicImage;
+use image::imageops::{self, FilterType};
+use std::env;
+use std::fmt::Write;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+// Converted frames, one string per line, and how long each of them stays on screen.
+pub type ConvertedFrames = (Vec<Vec<String>>, Vec<Duration>);
+
+// Frames to play, either all held in memory or decoded on demand from a .baa file.
+pub trait Frames {
+	fn delays(&self) -> &[Duration];
+	fn frame(&mut self, index: usize) -> Result<&[String], Error>;
+	fn into_converted(self: Box<Self>) -> Result<ConvertedFrames, Error>;
+}
+
+impl Frames for ConvertedFrames {
+	fn delays(&self) -> &[Duration] {
+		&self.1
+	}
+
+	fn frame(&mut self, index: usize) -> Result<&[String], Error> {
+		Ok(&self.0[index])
+	}
+
+	fn into_converted(self: Box<Self>) -> Result<ConvertedFrames, Error> {
+		Ok(*self)
+	}
+}
+
+impl Frames for baa::Mapped {
+	fn delays(&self) -> &[Duration] {
+		baa::Mapped::delays(self)
+	}
+
+	fn frame(&mut self, index: usize) -> Result<&[String], Error> {
+		baa::Mapped::frame(self, index)
+	}
+
+	fn into_converted(mut self: Box<Self>) -> Result<ConvertedFrames, Error> {
+		let delays = baa::Mapped::delays(&self).to_vec();
+		let frames = (0..delays.len())
+			.map(|index| self.frame(index).map(<[String]>::to_vec))
+			.collect::<Result<_, _>>()?;
+		Ok((frames, delays))
+	}
+}
+
+pub fn convert_frames(mut source: Box<dyn FrameSource>, converter: &Converter) -> Result<ConvertedFrames, Error> {
+	let mut ascii_frames = Vec::new();
+	let mut frame_delays = Vec::new();
+	while let Some(frame) = source.next_frame()? {
+		ascii_frames.push(converter.convert_picture(frame.picture));
+		frame_delays.push(frame.delay);
+	}
+
+	if ascii_frames.is_empty() {
+		return Err(Error::NoFrames);
+	}
+	Ok((ascii_frames, frame_delays))
+}
+
+// Converted frames as cells, for programs that do their own drawing.
+pub fn ascii_frames(frames: &ConvertedFrames) -> impl Iterator<Item = AsciiFrame> + '_ {
+	frames.0.iter().map(|lines| AsciiFrame::from_lines(lines))
+}
+
+// Converts a source's frames one at a time as they are iterated over, rather than all of them up
+// front as convert_frames does. The source running into an error ends the iteration, and finish()
+// returns the error.
+pub struct Conversion<'a> {
+	source: Box<dyn FrameSource + 'a>,
+	converter: &'a Converter,
+	delay: Duration,
+	error: Option<Error>,
+}
+
+impl<'a> Conversion<'a> {
+	pub fn new(source: Box<dyn FrameSource + 'a>, converter: &'a Converter) -> Self {
+		Self {
+			source,
+			converter,
+			delay: Duration::ZERO,
+			error: None,
+		}
+	}
+
+	// How long the frame returned last stays on screen.
+	pub fn delay(&self) -> Duration {
+		self.delay
+	}
+
+	pub fn finish(self) -> Result<(), Error> {
+		self.error.map_or(Ok(()), Err)
+	}
+}
+
+impl Iterator for Conversion<'_> {
+	type Item = AsciiFrame;
+
+	fn next(&mut self) -> Option<AsciiFrame> {
+		if self.error.is_some() {
+			return None;
+		}
+		match self.source.next_frame() {
+			Ok(Some(frame)) => {
+				self.delay = frame.delay;
+				Some(self.converter.convert_frame(frame.picture))
+			},
+			Ok(None) => None,
+			Err(error) => {
+				self.error = Some(error);
+				None
+			},
+		}
+	}
+}
+
+// The same as a stream, with decoding and conversion on a thread of their own so that they don't
+// hold up the executor. Each frame comes with its delay, and an error ends the stream.
+#[cfg(feature = "async")]
+pub fn stream(
+	source: Box<dyn FrameSource + Send>,
+	converter: std::sync::Arc<Converter>,
+) -> impl futures::Stream<Item = Result<(AsciiFrame, Duration), String>> {
+	// A few frames are converted ahead, not the whole source.
+	let (mut sender, receiver) = futures::channel::mpsc::channel(4);
+	std::thread::spawn(move || {
+		let mut conversion = Conversion::new(source, &converter);
+		while let Some(frame) = conversion.next() {
+			let delay = conversion.delay();
+			if futures::executor::block_on(futures::SinkExt::send(&mut sender, Ok((frame, delay)))).is_err() {
+				return;
+			}
+		}
+		if let Err(error) = conversion.finish() {
+			let _ = futures::executor::block_on(futures::SinkExt::send(&mut sender, Err(error.to_string())));
+		}
+	});
+	receiver
+}
+
+// Turns decoded pictures into lines of text, set up once from the style options.
+pub struct Converter {
+	config: Config,
+	theme: Theme,
+	color: Color,
+}
+
+// How frames are colored. Colors are worked out here rather than by artem, which would have them
+// depend on process-wide settings.
+#[derive(Clone, Copy, PartialEq)]
+enum Color {
+	Plain,
+	Basic,
+	Truecolor,
+}
+
+impl Converter {
+	// The charset, densest first, takes the place of the theme's. Colors are snapped to the theme's
+	// palette when it has one, and otherwise are truecolor if the terminal supports it.
+	pub fn new(target_size: NonZeroU32, color_mode: ColorMode, charset: Option<&str>, theme: Theme) -> Self {
+		let colored = match color_mode {
+			ColorMode::Auto => colored::control::SHOULD_COLORIZE.should_colorize(),
+			ColorMode::Always => true,
+			ColorMode::Never => false,
+		};
+		let truecolor = env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit");
+		let color = if !colored {
+			Color::Plain
+		} else if truecolor || !theme.palette().is_empty() {
+			Color::Truecolor
+		} else {
+			Color::Basic
+		};
+		let mut builder = ConfigBuilder::new();
+		builder.target_size(target_size).color(false);
+		if let Some(charset) = charset.or(theme.charset.as_deref()) {
+			builder.characters(charset.to_string());
+		}
+		Self {
+			config: builder.build(),
+			theme,
+			color,
+		}
+	}
+
+	// Colors with truecolor escapes, or the 16 basic colors, whatever COLORTERM says, for output that
+	// isn't for this process's terminal. Plain converters and themes with a palette are left as they
+	// are.
+	pub fn with_truecolor(mut self, truecolor: bool) -> Self {
+		if self.color != Color::Plain && self.theme.palette().is_empty() {
+			self.color = if truecolor { Color::Truecolor } else { Color::Basic };
+		}
+		self
+	}
+
+	pub fn convert(&self, frame: DynamicImage) -> Vec<String> {
+		if self.color == Color::Plain {
+			return convert(frame, &self.config).lines().map(String::from).collect();
+		}
+		let text = convert(frame.clone(), &self.config);
+		let lines = self.paint(&text, &frame);
+		if self.theme.palette().is_empty() {
+			lines
+		} else {
+			lines.iter().map(|line| self.theme.quantize(line)).collect()
+		}
+	}
+
+	// Gives every character the color of the part of the frame it stands for, each with its own
+	// escape and reset so that a character can be taken out on its own.
+	fn paint(&self, text: &str, frame: &DynamicImage) -> Vec<String> {
+		let lines: Vec<&str> = text.lines().collect();
+		let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u32;
+		if columns == 0 {
+			return lines.into_iter().map(String::from).collect();
+		}
+		let cells = imageops::resize(&frame.to_rgb8(), columns, lines.len() as u32, FilterType::Triangle);
+
+		let mut painted = Vec::with_capacity(lines.len());
+		for (row, line) in lines.iter().enumerate() {
+			let mut out = String::with_capacity(line.len() * 20);
+			for (column, character) in line.chars().enumerate() {
+				let [red, green, blue] = cells.get_pixel(column as u32, row as u32).0;
+				match self.color {
+					Color::Basic => match nearest_basic([red, green, blue]) {
+						color @ 0..=7 => write!(out, "\u{1b}[{}m", 30 + color as u16),
+						color => write!(out, "\u{1b}[{}m", 90 + color as u16 - 8),
+					},
+					_ => write!(out, "\u{1b}[38;2;{};{};{}m", red, green, blue),
+				}
+				.expect("writing to a String can't fail");
+				out.push(character);
+				out.push_str("\u{1b}[0m");
+			}
+			painted.push(out);
+		}
+		painted
+	}
+
+	// Lines that were already converted are kept as they are.
+	pub fn convert_picture(&self, picture: Picture) -> Vec<String> {
+		match picture {
+			Picture::Image(image) => self.convert(image),
+			Picture::Text(lines) => lines,
+		}
+	}
+
+	pub fn convert_frame(&self, picture: Picture) -> AsciiFrame {
+		AsciiFrame::from_lines(&self.convert_picture(picture))
+	}
+
+	pub fn target_size(&self) -> u32 {
+		self.config.target_size
+	}
+
+	pub fn theme(&self) -> &Theme {
+		&self.theme
+	}
+
+	pub fn charset(&self) -> &str {
+		&self.config.characters
+	}
+
+	// How the output is colored: with truecolor escapes, with the terminal's palette or not at all.
+	pub fn color_mode(&self) -> &'static str {
+		match self.color {
+			Color::Plain => "plain",
+			Color::Truecolor if self.theme.palette().is_empty() => "truecolor",
+			_ => "ansi",
+		}
+	}
+}
+
+// The nearest of the terminal's 16 basic colors.
+fn nearest_basic(rgb: [u8; 3]) -> u8 {
+	let distance = |color: u8| {
+		indexed_color(color as u32)
+			.0
+			.iter()
+			.zip(rgb)
+			.map(|(&a, b)| (a as i32 - b as i32).pow(2))
+			.sum::<i32>()
+	};
+	(0..16).min_by_key(|&color| distance(color)).unwrap_or(0)
+}
+
+// Auto colors when the output goes to a terminal, going by the environment like the colored crate.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ColorMode {
+	Auto,
+	Always,
+	Never,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn converter(color_mode: ColorMode, theme: &str) -> Converter {
+		let theme = Theme::built_in(theme).expect("the theme is built in");
+		Converter::new(NonZeroU32::new(4).expect("4 is above 0"), color_mode, None, theme)
+	}
+
+	#[test]
+	fn truecolor_can_be_picked_regardless_of_the_terminal() {
+		assert_eq!(
+			converter(ColorMode::Always, "default")
+				.with_truecolor(true)
+				.color_mode(),
+			"truecolor"
+		);
+		assert_eq!(
+			converter(ColorMode::Always, "default")
+				.with_truecolor(false)
+				.color_mode(),
+			"ansi"
+		);
+		assert_eq!(
+			converter(ColorMode::Never, "default").with_truecolor(true).color_mode(),
+			"plain"
+		);
+		assert_eq!(
+			converter(ColorMode::Always, "matrix").with_truecolor(true).color_mode(),
+			"ansi"
+		);
+	}
+}
@@ -0,0 +1,70 @@
+use std::io;
+use std::path::PathBuf;
+
+// Everything that can go wrong in the library. The CLI goes by the variant to pick a hint and an
+// exit code, see main.rs.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	// ffmpeg or ffprobe isn't installed, or isn't in PATH.
+	#[error("{program} was not found in PATH")]
+	FfmpegNotFound { program: &'static str },
+	// Needed to find the stream behind a video site's page.
+	#[error("yt-dlp was not found in PATH")]
+	YtdlpNotFound,
+	#[error("ffprobe could not read {input}: {stderr}")]
+	ProbeFailed { input: String, stderr: String },
+	// The input was readable, but a decoder gave up on it or the stream it came from went away.
+	#[error("{0}")]
+	DecodeError(String),
+	#[error("No frames could be decoded")]
+	NoFrames,
+	// Input that is malformed or of a kind bad_ascii can't play, like a corrupted .baa file.
+	#[error("{0}")]
+	BadInput(String),
+	// Options that are out of range or don't go together.
+	#[error("{0}")]
+	BadOptions(String),
+	#[error("{}: {message}", path.display())]
+	Config { path: PathBuf, message: String },
+	// The terminal couldn't be put into or out of the state playback needs.
+	#[error("The terminal could not be set up: {0}")]
+	TerminalError(#[source] io::Error),
+	// Serving to viewers or connecting to a server failed.
+	#[error("{0}")]
+	Network(String),
+	// Converted frames couldn't be written out in the format asked for.
+	#[error("{0}")]
+	EncodeError(String),
+	#[error(transparent)]
+	Io(#[from] io::Error),
+	#[error(transparent)]
+	Image(#[from] image::ImageError),
+}
+
+#[cfg(feature = "ffmpeg-lib")]
+impl From<ffmpeg_next::Error> for Error {
+	fn from(error: ffmpeg_next::Error) -> Self {
+		Error::DecodeError(error.to_string())
+	}
+}
+
+#[cfg(feature = "gstreamer")]
+impl From<gstreamer::glib::Error> for Error {
+	fn from(error: gstreamer::glib::Error) -> Self {
+		Error::DecodeError(error.to_string())
+	}
+}
+
+#[cfg(feature = "gstreamer")]
+impl From<gstreamer::glib::BoolError> for Error {
+	fn from(error: gstreamer::glib::BoolError) -> Self {
+		Error::DecodeError(error.to_string())
+	}
+}
+
+#[cfg(feature = "gstreamer")]
+impl From<gstreamer::StateChangeError> for Error {
+	fn from(error: gstreamer::StateChangeError) -> Self {
+		Error::DecodeError(error.to_string())
+	}
+}
@@ -0,0 +1,36 @@
+mod ans;
+mod ansi;
+mod cast;
+mod gif;
+mod html;
+mod raster;
+mod sheet;
+mod svg;
+mod text;
+mod video;
+
+pub use self::ans::write_ans;
+pub use self::ansi::{apply_sgr, indexed_color, parse_line, runs};
+pub use self::cast::write_cast;
+pub use self::gif::write_gif;
+pub use self::html::write_html;
+pub use self::raster::{grid_size, rasterize};
+pub use self::sheet::{contact_sheet, write_sheet};
+pub use self::svg::write_svg;
+pub use self::text::write_text;
+pub use self::video::write_video;
+
+pub fn json_string(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len() + 2);
+	escaped.push('"');
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped.push('"');
+	escaped
+}
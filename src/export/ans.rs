@@ -0,0 +1,142 @@
+use super::ansi::{self, DEFAULT_FOREGROUND, PALETTE};
+use crate::Error;
+use image::Rgb;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Writes the selected frames, numbered from 1, as CP437 text with 16-color SGR codes and a SAUCE
+// record. Every frame is written if none are selected.
+pub fn write_ans(frames: &[Vec<String>], selected: &[usize], path: &Path) -> Result<usize, Error> {
+	let selected: Vec<usize> = if selected.is_empty() {
+		(1..=frames.len()).collect()
+	} else {
+		selected.to_vec()
+	};
+
+	for &number in &selected {
+		let frame = number
+			.checked_sub(1)
+			.and_then(|index| frames.get(index))
+			.ok_or_else(|| {
+				Error::BadOptions(format!(
+					"There is no frame {}, only {} were converted",
+					number,
+					frames.len()
+				))
+			})?;
+
+		let frame_path = if selected.len() == 1 {
+			path.to_path_buf()
+		} else {
+			numbered_path(path, number)
+		};
+		fs::write(frame_path, encode(frame))?;
+	}
+
+	Ok(selected.len())
+}
+
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+	path.with_file_name(format!("{}_{:06}.ans", stem, number))
+}
+
+fn encode(frame: &[String]) -> Vec<u8> {
+	let mut output = Vec::new();
+	let mut columns = 0;
+
+	for line in frame {
+		let cells = ansi::parse_line(line);
+		columns = columns.max(cells.len());
+
+		let mut current = None;
+		for cell in cells {
+			let color = nearest_color(cell.fg.unwrap_or(DEFAULT_FOREGROUND));
+			if current != Some(color) {
+				let sgr = if color >= 8 {
+					format!("\x1B[0;1;{}m", 30 + color - 8)
+				} else {
+					format!("\x1B[0;{}m", 30 + color)
+				};
+				output.extend_from_slice(sgr.as_bytes());
+				current = Some(color);
+			}
+			output.push(to_cp437(cell.ch));
+		}
+		output.extend_from_slice(b"\x1B[0m\r\n");
+	}
+
+	output.extend_from_slice(&sauce(output.len(), columns, frame.len()));
+	output
+}
+
+fn nearest_color(color: Rgb<u8>) -> usize {
+	let distance = |candidate: &[u8; 3]| -> i32 {
+		(0..3)
+			.map(|channel| (candidate[channel] as i32 - color.0[channel] as i32).pow(2))
+			.sum()
+	};
+	(0..PALETTE.len())
+		.min_by_key(|&index| distance(&PALETTE[index]))
+		.unwrap_or(7)
+}
+
+fn to_cp437(c: char) -> u8 {
+	match c {
+		' '..='~' => c as u8,
+		'░' => 0xB0,
+		'▒' => 0xB1,
+		'▓' => 0xB2,
+		'█' => 0xDB,
+		'▄' => 0xDC,
+		'▌' => 0xDD,
+		'▐' => 0xDE,
+		'▀' => 0xDF,
+		'·' => 0xFA,
+		'■' => 0xFE,
+		_ => b'?',
+	}
+}
+
+// SAUCE metadata lets viewers show art wider than the usual 80 columns.
+fn sauce(file_size: usize, columns: usize, rows: usize) -> Vec<u8> {
+	let mut record = vec![0x1A];
+	record.extend_from_slice(b"SAUCE00");
+	record.extend_from_slice(&[b' '; 35 + 20 + 20]);
+	record.extend_from_slice(today().as_bytes());
+	record.extend_from_slice(&(file_size as u32).to_le_bytes());
+	// Character data, ANSi file type.
+	record.extend_from_slice(&[1, 1]);
+	record.extend_from_slice(&(columns as u16).to_le_bytes());
+	record.extend_from_slice(&(rows as u16).to_le_bytes());
+	record.extend_from_slice(&[0; 4]);
+	// No comments, no flags, no font name.
+	record.extend_from_slice(&[0, 0]);
+	record.extend_from_slice(&[0; 22]);
+	record
+}
+
+// Formats the current UTC date as CCYYMMDD.
+fn today() -> String {
+	let days = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |elapsed| elapsed.as_secs() / 86_400) as i64;
+
+	// Howard Hinnant's days_from_civil, inverted.
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let day_of_era = z.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	};
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+	format!("{:04}{:02}{:02}", year, month, day)
+}
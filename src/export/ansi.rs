@@ -0,0 +1,129 @@
+use crate::frame::Cell;
+use image::Rgb;
+
+pub const DEFAULT_FOREGROUND: Rgb<u8> = Rgb([229, 229, 229]);
+pub const BACKGROUND: Rgb<u8> = Rgb([0, 0, 0]);
+
+// xterm's default palette, which is what artem's basic colors look like in most terminals.
+pub const PALETTE: [[u8; 3]; 16] = [
+	[0, 0, 0],
+	[205, 0, 0],
+	[0, 205, 0],
+	[205, 205, 0],
+	[0, 0, 238],
+	[205, 0, 205],
+	[0, 205, 205],
+	[229, 229, 229],
+	[127, 127, 127],
+	[255, 0, 0],
+	[0, 255, 0],
+	[255, 255, 0],
+	[92, 92, 255],
+	[255, 0, 255],
+	[0, 255, 255],
+	[255, 255, 255],
+];
+
+// Splits a converted line into its characters and the SGR colors they are drawn with, None where
+// the terminal's own colors are left.
+pub fn parse_line(line: &str) -> Vec<Cell> {
+	let mut cells = Vec::new();
+	let mut foreground = None;
+	let mut background = None;
+	let mut chars = line.chars();
+
+	while let Some(c) = chars.next() {
+		if c != '\u{1b}' {
+			cells.push(Cell {
+				ch: c,
+				fg: foreground,
+				bg: background,
+			});
+			continue;
+		}
+
+		let mut sequence = String::new();
+		for c in chars.by_ref() {
+			if c.is_ascii_alphabetic() {
+				if c == 'm' {
+					apply_sgr(&sequence, &mut foreground, &mut background);
+				}
+				break;
+			}
+			sequence.push(c);
+		}
+	}
+
+	cells
+}
+
+// Groups a line's characters into runs that share a foreground color.
+pub fn runs(line: &str) -> Vec<(Rgb<u8>, String)> {
+	let mut runs: Vec<(Rgb<u8>, String)> = Vec::new();
+	for cell in parse_line(line) {
+		let foreground = cell.fg.unwrap_or(DEFAULT_FOREGROUND);
+		match runs.last_mut() {
+			Some((color, text)) if *color == foreground => text.push(cell.ch),
+			_ => runs.push((foreground, cell.ch.to_string())),
+		}
+	}
+	runs
+}
+
+// Applies the parameters of an SGR sequence, what comes between its CSI and the m.
+pub fn apply_sgr(sequence: &str, foreground: &mut Option<Rgb<u8>>, background: &mut Option<Rgb<u8>>) {
+	let params: Vec<u32> = sequence
+		.trim_start_matches('[')
+		.split(';')
+		.map(|param| param.parse().unwrap_or(0))
+		.collect();
+
+	let mut params = params.into_iter();
+	while let Some(param) = params.next() {
+		match param {
+			0 => {
+				*foreground = None;
+				*background = None;
+			},
+			30..=37 => *foreground = Some(Rgb(PALETTE[(param - 30) as usize])),
+			90..=97 => *foreground = Some(Rgb(PALETTE[(param - 90 + 8) as usize])),
+			39 => *foreground = None,
+			40..=47 => *background = Some(Rgb(PALETTE[(param - 40) as usize])),
+			100..=107 => *background = Some(Rgb(PALETTE[(param - 100 + 8) as usize])),
+			49 => *background = None,
+			38 | 48 => {
+				let color = match params.next() {
+					Some(2) => {
+						let mut channel = || params.next().unwrap_or(0).min(255) as u8;
+						Some(Rgb([channel(), channel(), channel()]))
+					},
+					Some(5) => params.next().map(indexed_color),
+					_ => None,
+				};
+				if color.is_some() {
+					if param == 38 {
+						*foreground = color;
+					} else {
+						*background = color;
+					}
+				}
+			},
+			_ => {},
+		}
+	}
+}
+
+pub fn indexed_color(index: u32) -> Rgb<u8> {
+	match index {
+		0..=15 => Rgb(PALETTE[index as usize]),
+		16..=231 => {
+			let level = |value: u32| if value == 0 { 0 } else { (55 + value * 40) as u8 };
+			let index = index - 16;
+			Rgb([level(index / 36), level(index / 6 % 6), level(index % 6)])
+		},
+		_ => {
+			let gray = (8 + (index.min(255) - 232) * 10) as u8;
+			Rgb([gray, gray, gray])
+		},
+	}
+}
@@ -0,0 +1,46 @@
+use super::{json_string, raster};
+use crate::Error;
+use crate::render::draw_frame;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Writes an asciinema v2 recording of the same escape sequences the player sends to the terminal.
+pub fn write_cast(frames: &[Vec<String>], delays: &[Duration], path: &Path) -> Result<(), Error> {
+	let (columns, rows) = raster::grid_size(frames);
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+
+	let mut cast = String::new();
+	writeln!(
+		cast,
+		"{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"env\": {{\"TERM\": \"xterm-256color\"}}}}",
+		columns, rows, timestamp
+	)
+	.expect("writing to a String can't fail");
+	writeln!(cast, "[0.000000, \"o\", {}]", json_string("\x1B[2J\x1B[?25l")).expect("writing to a String can't fail");
+
+	let mut time = Duration::ZERO;
+	let mut previous: Option<&[String]> = None;
+	for (frame, delay) in frames.iter().zip(delays) {
+		let mut output = Vec::new();
+		draw_frame(&mut output, frame, previous, 1, 1)?;
+		writeln!(
+			cast,
+			"[{:.6}, \"o\", {}]",
+			time.as_secs_f64(),
+			json_string(&String::from_utf8(output).expect("frames are UTF-8"))
+		)
+		.expect("writing to a String can't fail");
+		previous = Some(frame);
+		time += *delay;
+	}
+	writeln!(cast, "[{:.6}, \"o\", {}]", time.as_secs_f64(), json_string("\x1B[?25h"))
+		.expect("writing to a String can't fail");
+
+	fs::write(path, cast)?;
+	Ok(())
+}
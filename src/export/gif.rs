@@ -0,0 +1,69 @@
+use super::raster::{self, CELL_HEIGHT, CELL_WIDTH};
+use crate::Error;
+use gif::{Encoder, Frame, Repeat};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+pub fn write_gif(frames: &[Vec<String>], delays: &[Duration], path: &Path) -> Result<(), Error> {
+	let (columns, rows) = raster::grid_size(frames);
+	let width = u16::try_from(columns * CELL_WIDTH)
+		.map_err(|_| Error::EncodeError("Frames are too wide for a GIF".to_string()))?;
+	let height = u16::try_from(rows * CELL_HEIGHT)
+		.map_err(|_| Error::EncodeError("Frames are too tall for a GIF".to_string()))?;
+
+	let mut encoder = Encoder::new(BufWriter::new(File::create(path)?), width, height, &[]).map_err(gif_error)?;
+	encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+
+	// GIF delays are in hundredths of a second, so they are rounded from the running total to keep
+	// the rounding errors from adding up.
+	let mut elapsed = Duration::ZERO;
+	let mut written_centis = 0;
+
+	for (frame, delay) in frames.iter().zip(delays) {
+		let image = raster::rasterize(frame, columns, rows);
+		let mut gif_frame = indexed_frame(&image, width, height)
+			.unwrap_or_else(|| Frame::from_rgb_speed(width, height, image.as_raw(), 10));
+
+		elapsed += *delay;
+		let total_centis = (elapsed.as_millis() / 10) as u64;
+		gif_frame.delay = (total_centis - written_centis).min(u16::MAX as u64) as u16;
+		written_centis = total_centis;
+
+		encoder.write_frame(&gif_frame).map_err(gif_error)?;
+	}
+
+	Ok(())
+}
+
+fn gif_error(error: gif::EncodingError) -> Error {
+	Error::EncodeError(format!("Failed to write the GIF: {}", error))
+}
+
+// Converted frames rarely use more than a handful of colors, which fit a palette exactly without
+// having to be quantized.
+fn indexed_frame(image: &image::RgbImage, width: u16, height: u16) -> Option<Frame<'static>> {
+	let mut palette = Vec::new();
+	let mut indices = HashMap::new();
+	let mut pixels = Vec::with_capacity(image.len() / 3);
+
+	for pixel in image.pixels() {
+		let index = match indices.get(&pixel.0) {
+			Some(&index) => index,
+			None => {
+				if indices.len() == 256 {
+					return None;
+				}
+				let index = indices.len() as u8;
+				indices.insert(pixel.0, index);
+				palette.extend_from_slice(&pixel.0);
+				index
+			},
+		};
+		pixels.push(index);
+	}
+
+	Some(Frame::from_palette_pixels(width, height, pixels, palette, None))
+}
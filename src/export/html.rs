@@ -0,0 +1,80 @@
+use super::{ansi, json_string};
+use crate::Error;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use image::Rgb;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+const TEMPLATE: &str = include_str!("player.html");
+
+// The frames are embedded as raw deflated JSON, which the page inflates with DecompressionStream.
+// Every line is a list of color indices followed by the text drawn in that color.
+pub fn write_html(frames: &[Vec<String>], delays: &[Duration], path: &Path) -> Result<(), Error> {
+	let mut palette: HashMap<Rgb<u8>, usize> = HashMap::new();
+	let mut json = String::from("{\"delays\":[");
+
+	let delays_ms: Vec<String> = delays
+		.iter()
+		.map(|delay| format!("{:.3}", delay.as_secs_f64() * 1000.0))
+		.collect();
+	json.push_str(&delays_ms.join(","));
+	json.push_str("],\"frames\":[");
+
+	for (frame_index, frame) in frames.iter().enumerate() {
+		if frame_index > 0 {
+			json.push(',');
+		}
+		json.push('[');
+		for (line_index, line) in frame.iter().enumerate() {
+			if line_index > 0 {
+				json.push(',');
+			}
+			json.push('[');
+
+			let runs: Vec<String> = ansi::runs(line)
+				.into_iter()
+				.map(|(color, text)| {
+					let next_index = palette.len();
+					let color = *palette.entry(color).or_insert(next_index);
+					format!("{},{}", color, json_string(&text))
+				})
+				.collect();
+			json.push_str(&runs.join(","));
+			json.push(']');
+		}
+		json.push(']');
+	}
+	json.push_str("]}");
+
+	let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+	encoder.write_all(json.as_bytes())?;
+	let compressed = encoder.finish()?;
+
+	let mut colors: Vec<(&Rgb<u8>, &usize)> = palette.iter().collect();
+	colors.sort_by_key(|(_, index)| **index);
+	let css: Vec<String> = colors
+		.into_iter()
+		.map(|(Rgb([r, g, b]), index)| format!(".c{} {{ color: #{:02x}{:02x}{:02x}; }}", index, r, g, b))
+		.collect();
+
+	let title = path
+		.file_stem()
+		.map_or("bad_ascii".into(), |stem| stem.to_string_lossy());
+	let html = TEMPLATE
+		.replace("{{TITLE}}", &html_escape(&title))
+		.replace("{{PALETTE}}", &css.join("\n"))
+		.replace("{{FRAMES}}", &STANDARD.encode(compressed));
+	fs::write(path, html)?;
+
+	Ok(())
+}
+
+fn html_escape(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,48 @@
+use super::ansi::{self, BACKGROUND, DEFAULT_FOREGROUND};
+use font8x8::legacy::BASIC_LEGACY;
+use image::RgbImage;
+
+// Glyphs are 8x8, drawn with every row doubled so that cells have the 1:2 shape of a terminal's.
+pub const CELL_WIDTH: u32 = 8;
+pub const CELL_HEIGHT: u32 = 16;
+
+pub fn rasterize(frame: &[String], columns: u32, rows: u32) -> RgbImage {
+	let mut image = RgbImage::from_pixel(columns * CELL_WIDTH, rows * CELL_HEIGHT, BACKGROUND);
+
+	for (row, line) in frame.iter().enumerate().take(rows as usize) {
+		for (column, cell) in ansi::parse_line(line).into_iter().enumerate().take(columns as usize) {
+			let glyph = BASIC_LEGACY
+				.get(cell.ch as usize)
+				.unwrap_or(&BASIC_LEGACY[b'?' as usize]);
+			let left = column as u32 * CELL_WIDTH;
+			let top = row as u32 * CELL_HEIGHT;
+
+			for y in 0..CELL_HEIGHT {
+				let bits = glyph[(y / 2) as usize];
+				for x in 0..CELL_WIDTH {
+					let lit = bits & (1 << x) != 0;
+					let color = if lit {
+						cell.fg.unwrap_or(DEFAULT_FOREGROUND)
+					} else {
+						cell.bg.unwrap_or(BACKGROUND)
+					};
+					image.put_pixel(left + x, top + y, color);
+				}
+			}
+		}
+	}
+
+	image
+}
+
+// Size in cells that fits every frame, so that all of them are rendered to the same size.
+pub fn grid_size(frames: &[Vec<String>]) -> (u32, u32) {
+	let columns = frames
+		.iter()
+		.flatten()
+		.map(|line| ansi::parse_line(line).len())
+		.max()
+		.unwrap_or(0);
+	let rows = frames.iter().map(Vec::len).max().unwrap_or(0);
+	(columns as u32, rows as u32)
+}
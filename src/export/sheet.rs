@@ -0,0 +1,66 @@
+use super::ansi;
+use super::raster::{self, rasterize};
+use crate::Error;
+use crate::render::remove_ansi_escape_sequences;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const GAP: &str = "  ";
+
+// Tiles the thumbnails into rows of lines, each captioned with the time it was taken at.
+pub fn contact_sheet(thumbnails: &[(Duration, Vec<String>)], per_row: usize, color: bool) -> Vec<String> {
+	let width = thumbnails
+		.iter()
+		.flat_map(|(_, lines)| lines)
+		.map(|line| ansi::parse_line(line).len())
+		.max()
+		.unwrap_or(0);
+	let height = thumbnails.iter().map(|(_, lines)| lines.len()).max().unwrap_or(0);
+
+	let mut sheet = Vec::new();
+	for row in thumbnails.chunks(per_row) {
+		if !sheet.is_empty() {
+			sheet.push(String::new());
+		}
+
+		let captions: Vec<String> = row
+			.iter()
+			.map(|(timestamp, _)| {
+				let seconds = timestamp.as_secs();
+				format!("{:<width$}", format!("{:02}:{:02}", seconds / 60, seconds % 60))
+			})
+			.collect();
+		sheet.push(captions.join(GAP).trim_end().to_string());
+
+		for y in 0..height {
+			let cells: Vec<String> = row
+				.iter()
+				.map(|(_, lines)| {
+					let line = lines.get(y).map_or("", String::as_str);
+					let padding = " ".repeat(width - ansi::parse_line(line).len());
+					if color {
+						format!("{}{}", line, padding)
+					} else {
+						format!("{}{}", remove_ansi_escape_sequences(line), padding)
+					}
+				})
+				.collect();
+			sheet.push(cells.join(GAP).trim_end().to_string());
+		}
+	}
+	sheet
+}
+
+pub fn write_sheet(sheet: &[String], path: &Path) -> Result<(), Error> {
+	if path
+		.extension()
+		.is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+	{
+		let (columns, rows) = raster::grid_size(&[sheet.to_vec()]);
+		rasterize(sheet, columns, rows).save(path)?;
+	} else {
+		fs::write(path, sheet.iter().map(|line| format!("{}\n", line)).collect::<String>())?;
+	}
+	Ok(())
+}
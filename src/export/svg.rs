@@ -0,0 +1,87 @@
+use super::ansi;
+use super::raster;
+use crate::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const FONT_SIZE: f64 = 12.0;
+const CHAR_WIDTH: f64 = FONT_SIZE * 0.6;
+const LINE_HEIGHT: f64 = FONT_SIZE * 1.2;
+
+// Every frame is a group of text rows that a discrete SMIL animation shows only during its own
+// slice of the loop.
+pub fn write_svg(frames: &[Vec<String>], delays: &[Duration], path: &Path) -> Result<(), Error> {
+	let (columns, rows) = raster::grid_size(frames);
+	let width = columns as f64 * CHAR_WIDTH;
+	let height = rows as f64 * LINE_HEIGHT;
+	let total = delays.iter().sum::<Duration>().as_secs_f64();
+
+	let mut svg = String::new();
+	writeln!(
+		svg,
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.1}\" height=\"{h:.1}\" viewBox=\"0 0 {w:.1} {h:.1}\" \
+		 font-family=\"monospace\" font-size=\"{}\" xml:space=\"preserve\">",
+		FONT_SIZE,
+		w = width,
+		h = height
+	)
+	.expect("writing to a String can't fail");
+	writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"#000\"/>").expect("writing to a String can't fail");
+
+	let mut start = 0.0;
+	for (frame, delay) in frames.iter().zip(delays) {
+		let end = start + delay.as_secs_f64();
+		let visibility = if start == 0.0 { "visible" } else { "hidden" };
+		writeln!(svg, "<g visibility=\"{}\">", visibility).expect("writing to a String can't fail");
+
+		if frames.len() > 1 {
+			let (values, key_times) = match (start == 0.0, end >= total) {
+				(true, true) => ("visible".to_string(), "0".to_string()),
+				(true, false) => ("visible;hidden".into(), format!("0;{:.6}", end / total)),
+				(false, true) => ("hidden;visible".into(), format!("0;{:.6}", start / total)),
+				(false, false) => (
+					"hidden;visible;hidden".into(),
+					format!("0;{:.6};{:.6}", start / total, end / total),
+				),
+			};
+			writeln!(
+				svg,
+				"<animate attributeName=\"visibility\" values=\"{}\" keyTimes=\"{}\" dur=\"{:.3}s\" \
+				 calcMode=\"discrete\" repeatCount=\"indefinite\"/>",
+				values, key_times, total
+			)
+			.expect("writing to a String can't fail");
+		}
+
+		for (row, line) in frame.iter().enumerate() {
+			write!(svg, "<text y=\"{:.1}\">", (row as f64 + 0.8) * LINE_HEIGHT)
+				.expect("writing to a String can't fail");
+			for (color, text) in ansi::runs(line) {
+				let [r, g, b] = color.0;
+				write!(
+					svg,
+					"<tspan fill=\"#{:02x}{:02x}{:02x}\">{}</tspan>",
+					r,
+					g,
+					b,
+					xml_escape(&text)
+				)
+				.expect("writing to a String can't fail");
+			}
+			writeln!(svg, "</text>").expect("writing to a String can't fail");
+		}
+
+		writeln!(svg, "</g>").expect("writing to a String can't fail");
+		start = end;
+	}
+
+	writeln!(svg, "</svg>").expect("writing to a String can't fail");
+	fs::write(path, svg)?;
+	Ok(())
+}
+
+fn xml_escape(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,49 @@
+use crate::Error;
+use crate::render::remove_ansi_escape_sequences;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+pub fn write_text(frames: &[Vec<String>], delays: &[Duration], dir: &Path, color: bool) -> Result<(), Error> {
+	fs::create_dir_all(dir)?;
+
+	for (index, frame) in frames.iter().enumerate() {
+		let mut contents = String::new();
+		for line in frame {
+			if color {
+				contents.push_str(line);
+			} else {
+				contents.push_str(&remove_ansi_escape_sequences(line));
+			}
+			contents.push('\n');
+		}
+		fs::write(dir.join(format!("frame_{:06}.txt", index + 1)), contents)?;
+	}
+
+	let width = frames
+		.iter()
+		.flatten()
+		.map(|line| remove_ansi_escape_sequences(line).chars().count())
+		.max()
+		.unwrap_or(0);
+	let height = frames.iter().map(Vec::len).max().unwrap_or(0);
+	let total_duration: Duration = delays.iter().sum();
+	let fps = frames.len() as f64 / total_duration.as_secs_f64();
+	let delays_ms: Vec<String> = delays
+		.iter()
+		.map(|delay| format!("{:.3}", delay.as_secs_f64() * 1000.0))
+		.collect();
+
+	let manifest = format!(
+		"{{\n  \"frames\": {},\n  \"fps\": {:.3},\n  \"width\": {},\n  \"height\": {},\n  \"color\": {},\n  \"delays_ms\": [{}]\n}}\n",
+		frames.len(),
+		fps,
+		width,
+		height,
+		color,
+		delays_ms.join(", ")
+	);
+	fs::write(dir.join("manifest.json"), manifest)?;
+
+	Ok(())
+}
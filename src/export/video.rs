@@ -0,0 +1,88 @@
+use super::raster::{self, CELL_HEIGHT, CELL_WIDTH};
+use crate::{Error, OUTPUT_FPS};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+// Renders the frames at a constant rate and pipes them into ffmpeg, muxing in the audio of the
+// original input when it has any.
+pub fn write_video(
+	frames: &[Vec<String>],
+	delays: &[Duration],
+	audio_source: Option<&str>,
+	path: &Path,
+) -> Result<(), Error> {
+	let (columns, rows) = raster::grid_size(frames);
+	let size = format!("{}x{}", columns * CELL_WIDTH, rows * CELL_HEIGHT);
+
+	let mut args: Vec<String> = [
+		"-y",
+		"-loglevel",
+		"error",
+		"-f",
+		"rawvideo",
+		"-pix_fmt",
+		"rgb24",
+		"-s",
+		&size,
+		"-r",
+	]
+	.map(String::from)
+	.into();
+	args.extend([OUTPUT_FPS.to_string(), "-i".into(), "-".into()]);
+	if let Some(audio_source) = audio_source {
+		args.extend(
+			[
+				"-i",
+				audio_source,
+				"-map",
+				"0:v",
+				"-map",
+				"1:a?",
+				"-c:a",
+				"aac",
+				"-shortest",
+			]
+			.map(String::from),
+		);
+	}
+	args.extend(["-c:v", "libx264", "-pix_fmt", "yuv420p"].map(String::from));
+	args.push(path.to_string_lossy().into_owned());
+
+	let mut child = Command::new("ffmpeg")
+		.args(&args)
+		.stdin(Stdio::piped())
+		.spawn()
+		.map_err(|error| match error.kind() {
+			ErrorKind::NotFound => Error::FfmpegNotFound { program: "ffmpeg" },
+			_ => error.into(),
+		})?;
+	let mut stdin = child.stdin.take().unwrap();
+
+	// Every output frame shows whichever converted frame is due at that point in time.
+	let frame_interval = Duration::from_secs_f64(1.0 / OUTPUT_FPS as f64);
+	let total_duration: Duration = delays.iter().sum();
+	let mut shown_until = Duration::ZERO;
+	let mut current = None;
+	let mut index = 0;
+	let mut time = Duration::ZERO;
+
+	while time < total_duration {
+		while shown_until <= time && index < frames.len() {
+			shown_until += delays[index];
+			current = Some(raster::rasterize(&frames[index], columns, rows));
+			index += 1;
+		}
+		if let Some(image) = &current {
+			stdin.write_all(image.as_raw())?;
+		}
+		time += frame_interval;
+	}
+	drop(stdin);
+
+	if !child.wait()?.success() {
+		return Err(Error::EncodeError("ffmpeg failed to encode the video".to_string()));
+	}
+	Ok(())
+}
@@ -0,0 +1,42 @@
+use crate::control::{self, Command, Request};
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+// Reads one-line commands like `seek +10` from a named pipe, for scripts that just want to
+// `echo pause > fifo`. Nothing is written back, so lines that don't parse are skipped.
+pub fn listen(path: &Path, requests: Sender<Request>) -> io::Result<()> {
+	if !path.exists() {
+		let c_path = CString::new(path.as_os_str().as_bytes())?;
+		// Only the user playing gets to control it.
+		if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+			return Err(io::Error::last_os_error());
+		}
+	} else if !fs::metadata(path)?.file_type().is_fifo() {
+		return Err(io::Error::other("not a named pipe"));
+	}
+
+	let path = path.to_path_buf();
+	thread::spawn(move || read_commands(&path, &requests));
+	Ok(())
+}
+
+fn read_commands(path: &Path, requests: &Sender<Request>) -> io::Result<()> {
+	// Opening blocks until something writes, and reading ends when the last writer closes, so the
+	// pipe is reopened for the next one.
+	loop {
+		for line in BufReader::new(File::open(path)?).lines() {
+			let Ok(command) = Command::parse(&line?) else {
+				continue;
+			};
+			if control::send(requests, command).is_none() {
+				return Ok(());
+			}
+		}
+	}
+}
@@ -0,0 +1,228 @@
+use crate::export::parse_line;
+use image::Rgb;
+use std::fmt::Write as _;
+use std::ops::Range;
+
+// A character on screen and its colors, None being the terminal's own.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+	pub ch: char,
+	pub fg: Option<Rgb<u8>>,
+	pub bg: Option<Rgb<u8>>,
+}
+
+impl Default for Cell {
+	fn default() -> Self {
+		Self {
+			ch: ' ',
+			fg: None,
+			bg: None,
+		}
+	}
+}
+
+// A frame as a grid of cells, row after row. Converted lines carry their colors as escapes, which
+// can't be compared or drawn over a character at a time, so frames are turned into this to be
+// diffed, composited and handed to renderers.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct AsciiFrame {
+	pub cols: usize,
+	pub rows: usize,
+	pub cells: Vec<Cell>,
+}
+
+impl AsciiFrame {
+	pub fn new(cols: usize, rows: usize) -> Self {
+		Self {
+			cols,
+			rows,
+			cells: vec![Cell::default(); cols * rows],
+		}
+	}
+
+	// Lines shorter than the longest one are padded with blank cells.
+	pub fn from_lines(lines: &[String]) -> Self {
+		let rows: Vec<Vec<Cell>> = lines.iter().map(|line| parse_line(line)).collect();
+		let mut frame = Self::new(rows.iter().map(Vec::len).max().unwrap_or(0), rows.len());
+		for (row, cells) in rows.iter().enumerate() {
+			frame.row_mut(row)[..cells.len()].copy_from_slice(cells);
+		}
+		frame
+	}
+
+	pub fn to_lines(&self) -> Vec<String> {
+		(0..self.rows).map(|row| to_ansi(self.row(row))).collect()
+	}
+
+	pub fn row(&self, row: usize) -> &[Cell] {
+		&self.cells[row * self.cols..(row + 1) * self.cols]
+	}
+
+	pub fn row_mut(&mut self, row: usize) -> &mut [Cell] {
+		&mut self.cells[row * self.cols..(row + 1) * self.cols]
+	}
+
+	pub fn get(&self, col: usize, row: usize) -> Option<&Cell> {
+		(col < self.cols && row < self.rows).then(|| &self.cells[row * self.cols + col])
+	}
+
+	// Keeps the cells that still fit, filling new ones with blanks.
+	pub fn resize(&mut self, cols: usize, rows: usize) {
+		if (cols, rows) == (self.cols, self.rows) {
+			return;
+		}
+		let mut resized = Self::new(cols, rows);
+		for row in 0..self.rows.min(rows) {
+			let width = self.cols.min(cols);
+			resized.row_mut(row)[..width].copy_from_slice(&self.row(row)[..width]);
+		}
+		*self = resized;
+	}
+
+	// Replaces a row, growing the frame when it is below the last one or wider than the others.
+	pub fn set_row(&mut self, row: usize, cells: &[Cell]) {
+		self.resize(self.cols.max(cells.len()), self.rows.max(row + 1));
+		let target = self.row_mut(row);
+		target[..cells.len()].copy_from_slice(cells);
+		target[cells.len()..].fill(Cell::default());
+	}
+
+	// Draws another frame over this one with its top left corner at the given cell. Blank cells
+	// with no background are see-through, and whatever falls outside this frame is cut off.
+	pub fn overlay(&mut self, other: &AsciiFrame, top: usize, left: usize) {
+		for row in 0..other.rows.min(self.rows.saturating_sub(top)) {
+			for col in 0..other.cols.min(self.cols.saturating_sub(left)) {
+				let cell = other.row(row)[col];
+				if cell != Cell::default() {
+					self.row_mut(top + row)[left + col] = cell;
+				}
+			}
+		}
+	}
+
+	// Runs of cells that differ from the previous frame, row by row. Without a previous frame of the
+	// same size, every row is one whole run.
+	pub fn changes(&self, previous: Option<&AsciiFrame>) -> Vec<(usize, Range<usize>)> {
+		let previous = previous.filter(|previous| (previous.cols, previous.rows) == (self.cols, self.rows));
+		let Some(previous) = previous else {
+			return (0..self.rows).map(|row| (row, 0..self.cols)).collect();
+		};
+
+		let mut changes = Vec::new();
+		for row in 0..self.rows {
+			let (current, before) = (self.row(row), previous.row(row));
+			let mut col = 0;
+			while col < self.cols {
+				if current[col] == before[col] {
+					col += 1;
+					continue;
+				}
+				let start = col;
+				while col < self.cols && current[col] != before[col] {
+					col += 1;
+				}
+				changes.push((row, start..col));
+			}
+		}
+		changes
+	}
+}
+
+// Cells as text with the escapes for their colors, ending with a reset whenever a color was set.
+pub fn to_ansi(cells: &[Cell]) -> String {
+	let mut text = String::with_capacity(cells.len());
+	let (mut fg, mut bg) = (None, None);
+	for cell in cells {
+		if (cell.fg, cell.bg) != (fg, bg) {
+			// Going back to the terminal's colors takes a reset, after which both are set again.
+			let mut params = String::new();
+			if (cell.fg.is_none() && fg.is_some()) || (cell.bg.is_none() && bg.is_some()) {
+				params.push('0');
+				(fg, bg) = (None, None);
+			}
+			for (color, current, code) in [(cell.fg, &mut fg, 38), (cell.bg, &mut bg, 48)] {
+				if let Some(Rgb([red, green, blue])) = color.filter(|_| color != *current) {
+					if !params.is_empty() {
+						params.push(';');
+					}
+					write!(params, "{};2;{};{};{}", code, red, green, blue).expect("writing to a String can't fail");
+				}
+				*current = color;
+			}
+			write!(text, "\x1B[{}m", params).expect("writing to a String can't fail");
+		}
+		text.push(cell.ch);
+	}
+	if fg.is_some() || bg.is_some() {
+		text.push_str("\x1B[0m");
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RED: Option<Rgb<u8>> = Some(Rgb([255, 0, 0]));
+	const BLUE: Option<Rgb<u8>> = Some(Rgb([0, 0, 255]));
+
+	fn cell(ch: char, fg: Option<Rgb<u8>>, bg: Option<Rgb<u8>>) -> Cell {
+		Cell { ch, fg, bg }
+	}
+
+	fn plain(lines: &[&str]) -> AsciiFrame {
+		AsciiFrame::from_lines(&lines.iter().map(|line| line.to_string()).collect::<Vec<_>>())
+	}
+
+	#[test]
+	fn changes_are_runs_of_differing_cells() {
+		let before = plain(&["abcdef", "ghijkl"]);
+		let after = plain(&["aXYdeZ", "ghijkl"]);
+		assert_eq!(after.changes(Some(&before)), [(0, 1..3), (0, 5..6)]);
+		assert!(after.changes(Some(&after)).is_empty());
+	}
+
+	#[test]
+	fn changes_without_a_comparable_frame_are_whole_rows() {
+		let frame = plain(&["abc", "de"]);
+		assert_eq!(frame.changes(None), [(0, 0..3), (1, 0..3)]);
+		assert_eq!(frame.changes(Some(&plain(&["abc"]))), [(0, 0..3), (1, 0..3)]);
+	}
+
+	#[test]
+	fn changes_see_colors_too() {
+		let before = AsciiFrame {
+			cols: 2,
+			rows: 1,
+			cells: vec![cell('a', RED, None), cell('b', None, None)],
+		};
+		let mut after = before.clone();
+		after.cells[0].fg = BLUE;
+		assert_eq!(after.changes(Some(&before)), [(0, 0..1)]);
+	}
+
+	#[test]
+	fn to_ansi_sets_colors_only_when_they_change() {
+		assert_eq!(to_ansi(&[cell('a', None, None), cell('b', None, None)]), "ab");
+		assert_eq!(
+			to_ansi(&[cell('a', RED, None), cell('b', RED, None), cell('c', None, None)]),
+			"\x1B[38;2;255;0;0mab\x1B[0mc"
+		);
+		assert_eq!(
+			to_ansi(&[cell('a', RED, BLUE), cell('b', BLUE, BLUE)]),
+			"\x1B[38;2;255;0;0;48;2;0;0;255ma\x1B[38;2;0;0;255mb\x1B[0m"
+		);
+	}
+
+	#[test]
+	fn lines_round_trip() {
+		let lines = vec![
+			"\x1B[38;2;255;0;0mab\x1B[0mc".to_string(),
+			"\x1B[38;2;255;0;0;48;2;0;0;255ma\x1B[38;2;0;0;255mb\x1B[0m".to_string(),
+		];
+		let frame = AsciiFrame::from_lines(&lines);
+		assert_eq!((frame.cols, frame.rows), (3, 2));
+		assert_eq!(frame.get(2, 1), Some(&Cell::default()));
+		assert_eq!(AsciiFrame::from_lines(&frame.to_lines()), frame);
+	}
+}
@@ -0,0 +1,109 @@
+use crate::control::{self, Command, Request};
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+// Serves a protocol modelled on mpv's: every line is a JSON object like
+//   {"command": ["seek", 10], "request_id": 1}
+// and is answered with a line like
+//   {"data": null, "error": "success", "request_id": 1}
+// The socket is removed again when the server is dropped.
+pub struct IpcServer {
+	path: PathBuf,
+}
+
+impl IpcServer {
+	pub fn start(path: &Path, requests: Sender<Request>) -> io::Result<Self> {
+		// A socket nobody answers on was left behind by a player that didn't exit cleanly.
+		if path.exists() && UnixStream::connect(path).is_err() {
+			fs::remove_file(path)?;
+		}
+		let listener = UnixListener::bind(path)?;
+
+		thread::spawn(move || {
+			for client in listener.incoming().flatten() {
+				let requests = requests.clone();
+				thread::spawn(move || serve(client, &requests));
+			}
+		});
+		Ok(Self {
+			path: path.to_path_buf(),
+		})
+	}
+}
+
+impl Drop for IpcServer {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+fn serve(client: UnixStream, requests: &Sender<Request>) -> io::Result<()> {
+	let mut writer = client.try_clone()?;
+	for line in BufReader::new(client).lines() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let (request_id, reply) = match serde_json::from_str::<Value>(&line) {
+			Ok(message) => {
+				let reply = match parse(&message) {
+					Ok(command) => match control::send(requests, command) {
+						Some(reply) => reply,
+						// The player has exited.
+						None => return Ok(()),
+					},
+					Err(error) => Err(error),
+				};
+				(message.get("request_id").cloned(), reply)
+			},
+			Err(error) => (None, Err(format!("invalid JSON: {}", error))),
+		};
+
+		let mut response = match reply {
+			Ok(data) => json!({"data": data, "error": "success"}),
+			Err(error) => json!({"error": error}),
+		};
+		if let Some(request_id) = request_id {
+			response["request_id"] = request_id;
+		}
+		writeln!(writer, "{}", response)?;
+	}
+	Ok(())
+}
+
+fn parse(message: &Value) -> Result<Command, String> {
+	let arguments = message
+		.get("command")
+		.and_then(Value::as_array)
+		.ok_or("expected an object like {\"command\": [\"name\", arguments...]}")?;
+	let (name, arguments) = arguments.split_first().ok_or("the command is empty")?;
+	let name = name.as_str().ok_or("the command name has to be a string")?;
+
+	match (name, arguments) {
+		("quit", []) => Ok(Command::Quit),
+		("pause", []) => Ok(Command::Pause(None)),
+		("pause", [Value::Bool(paused)]) => Ok(Command::Pause(Some(*paused))),
+		("seek", [seconds]) | ("seek", [seconds, Value::String(_)]) => {
+			let seconds = seconds.as_f64().ok_or("seek takes a number of seconds")?;
+			let absolute = match arguments.get(1).and_then(Value::as_str) {
+				None | Some("relative") => false,
+				Some("absolute") => true,
+				Some(mode) => return Err(format!("unknown seek mode `{}`, try relative or absolute", mode)),
+			};
+			Ok(Command::Seek { seconds, absolute })
+		},
+		("loadfile", [Value::String(path)]) => Ok(Command::Load(path.clone())),
+		("get_position", []) => Ok(Command::GetPosition),
+		("quit" | "pause" | "seek" | "loadfile" | "get_position", _) => Err(format!("wrong arguments for {}", name)),
+		_ => Err(format!(
+			"unknown command `{}`, try one of quit, pause, seek, loadfile, get_position",
+			name
+		)),
+	}
+}
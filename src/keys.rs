@@ -0,0 +1,295 @@
+use bad_ascii::export::json_string;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use toml::{Table, Value};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+	Quit,
+	Pause,
+	SeekForward,
+	SeekBackward,
+	SpeedUp,
+	SlowDown,
+	Screenshot,
+	NextChapter,
+	PreviousChapter,
+}
+
+impl Action {
+	const ALL: [Action; 9] = [
+		Action::Quit,
+		Action::Pause,
+		Action::SeekForward,
+		Action::SeekBackward,
+		Action::SpeedUp,
+		Action::SlowDown,
+		Action::Screenshot,
+		Action::NextChapter,
+		Action::PreviousChapter,
+	];
+
+	// The name used for it under [keys] in config.toml.
+	fn name(self) -> &'static str {
+		match self {
+			Action::Quit => "quit",
+			Action::Pause => "pause",
+			Action::SeekForward => "seek-forward",
+			Action::SeekBackward => "seek-backward",
+			Action::SpeedUp => "speed-up",
+			Action::SlowDown => "slow-down",
+			Action::Screenshot => "screenshot",
+			Action::NextChapter => "next-chapter",
+			Action::PreviousChapter => "previous-chapter",
+		}
+	}
+
+	fn default_keys(self) -> &'static [&'static str] {
+		match self {
+			Action::Quit => &["q", "esc", "ctrl+c"],
+			Action::Pause => &["space"],
+			Action::SeekForward => &["right"],
+			Action::SeekBackward => &["left"],
+			Action::SpeedUp => &["]"],
+			Action::SlowDown => &["["],
+			Action::Screenshot => &["s"],
+			Action::NextChapter => &["pagedown"],
+			Action::PreviousChapter => &["pageup"],
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Key {
+	code: KeyCode,
+	modifiers: KeyModifiers,
+}
+
+impl Key {
+	fn parse(name: &str) -> Result<Self, String> {
+		let mut modifiers = KeyModifiers::NONE;
+		let mut rest = name;
+		// A lone "+" is a key of its own rather than a separator.
+		while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+			modifiers |= match modifier.to_ascii_lowercase().as_str() {
+				"ctrl" => KeyModifiers::CONTROL,
+				"alt" => KeyModifiers::ALT,
+				"shift" => KeyModifiers::SHIFT,
+				_ => return Err(format!("unknown modifier `{}` in `{}`", modifier, name)),
+			};
+			rest = key;
+		}
+
+		let code = match rest.to_ascii_lowercase().as_str() {
+			"space" => KeyCode::Char(' '),
+			"left" => KeyCode::Left,
+			"right" => KeyCode::Right,
+			"up" => KeyCode::Up,
+			"down" => KeyCode::Down,
+			"pageup" => KeyCode::PageUp,
+			"pagedown" => KeyCode::PageDown,
+			"home" => KeyCode::Home,
+			"end" => KeyCode::End,
+			"enter" => KeyCode::Enter,
+			"tab" => KeyCode::Tab,
+			"backspace" => KeyCode::Backspace,
+			"esc" => KeyCode::Esc,
+			function if function.len() > 1 && function.starts_with('f') => {
+				KeyCode::F(function[1..].parse().map_err(|_| format!("unknown key `{}`", name))?)
+			},
+			_ => {
+				let mut chars = rest.chars();
+				match (chars.next(), chars.next()) {
+					(Some(c), None) => KeyCode::Char(c),
+					_ => return Err(format!("unknown key `{}`", name)),
+				}
+			},
+		};
+		Ok(Self { code, modifiers })
+	}
+
+	// Terminals report shifted characters as the character itself, with or without SHIFT.
+	fn matches(&self, event: &KeyEvent) -> bool {
+		let modifiers = match event.code {
+			KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+			_ => event.modifiers,
+		};
+		self.code == event.code && self.modifiers == modifiers
+	}
+}
+
+impl fmt::Display for Key {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (modifier, name) in [
+			(KeyModifiers::CONTROL, "ctrl+"),
+			(KeyModifiers::ALT, "alt+"),
+			(KeyModifiers::SHIFT, "shift+"),
+		] {
+			if self.modifiers.contains(modifier) {
+				f.write_str(name)?;
+			}
+		}
+		match self.code {
+			KeyCode::Char(' ') => f.write_str("space"),
+			KeyCode::Char(c) => write!(f, "{}", c),
+			KeyCode::Left => f.write_str("left"),
+			KeyCode::Right => f.write_str("right"),
+			KeyCode::Up => f.write_str("up"),
+			KeyCode::Down => f.write_str("down"),
+			KeyCode::PageUp => f.write_str("pageup"),
+			KeyCode::PageDown => f.write_str("pagedown"),
+			KeyCode::Home => f.write_str("home"),
+			KeyCode::End => f.write_str("end"),
+			KeyCode::Enter => f.write_str("enter"),
+			KeyCode::Tab => f.write_str("tab"),
+			KeyCode::Backspace => f.write_str("backspace"),
+			KeyCode::Esc => f.write_str("esc"),
+			KeyCode::F(number) => write!(f, "f{}", number),
+			_ => f.write_str("?"),
+		}
+	}
+}
+
+// Which key does what while playing. Actions listed under [keys] in config.toml replace their
+// default keys, like `pause = ["space", "p"]`, and the rest keep theirs.
+pub struct KeyMap {
+	bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl KeyMap {
+	pub fn new(overrides: Option<&Table>) -> Result<Self, String> {
+		let overrides = overrides.cloned().unwrap_or_default();
+		if let Some(name) = overrides
+			.keys()
+			.find(|name| !Action::ALL.iter().any(|action| action.name() == name.as_str()))
+		{
+			return Err(format!("there is no `{}` action to bind keys to", name));
+		}
+
+		let mut bindings = Vec::new();
+		for action in Action::ALL {
+			let names = match overrides.get(action.name()) {
+				None => action.default_keys().iter().map(|name| name.to_string()).collect(),
+				Some(Value::String(name)) => vec![name.clone()],
+				Some(Value::Array(names)) => names
+					.iter()
+					.map(|name| name.as_str().map(String::from))
+					.collect::<Option<Vec<_>>>()
+					.ok_or_else(|| format!("the keys for `{}` have to be strings", action.name()))?,
+				Some(_) => return Err(format!("the keys for `{}` have to be strings", action.name())),
+			};
+			let keys = names
+				.iter()
+				.map(|name| Key::parse(name))
+				.collect::<Result<Vec<_>, _>>()?;
+			bindings.push((action, keys));
+		}
+
+		// A key can only do one thing, whether the clash is between two overrides or an override
+		// and a default that was left alone.
+		for (index, (action, keys)) in bindings.iter().enumerate() {
+			for key in keys {
+				if let Some((other, _)) = bindings[index + 1..]
+					.iter()
+					.find(|(_, other_keys)| other_keys.contains(key))
+				{
+					return Err(format!(
+						"`{}` is bound to both `{}` and `{}`",
+						key,
+						action.name(),
+						other.name()
+					));
+				}
+			}
+		}
+		Ok(Self { bindings })
+	}
+
+	pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+		self.bindings
+			.iter()
+			.find(|(_, keys)| keys.iter().any(|key| key.matches(event)))
+			.map(|(action, _)| *action)
+	}
+
+	// One line per action, in the same form config.toml takes.
+	pub fn dump(&self) -> Vec<String> {
+		self.bindings
+			.iter()
+			.map(|(action, keys)| {
+				let keys: Vec<String> = keys.iter().map(|key| json_string(&key.to_string())).collect();
+				format!("{} = [{}]", action.name(), keys.join(", "))
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+		KeyEvent::new(code, modifiers)
+	}
+
+	fn table(text: &str) -> Table {
+		text.parse().unwrap()
+	}
+
+	#[test]
+	fn keys_parse_with_their_modifiers() {
+		let key = Key::parse("Ctrl+Alt+x").unwrap();
+		assert_eq!(key.code, KeyCode::Char('x'));
+		assert_eq!(key.modifiers, KeyModifiers::CONTROL | KeyModifiers::ALT);
+		assert_eq!(Key::parse("+").unwrap().code, KeyCode::Char('+'));
+		assert_eq!(Key::parse("ctrl++").unwrap().code, KeyCode::Char('+'));
+		assert_eq!(Key::parse("f12").unwrap().code, KeyCode::F(12));
+		assert_eq!(Key::parse("f").unwrap().code, KeyCode::Char('f'));
+		for name in ["hyper+x", "fx", "xy", ""] {
+			assert!(Key::parse(name).is_err(), "{}", name);
+		}
+	}
+
+	#[test]
+	fn keys_print_the_way_they_parse() {
+		for name in ["ctrl+alt+x", "space", "shift+pagedown", "f5", "["] {
+			assert_eq!(Key::parse(name).unwrap().to_string(), name);
+		}
+	}
+
+	#[test]
+	fn shifted_characters_match_with_or_without_shift() {
+		let keys = KeyMap::new(None).unwrap();
+		let bracket = KeyCode::Char(']');
+		assert!(keys.action(&press(bracket, KeyModifiers::NONE)) == Some(Action::SpeedUp));
+		assert!(keys.action(&press(bracket, KeyModifiers::SHIFT)) == Some(Action::SpeedUp));
+		assert!(keys.action(&press(KeyCode::Right, KeyModifiers::SHIFT)).is_none());
+		assert!(keys.action(&press(KeyCode::Char('c'), KeyModifiers::CONTROL)) == Some(Action::Quit));
+	}
+
+	#[test]
+	fn overrides_replace_only_their_own_defaults() {
+		let keys = KeyMap::new(Some(&table("pause = [\"p\", \"space\"]\nquit = \"x\""))).unwrap();
+		assert!(keys.action(&press(KeyCode::Char('p'), KeyModifiers::NONE)) == Some(Action::Pause));
+		assert!(keys.action(&press(KeyCode::Char('x'), KeyModifiers::NONE)) == Some(Action::Quit));
+		assert!(keys.action(&press(KeyCode::Char('q'), KeyModifiers::NONE)).is_none());
+		assert!(keys.action(&press(KeyCode::Right, KeyModifiers::NONE)) == Some(Action::SeekForward));
+		assert_eq!(keys.dump()[0], "quit = [\"x\"]");
+		assert_eq!(keys.dump()[1], "pause = [\"p\", \"space\"]");
+	}
+
+	#[test]
+	fn bad_overrides_are_refused() {
+		for text in [
+			"rewind = \"r\"",
+			"pause = 1",
+			"pause = [\"p\", 1]",
+			"pause = \"ctrl+\"",
+			// s is already the screenshot key.
+			"pause = \"s\"",
+			"pause = \"p\"\nquit = \"p\"",
+		] {
+			assert!(KeyMap::new(Some(&table(text))).is_err(), "{}", text);
+		}
+	}
+}
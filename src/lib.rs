@@ -0,0 +1,25 @@
+// Probing, decoding, conversion, scheduling and rendering, the parts of the player other programs
+// can use too. The bad_ascii binary is the command line around them.
+pub mod baa;
+pub mod cache;
+pub mod convert;
+mod error;
+pub mod export;
+pub mod frame;
+pub mod player;
+pub mod probe;
+pub mod render;
+pub mod schedule;
+pub mod source;
+pub mod theme;
+#[cfg(feature = "ratatui")]
+pub mod widget;
+
+pub use self::error::Error;
+use std::num::NonZeroU32;
+
+// Columns frames are converted to when nothing else is asked for.
+pub const DEFAULT_WIDTH: NonZeroU32 = NonZeroU32::new(160).expect("160 is above 0");
+
+// Frame rate of sources that don't have one of their own.
+const OUTPUT_FPS: u64 = 24;
@@ -0,0 +1,193 @@
+use bad_ascii::Error;
+use std::fs;
+use std::path::Path;
+
+pub struct Lyrics {
+	lines: Vec<LyricLine>,
+}
+
+struct LyricLine {
+	time: f64,
+	text: String,
+	// Enhanced LRC word timings as (seconds relative to the line start, byte offset into text).
+	words: Vec<(f64, usize)>,
+}
+
+impl Lyrics {
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		Ok(Self::parse(&fs::read_to_string(path)?))
+	}
+
+	pub fn parse(source: &str) -> Self {
+		let mut offset = 0.0;
+		let mut lines = Vec::new();
+
+		for raw_line in source.lines() {
+			let mut rest = raw_line.trim();
+			let mut times = Vec::new();
+
+			while let Some(tag_end) = rest.strip_prefix('[').and_then(|tail| tail.find(']')) {
+				let tag = &rest[1..tag_end + 1];
+				rest = &rest[tag_end + 2..];
+
+				if let Some(time) = parse_timestamp(tag) {
+					times.push(time);
+				} else if let Some(value) = tag.strip_prefix("offset:") {
+					offset = value.trim().parse::<f64>().unwrap_or(0.0) / 1000.0;
+				}
+			}
+
+			if times.is_empty() {
+				continue;
+			}
+
+			let (text, words) = parse_words(rest.trim_start());
+			let line_start = times[0];
+			let words: Vec<(f64, usize)> = words
+				.into_iter()
+				.map(|(time, index)| (time - line_start, index))
+				.collect();
+
+			for time in times {
+				lines.push(LyricLine {
+					time,
+					text: text.clone(),
+					words: words.clone(),
+				});
+			}
+		}
+
+		// A positive offset makes the lyrics appear sooner.
+		for line in &mut lines {
+			line.time -= offset;
+		}
+		lines.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+		Self { lines }
+	}
+
+	pub fn render(&self, position: f64, rows: usize, width: u16) -> Vec<String> {
+		let started = self.lines.partition_point(|line| line.time <= position);
+
+		let mut rendered = Vec::with_capacity(rows);
+		let upcoming = if let Some(current) = started.checked_sub(1).map(|index| &self.lines[index]) {
+			rendered.push(center(
+				&highlight(current, position - current.time),
+				&current.text,
+				width,
+			));
+			started
+		} else {
+			0
+		};
+
+		for line in self.lines.iter().skip(upcoming).take(rows - rendered.len()) {
+			rendered.push(center(&format!("\x1B[2m{}\x1B[0m", line.text), &line.text, width));
+		}
+		rendered.resize(rows, String::new());
+
+		rendered
+	}
+}
+
+fn highlight(line: &LyricLine, elapsed: f64) -> String {
+	if line.words.is_empty() {
+		return format!("\x1B[1m{}\x1B[0m", line.text);
+	}
+
+	// A word counts as sung from its own timestamp until the next word's one.
+	let sung_words = line.words.iter().take_while(|(time, _)| *time <= elapsed).count();
+	let split = match sung_words {
+		0 => 0,
+		count => line.words.get(count).map_or(line.text.len(), |&(_, index)| index),
+	};
+	let (done, remaining) = line.text.split_at(split.min(line.text.len()));
+
+	format!("\x1B[1;33m{}\x1B[0;1m{}\x1B[0m", done, remaining)
+}
+
+fn center(styled: &str, plain: &str, width: u16) -> String {
+	let padding = (width as usize).saturating_sub(plain.chars().count()) / 2;
+	format!("{}{}", " ".repeat(padding), styled)
+}
+
+fn parse_timestamp(tag: &str) -> Option<f64> {
+	let (minutes, seconds) = tag.split_once(':')?;
+	let minutes: u32 = minutes.trim().parse().ok()?;
+	let seconds: f64 = seconds.trim().replacen(':', ".", 1).parse().ok()?;
+	Some(minutes as f64 * 60.0 + seconds)
+}
+
+fn parse_words(text: &str) -> (String, Vec<(f64, usize)>) {
+	let mut plain = String::with_capacity(text.len());
+	let mut words = Vec::new();
+	let mut rest = text;
+
+	while let Some(start) = rest.find('<') {
+		let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+			break;
+		};
+		match parse_timestamp(&rest[start + 1..end]) {
+			Some(time) => {
+				plain.push_str(&rest[..start]);
+				words.push((time, plain.len()));
+			},
+			None => plain.push_str(&rest[..=end]),
+		}
+		rest = &rest[end + 1..];
+	}
+	plain.push_str(rest);
+
+	(plain, words)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn times(lyrics: &Lyrics) -> Vec<f64> {
+		lyrics.lines.iter().map(|line| line.time).collect()
+	}
+
+	#[test]
+	fn lines_are_sorted_by_time() {
+		let lyrics = Lyrics::parse("[ar:Someone]\n[00:05.00]Second\nno timestamp\n[00:01.50]First\n");
+		assert_eq!(times(&lyrics), [1.5, 5.0]);
+		let texts: Vec<&str> = lyrics.lines.iter().map(|line| line.text.as_str()).collect();
+		assert_eq!(texts, ["First", "Second"]);
+	}
+
+	#[test]
+	fn a_line_can_have_several_times() {
+		let lyrics = Lyrics::parse("[01:00.00][00:10:50]Chorus");
+		assert_eq!(times(&lyrics), [10.5, 60.0]);
+		assert!(lyrics.lines.iter().all(|line| line.text == "Chorus"));
+	}
+
+	#[test]
+	fn offset_moves_every_line() {
+		let lyrics = Lyrics::parse("[offset:500]\n[00:02.00]Sooner");
+		assert_eq!(times(&lyrics), [1.5]);
+	}
+
+	#[test]
+	fn word_timings_are_relative_to_the_line() {
+		let lyrics = Lyrics::parse("[00:10.00]<00:10.00>One <00:10.50>two <bad>three");
+		let line = &lyrics.lines[0];
+		assert_eq!(line.text, "One two <bad>three");
+		assert_eq!(line.words, [(0.0, 0), (0.5, 4)]);
+
+		assert_eq!(highlight(line, 0.2), "\x1B[1;33mOne \x1B[0;1mtwo <bad>three\x1B[0m");
+		assert_eq!(highlight(line, 1.0), "\x1B[1;33mOne two <bad>three\x1B[0;1m\x1B[0m");
+	}
+
+	#[test]
+	fn render_shows_the_current_line_and_those_after_it() {
+		let lyrics = Lyrics::parse("[00:01.00]a\n[00:02.00]b\n[00:03.00]c");
+		assert_eq!(lyrics.render(2.5, 2, 3), [" \x1B[1mb\x1B[0m", " \x1B[2mc\x1B[0m"]);
+		assert_eq!(
+			lyrics.render(0.0, 4, 1),
+			["\x1B[2ma\x1B[0m", "\x1B[2mb\x1B[0m", "\x1B[2mc\x1B[0m", ""]
+		);
+	}
+}
@@ -1,191 +1,90 @@
-use artem::convert;
-use core::str;
-use crossterm::QueueableCommand;
-use crossterm::cursor::{self};
-use crossterm::terminal::{self, Clear, ClearType};
-use image::{DynamicImage, ImageBuffer};
-use std::io::{BufReader, Read, Write, stdout};
-use std::num::NonZeroU32;
-use std::process::{Command, Stdio};
-use std::thread::sleep;
-use std::time::{Duration, Instant};
-
-const INPUT: &str = "input.mp4";
-const OUTPUT_FPS: u64 = 24;
+mod cli;
+mod command;
+mod config;
+mod control;
+#[cfg(unix)]
+mod fifo;
+#[cfg(unix)]
+mod ipc;
+mod keys;
+mod lyrics;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod osc;
+mod osd;
+mod pipeline;
+mod record;
+mod remote;
+mod screenshot;
+mod serve;
+mod wire;
+
+use crate::cli::{Args, Command};
+use crate::config::Config;
+use bad_ascii::Error;
+use clap::{CommandFactory, FromArgMatches};
+use std::io;
+use std::process;
+
+// Playing and serving stop after this many seconds of the input, converting and exporting don't.
 const DURATION: u64 = 90;
-const TARGET_SIZE: Option<NonZeroU32> = NonZeroU32::new(160);
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-	let (video_width, video_height) = get_video_dimensions(INPUT)?;
-
-	let frames = extract_frames(video_width, video_height)?;
-	let ascii_frames: Vec<Vec<String>> = frames
-		.into_iter()
-		.map(|frame| frame_to_ascii(frame, TARGET_SIZE.expect("Invalid TARGET_SIZE definition")))
-		.collect();
-
-	let top = get_vertical_padding(&ascii_frames);
-	let left = get_horizontal_padding(&ascii_frames[0]);
-
-	let mut stdout = stdout();
-	stdout.queue(Clear(ClearType::All))?.queue(cursor::Hide)?;
-
-	let frame_duration = Duration::from_secs_f64(1.0 / OUTPUT_FPS as f64);
-	let start_time = Instant::now();
-	let end_time = start_time + Duration::from_secs(DURATION);
-
-	let mut frame_index = 0;
-	let total_frames = ascii_frames.len();
-
-	let mut previous_frame: Option<&Vec<String>> = None;
 
-	while Instant::now() < end_time {
-		let frame_start = Instant::now();
-		let current_frame = &ascii_frames[frame_index];
-
-		if let Some(previous) = &previous_frame {
-			for (row, line) in current_frame.iter().enumerate() {
-				if let Some(previous_line) = previous.get(row) {
-					if previous_line != line {
-						let cursor_move = format!("\x1B[{};{}H", top + row as u16, left);
-						stdout.write_all(format!("{}{}", cursor_move, line).as_bytes())?;
-					}
-				}
-			}
-		} else {
-			for (row, line) in current_frame.iter().enumerate() {
-				let cursor_move = format!("\x1B[{};{}H", top + row as u16, left);
-				stdout.write_all(format!("{}{}", cursor_move, line).as_bytes())?;
-			}
-		}
-
-		stdout.flush()?;
-		previous_frame = Some(current_frame);
-
-		let elapsed = frame_start.duration_since(start_time);
-		frame_index = ((elapsed.as_secs_f64() * OUTPUT_FPS as f64) as usize) % total_frames;
-
-		let frame_end = Instant::now();
-		let frame_processing_time = frame_end - frame_start;
-		if frame_processing_time < frame_duration {
-			sleep(frame_duration - frame_processing_time);
+fn main() {
+	if let Err(error) = run() {
+		eprintln!("Error: {}", error);
+		if let Some(hint) = hint(&error) {
+			eprintln!("{}", hint);
 		}
+		process::exit(exit_code(&error));
 	}
-
-	stdout.queue(cursor::Show)?;
-	Ok(())
 }
 
-fn get_video_dimensions(input: &str) -> Result<(u32, u32), Box<dyn std::error::Error>> {
-	let output = Command::new("ffprobe")
-		.args(&[
-			"-v",
-			"error",
-			"-select_streams",
-			"v:0",
-			"-count_packets",
-			"-show_entries",
-			"stream=width,height",
-			"-of",
-			"csv=p=0",
-			input,
-		])
-		.output()?;
-
-	let output_str = String::from_utf8(output.stdout)?;
-	let dimensions: Vec<u32> = output_str.trim().split(',').map(|s| s.parse().unwrap()).collect();
-
-	Ok((dimensions[0], dimensions[1]))
-}
-
-fn extract_frames(width: u32, height: u32) -> Result<Vec<DynamicImage>, Box<dyn std::error::Error>> {
-	let mut frames = Vec::new();
-	let mut child = Command::new("ffmpeg")
-		.args(&[
-			"-i",
-			INPUT,
-			"-t",
-			&DURATION.to_string(),
-			"-r",
-			&OUTPUT_FPS.to_string(),
-			"-f",
-			"image2pipe",
-			"-pix_fmt",
-			"rgb24",
-			"-vcodec",
-			"rawvideo",
-			"-",
-		])
-		.stdout(Stdio::piped())
-		.spawn()?;
-
-	let mut reader = BufReader::new(child.stdout.take().unwrap());
-	let mut buffer = vec![0u8; (width * height * 3) as usize];
-
-	while reader.read_exact(&mut buffer).is_ok() {
-		let image_buffer =
-			ImageBuffer::from_raw(width, height, buffer.clone()).ok_or("Failed to create image from buffer")?;
-		frames.push(DynamicImage::ImageRgb8(image_buffer));
+fn run() -> Result<(), Error> {
+	let config = Config::load()?;
+	let matches = config.apply(Args::command())?.get_matches();
+	let args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+	match &args.command {
+		Some(Command::Play(play_args)) => command::play::run(play_args, &config),
+		Some(Command::Convert(convert_args)) => command::convert::run(convert_args, &config),
+		Some(Command::Export(export_args)) => command::export::run(export_args, &config),
+		Some(Command::Info(info_args)) => command::info::run(info_args, &config),
+		Some(Command::Thumbnails(thumbnails_args)) => command::thumbnails::run(thumbnails_args, &config),
+		Some(Command::Bench(bench_args)) => command::bench::run(bench_args, &config),
+		Some(Command::Serve(serve_args)) => command::serve::run(serve_args, &config),
+		Some(Command::Connect(connect_args)) => command::play::connect(connect_args, &config),
+		Some(Command::Cache(cache_args)) => command::cache::run(cache_args),
+		None => command::play::run(&args.play, &config),
 	}
-
-	Ok(frames)
-}
-
-fn frame_to_ascii(frame: DynamicImage, target_size: NonZeroU32) -> Vec<String> {
-	let config = artem::config::ConfigBuilder::new().target_size(target_size).build();
-	convert(frame, &config).lines().map(String::from).collect()
 }
 
-fn get_vertical_padding(frames: &[Vec<String>]) -> u16 {
-	let (_, term_height) = terminal::size().unwrap();
-	let frame_height = frames[0].len();
-
-	if frame_height < term_height as usize {
-		(term_height - frame_height as u16) / 2
-	} else {
-		0
+// What the user can do about it, for the errors where that isn't obvious from the message.
+fn hint(error: &Error) -> Option<&'static str> {
+	match error {
+		Error::FfmpegNotFound { .. } => {
+			Some("Install ffmpeg, or play GIF, APNG, WebP and Y4M files with --backend native, which doesn't need it.")
+		},
+		Error::YtdlpNotFound => Some("Install yt-dlp to play videos from sites like YouTube, or pass a direct link."),
+		Error::NoFrames => {
+			Some("The input may be empty or in a format ffmpeg can't decode; `bad_ascii info` tells more.")
+		},
+		Error::TerminalError(_) => {
+			Some("Playing needs an interactive terminal. Use `convert` or `export` to write frames to a file instead.")
+		},
+		_ => None,
 	}
 }
 
-fn remove_ansi_escape_sequences(input: &str) -> String {
-	let mut result = String::new();
-	let mut in_escape_sequence = false;
-
-	for c in input.chars() {
-		if c == '\u{1b}' {
-			in_escape_sequence = true;
-		} else if in_escape_sequence {
-			if c.is_ascii_alphabetic() {
-				in_escape_sequence = false;
-			}
-			continue;
-		}
-		result.push(c);
-	}
-
-	result
-}
-
-fn get_horizontal_padding(frame: &[String]) -> u16 {
-	let (term_width, _) = terminal::size().unwrap();
-
-	let max_line_width = frame
-		.iter()
-		.filter_map(|line| {
-			let binding = remove_ansi_escape_sequences(line);
-			let trimmed_line = binding.trim();
-			if trimmed_line.is_empty() {
-				None
-			} else {
-				Some(trimmed_line.len())
-			}
-		})
-		.max()
-		.unwrap_or(0)
-		/ 3;
-	if max_line_width < term_width as usize {
-		(term_width - max_line_width as u16) / 2
-	} else {
-		0
+// Following sysexits.h, so that scripts can tell a missing program from a broken input.
+fn exit_code(error: &Error) -> i32 {
+	match error {
+		Error::BadOptions(_) => 64,
+		Error::ProbeFailed { .. } | Error::DecodeError(_) | Error::NoFrames | Error::BadInput(_) | Error::Image(_) => {
+			65
+		},
+		Error::Io(error) if error.kind() == io::ErrorKind::NotFound => 66,
+		Error::FfmpegNotFound { .. } | Error::YtdlpNotFound | Error::Network(_) => 69,
+		Error::EncodeError(_) => 73,
+		Error::TerminalError(_) | Error::Io(_) => 74,
+		Error::Config { .. } => 78,
 	}
 }
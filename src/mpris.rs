@@ -0,0 +1,311 @@
+use crate::control::{self, Command, Request};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use zbus::blocking::{Connection, connection};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, Value as Variant};
+use zbus::{fdo, interface};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+// There is no track list, but clients expect the one track to have an ID.
+const TRACK_ID: &str = "/org/bad_ascii/track/0";
+// How far, in seconds, the position can be from where the last status says it should be before
+// it counts as a seek.
+const SEEK_TOLERANCE: f64 = 0.5;
+
+// The player as of its last status, which properties are read from so that they are answered
+// even while the player is busy converting or loading.
+#[derive(Clone)]
+struct Snapshot {
+	title: Option<String>,
+	duration: Option<f64>,
+	// Streams have no position to report.
+	position: Option<f64>,
+	speed: f64,
+	paused: bool,
+}
+
+impl Snapshot {
+	fn new(status: &Value) -> Self {
+		Self {
+			title: status["title"].as_str().map(String::from),
+			duration: status["duration"].as_f64(),
+			position: status["position"].as_f64(),
+			speed: status["speed"].as_f64().unwrap_or(1.0),
+			paused: status["paused"].as_bool().unwrap_or(false),
+		}
+	}
+}
+
+// The snapshot and when it was taken, to tell where playback has got to since.
+type Shared = Arc<Mutex<(Snapshot, Instant)>>;
+
+fn position(shared: &Shared) -> Option<f64> {
+	let (snapshot, taken_at) = &*shared.lock().expect("the MPRIS snapshot lock was poisoned");
+	let moved = if snapshot.paused {
+		0.0
+	} else {
+		taken_at.elapsed().as_secs_f64() * snapshot.speed
+	};
+	snapshot.position.map(|position| position + moved)
+}
+
+// Publishes the player on the session bus, so that media keys, desktop widgets and playerctl can
+// drive it. It stays there until it is dropped.
+pub struct Mpris {
+	connection: Connection,
+	shared: Shared,
+}
+
+pub fn start(requests: Sender<Request>) -> zbus::Result<Mpris> {
+	let shared = Arc::new(Mutex::new((Snapshot::new(&Value::Null), Instant::now())));
+	let connection = connection::Builder::session()?
+		.name(format!("org.mpris.MediaPlayer2.bad_ascii.instance{}", process::id()))?
+		.serve_at(
+			OBJECT_PATH,
+			Root {
+				requests: requests.clone(),
+			},
+		)?
+		.serve_at(
+			OBJECT_PATH,
+			Player {
+				requests,
+				shared: shared.clone(),
+			},
+		)?
+		.build()?;
+	Ok(Mpris { connection, shared })
+}
+
+impl Mpris {
+	// Takes the player's status, as GetStatus answers it, and tells clients what changed.
+	pub fn update(&self, status: &Value) {
+		let next = Snapshot::new(status);
+		let expected = position(&self.shared);
+		let previous = {
+			let mut shared = self.shared.lock().expect("the MPRIS snapshot lock was poisoned");
+			std::mem::replace(&mut *shared, (next.clone(), Instant::now())).0
+		};
+		let paused = next.paused != previous.paused;
+		let metadata = (&next.title, next.duration) != (&previous.title, previous.duration);
+		let rate = next.speed != previous.speed;
+		let seeked = next
+			.position
+			.zip(expected)
+			.filter(|(position, expected)| (position - expected).abs() > SEEK_TOLERANCE);
+		if !paused && !metadata && !rate && seeked.is_none() {
+			return;
+		}
+
+		let Ok(player) = self.connection.object_server().interface::<_, Player>(OBJECT_PATH) else {
+			return;
+		};
+		let emitter = player.signal_emitter();
+		let player = player.get();
+		// Clients that miss a change catch up the next time they read the properties.
+		zbus::block_on(async {
+			if paused {
+				player.playback_status_changed(emitter).await.ok();
+			}
+			if metadata {
+				player.metadata_changed(emitter).await.ok();
+			}
+			if rate {
+				player.rate_changed(emitter).await.ok();
+			}
+			// Clients work out the position from the rate as it moves, it is only announced when it
+			// jumps.
+			if let Some((position, _)) = seeked {
+				Player::seeked(emitter, (position * 1e6) as i64).await.ok();
+			}
+		});
+	}
+}
+
+fn run(requests: &Sender<Request>, command: Command) -> fdo::Result<Value> {
+	match control::send(requests, command) {
+		Some(Ok(data)) => Ok(data),
+		Some(Err(error)) => Err(fdo::Error::Failed(error)),
+		None => Err(fdo::Error::Failed("the player has exited".into())),
+	}
+}
+
+struct Root {
+	requests: Sender<Request>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+	fn raise(&self) {}
+
+	fn quit(&self) -> fdo::Result<()> {
+		run(&self.requests, Command::Quit).map(drop)
+	}
+
+	#[zbus(property)]
+	fn can_quit(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_raise(&self) -> bool {
+		false
+	}
+
+	#[zbus(property)]
+	fn has_track_list(&self) -> bool {
+		false
+	}
+
+	#[zbus(property)]
+	fn identity(&self) -> &str {
+		"bad_ascii"
+	}
+
+	#[zbus(property)]
+	fn supported_uri_schemes(&self) -> Vec<String> {
+		vec!["file".into(), "http".into(), "https".into()]
+	}
+
+	#[zbus(property)]
+	fn supported_mime_types(&self) -> Vec<String> {
+		Vec::new()
+	}
+}
+
+struct Player {
+	requests: Sender<Request>,
+	shared: Shared,
+}
+
+impl Player {
+	fn command(&self, command: Command) -> fdo::Result<()> {
+		run(&self.requests, command).map(drop)
+	}
+
+	fn snapshot(&self) -> Snapshot {
+		self.shared
+			.lock()
+			.expect("the MPRIS snapshot lock was poisoned")
+			.0
+			.clone()
+	}
+}
+
+// MPRIS counts time in microseconds.
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+	fn play_pause(&self) -> fdo::Result<()> {
+		self.command(Command::Pause(None))
+	}
+
+	fn play(&self) -> fdo::Result<()> {
+		self.command(Command::Pause(Some(false)))
+	}
+
+	fn pause(&self) -> fdo::Result<()> {
+		self.command(Command::Pause(Some(true)))
+	}
+
+	// There is nothing to show once stopped, so stopping quits.
+	fn stop(&self) -> fdo::Result<()> {
+		self.command(Command::Quit)
+	}
+
+	fn next(&self) -> fdo::Result<()> {
+		self.command(Command::NextChapter)
+	}
+
+	fn previous(&self) -> fdo::Result<()> {
+		self.command(Command::PreviousChapter)
+	}
+
+	fn seek(&self, offset: i64) -> fdo::Result<()> {
+		self.command(Command::Seek {
+			seconds: offset as f64 / 1e6,
+			absolute: false,
+		})
+	}
+
+	fn set_position(&self, _track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+		self.command(Command::Seek {
+			seconds: position as f64 / 1e6,
+			absolute: true,
+		})
+	}
+
+	fn open_uri(&self, uri: &str) -> fdo::Result<()> {
+		let input = uri.strip_prefix("file://").unwrap_or(uri);
+		self.command(Command::Load(input.to_string()))
+	}
+
+	#[zbus(signal)]
+	async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+	#[zbus(property)]
+	fn playback_status(&self) -> String {
+		if self.snapshot().paused { "Paused" } else { "Playing" }.to_string()
+	}
+
+	#[zbus(property)]
+	fn metadata(&self) -> HashMap<String, Variant<'static>> {
+		let snapshot = self.snapshot();
+		let mut metadata = HashMap::new();
+		metadata.insert(
+			"mpris:trackid".to_string(),
+			Variant::from(ObjectPath::from_static_str_unchecked(TRACK_ID)),
+		);
+		if let Some(title) = snapshot.title {
+			metadata.insert("xesam:title".to_string(), Variant::from(title));
+		}
+		if let Some(duration) = snapshot.duration {
+			metadata.insert("mpris:length".to_string(), Variant::from((duration * 1e6) as i64));
+		}
+		metadata
+	}
+
+	#[zbus(property(emits_changed_signal = "false"))]
+	fn position(&self) -> i64 {
+		(position(&self.shared).unwrap_or(0.0) * 1e6) as i64
+	}
+
+	#[zbus(property)]
+	fn rate(&self) -> f64 {
+		self.snapshot().speed
+	}
+
+	#[zbus(property)]
+	fn can_go_next(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_go_previous(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_play(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_pause(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_seek(&self) -> bool {
+		true
+	}
+
+	#[zbus(property)]
+	fn can_control(&self) -> bool {
+		true
+	}
+}
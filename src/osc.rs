@@ -0,0 +1,268 @@
+use crate::control::{self, Command, Request};
+use std::io;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+const PREFIX: &str = "/bad_ascii/";
+
+enum Argument {
+	Number(f64),
+	Bool(bool),
+	Text(String),
+}
+
+struct Message {
+	address: String,
+	arguments: Vec<Argument>,
+}
+
+// Listens for OSC over UDP, as sent by VJ controllers, TouchOSC and most DAWs. Understood are
+//   /bad_ascii/speed f       playback speed, 1 is normal
+//   /bad_ascii/brightness f  0 is black, 1 leaves colors as they are
+//   /bad_ascii/seek f        seconds into the file
+//   /bad_ascii/pause [i|T|F] toggles without an argument
+// Bundles are unpacked right away, their timetags are ignored. Anyone who can reach the address can
+// send these, so it's only bound beyond the loopback interface when asked to.
+pub fn listen(address: IpAddr, port: u16, requests: Sender<Request>) -> io::Result<()> {
+	let socket = UdpSocket::bind((address, port))?;
+	thread::spawn(move || {
+		let mut buffer = [0; 65_536];
+		loop {
+			let Ok(length) = socket.recv(&mut buffer) else {
+				continue;
+			};
+			let mut messages = Vec::new();
+			if parse_packet(&buffer[..length], &mut messages).is_none() {
+				continue;
+			}
+			for command in messages.iter().filter_map(command) {
+				if control::send(&requests, command).is_none() {
+					return;
+				}
+			}
+		}
+	});
+	Ok(())
+}
+
+fn command(message: &Message) -> Option<Command> {
+	let number = match message.arguments.first() {
+		Some(Argument::Number(number)) => Some(*number),
+		Some(Argument::Bool(value)) => Some(if *value { 1.0 } else { 0.0 }),
+		Some(Argument::Text(text)) => text.parse().ok(),
+		None => None,
+	};
+	// NaN and infinities would stick to the position or speed until something replaced them.
+	let number = number.filter(|number: &f64| number.is_finite());
+
+	match message.address.strip_prefix(PREFIX)? {
+		"speed" => Some(Command::Speed {
+			value: number?,
+			absolute: true,
+		}),
+		"brightness" => Some(Command::Brightness(number?)),
+		"seek" => Some(Command::Seek {
+			seconds: number?,
+			absolute: true,
+		}),
+		"pause" => Some(Command::Pause(number.map(|number| number != 0.0))),
+		_ => None,
+	}
+}
+
+// Packets that are cut short or use argument types whose size isn't known are dropped whole.
+fn parse_packet(packet: &[u8], messages: &mut Vec<Message>) -> Option<()> {
+	let Some(mut elements) = packet.strip_prefix(b"#bundle\0") else {
+		messages.push(parse_message(packet)?);
+		return Some(());
+	};
+	// Skip the timetag.
+	elements = elements.get(8..)?;
+	while !elements.is_empty() {
+		let (size, rest) = take::<4>(elements)?;
+		let size = u32::from_be_bytes(size) as usize;
+		parse_packet(rest.get(..size)?, messages)?;
+		elements = &rest[size..];
+	}
+	Some(())
+}
+
+fn parse_message(packet: &[u8]) -> Option<Message> {
+	let (address, rest) = read_string(packet)?;
+	// Very old senders leave out the type tags, and with them any arguments.
+	let (tags, mut rest) = read_string(rest).unwrap_or_default();
+
+	let mut arguments = Vec::new();
+	for tag in tags.strip_prefix(',').unwrap_or_default().chars() {
+		let argument = match tag {
+			'i' => {
+				let (bytes, next) = take::<4>(rest)?;
+				rest = next;
+				Argument::Number(i32::from_be_bytes(bytes) as f64)
+			},
+			'f' => {
+				let (bytes, next) = take::<4>(rest)?;
+				rest = next;
+				Argument::Number(f32::from_be_bytes(bytes) as f64)
+			},
+			'h' => {
+				let (bytes, next) = take::<8>(rest)?;
+				rest = next;
+				Argument::Number(i64::from_be_bytes(bytes) as f64)
+			},
+			'd' => {
+				let (bytes, next) = take::<8>(rest)?;
+				rest = next;
+				Argument::Number(f64::from_be_bytes(bytes))
+			},
+			's' | 'S' => {
+				let (text, next) = read_string(rest)?;
+				rest = next;
+				Argument::Text(text)
+			},
+			'T' => Argument::Bool(true),
+			'F' => Argument::Bool(false),
+			_ => return None,
+		};
+		arguments.push(argument);
+	}
+	Some(Message { address, arguments })
+}
+
+// OSC strings end with a nul and are padded to a multiple of four bytes.
+fn read_string(bytes: &[u8]) -> Option<(String, &[u8])> {
+	let end = bytes.iter().position(|&byte| byte == 0)?;
+	let text = String::from_utf8_lossy(&bytes[..end]).into_owned();
+	let padded = (end + 4) & !3;
+	Some((text, bytes.get(padded..).unwrap_or_default()))
+}
+
+fn take<const N: usize>(bytes: &[u8]) -> Option<([u8; N], &[u8])> {
+	let (head, rest) = bytes.split_first_chunk::<N>()?;
+	Some((*head, rest))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// An OSC string: the text, a nul and padding to a multiple of four bytes.
+	fn string(text: &str) -> Vec<u8> {
+		let mut bytes = text.as_bytes().to_vec();
+		bytes.resize((text.len() + 4) & !3, 0);
+		bytes
+	}
+
+	fn message(address: &str, tags: &str, arguments: &[&[u8]]) -> Vec<u8> {
+		let mut packet = string(address);
+		packet.extend(string(tags));
+		arguments.iter().for_each(|argument| packet.extend_from_slice(argument));
+		packet
+	}
+
+	fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+		let mut packet = b"#bundle\0".to_vec();
+		packet.extend_from_slice(&1u64.to_be_bytes());
+		for element in elements {
+			packet.extend_from_slice(&(element.len() as u32).to_be_bytes());
+			packet.extend_from_slice(element);
+		}
+		packet
+	}
+
+	fn commands(packet: &[u8]) -> Option<Vec<Command>> {
+		let mut messages = Vec::new();
+		parse_packet(packet, &mut messages)?;
+		Some(messages.iter().filter_map(command).collect())
+	}
+
+	#[test]
+	fn arguments_of_every_type_are_read() {
+		let packet = message(
+			"/x",
+			",ifhdsTF",
+			&[
+				&7i32.to_be_bytes(),
+				&1.5f32.to_be_bytes(),
+				&(-3i64).to_be_bytes(),
+				&0.25f64.to_be_bytes(),
+				&string("hey"),
+			],
+		);
+		let message = parse_message(&packet).unwrap();
+		assert_eq!(message.address, "/x");
+		let numbers: Vec<f64> = message
+			.arguments
+			.iter()
+			.filter_map(|argument| match argument {
+				Argument::Number(number) => Some(*number),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(numbers, [7.0, 1.5, -3.0, 0.25]);
+		assert!(matches!(&message.arguments[4], Argument::Text(text) if text == "hey"));
+		assert!(matches!(
+			message.arguments[5..],
+			[Argument::Bool(true), Argument::Bool(false)]
+		));
+	}
+
+	#[test]
+	fn messages_become_commands() {
+		let speed = message("/bad_ascii/speed", ",f", &[&2.0f32.to_be_bytes()]);
+		assert!(matches!(
+			commands(&speed).unwrap()[..],
+			[Command::Speed { value, absolute: true }] if value == 2.0
+		));
+		let seek = message("/bad_ascii/seek", ",s", &[&string("12.5")]);
+		assert!(matches!(
+			commands(&seek).unwrap()[..],
+			[Command::Seek { seconds, absolute: true }] if seconds == 12.5
+		));
+		let pause = message("/bad_ascii/pause", ",F", &[]);
+		assert!(matches!(commands(&pause).unwrap()[..], [Command::Pause(Some(false))]));
+		// Very old senders leave out the type tags.
+		assert!(matches!(
+			commands(&string("/bad_ascii/pause")).unwrap()[..],
+			[Command::Pause(None)]
+		));
+		let elsewhere = message("/other/speed", ",f", &[&2.0f32.to_be_bytes()]);
+		assert!(commands(&elsewhere).unwrap().is_empty());
+	}
+
+	#[test]
+	fn numbers_have_to_be_finite() {
+		for number in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+			let speed = message("/bad_ascii/speed", ",d", &[&number.to_be_bytes()]);
+			assert!(commands(&speed).unwrap().is_empty());
+		}
+		let seek = message("/bad_ascii/seek", ",s", &[&string("inf")]);
+		assert!(commands(&seek).unwrap().is_empty());
+	}
+
+	#[test]
+	fn bundles_are_unpacked() {
+		let packet = bundle(&[
+			message("/bad_ascii/brightness", ",i", &[&0i32.to_be_bytes()]),
+			bundle(&[message("/bad_ascii/pause", ",T", &[])]),
+		]);
+		assert!(matches!(
+			commands(&packet).unwrap()[..],
+			[Command::Brightness(brightness), Command::Pause(Some(true))] if brightness == 0.0
+		));
+	}
+
+	#[test]
+	fn broken_packets_are_dropped_whole() {
+		// Cut short in the middle of an argument.
+		assert!(commands(&message("/bad_ascii/speed", ",f", &[&[0, 0]])).is_none());
+		// A type whose size isn't known.
+		assert!(commands(&message("/bad_ascii/speed", ",r", &[&[0; 4]])).is_none());
+		// A bundle element longer than the bundle.
+		let mut packet = bundle(&[message("/bad_ascii/pause", ",", &[])]);
+		packet.truncate(packet.len() - 1);
+		assert!(commands(&packet).is_none());
+		assert!(commands(b"").is_none());
+	}
+}
@@ -0,0 +1,47 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const OSD_DURATION: Duration = Duration::from_secs(1);
+
+pub struct Osd {
+	// SGR parameters the message is drawn with.
+	style: String,
+	message: Option<String>,
+	expires_at: Instant,
+	dirty: bool,
+}
+
+impl Osd {
+	pub fn new(style: &str) -> Self {
+		Self {
+			style: style.to_string(),
+			message: None,
+			expires_at: Instant::now(),
+			dirty: false,
+		}
+	}
+
+	pub fn show(&mut self, message: impl Into<String>) {
+		self.message = Some(message.into());
+		self.expires_at = Instant::now() + OSD_DURATION;
+		self.dirty = true;
+	}
+
+	// Returns true when the row under the OSD has to be repainted, either because a new message
+	// replaced the old one or because the current message just expired.
+	pub fn tick(&mut self) -> bool {
+		if self.message.is_some() && Instant::now() >= self.expires_at {
+			self.message = None;
+			self.dirty = true;
+		}
+		std::mem::take(&mut self.dirty)
+	}
+
+	pub fn draw(&self, out: &mut impl Write, top: u16, left: u16) -> io::Result<()> {
+		if let Some(message) = &self.message {
+			let cursor_move = format!("\x1B[{};{}H", top, left);
+			out.write_all(format!("{}\x1B[{}m {} \x1B[0m", cursor_move, self.style, message).as_bytes())?;
+		}
+		Ok(())
+	}
+}
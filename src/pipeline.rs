@@ -0,0 +1,124 @@
+use crate::cli::{CacheOptions, SourceArgs, StyleArgs};
+use bad_ascii::convert::{Converter, Frames, convert_frames};
+use bad_ascii::source::{self, Chapter, DecodeOptions, Frame, FrameSource, SourceOptions};
+use bad_ascii::theme::Theme;
+use bad_ascii::{Error, baa, cache, probe};
+use clap::ValueEnum;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::time::Duration;
+
+// A converter set up from the style options on the command line.
+pub fn converter(target_size: NonZeroU32, style: &StyleArgs, theme: Theme) -> Converter {
+	Converter::new(target_size, style.color_mode, style.charset.as_deref(), theme)
+}
+
+// Inputs that yt-dlp has to find the media for are swapped for the URL it resolves them to.
+pub fn resolve_input(args: &SourceArgs) -> Result<String, Error> {
+	if args.ytdl || source::needs_ytdl(&args.input) {
+		source::resolve_ytdl(&args.input, &args.ytdl_format)
+	} else {
+		Ok(args.input.clone())
+	}
+}
+
+pub fn source_options(args: &SourceArgs, decode: DecodeOptions) -> SourceOptions {
+	SourceOptions {
+		slide_duration: Duration::from_secs_f64(args.slide_duration),
+		transition: args.transition,
+		backend: args.backend,
+		decode,
+	}
+}
+
+// Frames ready to play along with the source's chapters.
+pub type Loaded = (Box<dyn Frames>, Vec<Chapter>);
+
+// Opens a .baa file as is, and converts anything else unless an earlier conversion with the same
+// settings is in the cache. Conversions that are cached go straight to the cache file rather than
+// being held in memory. Only as much of the input as the decode options' limit is converted.
+pub fn load(
+	input: &str,
+	args: &SourceArgs,
+	cache_options: &CacheOptions,
+	converter: &Converter,
+	decode: DecodeOptions,
+) -> Result<Loaded, Error> {
+	let (frames, chapters): Loaded = if baa::is_container(input) {
+		let mapped = baa::Mapped::open(Path::new(input))?;
+		let chapters = mapped.chapters().to_vec();
+		(Box::new(mapped), chapters)
+	} else {
+		let settings = format!(
+			"size={} limit={:?} vf={} backend={} color={} charset={:?} palette={:?}",
+			converter.target_size(),
+			decode.limit,
+			args.vf.as_deref().unwrap_or("none"),
+			args.backend
+				.to_possible_value()
+				.expect("backends have names")
+				.get_name(),
+			converter.color_mode(),
+			converter.charset(),
+			converter.theme().palette()
+		);
+		let cache_dir = cache_options.dir().filter(|_| !cache_options.no_cache);
+		let cache_entry = match &cache_dir {
+			Some(dir) => cache::Entry::find(dir, input, &settings)?,
+			None => None,
+		};
+
+		match cache_entry.as_ref().and_then(cache::Entry::load) {
+			Some(mapped) => {
+				let chapters = mapped.chapters().to_vec();
+				(Box::new(mapped), chapters)
+			},
+			None => {
+				let limit = decode.limit;
+				let mut source = source::open(input, &source_options(args, decode))?;
+				if let Some(remaining) = limit {
+					source = Box::new(Capped { source, remaining });
+				}
+				let chapters = probe::probe_chapters(input);
+				match (&cache_entry, &cache_dir) {
+					(Some(entry), Some(dir)) => {
+						let mapped = entry.convert(source.as_mut(), converter, chapters.clone())?;
+						if let Err(error) = cache::evict(dir, cache_options.cache_max_size) {
+							eprintln!("Failed to trim the cache: {}", error);
+						}
+						(Box::new(mapped), chapters)
+					},
+					_ => (Box::new(convert_frames(source, converter)?), chapters),
+				}
+			},
+		}
+	};
+	if frames.delays().is_empty() {
+		return Err(Error::NoFrames);
+	}
+	Ok((frames, chapters))
+}
+
+// Ends a source once the frames it gave add up to `remaining`, for the sources that don't stop
+// early by themselves.
+struct Capped {
+	source: Box<dyn FrameSource>,
+	remaining: Duration,
+}
+
+impl FrameSource for Capped {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		if self.remaining.is_zero() {
+			return Ok(None);
+		}
+		let frame = self.source.next_frame()?;
+		if let Some(frame) = &frame {
+			self.remaining = self.remaining.saturating_sub(frame.delay);
+		}
+		Ok(frame)
+	}
+
+	fn frame_rate(&self) -> f64 {
+		self.source.frame_rate()
+	}
+}
@@ -0,0 +1,327 @@
+#[cfg(feature = "async")]
+mod asynchronous;
+mod hooks;
+
+#[cfg(feature = "async")]
+pub use self::asynchronous::AsyncPlayer;
+pub use self::hooks::Event;
+
+use self::hooks::Hooks;
+
+use crate::convert::{ColorMode, Converter};
+use crate::frame::AsciiFrame;
+use crate::render::{Renderer, render};
+use crate::schedule;
+use crate::source::FrameSource;
+use crate::theme::Theme;
+use crate::{DEFAULT_WIDTH, Error};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Plays a source through a renderer, converting frames as they are decoded. Put one together with
+// Player::builder().
+pub struct Player {
+	source: Box<dyn FrameSource>,
+	converter: Converter,
+	renderer: Box<dyn Renderer>,
+	fps_cap: Option<f64>,
+	looping: bool,
+	speed: f64,
+	hooks: Hooks,
+	stop: StopHandle,
+}
+
+impl Player {
+	pub fn builder() -> PlayerBuilder {
+		PlayerBuilder::default()
+	}
+
+	pub fn stop_handle(&self) -> StopHandle {
+		self.stop.clone()
+	}
+
+	// Returns once the source runs out, which a looping player's never does, or once it's stopped
+	// through a StopHandle, having called the renderer's finish() either way.
+	pub fn play(mut self) -> Result<(), Error> {
+		let mut clock = Clock::new(schedule::min_frame_time(self.fps_cap));
+		self.hooks.event(Event::Started);
+		// Looping replays the frames already converted rather than decoding the source again.
+		let mut played = Vec::new();
+		let mut pts = Duration::ZERO;
+		while !self.stop.is_stopped() {
+			let Some(frame) = self.source.next_frame()? else {
+				break;
+			};
+			let ascii = self.converter.convert_frame(frame.picture);
+			self.show(&mut clock, &ascii, pts, frame.delay)?;
+			pts += frame.delay;
+			if self.looping {
+				played.push((ascii, frame.delay));
+			}
+		}
+
+		if self.looping && !self.stop.is_stopped() {
+			if played.is_empty() {
+				return Err(Error::NoFrames);
+			}
+			'replay: loop {
+				self.hooks.event(Event::Looped);
+				let mut pts = Duration::ZERO;
+				for (frame, delay) in &played {
+					if self.stop.is_stopped() {
+						break 'replay;
+					}
+					self.show(&mut clock, frame, pts, *delay)?;
+					pts += *delay;
+				}
+			}
+		}
+		self.hooks.event(Event::Finished);
+		self.renderer.finish()
+	}
+
+	// Draws a frame through the frame hooks, if the clock says to. Delays are the source's, before
+	// the speed is applied.
+	fn show(&mut self, clock: &mut Clock, frame: &AsciiFrame, pts: Duration, delay: Duration) -> Result<(), Error> {
+		let delay = delay.div_f64(self.speed);
+		if clock.wait(delay) {
+			let shown = self.hooks.frame(frame, pts);
+			render(self.renderer.as_mut(), &shown, clock.shown_for(delay))?;
+		}
+		Ok(())
+	}
+}
+
+// Ends playback from another thread or from the player's own hooks, after the frame being drawn, as
+// if the source had run out.
+#[derive(Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+	pub fn stop(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_stopped(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+// Keeps frames to their schedule, skipping the ones that would be drawn sooner after the last than
+// the frame rate cap allows. A frame that is late is still drawn, as no later one has been decoded
+// yet, so that a source slower than real time plays slowly rather than not at all.
+struct Clock {
+	min_frame_time: Duration,
+	// When the next frame is due, unset until the first one has been decoded.
+	next: Option<Instant>,
+	last_drawn: Option<Instant>,
+}
+
+impl Clock {
+	fn new(min_frame_time: Duration) -> Self {
+		Self {
+			min_frame_time,
+			next: None,
+			last_drawn: None,
+		}
+	}
+
+	// Whether the next frame, up for delay, is to be drawn, after sleeping until it is due if so.
+	fn wait(&mut self, delay: Duration) -> bool {
+		let now = Instant::now();
+		// Falling more than a frame behind starts the schedule over from now, rather than rushing
+		// through the frames after it to catch up.
+		let start = match self.next {
+			Some(next) if now < next + delay => next,
+			_ => now,
+		};
+		self.next = Some(start + delay);
+		if self
+			.last_drawn
+			.is_some_and(|last| start.duration_since(last) < self.min_frame_time)
+		{
+			return false;
+		}
+		thread::sleep(start.saturating_duration_since(now));
+		self.last_drawn = Some(start);
+		true
+	}
+
+	// Frames skipped for the cap leave the one drawn up in their place.
+	fn shown_for(&self, delay: Duration) -> Duration {
+		delay.max(self.min_frame_time)
+	}
+}
+
+// Everything but the source and renderer has the CLI's defaults: no frame rate cap, no looping,
+// normal speed, and a converter at the default size and theme that colors as the terminal allows.
+pub struct PlayerBuilder {
+	source: Option<Box<dyn FrameSource>>,
+	converter: Option<Converter>,
+	renderer: Option<Box<dyn Renderer>>,
+	fps_cap: Option<f64>,
+	looping: bool,
+	speed: f64,
+	hooks: Hooks,
+	stop: StopHandle,
+}
+
+impl Default for PlayerBuilder {
+	fn default() -> Self {
+		Self {
+			source: None,
+			converter: None,
+			renderer: None,
+			fps_cap: None,
+			looping: false,
+			speed: 1.0,
+			hooks: Hooks::default(),
+			stop: StopHandle::default(),
+		}
+	}
+}
+
+impl PlayerBuilder {
+	pub fn source(mut self, source: Box<dyn FrameSource>) -> Self {
+		self.source = Some(source);
+		self
+	}
+
+	pub fn converter(mut self, converter: Converter) -> Self {
+		self.converter = Some(converter);
+		self
+	}
+
+	pub fn renderer(mut self, renderer: Box<dyn Renderer>) -> Self {
+		self.renderer = Some(renderer);
+		self
+	}
+
+	// Draw at most this many frames a second, skipping the rest.
+	pub fn fps_cap(mut self, fps: f64) -> Self {
+		self.fps_cap = Some(fps);
+		self
+	}
+
+	pub fn loop_(mut self, looping: bool) -> Self {
+		self.looping = looping;
+		self
+	}
+
+	pub fn speed(mut self, speed: f64) -> Self {
+		self.speed = speed;
+		self
+	}
+
+	// Called with every frame about to be drawn, which the hook may change, and its position from
+	// the start of the source.
+	pub fn on_frame(mut self, hook: impl FnMut(&mut AsciiFrame, Duration) + Send + 'static) -> Self {
+		self.hooks.on_frame(hook);
+		self
+	}
+
+	pub fn on_event(mut self, hook: impl FnMut(Event) + Send + 'static) -> Self {
+		self.hooks.on_event(hook);
+		self
+	}
+
+	// The same handle the player gets, for hooks to stop it with.
+	pub fn stop_handle(&self) -> StopHandle {
+		self.stop.clone()
+	}
+
+	pub fn build(self) -> Result<Player, Error> {
+		if self.fps_cap.is_some_and(|fps| !(fps.is_finite() && fps > 0.0)) {
+			return Err(Error::BadOptions("fps_cap must be a positive number".to_string()));
+		}
+		if !(self.speed.is_finite() && self.speed > 0.0) {
+			return Err(Error::BadOptions("speed must be a positive number".to_string()));
+		}
+		Ok(Player {
+			source: self
+				.source
+				.ok_or_else(|| Error::BadOptions("A player needs a source".to_string()))?,
+			converter: self
+				.converter
+				.unwrap_or_else(|| Converter::new(DEFAULT_WIDTH, ColorMode::Auto, None, Theme::default())),
+			renderer: self
+				.renderer
+				.ok_or_else(|| Error::BadOptions("A player needs a renderer".to_string()))?,
+			fps_cap: self.fps_cap,
+			looping: self.looping,
+			speed: self.speed,
+			hooks: self.hooks,
+			stop: self.stop,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::frame::Cell;
+	use crate::source::{Frame, Picture};
+	use std::sync::atomic::AtomicUsize;
+
+	// Takes longer to decode each frame than the frame is up for.
+	struct SlowSource {
+		frames: usize,
+	}
+
+	impl FrameSource for SlowSource {
+		fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+			if self.frames == 0 {
+				return Ok(None);
+			}
+			self.frames -= 1;
+			thread::sleep(Duration::from_millis(20));
+			Ok(Some(Frame {
+				picture: Picture::Text(vec!["#".to_string()]),
+				delay: Duration::from_millis(5),
+			}))
+		}
+
+		fn frame_rate(&self) -> f64 {
+			200.0
+		}
+	}
+
+	struct Counter(Arc<AtomicUsize>);
+
+	impl Renderer for Counter {
+		fn begin_frame(&mut self) -> Result<(), Error> {
+			Ok(())
+		}
+
+		fn draw_cells(&mut self, _: usize, _: &[Cell]) -> Result<(), Error> {
+			Ok(())
+		}
+
+		fn present(&mut self, _: Duration) -> Result<(), Error> {
+			self.0.fetch_add(1, Ordering::Relaxed);
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn a_source_slower_than_real_time_still_has_every_frame_drawn() {
+		let drawn = Arc::new(AtomicUsize::new(0));
+		Player::builder()
+			.source(Box::new(SlowSource { frames: 5 }))
+			.renderer(Box::new(Counter(Arc::clone(&drawn))))
+			.build()
+			.expect("the player has a source and a renderer")
+			.play()
+			.expect("the frames play");
+		assert_eq!(drawn.load(Ordering::Relaxed), 5);
+	}
+
+	#[test]
+	fn frames_sooner_than_the_cap_are_skipped() {
+		let mut clock = Clock::new(Duration::from_millis(100));
+		assert!(clock.wait(Duration::from_millis(1)));
+		assert!(!clock.wait(Duration::from_millis(1)));
+	}
+}
@@ -0,0 +1,181 @@
+use super::{Event, Hooks};
+use crate::Error;
+use crate::convert::ConvertedFrames;
+use crate::frame::AsciiFrame;
+use crate::render::{Renderer, render};
+use crate::schedule;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{self, Instant};
+
+// What pause(), resume() and seek() ask of a play() in progress. Every seek gets a number of its
+// own, so that seeking to the same place twice still counts.
+#[derive(Clone, Copy, Default)]
+struct Control {
+	paused: bool,
+	seek: Option<(u64, f64)>,
+}
+
+// Plays converted frames on tokio's timers instead of a thread of its own. play() runs until the
+// frames are over, or forever when looping, while pause(), resume() and seek() are called from
+// elsewhere, through an Arc or from another branch of a select!.
+pub struct AsyncPlayer {
+	frames: Vec<Vec<String>>,
+	delays: Vec<Duration>,
+	timestamps: Vec<f64>,
+	renderer: Mutex<Box<dyn Renderer + Send>>,
+	control: watch::Sender<Control>,
+	fps_cap: Option<f64>,
+	looping: bool,
+	hooks: Mutex<Hooks>,
+}
+
+impl AsyncPlayer {
+	pub fn new((frames, delays): ConvertedFrames, renderer: Box<dyn Renderer + Send>) -> Self {
+		Self {
+			timestamps: schedule::timestamps(&delays),
+			frames,
+			delays,
+			renderer: Mutex::new(renderer),
+			control: watch::Sender::new(Control::default()),
+			fps_cap: None,
+			looping: false,
+			hooks: Mutex::new(Hooks::default()),
+		}
+	}
+
+	// Draw at most this many frames a second, skipping the rest.
+	pub fn fps_cap(mut self, fps: f64) -> Self {
+		self.fps_cap = Some(fps);
+		self
+	}
+
+	pub fn loop_(mut self, looping: bool) -> Self {
+		self.looping = looping;
+		self
+	}
+
+	// Called with every frame about to be drawn, which the hook may change, and its position.
+	pub fn on_frame(self, hook: impl FnMut(&mut AsciiFrame, Duration) + Send + 'static) -> Self {
+		self.hooks().on_frame(hook);
+		self
+	}
+
+	pub fn on_event(self, hook: impl FnMut(Event) + Send + 'static) -> Self {
+		self.hooks().on_event(hook);
+		self
+	}
+
+	pub fn duration(&self) -> Duration {
+		self.delays.iter().sum()
+	}
+
+	pub fn pause(&self) {
+		self.control.send_modify(|control| control.paused = true);
+	}
+
+	pub fn resume(&self) {
+		self.control.send_modify(|control| control.paused = false);
+	}
+
+	// Positions past the end are taken as the end.
+	pub fn seek(&self, position: Duration) {
+		self.control.send_modify(|control| {
+			let count = control.seek.map_or(0, |(count, _)| count + 1);
+			control.seek = Some((count, position.as_secs_f64()));
+		});
+	}
+
+	pub async fn play(&self) -> Result<(), Error> {
+		if self.fps_cap.is_some_and(|fps| !(fps.is_finite() && fps > 0.0)) {
+			return Err(Error::BadOptions("fps_cap must be a positive number".to_string()));
+		}
+		let duration = self.duration().as_secs_f64();
+		if self.frames.is_empty() || duration == 0.0 {
+			return Err(Error::NoFrames);
+		}
+		let min_frame_time = schedule::min_frame_time(self.fps_cap);
+		let mut control = self.control.subscribe();
+		let mut seen_seek = control.borrow().seek.map(|(count, _)| count);
+
+		// Where playback was at the anchor, from which the position is counted while playing.
+		let mut anchor = Instant::now();
+		let mut anchor_position = 0.0;
+		let mut shown: Option<usize> = None;
+		let mut last_drawn: Option<Instant> = None;
+		let mut was_paused = false;
+		self.hooks().event(Event::Started);
+		loop {
+			let state = *control.borrow_and_update();
+			if state.paused != was_paused {
+				was_paused = state.paused;
+				self.hooks()
+					.event(if state.paused { Event::Paused } else { Event::Resumed });
+			}
+			let now = Instant::now();
+			let mut position = if state.paused {
+				anchor_position
+			} else {
+				anchor_position + now.duration_since(anchor).as_secs_f64()
+			};
+			if let Some((count, target)) = state.seek.filter(|(count, _)| Some(*count) != seen_seek) {
+				seen_seek = Some(count);
+				position = target.clamp(0.0, duration);
+				shown = None;
+				self.hooks().event(Event::Seeked(Duration::from_secs_f64(position)));
+			}
+			if position >= duration {
+				if !self.looping {
+					self.hooks().event(Event::Finished);
+					return Ok(());
+				}
+				position %= duration;
+				self.hooks().event(Event::Looped);
+			}
+			(anchor, anchor_position) = (now, position);
+
+			let index = schedule::frame_at(&self.timestamps, position);
+			let capped = last_drawn.is_some_and(|last| now.duration_since(last) < min_frame_time);
+			if shown != Some(index) && !(capped && shown.is_some()) {
+				let mut frame = AsciiFrame::from_lines(&self.frames[index]);
+				let pts = Duration::from_secs_f64(self.timestamps[index]);
+				self.hooks().frame_mut(&mut frame, pts);
+				let mut renderer = self.renderer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+				render(renderer.as_mut(), &frame, self.delays[index])?;
+				shown = Some(index);
+				last_drawn = Some(now);
+			}
+
+			if state.paused {
+				// Nothing happens until the next pause, resume or seek.
+				if control.changed().await.is_err() {
+					return Ok(());
+				}
+				anchor = Instant::now();
+				continue;
+			}
+			let next = self.timestamps.get(index + 1).copied().unwrap_or(duration);
+			let mut wake = anchor + Duration::from_secs_f64(next - position);
+			if let Some(last) = last_drawn {
+				wake = wake.max(last + min_frame_time);
+			}
+			tokio::select! {
+				() = time::sleep_until(wake) => {},
+				changed = control.changed() => if changed.is_err() {
+					return Ok(());
+				},
+			}
+		}
+	}
+
+	fn hooks(&self) -> std::sync::MutexGuard<'_, Hooks> {
+		self.hooks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	// Calls the renderer's finish(), once playing is over.
+	pub fn finish(&self) -> Result<(), Error> {
+		let mut renderer = self.renderer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+		renderer.finish()
+	}
+}
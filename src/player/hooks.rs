@@ -0,0 +1,56 @@
+use crate::frame::AsciiFrame;
+use std::borrow::Cow;
+use std::time::Duration;
+
+// What happens to playback, as passed to on_event hooks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+	Started,
+	Paused,
+	Resumed,
+	// To this position, from the start of the source.
+	Seeked(Duration),
+	// Back to the first frame after the last.
+	Looped,
+	Finished,
+}
+
+type FrameHook = Box<dyn FnMut(&mut AsciiFrame, Duration) + Send>;
+type EventHook = Box<dyn FnMut(Event) + Send>;
+
+// The callbacks registered with on_frame and on_event, called in the order they were added.
+#[derive(Default)]
+pub struct Hooks {
+	frame: Vec<FrameHook>,
+	event: Vec<EventHook>,
+}
+
+impl Hooks {
+	pub fn on_frame(&mut self, hook: impl FnMut(&mut AsciiFrame, Duration) + Send + 'static) {
+		self.frame.push(Box::new(hook));
+	}
+
+	pub fn on_event(&mut self, hook: impl FnMut(Event) + Send + 'static) {
+		self.event.push(Box::new(hook));
+	}
+
+	// Hands a frame about to be drawn to the frame hooks, along with its position from the start of
+	// the source. Frames are only copied for hooks to change when there are any.
+	pub fn frame<'a>(&mut self, frame: &'a AsciiFrame, pts: Duration) -> Cow<'a, AsciiFrame> {
+		if self.frame.is_empty() {
+			return Cow::Borrowed(frame);
+		}
+		let mut frame = frame.clone();
+		self.frame_mut(&mut frame, pts);
+		Cow::Owned(frame)
+	}
+
+	// The same for a frame that is already a copy.
+	pub fn frame_mut(&mut self, frame: &mut AsciiFrame, pts: Duration) {
+		self.frame.iter_mut().for_each(|hook| hook(frame, pts));
+	}
+
+	pub fn event(&mut self, event: Event) {
+		self.event.iter_mut().for_each(|hook| hook(event));
+	}
+}
@@ -0,0 +1,131 @@
+use crate::Error;
+use crate::source::Chapter;
+use std::io::ErrorKind;
+use std::process::Command;
+use std::time::Duration;
+
+pub struct MediaInfo {
+	pub duration: Option<f64>,
+	pub streams: Vec<StreamInfo>,
+}
+
+pub struct StreamInfo {
+	pub kind: String,
+	pub codec: String,
+	pub width: Option<u32>,
+	pub height: Option<u32>,
+	pub fps: Option<f64>,
+	// Degrees the picture is turned by when shown, which ffmpeg applies while decoding.
+	pub rotation: Option<i32>,
+}
+
+// Fails with what ffprobe had to say when it can't read the input.
+pub fn probe(input: &str) -> Result<MediaInfo, Error> {
+	let output = Command::new("ffprobe")
+		.args([
+			"-v",
+			"error",
+			"-show_entries",
+			"stream=codec_type,codec_name,width,height,avg_frame_rate:stream_tags=rotate:stream_side_data=rotation:format=duration",
+			"-of",
+			"default",
+			input,
+		])
+		.output()
+		.map_err(|error| match error.kind() {
+			ErrorKind::NotFound => Error::FfmpegNotFound { program: "ffprobe" },
+			_ => error.into(),
+		})?;
+	if !output.status.success() {
+		return Err(Error::ProbeFailed {
+			input: input.to_string(),
+			stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+		});
+	}
+
+	let mut info = MediaInfo {
+		duration: None,
+		streams: Vec::new(),
+	};
+	// Side data sections are nested inside their stream's, so keys are taken to belong to the last
+	// stream opened.
+	let mut in_stream = false;
+	for line in String::from_utf8_lossy(&output.stdout).lines() {
+		match line {
+			"[STREAM]" => {
+				in_stream = true;
+				info.streams.push(StreamInfo {
+					kind: String::new(),
+					codec: String::new(),
+					width: None,
+					height: None,
+					fps: None,
+					rotation: None,
+				});
+				continue;
+			},
+			"[/STREAM]" | "[FORMAT]" => {
+				in_stream = false;
+				continue;
+			},
+			_ => {},
+		}
+		let Some((key, value)) = line.split_once('=') else {
+			continue;
+		};
+		let stream = info.streams.last_mut().filter(|_| in_stream);
+		match (key, stream) {
+			("duration", None) => info.duration = value.parse().ok(),
+			("codec_type", Some(stream)) => stream.kind = value.to_string(),
+			("codec_name", Some(stream)) => stream.codec = value.to_string(),
+			("width", Some(stream)) => stream.width = value.parse().ok(),
+			("height", Some(stream)) => stream.height = value.parse().ok(),
+			("avg_frame_rate", Some(stream)) => stream.fps = parse_rate(value),
+			// The display matrix turns the other way from the older rotate tag.
+			("rotation", Some(stream)) => stream.rotation = value.parse::<i32>().ok().map(|rotation| -rotation),
+			("TAG:rotate", Some(stream)) if stream.rotation.is_none() => stream.rotation = value.parse().ok(),
+			_ => {},
+		}
+	}
+	Ok(info)
+}
+
+fn parse_rate(rate: &str) -> Option<f64> {
+	let (numerator, denominator) = rate.split_once('/')?;
+	let (numerator, denominator): (f64, f64) = (numerator.parse().ok()?, denominator.parse().ok()?);
+	Some(numerator / denominator).filter(|rate| rate.is_finite() && *rate > 0.0)
+}
+
+// Inputs without chapters, or that ffprobe can't read at all, have none.
+pub fn probe_chapters(input: &str) -> Vec<Chapter> {
+	let output = Command::new("ffprobe")
+		.args([
+			"-v",
+			"error",
+			"-show_entries",
+			"chapter=start_time:chapter_tags=title",
+			"-of",
+			"default=noprint_wrappers=1",
+			input,
+		])
+		.output();
+	let Ok(output) = output else {
+		return Vec::new();
+	};
+
+	// Each chapter is a start_time line, followed by a title line if it has one.
+	let mut chapters: Vec<Chapter> = Vec::new();
+	for line in String::from_utf8_lossy(&output.stdout).lines() {
+		if let Some(start) = line.strip_prefix("start_time=") {
+			if let Ok(start) = start.parse() {
+				chapters.push(Chapter {
+					start: Duration::from_secs_f64(start),
+					title: format!("Chapter {}", chapters.len() + 1),
+				});
+			}
+		} else if let (Some(title), Some(chapter)) = (line.strip_prefix("TAG:title="), chapters.last_mut()) {
+			chapter.title = title.to_string();
+		}
+	}
+	chapters
+}
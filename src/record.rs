@@ -0,0 +1,368 @@
+use bad_ascii::Error;
+use bad_ascii::baa::{self, BaaWriter, Header};
+use bad_ascii::export::{apply_sgr, json_string};
+use bad_ascii::frame::{AsciiFrame, Cell};
+use crossterm::terminal;
+use image::Rgb;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Redraws come whenever playback makes them, so a .baa recording's header only gives a nominal
+// frame rate. Each frame's own delay is what gets replayed.
+const RECORDING_FPS: f32 = 24.0;
+
+enum Recording {
+	Cast(Cast),
+	Baa(Baa),
+}
+
+struct Cast {
+	file: BufWriter<File>,
+	started_at: Instant,
+}
+
+struct Baa {
+	writer: BaaWriter<BufWriter<File>>,
+	screen: Screen,
+	// The screen as of the last redraw and when it was drawn. It is written once the next redraw
+	// shows how long it stayed up.
+	shown: Option<(Vec<String>, Instant)>,
+}
+
+// Passes everything through to the terminal while also recording it, so that overlays, seeks and
+// dropped frames are replayed exactly as they were shown. An asciinema v2 recording keeps the
+// output itself, while a .baa file keeps the screen it drew after each redraw.
+pub struct Recorder<W> {
+	inner: W,
+	recording: Option<Recording>,
+	// Output since the last flush, which becomes one event or frame so that each redraw is replayed
+	// at once.
+	pending: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+	pub fn new(inner: W, path: Option<&Path>) -> Result<Self, Error> {
+		let Some(path) = path else {
+			return Ok(Self {
+				inner,
+				recording: None,
+				pending: Vec::new(),
+			});
+		};
+		let is = |wanted: &str| {
+			path.extension()
+				.is_some_and(|extension| extension.eq_ignore_ascii_case(wanted))
+		};
+		if !is("cast") && !is("baa") {
+			return Err(Error::BadOptions(format!(
+				"Can't record to {}; only .cast and .baa recordings are supported",
+				path.display()
+			)));
+		}
+
+		let (columns, rows) = terminal::size().map_err(Error::TerminalError)?;
+		let mut file = BufWriter::new(File::create(path)?);
+		let recording = if is("baa") {
+			let header = Header {
+				fps: RECORDING_FPS,
+				columns,
+				rows,
+				color: true,
+				compressed: true,
+				frame_count: 0,
+			};
+			Recording::Baa(Baa {
+				writer: BaaWriter::new(file, &header, baa::DEFAULT_LEVEL)?,
+				screen: Screen::new(columns as usize, rows as usize),
+				shown: None,
+			})
+		} else {
+			let timestamp = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs();
+			writeln!(
+				file,
+				"{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"env\": {{\"TERM\": \"xterm-256color\"}}}}",
+				columns, rows, timestamp
+			)?;
+			Recording::Cast(Cast {
+				file,
+				started_at: Instant::now(),
+			})
+		};
+
+		Ok(Self {
+			inner,
+			recording: Some(recording),
+			pending: Vec::new(),
+		})
+	}
+
+	// A .baa recording's last frame and index are only written here, so it has to be called once
+	// playback is over for the file to be complete.
+	pub fn finish(&mut self) -> Result<(), Error> {
+		let Some(Recording::Baa(mut recording)) = self.recording.take() else {
+			return Ok(());
+		};
+		if let Some((lines, drawn_at)) = recording.shown.take() {
+			recording.writer.write_frame(&lines, delay_since(drawn_at))?;
+		}
+		let (mut file, _) = recording.writer.finish()?;
+		file.flush()?;
+		Ok(())
+	}
+}
+
+impl<W: Write> Write for Recorder<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.inner.write(buf)?;
+		if self.recording.is_some() {
+			self.pending.extend_from_slice(&buf[..written]);
+		}
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()?;
+		let Some(recording) = &mut self.recording else {
+			return Ok(());
+		};
+
+		// A character split across two flushes is held back until the rest of it arrives.
+		let valid = match std::str::from_utf8(&self.pending) {
+			Ok(text) => text.len(),
+			Err(error) => error.valid_up_to(),
+		};
+		if valid == 0 {
+			return Ok(());
+		}
+		let text = String::from_utf8_lossy(&self.pending[..valid]).into_owned();
+		self.pending.drain(..valid);
+
+		match recording {
+			Recording::Cast(cast) => {
+				writeln!(
+					cast.file,
+					"[{:.6}, \"o\", {}]",
+					cast.started_at.elapsed().as_secs_f64(),
+					json_string(&text)
+				)?;
+				cast.file.flush()
+			},
+			Recording::Baa(recording) => {
+				if let Ok((columns, rows)) = terminal::size() {
+					recording.screen.resize(columns as usize, rows as usize);
+				}
+				recording.screen.feed(&text);
+				let lines = recording.screen.frame.to_lines();
+				if recording.shown.as_ref().is_some_and(|(shown, _)| *shown == lines) {
+					return Ok(());
+				}
+				if let Some((shown, drawn_at)) = recording.shown.replace((lines, Instant::now())) {
+					recording
+						.writer
+						.write_frame(&shown, delay_since(drawn_at))
+						.map_err(io::Error::other)?;
+				}
+				Ok(())
+			},
+		}
+	}
+}
+
+// Frame delays are stored in microseconds that fit a u32, which a long enough pause wouldn't.
+fn delay_since(drawn_at: Instant) -> Duration {
+	drawn_at.elapsed().min(Duration::from_micros(u32::MAX.into()))
+}
+
+// Where an escape sequence being read is at.
+enum Escape {
+	None,
+	// Just after the escape character.
+	Start,
+	// A control sequence, with the parameters read so far.
+	Csi(String),
+	// An OSC, DCS or APC string, like sixel and kitty graphics, which leave no text behind. The flag
+	// is whether the last character was an escape, which with a backslash ends the string.
+	String(bool),
+}
+
+// Enough of a terminal to follow what playback draws: text, cursor moves, clears and colors.
+struct Screen {
+	frame: AsciiFrame,
+	row: usize,
+	col: usize,
+	fg: Option<Rgb<u8>>,
+	bg: Option<Rgb<u8>>,
+	escape: Escape,
+}
+
+impl Screen {
+	fn new(cols: usize, rows: usize) -> Self {
+		Self {
+			frame: AsciiFrame::new(cols, rows),
+			row: 0,
+			col: 0,
+			fg: None,
+			bg: None,
+			escape: Escape::None,
+		}
+	}
+
+	fn resize(&mut self, cols: usize, rows: usize) {
+		self.frame.resize(cols, rows);
+	}
+
+	fn feed(&mut self, text: &str) {
+		for c in text.chars() {
+			self.escape = match std::mem::replace(&mut self.escape, Escape::None) {
+				Escape::None if c == '\u{1b}' => Escape::Start,
+				Escape::None => {
+					self.put(c);
+					Escape::None
+				},
+				Escape::Start => match c {
+					'[' => Escape::Csi(String::new()),
+					']' | 'P' | '_' => Escape::String(false),
+					_ => Escape::None,
+				},
+				Escape::Csi(mut params) => {
+					if ('\u{40}'..='\u{7e}').contains(&c) {
+						self.control(&params, c);
+						Escape::None
+					} else {
+						params.push(c);
+						Escape::Csi(params)
+					}
+				},
+				Escape::String(after_escape) => match c {
+					'\u{7}' => Escape::None,
+					'\\' if after_escape => Escape::None,
+					_ => Escape::String(c == '\u{1b}'),
+				},
+			};
+		}
+	}
+
+	fn put(&mut self, c: char) {
+		match c {
+			'\r' => self.col = 0,
+			'\n' => self.row = (self.row + 1).min(self.frame.rows.saturating_sub(1)),
+			'\u{8}' => self.col = self.col.saturating_sub(1),
+			c if c.is_control() => {},
+			ch => {
+				if self.row < self.frame.rows && self.col < self.frame.cols {
+					self.frame.row_mut(self.row)[self.col] = Cell {
+						ch,
+						fg: self.fg,
+						bg: self.bg,
+					};
+				}
+				self.col += 1;
+			},
+		}
+	}
+
+	fn control(&mut self, params: &str, command: char) {
+		// Private sequences, like showing and hiding the cursor, don't change what is on screen.
+		if params.starts_with(['?', '>', '<', '=']) {
+			return;
+		}
+		if command == 'm' {
+			apply_sgr(params, &mut self.fg, &mut self.bg);
+			return;
+		}
+
+		let numbers: Vec<usize> = params.split(';').map(|param| param.parse().unwrap_or(0)).collect();
+		let number = |index: usize| numbers.get(index).copied().unwrap_or(0);
+		let count = number(0).max(1);
+		let blank = Cell {
+			bg: self.bg,
+			..Cell::default()
+		};
+		let (cols, rows) = (self.frame.cols, self.frame.rows);
+		match command {
+			'H' | 'f' => {
+				self.row = (number(0).max(1) - 1).min(rows.saturating_sub(1));
+				self.col = number(1).max(1) - 1;
+			},
+			'A' => self.row = self.row.saturating_sub(count),
+			'B' => self.row = (self.row + count).min(rows.saturating_sub(1)),
+			'C' => self.col += count,
+			'D' => self.col = self.col.saturating_sub(count),
+			'G' => self.col = count - 1,
+			'J' => {
+				let cursor = (self.row * cols + self.col).min(self.frame.cells.len());
+				let range = match number(0) {
+					0 => cursor..self.frame.cells.len(),
+					1 => 0..(cursor + 1).min(self.frame.cells.len()),
+					_ => 0..self.frame.cells.len(),
+				};
+				self.frame.cells[range].fill(blank);
+			},
+			'K' if self.row < rows => {
+				let line = self.frame.row_mut(self.row);
+				let range = match number(0) {
+					0 => self.col.min(cols)..cols,
+					1 => 0..(self.col + 1).min(cols),
+					_ => 0..cols,
+				};
+				line[range].fill(blank);
+			},
+			_ => {},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn text(screen: &Screen) -> Vec<String> {
+		(0..screen.frame.rows)
+			.map(|row| screen.frame.row(row).iter().map(|cell| cell.ch).collect())
+			.collect()
+	}
+
+	#[test]
+	fn text_lands_where_the_cursor_was_moved() {
+		let mut screen = Screen::new(6, 3);
+		screen.feed("\x1B[2;3Hab\x1B[1;1Hc\r\ndef");
+		assert_eq!(text(&screen), ["c     ", "defb  ", "      "]);
+	}
+
+	#[test]
+	fn colors_are_kept_with_each_cell() {
+		let mut screen = Screen::new(3, 1);
+		screen.feed("\x1B[38;2;255;0;0ma\x1B[0mb");
+		assert_eq!(screen.frame.row(0)[0].fg, Some(Rgb([255, 0, 0])));
+		assert_eq!(screen.frame.row(0)[1].fg, None);
+		assert_eq!(screen.frame.to_lines(), ["\x1B[38;2;255;0;0ma\x1B[0mb "]);
+	}
+
+	#[test]
+	fn clears_blank_the_screen_and_lines() {
+		let mut screen = Screen::new(3, 2);
+		screen.feed("abc\r\ndef\x1B[1;2H\x1B[K");
+		assert_eq!(text(&screen), ["a  ", "def"]);
+		screen.feed("\x1B[2J");
+		assert_eq!(text(&screen), ["   ", "   "]);
+	}
+
+	#[test]
+	fn graphics_and_private_sequences_leave_no_text() {
+		let mut screen = Screen::new(4, 1);
+		screen.feed("\x1B[?25l\x1BPq#0;2;0;0;0~~\x1B\\\x1B_Ga=T;AAAA\x1B\\\x1B]0;title\x07ok");
+		assert_eq!(text(&screen), ["ok  "]);
+	}
+
+	#[test]
+	fn drawing_past_the_edges_is_cut_off() {
+		let mut screen = Screen::new(2, 1);
+		screen.feed("abcd\n\n\x1B[9;9Hx");
+		assert_eq!(text(&screen), ["ab"]);
+	}
+}
@@ -0,0 +1,127 @@
+use crate::wire::{self, Message};
+use bad_ascii::Error;
+use crossterm::terminal;
+use std::net::{Shutdown, TcpStream};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(16);
+const SIZE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Plays what a `serve --stream` server sends over the wire protocol. A connection that is lost is
+// retried for as long as the player runs.
+pub struct Remote {
+	address: String,
+	connection: Option<Connection>,
+}
+
+struct Connection {
+	stream: TcpStream,
+	capabilities: u32,
+	previous: Option<Vec<String>>,
+}
+
+// Also stops the thread reporting the terminal's size, whose next write fails.
+impl Drop for Connection {
+	fn drop(&mut self) {
+		let _ = self.stream.shutdown(Shutdown::Both);
+	}
+}
+
+impl Remote {
+	// Fails if the server can't be reached at all, rather than retrying something that never worked.
+	pub fn connect(address: &str) -> Result<Self, Error> {
+		let connection = open(address).map_err(|error| Error::Network(format!("{}: {}", address, error)))?;
+		Ok(Self {
+			address: address.to_string(),
+			connection: Some(connection),
+		})
+	}
+
+	// The server sends frames when they are due, so each is returned as soon as it arrives.
+	pub fn next_frame(&mut self) -> Result<Option<Vec<String>>, Error> {
+		let mut retry = FIRST_RETRY;
+		loop {
+			let connection = match &mut self.connection {
+				Some(connection) => connection,
+				None => match open(&self.address) {
+					Ok(connection) => self.connection.insert(connection),
+					Err(_) => {
+						sleep(retry);
+						retry = (retry * 2).min(MAX_RETRY);
+						continue;
+					},
+				},
+			};
+			match Message::read(&mut connection.stream) {
+				Ok(Message::Frame { record, .. }) => {
+					let previous = connection.previous.as_deref();
+					if let Ok((_, frame)) = wire::decode_frame(&record, previous, connection.capabilities) {
+						connection.previous = Some(frame.clone());
+						return Ok(Some(frame));
+					}
+					self.connection = None;
+				},
+				Ok(Message::Format { .. }) => connection.previous = None,
+				Ok(Message::Error(_)) => self.connection = None,
+				Ok(_) => {},
+				Err(_) => {
+					self.connection = None;
+					sleep(retry);
+				},
+			}
+		}
+	}
+}
+
+fn open(address: &str) -> Result<Connection, Error> {
+	let mut stream = TcpStream::connect(address)?;
+	stream.set_nodelay(true)?;
+	stream.set_read_timeout(Some(wire::PEER_TIMEOUT))?;
+	let size = terminal::size().map_err(Error::TerminalError)?;
+	Message::Hello {
+		version: wire::VERSION,
+		capabilities: wire::CAPABILITIES,
+		columns: size.0,
+		rows: size.1,
+	}
+	.write(&mut stream)?;
+
+	let capabilities = match Message::read(&mut stream)? {
+		Message::Welcome { capabilities, .. } => capabilities,
+		Message::Error(reason) => return Err(Error::Network(reason)),
+		_ => return Err(Error::Network("The server didn't answer with a WELCOME".to_string())),
+	};
+	let out = stream.try_clone()?;
+	thread::spawn(move || report(out, size));
+	Ok(Connection {
+		stream,
+		capabilities,
+		previous: None,
+	})
+}
+
+// Tells the server when the terminal is resized, and that the client is still there otherwise.
+fn report(mut out: TcpStream, mut size: (u16, u16)) {
+	let mut last_sent = Instant::now();
+	loop {
+		sleep(SIZE_POLL_INTERVAL);
+		let current = terminal::size().unwrap_or(size);
+		let message = if current != size {
+			size = current;
+			Message::Resize {
+				columns: size.0,
+				rows: size.1,
+			}
+		} else if last_sent.elapsed() >= wire::KEEPALIVE_INTERVAL {
+			Message::KeepAlive
+		} else {
+			continue;
+		};
+		if message.write(&mut out).is_err() {
+			return;
+		}
+		last_sent = Instant::now();
+	}
+}
@@ -0,0 +1,124 @@
+mod file;
+mod graphics;
+mod terminal;
+
+pub use self::file::{FileFormat, FileRenderer};
+pub use self::graphics::{Graphics, KittyRenderer, SixelRenderer};
+pub use self::terminal::AnsiRenderer;
+
+use crate::frame::{AsciiFrame, Cell};
+use crate::{Error, export};
+#[cfg(not(target_arch = "wasm32"))]
+use crossterm::terminal::size;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+
+// Where frames go: a terminal, a file or a connection. A frame is begun, drawn a row of cells at a
+// time and presented once it is complete, to stay up for its delay.
+pub trait Renderer {
+	fn begin_frame(&mut self) -> Result<(), Error>;
+	fn draw_cells(&mut self, row: usize, cells: &[Cell]) -> Result<(), Error>;
+	fn present(&mut self, delay: Duration) -> Result<(), Error>;
+	// Called after the last frame, for renderers that write everything out at the end.
+	fn finish(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+}
+
+// Draws a whole frame and presents it.
+pub fn render(renderer: &mut dyn Renderer, frame: &AsciiFrame, delay: Duration) -> Result<(), Error> {
+	renderer.begin_frame()?;
+	for row in 0..frame.rows {
+		renderer.draw_cells(row, frame.row(row))?;
+	}
+	renderer.present(delay)
+}
+
+pub fn draw_frame(
+	stdout: &mut impl Write,
+	frame: &[String],
+	previous: Option<&[String]>,
+	top: u16,
+	left: u16,
+) -> std::io::Result<()> {
+	for (row, line) in frame.iter().enumerate() {
+		if previous.and_then(|previous| previous.get(row)) == Some(line) {
+			continue;
+		}
+		let cursor_move = format!("\x1B[{};{}H", top + row as u16, left);
+		stdout.write_all(format!("{}{}", cursor_move, line).as_bytes())?;
+	}
+	Ok(())
+}
+
+// Scales the color of every character, which greys out uncolored text too. Frames are converted and
+// cached long before playback, so live changes are made to the text rather than the pictures.
+pub fn adjust_brightness(frame: &[String], brightness: f64) -> Vec<String> {
+	if brightness == 1.0 {
+		return frame.to_vec();
+	}
+	frame
+		.iter()
+		.map(|line| {
+			let mut adjusted = String::with_capacity(line.len());
+			for (color, text) in export::runs(line) {
+				let [red, green, blue] = color.0.map(|channel| (channel as f64 * brightness).min(255.0) as u8);
+				write!(adjusted, "\x1B[38;2;{};{};{}m{}", red, green, blue, text)
+					.expect("writing to a String can't fail");
+			}
+			adjusted.push_str("\x1B[0m");
+			adjusted
+		})
+		.collect()
+}
+
+pub fn remove_ansi_escape_sequences(input: &str) -> String {
+	let mut result = String::new();
+	let mut in_escape_sequence = false;
+
+	for c in input.chars() {
+		if c == '\u{1b}' {
+			in_escape_sequence = true;
+			continue;
+		} else if in_escape_sequence {
+			if c.is_ascii_alphabetic() {
+				in_escape_sequence = false;
+			}
+			continue;
+		}
+		result.push(c);
+	}
+
+	result
+}
+
+// Rows above a frame that centers it in the terminal, leaving extra_rows below it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_vertical_padding(frame_height: usize, extra_rows: usize) -> u16 {
+	let (_, term_height) = size().unwrap();
+	let frame_height = frame_height + extra_rows;
+
+	if frame_height < term_height as usize {
+		(term_height - frame_height as u16) / 2
+	} else {
+		0
+	}
+}
+
+// Columns left of a frame that center it in the terminal.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_horizontal_padding(frame: &[String]) -> u16 {
+	let (term_width, _) = size().unwrap();
+
+	let max_line_width = frame
+		.iter()
+		.map(|line| remove_ansi_escape_sequences(line).chars().count())
+		.max()
+		.unwrap_or(0);
+	if max_line_width < term_width as usize {
+		(term_width - max_line_width as u16) / 2
+	} else {
+		0
+	}
+}
@@ -0,0 +1,61 @@
+use super::Renderer;
+use crate::frame::{AsciiFrame, Cell};
+use crate::{Error, export};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+	Gif,
+	Html,
+	Svg,
+	Cast,
+}
+
+// Collects the frames presented to it and writes them out as one file in finish().
+pub struct FileRenderer {
+	format: FileFormat,
+	path: PathBuf,
+	frames: Vec<Vec<String>>,
+	delays: Vec<Duration>,
+	current: AsciiFrame,
+}
+
+impl FileRenderer {
+	pub fn new(format: FileFormat, path: impl Into<PathBuf>) -> Self {
+		Self {
+			format,
+			path: path.into(),
+			frames: Vec::new(),
+			delays: Vec::new(),
+			current: AsciiFrame::default(),
+		}
+	}
+}
+
+impl Renderer for FileRenderer {
+	fn begin_frame(&mut self) -> Result<(), Error> {
+		self.current = AsciiFrame::default();
+		Ok(())
+	}
+
+	fn draw_cells(&mut self, row: usize, cells: &[Cell]) -> Result<(), Error> {
+		self.current.set_row(row, cells);
+		Ok(())
+	}
+
+	fn present(&mut self, delay: Duration) -> Result<(), Error> {
+		self.frames.push(std::mem::take(&mut self.current).to_lines());
+		self.delays.push(delay);
+		Ok(())
+	}
+
+	fn finish(&mut self) -> Result<(), Error> {
+		match self.format {
+			FileFormat::Gif => export::write_gif(&self.frames, &self.delays, &self.path),
+			FileFormat::Html => export::write_html(&self.frames, &self.delays, &self.path),
+			FileFormat::Svg => export::write_svg(&self.frames, &self.delays, &self.path),
+			FileFormat::Cast => export::write_cast(&self.frames, &self.delays, &self.path),
+		}
+	}
+}
@@ -0,0 +1,221 @@
+use super::Renderer;
+use crate::Error;
+use crate::export::rasterize;
+use crate::frame::{AsciiFrame, Cell};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::RgbImage;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+
+// Kitty takes image data in escape sequences of at most this many base64 characters.
+const KITTY_CHUNK: usize = 4096;
+// The one image kept on screen, replaced by every frame.
+const KITTY_IMAGE_ID: u32 = 1;
+// Sixel colors are snapped to this many levels a channel, which makes 216 registers.
+const SIXEL_LEVELS: u32 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Graphics {
+	Sixel,
+	Kitty,
+}
+
+impl Graphics {
+	pub fn renderer<'a, W: Write + 'a>(self, out: W, top: u16, left: u16) -> Box<dyn Renderer + 'a> {
+		match self {
+			Graphics::Sixel => Box::new(SixelRenderer::new(out, top, left)),
+			Graphics::Kitty => Box::new(KittyRenderer::new(out, top, left)),
+		}
+	}
+}
+
+// Draws frames as pictures of their text, with the sixel graphics that xterm, foot, WezTerm and
+// others understand. Pictures are as large as the font makes them, so they may not line up with the
+// terminal's own cells.
+pub struct SixelRenderer<W: Write> {
+	out: W,
+	top: u16,
+	left: u16,
+	current: AsciiFrame,
+}
+
+impl<W: Write> SixelRenderer<W> {
+	pub fn new(out: W, top: u16, left: u16) -> Self {
+		Self {
+			out,
+			top,
+			left,
+			current: AsciiFrame::default(),
+		}
+	}
+}
+
+impl<W: Write> Renderer for SixelRenderer<W> {
+	fn begin_frame(&mut self) -> Result<(), Error> {
+		self.current = AsciiFrame::default();
+		Ok(())
+	}
+
+	fn draw_cells(&mut self, row: usize, cells: &[Cell]) -> Result<(), Error> {
+		self.current.set_row(row, cells);
+		Ok(())
+	}
+
+	fn present(&mut self, _delay: Duration) -> Result<(), Error> {
+		let image = picture(&self.current);
+		write!(self.out, "\x1B[{};{}H{}", self.top, self.left, sixel(&image))?;
+		self.out.flush()?;
+		Ok(())
+	}
+}
+
+// Draws frames as pictures of their text with kitty's graphics protocol, scaled by the terminal to
+// cover the cells the text would have.
+pub struct KittyRenderer<W: Write> {
+	out: W,
+	top: u16,
+	left: u16,
+	current: AsciiFrame,
+}
+
+impl<W: Write> KittyRenderer<W> {
+	pub fn new(out: W, top: u16, left: u16) -> Self {
+		Self {
+			out,
+			top,
+			left,
+			current: AsciiFrame::default(),
+		}
+	}
+}
+
+impl<W: Write> Renderer for KittyRenderer<W> {
+	fn begin_frame(&mut self) -> Result<(), Error> {
+		self.current = AsciiFrame::default();
+		Ok(())
+	}
+
+	fn draw_cells(&mut self, row: usize, cells: &[Cell]) -> Result<(), Error> {
+		self.current.set_row(row, cells);
+		Ok(())
+	}
+
+	fn present(&mut self, _delay: Duration) -> Result<(), Error> {
+		let (columns, rows) = (self.current.cols, self.current.rows);
+		let image = picture(&self.current);
+		let data = STANDARD.encode(image.as_raw());
+
+		// q=2 keeps the terminal from answering, which would end up as input.
+		let mut escapes = format!(
+			"\x1B_Ga=d,d=I,i={},q=2\x1B\\\x1B[{};{}H",
+			KITTY_IMAGE_ID, self.top, self.left
+		);
+		let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+		for (index, chunk) in chunks.iter().enumerate() {
+			let more = u8::from(index + 1 < chunks.len());
+			let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+			if index == 0 {
+				write!(
+					escapes,
+					"\x1B_Ga=T,f=24,s={},v={},c={},r={},i={},q=2,m={};{}\x1B\\",
+					image.width(),
+					image.height(),
+					columns,
+					rows,
+					KITTY_IMAGE_ID,
+					more,
+					chunk
+				)
+				.expect("writing to a String can't fail");
+			} else {
+				write!(escapes, "\x1B_Gm={};{}\x1B\\", more, chunk).expect("writing to a String can't fail");
+			}
+		}
+		self.out.write_all(escapes.as_bytes())?;
+		self.out.flush()?;
+		Ok(())
+	}
+}
+
+fn picture(frame: &AsciiFrame) -> RgbImage {
+	rasterize(&frame.to_lines(), frame.cols.max(1) as u32, frame.rows.max(1) as u32)
+}
+
+// Every band of six pixel rows is drawn once per color in it, each pass a sixel character per
+// column with the bits of the pixels that have that color, run-length encoded.
+fn sixel(image: &RgbImage) -> String {
+	let (width, height) = image.dimensions();
+	let level = |channel: u8| (channel as u32 * (SIXEL_LEVELS - 1) + 127) / 255;
+	let colors: Vec<u32> = image
+		.pixels()
+		.map(|pixel| (level(pixel[0]) * SIXEL_LEVELS + level(pixel[1])) * SIXEL_LEVELS + level(pixel[2]))
+		.collect();
+
+	let mut out = format!("\x1BPq\"1;1;{};{}", width, height);
+	let registers = SIXEL_LEVELS.pow(3);
+	let percent = |level: u32| level * 100 / (SIXEL_LEVELS - 1);
+	for register in 0..registers {
+		let (red, green, blue) = (
+			register / (SIXEL_LEVELS * SIXEL_LEVELS),
+			register / SIXEL_LEVELS % SIXEL_LEVELS,
+			register % SIXEL_LEVELS,
+		);
+		write!(
+			out,
+			"#{};2;{};{};{}",
+			register,
+			percent(red),
+			percent(green),
+			percent(blue)
+		)
+		.expect("writing to a String can't fail");
+	}
+
+	for band in (0..height).step_by(6) {
+		let rows = band..(band + 6).min(height);
+		let mut used = vec![false; registers as usize];
+		for y in rows.clone() {
+			for x in 0..width {
+				used[colors[(y * width + x) as usize] as usize] = true;
+			}
+		}
+		for register in (0..registers).filter(|&register| used[register as usize]) {
+			write!(out, "#{}", register).expect("writing to a String can't fail");
+			let mut run: Option<(char, u32)> = None;
+			for x in 0..width {
+				let bits = rows
+					.clone()
+					.filter(|&y| colors[(y * width + x) as usize] == register)
+					.fold(0, |bits, y| bits | 1 << (y - band));
+				let character = char::from(63 + bits as u8);
+				run = match run {
+					Some((previous, length)) if previous == character => Some((previous, length + 1)),
+					Some((previous, length)) => {
+						push_run(&mut out, previous, length);
+						Some((character, 1))
+					},
+					None => Some((character, 1)),
+				};
+			}
+			if let Some((character, length)) = run {
+				push_run(&mut out, character, length);
+			}
+			// Back to the start of the band for the next color.
+			out.push('$');
+		}
+		out.push('-');
+	}
+	out.push_str("\x1B\\");
+	out
+}
+
+fn push_run(out: &mut String, character: char, length: u32) {
+	if length > 3 {
+		write!(out, "!{}{}", length, character).expect("writing to a String can't fail");
+	} else {
+		(0..length).for_each(|_| out.push(character));
+	}
+}
@@ -0,0 +1,57 @@
+use super::Renderer;
+use crate::Error;
+use crate::frame::{AsciiFrame, Cell, to_ansi};
+use std::io::Write;
+use std::time::Duration;
+
+// Draws frames as text with their color escapes, redrawing only the cells that changed. Anything that
+// takes ANSI works, a terminal or a connection to one.
+pub struct AnsiRenderer<W: Write> {
+	out: W,
+	top: u16,
+	left: u16,
+	current: AsciiFrame,
+	previous: Option<AsciiFrame>,
+}
+
+impl<W: Write> AnsiRenderer<W> {
+	// The frame's top left corner goes at row top and column left, both counted from 1.
+	pub fn new(out: W, top: u16, left: u16) -> Self {
+		Self {
+			out,
+			top,
+			left,
+			current: AsciiFrame::default(),
+			previous: None,
+		}
+	}
+
+	// Moves the frame, which is then drawn whole.
+	pub fn set_position(&mut self, top: u16, left: u16) {
+		(self.top, self.left) = (top, left);
+		self.previous = None;
+	}
+}
+
+impl<W: Write> Renderer for AnsiRenderer<W> {
+	fn begin_frame(&mut self) -> Result<(), Error> {
+		self.current = AsciiFrame::default();
+		Ok(())
+	}
+
+	fn draw_cells(&mut self, row: usize, cells: &[Cell]) -> Result<(), Error> {
+		self.current.set_row(row, cells);
+		Ok(())
+	}
+
+	fn present(&mut self, _delay: Duration) -> Result<(), Error> {
+		for (row, cols) in self.current.changes(self.previous.as_ref()) {
+			let (top, left) = (self.top + row as u16, self.left + cols.start as u16);
+			let text = to_ansi(&self.current.row(row)[cols]);
+			write!(self.out, "\x1B[{};{}H{}", top, left, text)?;
+		}
+		self.out.flush()?;
+		self.previous = Some(std::mem::take(&mut self.current));
+		Ok(())
+	}
+}
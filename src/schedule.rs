@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+// When each frame starts, in seconds from the first.
+pub fn timestamps(delays: &[Duration]) -> Vec<f64> {
+	delays
+		.iter()
+		.scan(0.0, |elapsed, delay| {
+			let timestamp = *elapsed;
+			*elapsed += delay.as_secs_f64();
+			Some(timestamp)
+		})
+		.collect()
+}
+
+// The frame on screen at position, in seconds from the first, going by its timestamps.
+pub fn frame_at(timestamps: &[f64], position: f64) -> usize {
+	timestamps
+		.partition_point(|&timestamp| timestamp <= position)
+		.saturating_sub(1)
+}
+
+// The shortest time a frame is shown for when playback is capped at max_fps.
+pub fn min_frame_time(max_fps: Option<f64>) -> Duration {
+	max_fps.map_or(Duration::ZERO, |fps| Duration::from_secs_f64(1.0 / fps))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn timestamps_add_up_the_delays() {
+		let delays = [100, 200, 50].map(Duration::from_millis);
+		let timestamps = timestamps(&delays);
+		assert_eq!(timestamps.len(), 3);
+		assert_eq!(timestamps[0], 0.0);
+		assert!((timestamps[1] - 0.1).abs() < 1e-9);
+		assert!((timestamps[2] - 0.3).abs() < 1e-9);
+	}
+
+	#[test]
+	fn frame_at_finds_the_frame_on_screen() {
+		let timestamps = [0.0, 0.1, 0.3];
+		assert_eq!(frame_at(&timestamps, 0.0), 0);
+		assert_eq!(frame_at(&timestamps, 0.05), 0);
+		assert_eq!(frame_at(&timestamps, 0.1), 1);
+		assert_eq!(frame_at(&timestamps, 0.29), 1);
+		assert_eq!(frame_at(&timestamps, 0.3), 2);
+		// Past the end the last frame stays up, and before the start the first one is shown.
+		assert_eq!(frame_at(&timestamps, 10.0), 2);
+		assert_eq!(frame_at(&timestamps, -1.0), 0);
+		assert_eq!(frame_at(&[], 1.0), 0);
+	}
+
+	#[test]
+	fn min_frame_time_follows_the_cap() {
+		assert_eq!(min_frame_time(None), Duration::ZERO);
+		assert_eq!(min_frame_time(Some(4.0)), Duration::from_millis(250));
+	}
+}
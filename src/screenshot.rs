@@ -0,0 +1,41 @@
+use bad_ascii::render::remove_ansi_escape_sequences;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Screenshots {
+	dir: PathBuf,
+	color: bool,
+}
+
+impl Screenshots {
+	pub fn new(dir: PathBuf, color: bool) -> Self {
+		Self { dir, color }
+	}
+
+	// Writes the frame to a file named after the current time in milliseconds and returns its path.
+	pub fn save(&self, frame: &[String]) -> io::Result<PathBuf> {
+		let millis = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis();
+		let path = self.dir.join(format!("bad_ascii_{}.txt", millis));
+
+		let mut contents = String::new();
+		for line in frame {
+			if self.color {
+				contents.push_str(line);
+				// Leave the terminal's colors alone when the file is printed with cat.
+				contents.push_str("\x1B[0m");
+			} else {
+				contents.push_str(&remove_ansi_escape_sequences(line));
+			}
+			contents.push('\n');
+		}
+
+		fs::create_dir_all(&self.dir)?;
+		fs::write(&path, contents)?;
+		Ok(path)
+	}
+}
@@ -0,0 +1,268 @@
+mod broadcast;
+mod http;
+mod limits;
+mod metrics;
+mod ssh;
+mod stream;
+mod telnet;
+mod tls;
+mod ws;
+
+pub use self::http::listen_http;
+pub use self::limits::Limits;
+pub use self::metrics::listen_metrics;
+pub use self::ssh::listen_ssh;
+pub use self::stream::listen_stream;
+pub use self::telnet::listen_telnet;
+pub use self::tls::load_tls;
+pub use self::ws::listen_ws;
+
+use self::broadcast::Broadcasts;
+use self::limits::Slot;
+use self::metrics::{Client, ClientStats, Metrics};
+use crate::DURATION;
+use crate::cli::{CacheOptions, SourceArgs, StyleArgs};
+use crate::pipeline;
+use bad_ascii::convert::{ColorMode, ConvertedFrames};
+use bad_ascii::render::{draw_frame, remove_ansi_escape_sequences};
+use bad_ascii::source::DecodeOptions;
+use bad_ascii::theme::Theme;
+use bad_ascii::{DEFAULT_WIDTH, Error};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Assumed until a client reports its own, which not every one does.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CTRL_C: u8 = 3;
+// Conversions are only made at these widths and the server's own, so that viewers can't have one
+// made for every width there is.
+const WIDTHS: [u32; 6] = [20, 40, 60, 80, 120, 160];
+// How many conversions are kept around, the least recently used being dropped first.
+const MAX_VIDEOS: usize = 4;
+
+// A conversion that viewers wanting the same width and colors wait on together.
+type Video = Arc<Mutex<Option<Arc<ConvertedFrames>>>>;
+
+// The served input, converted once for each width and color choice viewers need and shared between
+// them. Only the few most recently used conversions are kept.
+pub struct Library {
+	input: String,
+	source: SourceArgs,
+	style: StyleArgs,
+	cache: CacheOptions,
+	theme: Theme,
+	default_width: NonZeroU32,
+	broadcasts: Option<Broadcasts>,
+	limits: Limits,
+	metrics: Metrics,
+	// Most recently used last.
+	videos: Mutex<Vec<((NonZeroU32, bool), Video)>>,
+}
+
+impl Library {
+	pub fn new(
+		input: String,
+		(source, style, cache): (SourceArgs, StyleArgs, CacheOptions),
+		theme: Theme,
+		default_width: NonZeroU32,
+		broadcast: bool,
+		limits: Limits,
+	) -> Self {
+		Self {
+			input,
+			source,
+			style,
+			cache,
+			theme,
+			default_width,
+			broadcasts: broadcast.then(Broadcasts::new),
+			limits,
+			metrics: Metrics::default(),
+			videos: Mutex::new(Vec::new()),
+		}
+	}
+
+	pub fn default_width(&self) -> NonZeroU32 {
+		self.default_width
+	}
+
+	// Every listener asks before serving a connection, and keeps the slot for as long as it's open.
+	pub fn admit(&self, address: IpAddr) -> Result<Slot, &'static str> {
+		self.limits.admit(address)
+	}
+
+	pub fn metrics(&self) -> &Metrics {
+		&self.metrics
+	}
+
+	// Colored unless the server was started with --color-mode never.
+	pub fn colored(&self) -> bool {
+		self.style.color_mode != ColorMode::Never
+	}
+
+	// The conversion for clients that don't say how big their terminal is.
+	pub fn default_video(&self) -> Result<Arc<ConvertedFrames>, Error> {
+		self.video(self.default_width, self.colored())
+	}
+
+	// The widest conversion, up to the usual playback size, that fits the terminal without wrapping
+	// or scrolling.
+	pub fn fitting_width(&self, (columns, rows): (u16, u16)) -> Result<NonZeroU32, Error> {
+		let sample = self.default_video()?;
+		let rows_per_column = sample.0[0].len() as f64 / self.default_width.get() as f64;
+		let width = (columns as u32)
+			.min((rows as f64 / rows_per_column) as u32)
+			.min(DEFAULT_WIDTH.get());
+		Ok(self.snap(NonZeroU32::new(width).unwrap_or(self.default_width)))
+	}
+
+	// The widest of the widths conversions are made at that isn't wider than `width`, or the
+	// narrowest if they all are.
+	pub fn snap(&self, width: NonZeroU32) -> NonZeroU32 {
+		let widths = || WIDTHS.into_iter().chain([self.default_width.get()]);
+		widths()
+			.filter(|&snapped| snapped <= width.get())
+			.max()
+			.or_else(|| widths().min())
+			.and_then(NonZeroU32::new)
+			.expect("widths are above 0")
+	}
+
+	// Viewers arriving together for the same conversion wait for one rather than each starting their
+	// own. Only the list is locked while looking it up, so other conversions aren't held up.
+	pub fn video(&self, width: NonZeroU32, colored: bool) -> Result<Arc<ConvertedFrames>, Error> {
+		let key = (self.snap(width), colored);
+		let entry = {
+			let mut videos = self.videos.lock().unwrap_or_else(PoisonError::into_inner);
+			let entry = match videos.iter().position(|(video, _)| *video == key) {
+				Some(index) => videos.remove(index).1,
+				None => Video::default(),
+			};
+			videos.push((key, Arc::clone(&entry)));
+			if videos.len() > MAX_VIDEOS {
+				videos.remove(0);
+			}
+			entry
+		};
+
+		let mut video = entry.lock().unwrap_or_else(PoisonError::into_inner);
+		if let Some(video) = &*video {
+			return Ok(Arc::clone(video));
+		}
+		let converted = Arc::new(self.convert(key.0, colored)?);
+		*video = Some(Arc::clone(&converted));
+		Ok(converted)
+	}
+
+	fn convert(&self, width: NonZeroU32, colored: bool) -> Result<ConvertedFrames, Error> {
+		let mut style = self.style.clone();
+		// Whether to color is up to the viewers' terminals, not to wherever the server's output goes.
+		style.color_mode = if colored { ColorMode::Always } else { ColorMode::Never };
+		let converter = pipeline::converter(width, &style, self.theme.clone());
+		let decode = DecodeOptions {
+			hwaccel: self.source.hwaccel,
+			columns: Some(width.get()),
+			filters: self.source.vf.clone(),
+			limit: Some(Duration::from_secs(DURATION)),
+		};
+		let started = Instant::now();
+		let (frames, _) = pipeline::load(&self.input, &self.source, &self.cache, &converter, decode)?;
+		let video = frames.into_converted()?;
+		self.metrics.converted(started.elapsed());
+		Ok(video)
+	}
+}
+
+// Plays to one viewer, by itself or as part of the broadcast, until it quits or hangs up.
+pub fn watch(
+	out: &mut impl Write,
+	events: &Receiver<ClientEvent>,
+	library: &Library,
+	client: &Client,
+	width: NonZeroU32,
+	colored: bool,
+) -> Result<(), Error> {
+	let video = library.video(width, colored)?;
+	let mut out = library.limits.throttle(client.counting(out));
+	match &library.broadcasts {
+		Some(broadcasts) => broadcasts.watch(&mut out, events, client.stats(), &video, width, colored)?,
+		None => play_session(&mut out, events, client.stats(), &video)?,
+	}
+	Ok(())
+}
+
+// What a viewer did, whichever way it's connected.
+pub enum ClientEvent {
+	Key(u8),
+	Resize(u16, u16),
+}
+
+// Plays to one viewer at its own pace: every connection starts from the beginning, can pause with
+// space and leaves with q. Returns once the viewer quits or hangs up.
+fn play_session(
+	out: &mut impl Write,
+	events: &Receiver<ClientEvent>,
+	stats: &ClientStats,
+	video: &ConvertedFrames,
+) -> io::Result<()> {
+	let (frames, delays) = video;
+	let mut size = DEFAULT_SIZE;
+	let mut paused = false;
+	let mut index = 0;
+	let mut previous: Option<&[String]> = None;
+
+	out.write_all(b"\x1B[2J\x1B[?25l")?;
+	loop {
+		loop {
+			match events.try_recv() {
+				Ok(ClientEvent::Key(b'q' | CTRL_C)) => {
+					out.write_all(b"\x1B[0m\x1B[2J\x1B[H\x1B[?25h")?;
+					return out.flush();
+				},
+				Ok(ClientEvent::Key(b' ')) => paused = !paused,
+				Ok(ClientEvent::Key(_)) => {},
+				Ok(ClientEvent::Resize(columns, rows)) => {
+					size = (columns, rows);
+					previous = None;
+					out.write_all(b"\x1B[2J")?;
+				},
+				Err(TryRecvError::Empty) => break,
+				Err(TryRecvError::Disconnected) => return Ok(()),
+			}
+		}
+
+		let frame = &frames[index];
+		let (top, left) = centered(frame, size);
+		draw_frame(out, frame, previous, top, left)?;
+		out.flush()?;
+		// Paused, the same frame is drawn over itself, which sends nothing new.
+		if !paused || previous.is_none() {
+			stats.frame_sent();
+		}
+		previous = Some(frame);
+
+		if paused {
+			sleep(PAUSED_POLL_INTERVAL);
+		} else {
+			sleep(delays[index]);
+			index = (index + 1) % frames.len();
+		}
+	}
+}
+
+fn centered(frame: &[String], (columns, rows): (u16, u16)) -> (u16, u16) {
+	let width = frame
+		.iter()
+		.map(|line| remove_ansi_escape_sequences(line).chars().count())
+		.max()
+		.unwrap_or(0);
+	let top = rows.saturating_sub(frame.len() as u16) / 2;
+	let left = columns.saturating_sub(width as u16) / 2;
+	(top, left)
+}
@@ -0,0 +1,190 @@
+use super::metrics::ClientStats;
+use super::{CTRL_C, ClientEvent, PAUSED_POLL_INTERVAL, centered};
+use bad_ascii::convert::ConvertedFrames;
+use bad_ascii::render::draw_frame;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+// How many frames a viewer may fall behind before it's skipped ahead to a full redraw.
+const BACKLOG: usize = 4;
+
+type Update = Arc<Vec<u8>>;
+
+// Where a viewer's updates go, and what counts the ones it misses.
+type Viewer = (SyncSender<Update>, Arc<ClientStats>);
+
+// Viewers that would be sent exactly the same bytes: same conversion, same terminal size.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Format {
+	width: NonZeroU32,
+	colored: bool,
+	size: (u16, u16),
+}
+
+// One playback everyone joins in progress. Each format is drawn once per frame, on a thread that
+// lives as long as someone is watching it, and what it draws is handed to all of its viewers.
+pub struct Broadcasts {
+	started: Instant,
+	formats: Mutex<HashMap<Format, Sender<Viewer>>>,
+}
+
+impl Broadcasts {
+	pub fn new() -> Self {
+		Self {
+			started: Instant::now(),
+			formats: Mutex::new(HashMap::new()),
+		}
+	}
+
+	// Viewers can't pause a broadcast, only leave it with q.
+	pub fn watch(
+		&self,
+		out: &mut impl Write,
+		events: &Receiver<ClientEvent>,
+		stats: &Arc<ClientStats>,
+		video: &Arc<ConvertedFrames>,
+		width: NonZeroU32,
+		colored: bool,
+	) -> io::Result<()> {
+		let mut size = super::DEFAULT_SIZE;
+		let mut updates = None;
+
+		out.write_all(b"\x1B[?25l")?;
+		loop {
+			loop {
+				match events.try_recv() {
+					Ok(ClientEvent::Key(b'q' | CTRL_C)) => {
+						out.write_all(b"\x1B[0m\x1B[2J\x1B[H\x1B[?25h")?;
+						return out.flush();
+					},
+					Ok(ClientEvent::Key(_)) => {},
+					Ok(ClientEvent::Resize(columns, rows)) => {
+						size = (columns, rows);
+						updates = None;
+					},
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => return Ok(()),
+				}
+			}
+
+			let receiver = match &updates {
+				Some(receiver) => receiver,
+				None => {
+					out.write_all(b"\x1B[2J")?;
+					let format = Format { width, colored, size };
+					updates.insert(self.subscribe(format, stats, video))
+				},
+			};
+			match receiver.recv_timeout(PAUSED_POLL_INTERVAL) {
+				Ok(update) => {
+					out.write_all(&update)?;
+					out.flush()?;
+					stats.frame_sent();
+				},
+				Err(RecvTimeoutError::Timeout) => {},
+				// The format's thread stopped for lack of viewers just as this one joined.
+				Err(RecvTimeoutError::Disconnected) => updates = None,
+			}
+		}
+	}
+
+	fn subscribe(&self, format: Format, stats: &Arc<ClientStats>, video: &Arc<ConvertedFrames>) -> Receiver<Update> {
+		let (sender, updates) = mpsc::sync_channel(BACKLOG);
+		let viewer = (sender, Arc::clone(stats));
+		let mut formats = self.formats.lock().unwrap_or_else(PoisonError::into_inner);
+		let viewer = match formats.get(&format) {
+			Some(joining) => match joining.send(viewer) {
+				Ok(()) => return updates,
+				Err(error) => error.0,
+			},
+			None => viewer,
+		};
+
+		let (joining, joiners) = mpsc::channel();
+		let _ = joining.send(viewer);
+		formats.insert(format, joining);
+		let video = Arc::clone(video);
+		let started = self.started;
+		thread::spawn(move || draw(&video, format.size, started, &joiners));
+		updates
+	}
+}
+
+// Everyone sees the frame the shared clock is at, whichever format they get it in.
+fn draw(video: &ConvertedFrames, size: (u16, u16), started: Instant, joiners: &Receiver<Viewer>) {
+	let (frames, delays) = video;
+	let length: Duration = delays.iter().sum();
+	// Viewers that are up to date get only the lines that changed, the rest are sent whole frames.
+	let mut viewers = Vec::new();
+	let mut stale = Vec::new();
+	let mut previous: Option<usize> = None;
+
+	loop {
+		stale.extend(joiners.try_iter());
+		if viewers.is_empty() && stale.is_empty() {
+			return;
+		}
+
+		let (index, remaining) = position(delays, started.elapsed(), length);
+		let frame = &frames[index];
+		let (top, left) = centered(frame, size);
+		if previous != Some(index) && !viewers.is_empty() {
+			let mut update = Vec::new();
+			let _ = draw_frame(
+				&mut update,
+				frame,
+				previous.map(|previous| &frames[previous][..]),
+				top,
+				left,
+			);
+			let update = Arc::new(update);
+			viewers.retain(|viewer: &Viewer| match viewer.0.try_send(Arc::clone(&update)) {
+				Ok(()) => true,
+				Err(TrySendError::Full(_)) => {
+					viewer.1.frame_dropped();
+					stale.push(viewer.clone());
+					false
+				},
+				Err(TrySendError::Disconnected(_)) => false,
+			});
+		}
+		if !stale.is_empty() {
+			let mut update = b"\x1B[2J".to_vec();
+			let _ = draw_frame(&mut update, frame, None, top, left);
+			let update = Arc::new(update);
+			stale.retain(|viewer| match viewer.0.try_send(Arc::clone(&update)) {
+				Ok(()) => {
+					viewers.push(viewer.clone());
+					false
+				},
+				Err(TrySendError::Full(_)) => {
+					viewer.1.frame_dropped();
+					true
+				},
+				Err(TrySendError::Disconnected(_)) => false,
+			});
+		}
+		previous = Some(index);
+		sleep(remaining.min(PAUSED_POLL_INTERVAL));
+	}
+}
+
+// The frame showing at this point of the endlessly looping video, and how long until the next.
+fn position(delays: &[Duration], elapsed: Duration, length: Duration) -> (usize, Duration) {
+	if length.is_zero() {
+		return (0, PAUSED_POLL_INTERVAL);
+	}
+	let mut offset = Duration::from_nanos((elapsed.as_nanos() % length.as_nanos()) as u64);
+	for (index, &delay) in delays.iter().enumerate() {
+		if offset < delay {
+			return (index, delay - offset);
+		}
+		offset -= delay;
+	}
+	(delays.len() - 1, PAUSED_POLL_INTERVAL)
+}
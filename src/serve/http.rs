@@ -0,0 +1,212 @@
+use super::tls::Connection;
+use super::{ClientEvent, Library};
+use crate::wire;
+use bad_ascii::convert::ColorMode;
+use bad_ascii::{DEFAULT_WIDTH, Error};
+use clap::ValueEnum;
+use rustls::ServerConfig;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+// Streams to anything that keeps reading, like `curl http://host:port/play`, until it hangs up.
+// The query picks the conversion:
+//   columns=C&rows=R  the largest that fits a terminal of that size
+//   width=W           up to W characters wide, at one of the widths the server converts for
+//   color=never       without colors, or always to color even if the server doesn't
+pub fn listen_http(
+	address: SocketAddr,
+	library: Arc<Library>,
+	tls: Option<Arc<ServerConfig>>,
+) -> io::Result<JoinHandle<()>> {
+	let listener = TcpListener::bind(address)?;
+	Ok(thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let library = Arc::clone(&library);
+			let tls = tls.clone();
+			thread::spawn(move || {
+				if let Ok(client) = Connection::new(client, tls.as_ref()) {
+					let _ = session(client, &library);
+				}
+			});
+		}
+	}))
+}
+
+fn session(mut client: Connection, library: &Library) -> Result<(), Error> {
+	// Also bounds the TLS handshake, which happens on the first read.
+	client.socket().set_read_timeout(Some(wire::PEER_TIMEOUT))?;
+	let _slot = match library.admit(client.socket().peer_addr()?.ip()) {
+		Ok(slot) => slot,
+		Err(reason) => return Ok(refuse(&mut client, reason)?),
+	};
+	let request = read_request(&mut client)?;
+	if request.path() != "/play" {
+		return Ok(respond(&mut client, "404 Not Found", "text/plain", "Not found\n")?);
+	}
+
+	let colored = match request.query("color").map(|mode| ColorMode::from_str(mode, true)) {
+		None | Some(Ok(ColorMode::Auto)) => library.colored(),
+		Some(Ok(mode)) => mode == ColorMode::Always,
+		Some(Err(_)) => {
+			let message = "color must be one of auto, always or never\n";
+			return Ok(respond(&mut client, "400 Bad Request", "text/plain", message)?);
+		},
+	};
+	let size = request.size();
+	let width = match (request.query("width"), size) {
+		(Some(width), _) => match width.parse().ok().and_then(NonZeroU32::new) {
+			Some(width) => library.snap(width.min(DEFAULT_WIDTH)),
+			None => {
+				let message = "width must be a positive number\n";
+				return Ok(respond(&mut client, "400 Bad Request", "text/plain", message)?);
+			},
+		},
+		(None, Some(size)) => library.fitting_width(size)?,
+		(None, None) => library.default_width(),
+	};
+
+	write!(
+		client,
+		"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n"
+	)?;
+	client.socket().set_nodelay(true)?;
+	// Nothing is read back, so the sender only has to outlive the session to keep it going.
+	let (sender, events) = mpsc::channel();
+	if let Some((columns, rows)) = size {
+		let _ = sender.send(ClientEvent::Resize(columns, rows));
+	}
+	let viewer = library.metrics().connect("http", client.socket().peer_addr()?);
+	super::watch(&mut client, &events, library, &viewer, width, colored)
+}
+
+// Answers before reading the request, so that refusing takes as little as possible.
+pub(super) fn refuse(client: &mut impl Write, reason: &str) -> io::Result<()> {
+	respond(
+		client,
+		"503 Service Unavailable",
+		"text/plain",
+		&format!("{}\n", reason),
+	)
+}
+
+pub(super) fn respond(client: &mut impl Write, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+	write!(
+		client,
+		"HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		status,
+		content_type,
+		body.len(),
+		body
+	)?;
+	client.flush()
+}
+
+pub(super) struct Request {
+	target: String,
+	headers: Vec<(String, String)>,
+}
+
+impl Request {
+	pub(super) fn header(&self, name: &str) -> Option<&str> {
+		self.headers
+			.iter()
+			.find(|(header, _)| header.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub(super) fn path(&self) -> &str {
+		self.target.split('?').next().unwrap_or_default()
+	}
+
+	pub(super) fn query(&self, name: &str) -> Option<&str> {
+		let (_, query) = self.target.split_once('?')?;
+		query
+			.split('&')
+			.filter_map(|pair| pair.split_once('='))
+			.find(|(key, _)| *key == name)
+			.map(|(_, value)| value)
+	}
+
+	// The viewer's terminal size, from columns and rows in the query.
+	pub(super) fn size(&self) -> Option<(u16, u16)> {
+		let columns = self.query("columns")?.parse().ok()?;
+		let rows = self.query("rows")?.parse().ok()?;
+		Some((columns, rows))
+	}
+}
+
+// Clients send nothing more until they've had an answer, so reading ahead of the headers loses
+// nothing.
+pub(super) fn read_request(client: &mut impl Read) -> io::Result<Request> {
+	let mut reader = BufReader::new(client);
+	let mut line = String::new();
+	reader.read_line(&mut line)?;
+	let target = line.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+	let mut headers = Vec::new();
+	loop {
+		line.clear();
+		if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':') {
+			headers.push((name.trim().to_string(), value.trim().to_string()));
+		}
+	}
+	Ok(Request { target, headers })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn request(text: &str) -> Request {
+		read_request(&mut text.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn the_target_splits_into_path_and_query() {
+		let request = request("GET /play?columns=120&rows=40&color=never HTTP/1.1\r\nHost: x\r\n\r\n");
+		assert_eq!(request.path(), "/play");
+		assert_eq!(request.query("color"), Some("never"));
+		assert_eq!(request.query("width"), None);
+		assert_eq!(request.size(), Some((120, 40)));
+	}
+
+	#[test]
+	fn sizes_need_both_numbers() {
+		assert_eq!(request("GET /play?columns=120 HTTP/1.1\r\n\r\n").size(), None);
+		assert_eq!(request("GET /play?columns=120&rows=-1 HTTP/1.1\r\n\r\n").size(), None);
+		assert_eq!(request("GET /play?columns=70000&rows=40 HTTP/1.1\r\n\r\n").size(), None);
+	}
+
+	#[test]
+	fn query_pairs_without_a_value_are_skipped() {
+		let request = request("GET /play?color&width=80&width=90 HTTP/1.1\r\n\r\n");
+		assert_eq!(request.query("color"), None);
+		// The first of a repeated name wins.
+		assert_eq!(request.query("width"), Some("80"));
+		assert_eq!(request.path(), "/play");
+	}
+
+	#[test]
+	fn headers_are_found_whatever_their_case() {
+		let request =
+			request("GET / HTTP/1.1\r\nUpgrade: websocket\r\nSec-WebSocket-Key:  abc== \r\n\r\nignored: yes\r\n");
+		assert_eq!(request.path(), "/");
+		assert_eq!(request.header("upgrade"), Some("websocket"));
+		assert_eq!(request.header("SEC-WEBSOCKET-KEY"), Some("abc=="));
+		assert_eq!(request.header("ignored"), None);
+	}
+
+	#[test]
+	fn empty_requests_go_to_the_root() {
+		let request = request("");
+		assert_eq!(request.path(), "/");
+		assert_eq!(request.query("width"), None);
+	}
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// How many connections there may be, and how fast each is sent to. Unset limits don't apply.
+pub struct Limits {
+	max_clients: Option<u32>,
+	max_clients_per_address: Option<u32>,
+	// In bytes per second.
+	max_bandwidth: Option<u32>,
+	connected: Arc<Mutex<Connected>>,
+}
+
+#[derive(Default)]
+struct Connected {
+	total: u32,
+	addresses: HashMap<IpAddr, u32>,
+}
+
+// An admitted connection, counted against the limits until it's dropped.
+pub struct Slot {
+	connected: Arc<Mutex<Connected>>,
+	address: IpAddr,
+}
+
+impl Drop for Slot {
+	fn drop(&mut self) {
+		let mut connected = self.connected.lock().unwrap_or_else(PoisonError::into_inner);
+		connected.total -= 1;
+		if let Some(count) = connected.addresses.get_mut(&self.address) {
+			*count -= 1;
+			if *count == 0 {
+				connected.addresses.remove(&self.address);
+			}
+		}
+	}
+}
+
+impl Limits {
+	pub fn new(max_clients: Option<u32>, max_clients_per_address: Option<u32>, max_bandwidth: Option<u32>) -> Self {
+		Self {
+			max_clients,
+			max_clients_per_address,
+			max_bandwidth,
+			connected: Arc::default(),
+		}
+	}
+
+	// Refusals come with the reason to tell the client.
+	pub fn admit(&self, address: IpAddr) -> Result<Slot, &'static str> {
+		let mut connected = self.connected.lock().unwrap_or_else(PoisonError::into_inner);
+		if self.max_clients.is_some_and(|max| connected.total >= max) {
+			return Err("Too many viewers, try again later");
+		}
+		let from_address = connected.addresses.get(&address).copied().unwrap_or(0);
+		if self.max_clients_per_address.is_some_and(|max| from_address >= max) {
+			return Err("Too many connections from your address");
+		}
+		connected.total += 1;
+		connected.addresses.insert(address, from_address + 1);
+		Ok(Slot {
+			connected: Arc::clone(&self.connected),
+			address,
+		})
+	}
+
+	pub fn throttle<W: Write>(&self, out: W) -> Throttled<W> {
+		Throttled {
+			out,
+			rate: self.max_bandwidth.map(f64::from),
+			allowance: 0.0,
+			refilled: Instant::now(),
+		}
+	}
+}
+
+// Holds the writer back to the bandwidth limit, letting through up to a second's worth in a burst.
+pub struct Throttled<W: Write> {
+	out: W,
+	rate: Option<f64>,
+	allowance: f64,
+	refilled: Instant,
+}
+
+impl<W: Write> Write for Throttled<W> {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		let written = self.out.write(bytes)?;
+		if let Some(rate) = self.rate {
+			self.allowance = (self.allowance + self.refilled.elapsed().as_secs_f64() * rate).min(rate);
+			self.refilled = Instant::now();
+			self.allowance -= written as f64;
+			if self.allowance < 0.0 {
+				sleep(Duration::from_secs_f64(-self.allowance / rate));
+			}
+		}
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
@@ -0,0 +1,284 @@
+use super::Library;
+use super::http::{read_request, respond};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Upper bounds of the conversion time buckets, in seconds.
+const CONVERSION_BUCKETS: [f64; 9] = [0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+// What every listener has sent, and what each connected viewer is being sent, in the Prometheus
+// text format on /metrics.
+#[derive(Default)]
+pub struct Metrics {
+	next_id: AtomicU64,
+	clients: Mutex<BTreeMap<u64, Arc<ClientStats>>>,
+	// Counts of viewers that have left, so the totals never go down.
+	departed: Mutex<BTreeMap<&'static str, Totals>>,
+	conversions: Mutex<Histogram>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Totals {
+	frames: u64,
+	bytes: u64,
+	dropped: u64,
+}
+
+pub struct ClientStats {
+	listener: &'static str,
+	address: String,
+	frames: AtomicU64,
+	bytes: AtomicU64,
+	dropped: AtomicU64,
+}
+
+impl ClientStats {
+	pub fn frame_sent(&self) {
+		self.frames.fetch_add(1, Ordering::Relaxed);
+	}
+
+	// Frames a viewer missed for falling behind.
+	pub fn frame_dropped(&self) {
+		self.dropped.fetch_add(1, Ordering::Relaxed);
+	}
+
+	fn totals(&self) -> Totals {
+		Totals {
+			frames: self.frames.load(Ordering::Relaxed),
+			bytes: self.bytes.load(Ordering::Relaxed),
+			dropped: self.dropped.load(Ordering::Relaxed),
+		}
+	}
+}
+
+#[derive(Default)]
+struct Histogram {
+	counts: [u64; CONVERSION_BUCKETS.len()],
+	count: u64,
+	sum: f64,
+}
+
+// A connected viewer, counted until it's dropped.
+pub struct Client<'a> {
+	metrics: &'a Metrics,
+	id: u64,
+	stats: Arc<ClientStats>,
+}
+
+impl Client<'_> {
+	pub fn stats(&self) -> &Arc<ClientStats> {
+		&self.stats
+	}
+
+	// Counts the bytes written through it.
+	pub fn counting<W: Write>(&self, out: W) -> Counted<'_, W> {
+		Counted {
+			out,
+			stats: &self.stats,
+		}
+	}
+}
+
+impl Drop for Client<'_> {
+	// Locks in the same order as render, so that a scrape sees the viewer either connected or gone.
+	fn drop(&mut self) {
+		let mut departed = self.metrics.departed.lock().unwrap_or_else(PoisonError::into_inner);
+		self.metrics
+			.clients
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&self.id);
+		let totals = departed.entry(self.stats.listener).or_default();
+		let stats = self.stats.totals();
+		totals.frames += stats.frames;
+		totals.bytes += stats.bytes;
+		totals.dropped += stats.dropped;
+	}
+}
+
+pub struct Counted<'a, W: Write> {
+	out: W,
+	stats: &'a ClientStats,
+}
+
+impl<W: Write> Write for Counted<'_, W> {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		let written = self.out.write(bytes)?;
+		self.stats.bytes.fetch_add(written as u64, Ordering::Relaxed);
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
+
+impl Metrics {
+	pub fn connect(&self, listener: &'static str, address: impl ToString) -> Client<'_> {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let stats = Arc::new(ClientStats {
+			listener,
+			address: address.to_string(),
+			frames: AtomicU64::new(0),
+			bytes: AtomicU64::new(0),
+			dropped: AtomicU64::new(0),
+		});
+		self.clients
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(id, Arc::clone(&stats));
+		Client {
+			metrics: self,
+			id,
+			stats,
+		}
+	}
+
+	pub fn converted(&self, took: Duration) {
+		let seconds = took.as_secs_f64();
+		let mut histogram = self.conversions.lock().unwrap_or_else(PoisonError::into_inner);
+		for (count, bound) in histogram.counts.iter_mut().zip(CONVERSION_BUCKETS) {
+			if seconds <= bound {
+				*count += 1;
+			}
+		}
+		histogram.count += 1;
+		histogram.sum += seconds;
+	}
+
+	fn render(&self) -> String {
+		let departed = self.departed.lock().unwrap_or_else(PoisonError::into_inner);
+		let clients: Vec<_> = self
+			.clients
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.values()
+			.cloned()
+			.collect();
+		let mut listeners = departed.clone();
+		drop(departed);
+		let mut connected = BTreeMap::new();
+		for client in &clients {
+			let totals = listeners.entry(client.listener).or_default();
+			let stats = client.totals();
+			totals.frames += stats.frames;
+			totals.bytes += stats.bytes;
+			totals.dropped += stats.dropped;
+			*connected.entry(client.listener).or_insert(0) += 1;
+		}
+
+		let mut text = String::new();
+		let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+			let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+			for (labels, value) in samples {
+				let _ = writeln!(text, "{}{} {}", name, labels, value);
+			}
+		};
+		let per_listener = |value: &dyn Fn(&'static str, &Totals) -> u64| {
+			listeners
+				.iter()
+				.map(|(&listener, totals)| {
+					(
+						format!("{{listener=\"{}\"}}", listener),
+						value(listener, totals).to_string(),
+					)
+				})
+				.collect()
+		};
+
+		family(
+			"bad_ascii_clients",
+			"gauge",
+			"Viewers connected right now.",
+			per_listener(&|listener, _| connected.get(listener).copied().unwrap_or(0)),
+		);
+		family(
+			"bad_ascii_frames_sent_total",
+			"counter",
+			"Frames sent to viewers.",
+			per_listener(&|_, totals| totals.frames),
+		);
+		family(
+			"bad_ascii_bytes_sent_total",
+			"counter",
+			"Bytes sent to viewers.",
+			per_listener(&|_, totals| totals.bytes),
+		);
+		family(
+			"bad_ascii_frames_dropped_total",
+			"counter",
+			"Frames viewers missed for falling behind.",
+			per_listener(&|_, totals| totals.dropped),
+		);
+		family(
+			"bad_ascii_client_frames_dropped",
+			"gauge",
+			"Frames each connected viewer has missed for falling behind.",
+			clients
+				.iter()
+				.map(|client| {
+					let labels = format!(
+						"{{listener=\"{}\",client=\"{}\"}}",
+						client.listener,
+						escape(&client.address)
+					);
+					(labels, client.dropped.load(Ordering::Relaxed).to_string())
+				})
+				.collect(),
+		);
+
+		let histogram = self.conversions.lock().unwrap_or_else(PoisonError::into_inner);
+		let mut buckets: Vec<_> = CONVERSION_BUCKETS
+			.iter()
+			.zip(histogram.counts)
+			.map(|(bound, count)| (format!("_bucket{{le=\"{}\"}}", bound), count.to_string()))
+			.collect();
+		buckets.push(("_bucket{le=\"+Inf\"}".into(), histogram.count.to_string()));
+		buckets.push(("_sum".into(), histogram.sum.to_string()));
+		buckets.push(("_count".into(), histogram.count.to_string()));
+		family(
+			"bad_ascii_conversion_seconds",
+			"histogram",
+			"How long converting the input for a new size or color mode took.",
+			buckets,
+		);
+		text
+	}
+}
+
+// Label values are quoted, so quotes and backslashes in them have to be escaped.
+fn escape(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Serves /metrics on an address of its own, so that it can be kept off the public one.
+pub fn listen_metrics(address: SocketAddr, library: Arc<Library>) -> io::Result<JoinHandle<()>> {
+	let listener = TcpListener::bind(address)?;
+	Ok(thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let library = Arc::clone(&library);
+			thread::spawn(move || {
+				let _ = scrape(client, &library);
+			});
+		}
+	}))
+}
+
+fn scrape(mut client: TcpStream, library: &Library) -> io::Result<()> {
+	let request = read_request(&mut client)?;
+	match request.path() {
+		"/metrics" => respond(
+			&mut client,
+			"200 OK",
+			"text/plain; version=0.0.4",
+			&library.metrics().render(),
+		),
+		_ => respond(&mut client, "404 Not Found", "text/plain", "Not found\n"),
+	}
+}
@@ -0,0 +1,224 @@
+use super::limits::Slot;
+use super::{ClientEvent, Library};
+use bad_ascii::Error;
+use russh::keys::{self, Algorithm, PrivateKey};
+use russh::server::{Auth, ChannelOpenHandle, Config, Handle, Handler, Msg, Server, Session};
+use russh::{Channel, ChannelId};
+use std::io::{self, Write};
+use std::mem;
+use std::net::{SocketAddr, TcpListener as StdTcpListener};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use tokio::net::TcpListener;
+use tokio::runtime::{self, Runtime};
+
+// Binds right away like the telnet listener. Anyone may log in with any name and no password; all
+// there is to see is the video.
+pub fn listen_ssh(
+	address: SocketAddr,
+	library: Arc<Library>,
+	host_key: Option<&Path>,
+) -> Result<JoinHandle<()>, Error> {
+	let key = match host_key {
+		Some(path) => keys::load_secret_key(path, None)
+			.map_err(|error| Error::BadOptions(format!("{}: {}", path.display(), error)))?,
+		None => PrivateKey::random(&mut rand::rng(), Algorithm::Ed25519)
+			.map_err(|error| Error::Network(format!("Could not make a host key: {}", error)))?,
+	};
+	let config = Arc::new(Config {
+		keys: vec![key],
+		..Default::default()
+	});
+	let listener = StdTcpListener::bind(address)?;
+	listener.set_nonblocking(true)?;
+	let runtime = Runtime::new()?;
+
+	Ok(thread::spawn(move || {
+		runtime.block_on(async {
+			let Ok(listener) = TcpListener::from_std(listener) else {
+				return;
+			};
+			let _ = Viewers { library }.run_on_socket(config, &listener).await;
+		});
+	}))
+}
+
+struct Viewers {
+	library: Arc<Library>,
+}
+
+impl Server for Viewers {
+	type Handler = Viewer;
+
+	fn new_client(&mut self, address: Option<SocketAddr>) -> Viewer {
+		Viewer {
+			library: Arc::clone(&self.library),
+			address,
+			slot: address.map_or(Err("Unknown address"), |address| self.library.admit(address.ip())),
+			size: None,
+			events: None,
+		}
+	}
+}
+
+// One SSH connection. Only its first shell gets played to, any further channels are left idle.
+struct Viewer {
+	library: Arc<Library>,
+	address: Option<SocketAddr>,
+	// Held for as long as the connection is open. A refused one is told why when it asks for a shell.
+	slot: Result<Slot, &'static str>,
+	size: Option<(u16, u16)>,
+	events: Option<Sender<ClientEvent>>,
+}
+
+impl Handler for Viewer {
+	type Error = russh::Error;
+
+	async fn auth_none(&mut self, _user: &str) -> Result<Auth, Self::Error> {
+		Ok(Auth::Accept)
+	}
+
+	async fn auth_password(&mut self, _user: &str, _password: &str) -> Result<Auth, Self::Error> {
+		Ok(Auth::Accept)
+	}
+
+	async fn auth_publickey(&mut self, _user: &str, _key: &keys::PublicKey) -> Result<Auth, Self::Error> {
+		Ok(Auth::Accept)
+	}
+
+	async fn channel_open_session(
+		&mut self,
+		_channel: Channel<Msg>,
+		reply: ChannelOpenHandle,
+		_session: &mut Session,
+	) -> Result<(), Self::Error> {
+		reply.accept().await;
+		Ok(())
+	}
+
+	async fn pty_request(
+		&mut self,
+		channel: ChannelId,
+		_term: &str,
+		columns: u32,
+		rows: u32,
+		_pixel_width: u32,
+		_pixel_height: u32,
+		_modes: &[(russh::Pty, u32)],
+		session: &mut Session,
+	) -> Result<(), Self::Error> {
+		self.size = Some((clamp(columns), clamp(rows)));
+		session.channel_success(channel)
+	}
+
+	async fn shell_request(&mut self, channel: ChannelId, session: &mut Session) -> Result<(), Self::Error> {
+		if self.events.is_some() {
+			return session.channel_failure(channel);
+		}
+		session.channel_success(channel)?;
+		if let Err(reason) = self.slot {
+			session.data(channel, format!("{}\r\n", reason))?;
+			return session.close(channel);
+		}
+
+		let (sender, events) = mpsc::channel();
+		if let Some((columns, rows)) = self.size {
+			let _ = sender.send(ClientEvent::Resize(columns, rows));
+		}
+		self.events = Some(sender);
+
+		let library = Arc::clone(&self.library);
+		let size = self.size;
+		let address = self
+			.address
+			.map_or_else(|| "unknown".to_string(), |address| address.to_string());
+		let mut out = ChannelWriter {
+			handle: session.handle(),
+			channel,
+			runtime: runtime::Handle::current(),
+			buffer: Vec::new(),
+		};
+		// Playing blocks on every frame, so it runs on a thread of its own rather than in the runtime.
+		thread::spawn(move || {
+			let width = match size {
+				Some(size) => library.fitting_width(size),
+				None => Ok(library.default_width()),
+			};
+			let viewer = library.metrics().connect("ssh", address);
+			let watched =
+				width.and_then(|width| super::watch(&mut out, &events, &library, &viewer, width, library.colored()));
+			if let Err(error) = watched {
+				let _ = write!(out, "{}\r\n", error).and_then(|()| out.flush());
+			}
+			out.close();
+		});
+		Ok(())
+	}
+
+	async fn data(&mut self, _channel: ChannelId, data: &[u8], _session: &mut Session) -> Result<(), Self::Error> {
+		if let Some(events) = &self.events {
+			for &key in data {
+				let _ = events.send(ClientEvent::Key(key));
+			}
+		}
+		Ok(())
+	}
+
+	async fn window_change_request(
+		&mut self,
+		_channel: ChannelId,
+		columns: u32,
+		rows: u32,
+		_pixel_width: u32,
+		_pixel_height: u32,
+		_session: &mut Session,
+	) -> Result<(), Self::Error> {
+		if let Some(events) = &self.events {
+			let _ = events.send(ClientEvent::Resize(clamp(columns), clamp(rows)));
+		}
+		Ok(())
+	}
+
+	// Dropping the sender tells the playing thread the viewer has gone.
+	async fn channel_close(&mut self, _channel: ChannelId, _session: &mut Session) -> Result<(), Self::Error> {
+		self.events = None;
+		Ok(())
+	}
+}
+
+fn clamp(size: u32) -> u16 {
+	size.try_into().unwrap_or(u16::MAX)
+}
+
+// Collects a frame's worth of output and sends it as one message on flush.
+struct ChannelWriter {
+	handle: Handle,
+	channel: ChannelId,
+	runtime: runtime::Handle,
+	buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+	fn close(self) {
+		let _ = self.runtime.block_on(self.handle.close(self.channel));
+	}
+}
+
+impl Write for ChannelWriter {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		self.buffer.extend_from_slice(bytes);
+		Ok(bytes.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if self.buffer.is_empty() {
+			return Ok(());
+		}
+		let data = mem::take(&mut self.buffer);
+		self.runtime
+			.block_on(self.handle.data(self.channel, data))
+			.map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+	}
+}
@@ -0,0 +1,140 @@
+use super::Library;
+use crate::wire::{self, Message};
+use bad_ascii::{Error, export};
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread::{self, JoinHandle, sleep};
+use std::time::Duration;
+
+// Streams frames over the wire protocol to `bad_ascii connect` and anything else that speaks it,
+// each client from the start at its own pace.
+pub fn listen_stream(address: SocketAddr, library: Arc<Library>) -> io::Result<JoinHandle<()>> {
+	let listener = TcpListener::bind(address)?;
+	Ok(thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let library = Arc::clone(&library);
+			thread::spawn(move || {
+				let _ = session(client, &library);
+			});
+		}
+	}))
+}
+
+fn session(mut client: TcpStream, library: &Library) -> Result<(), Error> {
+	client.set_nodelay(true)?;
+	client.set_read_timeout(Some(wire::PEER_TIMEOUT))?;
+	let _slot = match library.admit(client.peer_addr()?.ip()) {
+		Ok(slot) => slot,
+		Err(reason) => return Ok(Message::Error(reason.into()).write(&mut client)?),
+	};
+	let Message::Hello {
+		version,
+		capabilities,
+		columns,
+		rows,
+	} = Message::read(&mut client)?
+	else {
+		Message::Error("the stream has to start with a HELLO".into()).write(&mut client)?;
+		return Ok(());
+	};
+	if version == 0 {
+		Message::Error("there is no version 0".into()).write(&mut client)?;
+		return Ok(());
+	}
+	let capabilities = capabilities & wire::CAPABILITIES;
+	let colored = library.colored() && capabilities & wire::CAP_COLOR != 0;
+	let mut width = library.fitting_width((columns, rows))?;
+	let mut video = library.video(width, colored)?;
+	Message::Welcome {
+		version: version.min(wire::VERSION),
+		capabilities,
+		fps: fps(&video.1),
+	}
+	.write(&mut client)?;
+
+	// Reading stops when the client hangs up or goes quiet for too long, which ends the session.
+	let (sender, messages) = mpsc::channel();
+	let mut input = client.try_clone()?;
+	thread::spawn(move || {
+		while let Ok(message) = Message::read(&mut input) {
+			if sender.send(message).is_err() {
+				return;
+			}
+		}
+	});
+
+	let viewer = library.metrics().connect("stream", client.peer_addr()?);
+	let mut out = library.limits.throttle(viewer.counting(&client));
+	let mut index = 0;
+	let mut previous: Option<usize> = None;
+	let mut pts = Duration::ZERO;
+	loop {
+		loop {
+			match messages.try_recv() {
+				Ok(Message::Resize { columns, rows }) => {
+					let fitting = library.fitting_width((columns, rows))?;
+					if fitting != width {
+						width = fitting;
+						video = library.video(width, colored)?;
+						previous = None;
+					}
+				},
+				Ok(_) => {},
+				Err(TryRecvError::Empty) => break,
+				Err(TryRecvError::Disconnected) => return Ok(()),
+			}
+		}
+
+		let (frames, delays) = &*video;
+		index %= frames.len();
+		if previous.is_none() {
+			let (columns, rows) = export::grid_size(frames);
+			Message::Format {
+				columns: columns.try_into().map_err(|_| too_large())?,
+				rows: rows.try_into().map_err(|_| too_large())?,
+				color: colored,
+			}
+			.write(&mut out)?;
+		}
+		let record = wire::encode_frame(
+			previous.map(|previous| &frames[previous][..]),
+			&frames[index],
+			delays[index],
+			capabilities,
+		)?;
+		Message::Frame { pts, record }.write(&mut out)?;
+		viewer.stats().frame_sent();
+
+		wait(&mut out, delays[index])?;
+		previous = Some(index);
+		pts += delays[index];
+		index += 1;
+	}
+}
+
+// Holds a frame for its delay, keeping the connection alive through long ones.
+fn wait(client: &mut impl Write, mut delay: Duration) -> io::Result<()> {
+	while delay > wire::KEEPALIVE_INTERVAL {
+		sleep(wire::KEEPALIVE_INTERVAL);
+		Message::KeepAlive.write(client)?;
+		delay -= wire::KEEPALIVE_INTERVAL;
+	}
+	sleep(delay);
+	Ok(())
+}
+
+fn fps(delays: &[Duration]) -> f32 {
+	let total: f64 = delays.iter().map(Duration::as_secs_f64).sum();
+	if total > 0.0 {
+		(delays.len() as f64 / total) as f32
+	} else {
+		0.0
+	}
+}
+
+// Frames wider or taller than the wire protocol's u16 sizes.
+fn too_large() -> Error {
+	Error::EncodeError("Frames are too large to stream".to_string())
+}
@@ -0,0 +1,169 @@
+use super::{ClientEvent, Library};
+use bad_ascii::Error;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+
+// Binds right away, so that a taken port is reported before anything is served, and then accepts
+// clients on its own thread.
+pub fn listen_telnet(address: SocketAddr, library: Arc<Library>) -> io::Result<JoinHandle<()>> {
+	let listener = TcpListener::bind(address)?;
+	Ok(thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let library = Arc::clone(&library);
+			thread::spawn(move || {
+				let _ = session(client, &library);
+			});
+		}
+	}))
+}
+
+// Telnet clients report their size only after the first frames have gone out, so they are all
+// served the default width.
+fn session(client: TcpStream, library: &Library) -> Result<(), Error> {
+	client.set_nodelay(true)?;
+	let mut out = client.try_clone()?;
+	let _slot = match library.admit(client.peer_addr()?.ip()) {
+		Ok(slot) => slot,
+		Err(reason) => return Ok(write!(out, "{}\r\n", reason)?),
+	};
+	// Ask for keys as they are typed rather than a line at a time, without the client echoing them,
+	// and for the window size. Plain TCP clients just get a few bytes they don't draw.
+	out.write_all(&[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS])?;
+
+	let (sender, events) = mpsc::channel();
+	let viewer = library.metrics().connect("telnet", client.peer_addr()?);
+	thread::spawn(move || read_events(client, &sender));
+	let result = super::watch(
+		&mut out,
+		&events,
+		library,
+		&viewer,
+		library.default_width(),
+		library.colored(),
+	);
+	// Also ends the reading thread.
+	let _ = out.shutdown(Shutdown::Both);
+	result
+}
+
+fn read_events(mut client: TcpStream, events: &Sender<ClientEvent>) {
+	let mut parser = Parser::default();
+	let mut buffer = [0; 256];
+	while let Ok(read @ 1..) = client.read(&mut buffer) {
+		for event in buffer[..read].iter().filter_map(|&byte| parser.feed(byte)) {
+			if events.send(event).is_err() {
+				return;
+			}
+		}
+	}
+}
+
+#[derive(Default)]
+enum State {
+	#[default]
+	Data,
+	Command,
+	Option,
+	Subnegotiation,
+	SubnegotiationCommand,
+}
+
+// Separates keys from the telnet commands mixed in with them.
+#[derive(Default)]
+struct Parser {
+	state: State,
+	subnegotiation: Vec<u8>,
+}
+
+impl Parser {
+	fn feed(&mut self, byte: u8) -> Option<ClientEvent> {
+		match self.state {
+			State::Data if byte == IAC => self.state = State::Command,
+			State::Data => return Some(ClientEvent::Key(byte)),
+			State::Command => {
+				self.state = match byte {
+					// An escaped 255 in the data.
+					IAC => {
+						self.state = State::Data;
+						return Some(ClientEvent::Key(IAC));
+					},
+					WILL | WONT | DO | DONT => State::Option,
+					SB => {
+						self.subnegotiation.clear();
+						State::Subnegotiation
+					},
+					_ => State::Data,
+				}
+			},
+			// Whatever the client agrees or refuses to do, it's served the same.
+			State::Option => self.state = State::Data,
+			State::Subnegotiation if byte == IAC => self.state = State::SubnegotiationCommand,
+			State::Subnegotiation => self.subnegotiation.push(byte),
+			State::SubnegotiationCommand if byte == SE => {
+				self.state = State::Data;
+				if let [NAWS, width_high, width_low, height_high, height_low] = self.subnegotiation[..] {
+					return Some(ClientEvent::Resize(
+						u16::from_be_bytes([width_high, width_low]),
+						u16::from_be_bytes([height_high, height_low]),
+					));
+				}
+			},
+			State::SubnegotiationCommand => {
+				self.subnegotiation.push(byte);
+				self.state = State::Subnegotiation;
+			},
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn feed(bytes: &[u8]) -> Vec<ClientEvent> {
+		let mut parser = Parser::default();
+		bytes.iter().filter_map(|&byte| parser.feed(byte)).collect()
+	}
+
+	#[test]
+	fn naws_reports_the_window_size() {
+		let events = feed(&[IAC, SB, NAWS, 0, 80, 0, 24, IAC, SE]);
+		assert!(matches!(events[..], [ClientEvent::Resize(80, 24)]));
+	}
+
+	#[test]
+	fn naws_sizes_can_hold_an_escaped_iac() {
+		let events = feed(&[IAC, SB, NAWS, 1, IAC, IAC, 0, 50, IAC, SE]);
+		assert!(matches!(events[..], [ClientEvent::Resize(511, 50)]));
+	}
+
+	#[test]
+	fn negotiation_is_not_taken_for_keys() {
+		let events = feed(&[b'a', IAC, WILL, NAWS, IAC, DO, ECHO, b'q', IAC, IAC]);
+		assert!(matches!(
+			events[..],
+			[ClientEvent::Key(b'a'), ClientEvent::Key(b'q'), ClientEvent::Key(IAC)]
+		));
+	}
+
+	#[test]
+	fn other_subnegotiations_are_ignored() {
+		let events = feed(&[IAC, SB, 24, 0, b'x', b't', IAC, SE, b' ']);
+		assert!(matches!(events[..], [ClientEvent::Key(b' ')]));
+	}
+}
@@ -0,0 +1,89 @@
+use bad_ascii::Error;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::{self, PemObject};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+
+// The certificate chain and its key, both PEM, as certbot and most CAs hand them out.
+pub fn load_tls(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>, Error> {
+	let chain = CertificateDer::pem_file_iter(cert)
+		.and_then(Iterator::collect::<Result<Vec<_>, _>>)
+		.map_err(|error| Error::BadOptions(format!("{}: {}", cert.display(), describe(error))))?;
+	if chain.is_empty() {
+		return Err(Error::BadOptions(format!(
+			"{}: No certificates in the PEM file",
+			cert.display()
+		)));
+	}
+	let key = PrivateKeyDer::from_pem_file(key)
+		.map_err(|error| Error::BadOptions(format!("{}: {}", key.display(), describe(error))))?;
+	let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+		.with_safe_default_protocol_versions()
+		.and_then(|config| config.with_no_client_auth().with_single_cert(chain, key))
+		.map_err(|error| Error::BadOptions(error.to_string()))?;
+	Ok(Arc::new(config))
+}
+
+fn describe(error: pem::Error) -> String {
+	match error {
+		pem::Error::Io(error) => error.to_string(),
+		pem::Error::NoItemsFound => "Nothing of the expected kind in the PEM file".to_string(),
+		error => format!("Not a valid PEM file ({:?})", error),
+	}
+}
+
+// A client of the HTTP or WebSocket listener, over TLS when the server has a certificate. The
+// handshake happens on the first read or write.
+pub enum Connection {
+	Plain(TcpStream),
+	Tls(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl Connection {
+	pub fn new(client: TcpStream, tls: Option<&Arc<ServerConfig>>) -> io::Result<Self> {
+		Ok(match tls {
+			Some(config) => {
+				let connection = ServerConnection::new(Arc::clone(config)).map_err(io::Error::other)?;
+				Connection::Tls(Box::new(StreamOwned::new(connection, client)))
+			},
+			None => Connection::Plain(client),
+		})
+	}
+
+	// For the address and socket options, never to read or write around TLS.
+	pub fn socket(&self) -> &TcpStream {
+		match self {
+			Connection::Plain(client) => client,
+			Connection::Tls(stream) => stream.get_ref(),
+		}
+	}
+}
+
+impl Read for Connection {
+	fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
+		match self {
+			Connection::Plain(client) => client.read(bytes),
+			Connection::Tls(stream) => stream.read(bytes),
+		}
+	}
+}
+
+impl Write for Connection {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		match self {
+			Connection::Plain(client) => client.write(bytes),
+			Connection::Tls(stream) => stream.write(bytes),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			Connection::Plain(client) => client.flush(),
+			Connection::Tls(stream) => stream.flush(),
+		}
+	}
+}
@@ -0,0 +1,133 @@
+use super::http::{read_request, refuse, respond};
+use super::tls::Connection;
+use super::{ClientEvent, Library};
+use crate::wire;
+use bad_ascii::Error;
+use rustls::ServerConfig;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+const PAGE: &str = include_str!("ws.html");
+
+// Serves a page with an xterm.js terminal on /, which plays what it receives from the WebSocket on
+// /ws. Any other WebSocket client gets the same: frames as raw ANSI in binary messages, one per
+// frame.
+pub fn listen_ws(
+	address: SocketAddr,
+	library: Arc<Library>,
+	tls: Option<Arc<ServerConfig>>,
+) -> io::Result<JoinHandle<()>> {
+	let listener = TcpListener::bind(address)?;
+	Ok(thread::spawn(move || {
+		for client in listener.incoming().flatten() {
+			let library = Arc::clone(&library);
+			let tls = tls.clone();
+			thread::spawn(move || {
+				if let Ok(client) = Connection::new(client, tls.as_ref()) {
+					let _ = session(client, &library);
+				}
+			});
+		}
+	}))
+}
+
+fn session(mut client: Connection, library: &Library) -> Result<(), Error> {
+	// Also bounds the TLS handshake, which happens on the first read.
+	client.socket().set_read_timeout(Some(wire::PEER_TIMEOUT))?;
+	let _slot = match library.admit(client.socket().peer_addr()?.ip()) {
+		Ok(slot) => slot,
+		Err(reason) => return Ok(refuse(&mut client, reason)?),
+	};
+	let request = read_request(&mut client)?;
+	let Some(key) = request.header("sec-websocket-key") else {
+		return Ok(match request.path() {
+			"/" => respond(&mut client, "200 OK", "text/html", PAGE),
+			_ => respond(&mut client, "404 Not Found", "text/plain", "Not found\n"),
+		}?);
+	};
+	write!(
+		client,
+		"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+		derive_accept_key(key.as_bytes())
+	)?;
+	client.socket().set_nodelay(true)?;
+
+	let (sender, events) = mpsc::channel();
+	let size = request.size();
+	if let Some((columns, rows)) = size {
+		let _ = sender.send(ClientEvent::Resize(columns, rows));
+	}
+	let width = match size {
+		Some(size) => library.fitting_width(size)?,
+		None => library.default_width(),
+	};
+	let viewer = library.metrics().connect("ws", client.socket().peer_addr()?);
+	let mut out = Socket {
+		websocket: WebSocket::from_raw_socket(client, Role::Server, None),
+		events: sender,
+		buffer: Vec::new(),
+	};
+	let result = super::watch(&mut out, &events, library, &viewer, width, library.colored());
+	let _ = out.websocket.close(None).and_then(|()| out.websocket.flush());
+	result
+}
+
+// Sends each frame as one message when play_session flushes, and picks up whatever the viewer sent
+// in the meantime: keys as binary messages and sizes as {"columns": C, "rows": R}.
+struct Socket {
+	websocket: WebSocket<Connection>,
+	events: Sender<ClientEvent>,
+	buffer: Vec<u8>,
+}
+
+impl Socket {
+	fn receive(&mut self) -> io::Result<()> {
+		loop {
+			match self.websocket.read() {
+				Ok(Message::Binary(keys)) => {
+					for &key in keys.iter() {
+						let _ = self.events.send(ClientEvent::Key(key));
+					}
+				},
+				Ok(Message::Text(text)) => {
+					let size: Value = serde_json::from_str(text.as_str()).unwrap_or_default();
+					if let (Some(columns), Some(rows)) = (size["columns"].as_u64(), size["rows"].as_u64()) {
+						let clamp = |size: u64| size.try_into().unwrap_or(u16::MAX);
+						let _ = self.events.send(ClientEvent::Resize(clamp(columns), clamp(rows)));
+					}
+				},
+				Ok(Message::Close(_)) => return Err(io::ErrorKind::BrokenPipe.into()),
+				Ok(_) => {},
+				Err(tungstenite::Error::Io(error)) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+				Err(tungstenite::Error::Io(error)) => return Err(error),
+				Err(error) => return Err(io::Error::new(io::ErrorKind::BrokenPipe, error)),
+			}
+		}
+	}
+}
+
+impl Write for Socket {
+	fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+		self.buffer.extend_from_slice(bytes);
+		Ok(bytes.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if !self.buffer.is_empty() {
+			let frame = std::mem::take(&mut self.buffer);
+			self.websocket.send(Message::binary(frame)).map_err(io::Error::other)?;
+		}
+		// Only reading is done without blocking, a slow viewer still holds back its own playback.
+		self.websocket.get_ref().socket().set_nonblocking(true)?;
+		let received = self.receive();
+		self.websocket.get_ref().socket().set_nonblocking(false)?;
+		received
+	}
+}
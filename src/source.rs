@@ -0,0 +1,161 @@
+mod animation;
+mod container;
+mod ffmpeg;
+#[cfg(feature = "gstreamer")]
+mod gstreamer;
+#[cfg(feature = "ffmpeg-lib")]
+mod libav;
+mod raw;
+mod slideshow;
+mod y4m;
+mod ytdl;
+
+pub use self::animation::AnimationSource;
+pub use self::container::BaaSource;
+pub use self::ffmpeg::{DecodeOptions, FfmpegSource, Hwaccel};
+#[cfg(feature = "gstreamer")]
+pub use self::gstreamer::GstreamerSource;
+#[cfg(feature = "ffmpeg-lib")]
+pub use self::libav::LibavSource;
+pub use self::raw::{PixelFormat, RawSource};
+pub use self::slideshow::{SlideshowSource, Transition};
+pub use self::y4m::Y4mSource;
+pub use self::ytdl::{needs_ytdl, resolve_ytdl};
+use crate::{Error, baa};
+use image::{DynamicImage, ImageFormat};
+use std::path::Path;
+use std::time::Duration;
+
+// A picture still to be converted, or lines that already were, as in a .baa file.
+pub enum Picture {
+	Image(DynamicImage),
+	Text(Vec<String>),
+}
+
+pub struct Frame {
+	pub picture: Picture,
+	pub delay: Duration,
+}
+
+#[derive(Clone)]
+pub struct Chapter {
+	pub start: Duration,
+	pub title: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Backend {
+	Ffmpeg,
+	// Pure-Rust decoders only, for systems without ffmpeg: GIF, APNG, WebP, still images and Y4M.
+	// Compressed video like MP4, VP9 or AV1 still needs ffmpeg.
+	Native,
+	#[cfg(feature = "ffmpeg-lib")]
+	FfmpegLib,
+	#[cfg(feature = "gstreamer")]
+	Gstreamer,
+}
+
+impl Default for Backend {
+	fn default() -> Self {
+		#[cfg(feature = "ffmpeg-lib")]
+		return Self::FfmpegLib;
+		#[cfg(not(feature = "ffmpeg-lib"))]
+		return Self::Ffmpeg;
+	}
+}
+
+pub struct SourceOptions {
+	pub slide_duration: Duration,
+	pub transition: Transition,
+	pub backend: Backend,
+	pub decode: DecodeOptions,
+}
+
+// Anything frames can be played or converted from. New kinds of input only need one of these and a
+// case in open().
+pub trait FrameSource {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error>;
+	// Frames per second, as far as the source knows without decoding them all.
+	fn frame_rate(&self) -> f64;
+}
+
+pub fn is_url(input: &str) -> bool {
+	input.starts_with("http://") || input.starts_with("https://")
+}
+
+pub fn is_live_url(input: &str) -> bool {
+	["rtsp://", "rtsps://", "rtmp://", "rtmps://"]
+		.iter()
+		.any(|scheme| input.starts_with(scheme))
+}
+
+pub fn load_still(input: &str) -> Result<Option<DynamicImage>, Error> {
+	let is_still = match ImageFormat::from_path(input) {
+		Ok(ImageFormat::Png) => AnimationSource::apng(input)?.is_none(),
+		Ok(ImageFormat::WebP) => AnimationSource::webp(input)?.is_none(),
+		Ok(ImageFormat::Jpeg) => true,
+		_ => false,
+	};
+
+	if is_still {
+		Ok(Some(image::open(input)?))
+	} else {
+		Ok(None)
+	}
+}
+
+pub fn open(input: &str, options: &SourceOptions) -> Result<Box<dyn FrameSource>, Error> {
+	if Path::new(input).is_dir() {
+		return Ok(Box::new(SlideshowSource::open(
+			Path::new(input),
+			options.slide_duration,
+			options.transition,
+		)?));
+	}
+
+	if baa::is_container(input) {
+		return Ok(Box::new(BaaSource::open(input)?));
+	}
+
+	if Path::new(input)
+		.extension()
+		.is_some_and(|extension| extension.eq_ignore_ascii_case("y4m"))
+	{
+		return Ok(Box::new(Y4mSource::open(input)?));
+	}
+
+	let source: Option<Box<dyn FrameSource>> = match ImageFormat::from_path(input) {
+		Ok(ImageFormat::Gif) => Some(Box::new(AnimationSource::gif(input)?)),
+		Ok(ImageFormat::Png) => AnimationSource::apng(input)?.map(|source| Box::new(source) as _),
+		Ok(ImageFormat::WebP) => AnimationSource::webp(input)?.map(|source| Box::new(source) as _),
+		_ => None,
+	};
+
+	if let Some(source) = source {
+		return Ok(source);
+	}
+
+	if options.backend != Backend::Ffmpeg && options.decode.hwaccel.is_some() {
+		return Err(Error::BadOptions(
+			"--hwaccel is only supported by the ffmpeg backend".to_string(),
+		));
+	}
+	if options.backend != Backend::Ffmpeg && options.decode.filters.is_some() {
+		return Err(Error::BadOptions(
+			"--vf is only supported by the ffmpeg backend".to_string(),
+		));
+	}
+
+	match options.backend {
+		Backend::Ffmpeg => Ok(Box::new(FfmpegSource::spawn(input, &options.decode)?)),
+		Backend::Native => Err(Error::BadInput(format!(
+			"{} needs ffmpeg; the native backend only decodes GIF, APNG, WebP and Y4M",
+			input
+		))),
+		#[cfg(feature = "ffmpeg-lib")]
+		Backend::FfmpegLib => Ok(Box::new(LibavSource::open(input)?)),
+		#[cfg(feature = "gstreamer")]
+		Backend::Gstreamer => Ok(Box::new(GstreamerSource::open(input)?)),
+	}
+}
@@ -0,0 +1,82 @@
+use super::{Frame, FrameSource, Picture};
+use crate::Error;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, Frames};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+
+// Browsers bump delays below 20ms up to 100ms, and animations in the wild are authored with that
+// in mind.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+pub struct AnimationSource {
+	frames: Frames<'static>,
+	// How many frames were decoded so far and how long they take, for frame_rate.
+	decoded: u32,
+	elapsed: Duration,
+}
+
+impl AnimationSource {
+	pub fn gif(input: &str) -> Result<Self, Error> {
+		let decoder = GifDecoder::new(BufReader::new(File::open(input)?))?;
+		Ok(Self::new(decoder.into_frames()))
+	}
+
+	// Still PNGs and WebPs yield None so that they can fall through to another source.
+	pub fn apng(input: &str) -> Result<Option<Self>, Error> {
+		let decoder = PngDecoder::new(BufReader::new(File::open(input)?))?;
+		if !decoder.is_apng()? {
+			return Ok(None);
+		}
+		Ok(Some(Self::new(decoder.apng()?.into_frames())))
+	}
+
+	pub fn webp(input: &str) -> Result<Option<Self>, Error> {
+		let decoder = WebPDecoder::new(BufReader::new(File::open(input)?))?;
+		if !decoder.has_animation() {
+			return Ok(None);
+		}
+		Ok(Some(Self::new(decoder.into_frames())))
+	}
+
+	fn new(frames: Frames<'static>) -> Self {
+		Self {
+			frames,
+			decoded: 0,
+			elapsed: Duration::ZERO,
+		}
+	}
+}
+
+impl FrameSource for AnimationSource {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		let Some(frame) = self.frames.next().transpose()? else {
+			return Ok(None);
+		};
+
+		let delay = match Duration::from(frame.delay()) {
+			delay if delay < MIN_FRAME_DELAY => DEFAULT_FRAME_DELAY,
+			delay => delay,
+		};
+
+		self.decoded += 1;
+		self.elapsed += delay;
+		Ok(Some(Frame {
+			picture: Picture::Image(DynamicImage::ImageRgba8(frame.into_buffer())),
+			delay,
+		}))
+	}
+
+	// Every frame has its own delay, so this is what those decoded so far average out to.
+	fn frame_rate(&self) -> f64 {
+		if self.decoded == 0 {
+			1.0 / DEFAULT_FRAME_DELAY.as_secs_f64()
+		} else {
+			self.decoded as f64 / self.elapsed.as_secs_f64()
+		}
+	}
+}
@@ -0,0 +1,37 @@
+use super::{Frame, FrameSource, Picture};
+use crate::Error;
+use crate::baa::BaaReader;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+// Plays back what was converted into a .baa file, read in order so that pipes work too.
+pub struct BaaSource<R: Read> {
+	reader: BaaReader<R>,
+}
+
+impl BaaSource<BufReader<File>> {
+	pub fn open(path: &str) -> Result<Self, Error> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+
+impl<R: Read> BaaSource<R> {
+	pub fn new(input: R) -> Result<Self, Error> {
+		Ok(Self {
+			reader: BaaReader::new(input)?,
+		})
+	}
+}
+
+impl<R: Read> FrameSource for BaaSource<R> {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		Ok(self.reader.next_frame()?.map(|(delay, lines)| Frame {
+			picture: Picture::Text(lines),
+			delay,
+		}))
+	}
+
+	fn frame_rate(&self) -> f64 {
+		self.reader.header().fps as f64
+	}
+}
@@ -0,0 +1,435 @@
+use super::{Frame, FrameSource, Picture, Y4mSource};
+use crate::{Error, OUTPUT_FPS};
+use image::DynamicImage;
+use std::env;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::process::{Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+struct Input {
+	format: Option<&'static str>,
+	options: Vec<String>,
+	url: String,
+}
+
+impl Input {
+	fn args(&self) -> Vec<String> {
+		let mut args = Vec::new();
+		if let Some(format) = self.format {
+			args.extend(["-f".into(), format.into()]);
+		}
+		args.extend(self.options.iter().cloned());
+		args.extend(["-i".into(), self.url.clone()]);
+		args
+	}
+
+	// The same input for ffprobe, which rejects ffmpeg-only options like -re.
+	fn probe_args(&self) -> Vec<String> {
+		let mut args = Vec::new();
+		if let Some(format) = self.format {
+			args.extend(["-f".into(), format.into()]);
+		}
+		args.extend(["-i".into(), self.url.clone()]);
+		args
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Hwaccel {
+	Vaapi,
+	Nvdec,
+	Videotoolbox,
+}
+
+impl Hwaccel {
+	fn args(self) -> [&'static str; 2] {
+		match self {
+			Hwaccel::Vaapi => ["-hwaccel", "vaapi"],
+			// ffmpeg exposes NVDEC through its CUDA hwaccel.
+			Hwaccel::Nvdec => ["-hwaccel", "cuda"],
+			Hwaccel::Videotoolbox => ["-hwaccel", "videotoolbox"],
+		}
+	}
+}
+
+#[derive(Clone, Default)]
+pub struct DecodeOptions {
+	pub hwaccel: Option<Hwaccel>,
+	// Width of the ASCII output in characters, used to downscale frames before they leave ffmpeg.
+	pub columns: Option<u32>,
+	// Extra ffmpeg filters applied before the frames are scaled down.
+	pub filters: Option<String>,
+	// Stops decoding after this much of the input, or decodes all of it when None.
+	pub limit: Option<Duration>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+	// Decoded as fast as possible and buffered before playback starts.
+	Buffered,
+	// Paced at the native frame rate with -re and played as it arrives.
+	Streamed,
+	// Devices that produce frames in real time and never end.
+	Live,
+}
+
+// artem averages a tile of pixels into every character, so frames only need a few pixels per column
+// to convert the same as they would at full resolution.
+const PIXELS_PER_COLUMN: u32 = 4;
+// Maps PQ and HLG down to SDR BT.709 through linear light, so HDR sources don't come out washed out.
+const TONEMAP: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=hable:desat=0,\
+                       zscale=t=bt709:m=bt709:r=tv";
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+// A decoded frame and its presentation timestamp in seconds, when ffmpeg reports one.
+type TimedImage = (DynamicImage, Option<f64>);
+
+struct Process {
+	child: Child,
+	frames: Y4mSource<BufReader<ChildStdout>>,
+	// Presentation timestamps in seconds, one for every frame that comes out of stdout.
+	timestamps: Option<Receiver<f64>>,
+}
+
+impl Process {
+	fn spawn(input: &Input, mode: Mode, decode: &DecodeOptions) -> Result<Self, Error> {
+		let mut filters = Vec::new();
+		// Devices are never HDR, and probing them would hold on to the device.
+		if mode != Mode::Live && is_hdr(input) {
+			if has_filter("zscale") {
+				filters.push(TONEMAP.to_string());
+			} else {
+				eprintln!(
+					"{} is HDR, but ffmpeg was built without zscale to tonemap it",
+					input.url
+				);
+			}
+		}
+		filters.extend(decode.filters.iter().cloned());
+		// Anamorphic sources are stretched to square pixels by scaling the width by the sample aspect
+		// ratio and deriving the height from the display aspect ratio. Commas are escaped so that they
+		// aren't taken as the start of the next filter.
+		let width = match decode.columns {
+			Some(columns) => format!("min(iw*sar\\,{})", columns * PIXELS_PER_COLUMN),
+			None => "iw*sar".into(),
+		};
+		filters.push(format!("scale=w={}:h=trunc(ow/dar/2)*2,setsar=1", width));
+		// Buffered sources keep their own timing instead of being resampled to a constant rate, and
+		// showinfo logs the timestamp of every frame that is passed on.
+		let variable_rate = mode == Mode::Buffered;
+		if variable_rate {
+			filters.push("showinfo".into());
+		}
+
+		let mut args: Vec<String> = vec!["-nostdin".into(), "-hide_banner".into()];
+		if variable_rate {
+			args.push("-nostats".into());
+		} else {
+			// Progress output would scribble over the frames, which are drawn while ffmpeg runs.
+			args.extend(["-loglevel", "error", "-nostats"].map(String::from));
+		}
+		if mode == Mode::Live {
+			args.extend(["-fflags", "nobuffer", "-flags", "low_delay"].map(String::from));
+		}
+		if let Some(hwaccel) = decode.hwaccel {
+			// Decoded surfaces are copied back to system memory automatically, since the frames are
+			// piped out.
+			args.extend(hwaccel.args().map(String::from));
+		}
+		// Rotation metadata is applied by ffmpeg's autorotate, which is on by default and runs before
+		// any filters, so the Y4M header already has the rotated size.
+		args.extend(input.args());
+		if let Some(limit) = decode.limit.filter(|_| mode != Mode::Live) {
+			args.extend(["-t".into(), limit.as_secs_f64().to_string()]);
+		}
+		args.extend(["-vf".into(), filters.join(",")]);
+		if variable_rate {
+			args.extend(["-fps_mode".into(), "passthrough".into()]);
+		} else {
+			args.extend(["-r".into(), OUTPUT_FPS.to_string()]);
+		}
+		// Y4M carries the frame size in its header, which is only known once the filters have run.
+		args.extend(["-pix_fmt", "yuv420p", "-f", "yuv4mpegpipe", "-"].map(String::from));
+
+		let mut child = Command::new("ffmpeg")
+			.args(&args)
+			.stdout(Stdio::piped())
+			.stderr(if variable_rate {
+				Stdio::piped()
+			} else {
+				Stdio::inherit()
+			})
+			.spawn()
+			.map_err(|error| match error.kind() {
+				ErrorKind::NotFound => Error::FfmpegNotFound { program: "ffmpeg" },
+				_ => error.into(),
+			})?;
+
+		let timestamps = child.stderr.take().map(read_timestamps);
+		let reader = BufReader::new(child.stdout.take().unwrap());
+		let frames = match Y4mSource::new(reader) {
+			Ok(frames) => frames,
+			Err(_) => {
+				let _ = child.kill();
+				let _ = child.wait();
+				return Err(Error::DecodeError(format!(
+					"ffmpeg did not produce any video from {}",
+					input.url
+				)));
+			},
+		};
+
+		Ok(Self {
+			child,
+			frames,
+			timestamps,
+		})
+	}
+
+	fn read_frame(&mut self) -> Option<TimedImage> {
+		let image = self.frames.next_image().ok().flatten()?;
+		let timestamp = self.timestamps.as_ref().and_then(|timestamps| timestamps.recv().ok());
+		Some((image, timestamp))
+	}
+}
+
+impl Drop for Process {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+pub struct FfmpegSource {
+	process: Process,
+	input: Input,
+	mode: Mode,
+	decode: DecodeOptions,
+	reconnect: bool,
+	failures: u32,
+	delay: Duration,
+	pending: Option<TimedImage>,
+}
+
+impl FfmpegSource {
+	pub fn spawn(input: &str, decode: &DecodeOptions) -> Result<Self, Error> {
+		let input = Input {
+			format: None,
+			options: Vec::new(),
+			url: input.into(),
+		};
+		Self::spawn_input(input, Mode::Buffered, decode)
+	}
+
+	pub fn stream(url: &str, decode: &DecodeOptions) -> Result<Self, Error> {
+		let input = Input {
+			format: None,
+			options: vec!["-re".into()],
+			url: url.into(),
+		};
+		Self::spawn_input(input, Mode::Streamed, decode)
+	}
+
+	pub fn capture(device: &str, decode: &DecodeOptions) -> Result<Self, Error> {
+		let (format, device) = if cfg!(target_os = "linux") {
+			("v4l2", device.to_string())
+		} else if cfg!(target_os = "macos") {
+			("avfoundation", device.to_string())
+		} else if device.starts_with("video=") {
+			("dshow", device.to_string())
+		} else {
+			("dshow", format!("video={}", device))
+		};
+
+		let input = Input {
+			format: Some(format),
+			options: Vec::new(),
+			url: device,
+		};
+		Self::spawn_input(input, Mode::Live, decode)
+	}
+
+	pub fn capture_screen(decode: &DecodeOptions) -> Result<Self, Error> {
+		let (format, url) = if cfg!(target_os = "linux") {
+			if env::var_os("WAYLAND_DISPLAY").is_some() && env::var_os("DISPLAY").is_none() {
+				("lavfi", "pipewiregrab".to_string())
+			} else {
+				("x11grab", env::var("DISPLAY").unwrap_or_else(|_| ":0".into()))
+			}
+		} else if cfg!(target_os = "macos") {
+			("avfoundation", "Capture screen 0".to_string())
+		} else {
+			("gdigrab", "desktop".to_string())
+		};
+
+		let options = if format == "lavfi" {
+			Vec::new()
+		} else {
+			vec!["-framerate".into(), OUTPUT_FPS.to_string()]
+		};
+
+		let input = Input {
+			format: Some(format),
+			options,
+			url,
+		};
+		Self::spawn_input(input, Mode::Live, decode)
+	}
+
+	pub fn live_stream(url: &str, decode: &DecodeOptions) -> Result<Self, Error> {
+		let options = match url.split_once("://").map(|(scheme, _)| scheme) {
+			Some("rtsp" | "rtsps") => vec!["-rtsp_transport".into(), "tcp".into()],
+			Some("rtmp" | "rtmps") => vec!["-rtmp_live".into(), "live".into()],
+			_ => Vec::new(),
+		};
+
+		let input = Input {
+			format: None,
+			options,
+			url: url.into(),
+		};
+		let mut source = Self::spawn_input(input, Mode::Live, decode)?;
+		source.reconnect = true;
+		Ok(source)
+	}
+
+	fn spawn_input(input: Input, mode: Mode, decode: &DecodeOptions) -> Result<Self, Error> {
+		Ok(Self {
+			process: Process::spawn(&input, mode, decode)?,
+			input,
+			mode,
+			decode: decode.clone(),
+			reconnect: false,
+			failures: 0,
+			delay: Duration::from_secs_f64(1.0 / OUTPUT_FPS as f64),
+			pending: None,
+		})
+	}
+
+	fn read_frame(&mut self) -> Result<Option<TimedImage>, Error> {
+		loop {
+			if let Some(frame) = self.process.read_frame() {
+				self.failures = 0;
+				return Ok(Some(frame));
+			}
+
+			if !self.reconnect {
+				return Ok(None);
+			}
+			if self.failures == MAX_RECONNECT_ATTEMPTS {
+				return Err(Error::DecodeError(format!(
+					"Lost {} and could not reconnect",
+					self.input.url
+				)));
+			}
+
+			self.failures += 1;
+			thread::sleep(RECONNECT_DELAY * self.failures);
+			// A failed attempt leaves the dead process in place, so the next read fails right away and
+			// another attempt is made.
+			if let Ok(process) = Process::spawn(&self.input, self.mode, &self.decode) {
+				self.process = process;
+			}
+		}
+	}
+}
+
+impl FrameSource for FfmpegSource {
+	// Timestamped frames are held back until their successor arrives, since the gap between the two
+	// is how long they have to stay on screen.
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		let Some((image, timestamp)) = self
+			.pending
+			.take()
+			.map_or_else(|| self.read_frame(), |frame| Ok(Some(frame)))?
+		else {
+			return Ok(None);
+		};
+
+		let Some(timestamp) = timestamp else {
+			return Ok(Some(Frame {
+				picture: Picture::Image(image),
+				delay: self.delay,
+			}));
+		};
+
+		self.pending = self.read_frame()?;
+		let delay = match &self.pending {
+			Some((_, Some(next_timestamp))) if *next_timestamp > timestamp => {
+				Duration::from_secs_f64(next_timestamp - timestamp)
+			},
+			_ => self.delay,
+		};
+
+		Ok(Some(Frame {
+			picture: Picture::Image(image),
+			delay,
+		}))
+	}
+
+	fn frame_rate(&self) -> f64 {
+		1.0 / self.delay.as_secs_f64()
+	}
+}
+
+// Forwards everything but showinfo's output to our own stderr, so that errors are still shown.
+fn read_timestamps(stderr: ChildStderr) -> Receiver<f64> {
+	let (sender, receiver) = mpsc::channel();
+	thread::spawn(move || {
+		for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+			if !line.contains("Parsed_showinfo") {
+				eprintln!("{}", line);
+				continue;
+			}
+			let timestamp = line
+				.split_once(" pts_time:")
+				.and_then(|(_, rest)| rest.split_whitespace().next())
+				.and_then(|timestamp| timestamp.parse().ok());
+			if let Some(timestamp) = timestamp {
+				if sender.send(timestamp).is_err() {
+					break;
+				}
+			}
+		}
+	});
+	receiver
+}
+
+fn is_hdr(input: &Input) -> bool {
+	let output = Command::new("ffprobe")
+		.args([
+			"-v",
+			"error",
+			"-select_streams",
+			"v:0",
+			"-show_entries",
+			"stream=color_transfer",
+			"-of",
+			"csv=p=0",
+		])
+		.args(input.probe_args())
+		.output();
+
+	output.is_ok_and(|output| {
+		let transfer = String::from_utf8_lossy(&output.stdout);
+		matches!(transfer.trim(), "smpte2084" | "arib-std-b67")
+	})
+}
+
+fn has_filter(name: &str) -> bool {
+	static FILTERS: OnceLock<String> = OnceLock::new();
+	let filters = FILTERS.get_or_init(|| {
+		Command::new("ffmpeg")
+			.args(["-hide_banner", "-filters"])
+			.output()
+			.map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+			.unwrap_or_default()
+	});
+
+	filters.lines().any(|line| line.split_whitespace().nth(1) == Some(name))
+}
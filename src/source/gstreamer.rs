@@ -0,0 +1,136 @@
+use super::{Frame, FrameSource, Picture};
+use crate::Error;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::AppSink;
+use gstreamer_video::VideoInfo;
+use image::{DynamicImage, RgbImage};
+use std::fs;
+use std::time::Duration;
+
+// videoflip follows the orientation tags that phones write instead of rotating the pixels, and
+// videoscale stretches anamorphic video to the square pixels the caps ask for.
+const PIPELINE: &str = "uridecodebin name=source ! videoconvert ! videoflip video-direction=auto ! videoscale ! \
+                        videoconvert ! video/x-raw,format=RGB,pixel-aspect-ratio=1/1 ! appsink name=sink sync=false";
+
+pub struct GstreamerSource {
+	pipeline: gst::Pipeline,
+	sink: AppSink,
+	nominal_delay: Duration,
+	pending: Option<(DynamicImage, f64)>,
+}
+
+impl GstreamerSource {
+	pub fn open(input: &str) -> Result<Self, Error> {
+		gst::init()?;
+
+		let uri = if input.contains("://") {
+			input.to_string()
+		} else {
+			gst::glib::filename_to_uri(fs::canonicalize(input)?, None)?.to_string()
+		};
+
+		let pipeline = gst::parse::launch(PIPELINE)?
+			.downcast::<gst::Pipeline>()
+			.map_err(|_| Error::DecodeError("Failed to build the GStreamer pipeline".to_string()))?;
+		pipeline
+			.by_name("source")
+			.ok_or_else(|| Error::DecodeError("Pipeline has no source".to_string()))?
+			.set_property("uri", &uri);
+		let sink = pipeline
+			.by_name("sink")
+			.ok_or_else(|| Error::DecodeError("Pipeline has no sink".to_string()))?
+			.downcast::<AppSink>()
+			.map_err(|_| Error::DecodeError("Pipeline sink is not an appsink".to_string()))?;
+
+		pipeline.set_state(gst::State::Playing)?;
+
+		Ok(Self {
+			pipeline,
+			sink,
+			nominal_delay: Duration::from_secs_f64(1.0 / crate::OUTPUT_FPS as f64),
+			pending: None,
+		})
+	}
+
+	fn receive(&mut self) -> Result<Option<(DynamicImage, f64)>, Error> {
+		let sample = match self.sink.pull_sample() {
+			Ok(sample) => sample,
+			Err(_) if self.sink.is_eos() => return Ok(None),
+			Err(_) => return Err(Error::DecodeError(self.bus_error())),
+		};
+
+		let buffer = sample
+			.buffer()
+			.ok_or_else(|| Error::DecodeError("Sample has no buffer".to_string()))?;
+		let caps = sample
+			.caps()
+			.ok_or_else(|| Error::DecodeError("Sample has no caps".to_string()))?;
+		let info = VideoInfo::from_caps(caps)?;
+		let timestamp = buffer.pts().map_or(0.0, |pts| pts.seconds_f64());
+		let map = buffer.map_readable()?;
+
+		Ok(Some((to_image(map.as_slice(), &info)?, timestamp)))
+	}
+
+	fn bus_error(&self) -> String {
+		let message = self
+			.pipeline
+			.bus()
+			.and_then(|bus| bus.pop_filtered(&[gst::MessageType::Error]));
+		match message.as_ref().map(|message| message.view()) {
+			Some(gst::MessageView::Error(error)) => error.error().to_string(),
+			_ => "GStreamer pipeline failed".into(),
+		}
+	}
+}
+
+impl FrameSource for GstreamerSource {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		if self.pending.is_none() {
+			self.pending = self.receive()?;
+		}
+		let Some((image, timestamp)) = self.pending.take() else {
+			return Ok(None);
+		};
+
+		self.pending = self.receive()?;
+		let delay = match &self.pending {
+			Some((_, next_timestamp)) if *next_timestamp > timestamp => {
+				Duration::from_secs_f64(next_timestamp - timestamp)
+			},
+			_ => self.nominal_delay,
+		};
+
+		Ok(Some(Frame {
+			picture: Picture::Image(image),
+			delay,
+		}))
+	}
+
+	fn frame_rate(&self) -> f64 {
+		1.0 / self.nominal_delay.as_secs_f64()
+	}
+}
+
+impl Drop for GstreamerSource {
+	fn drop(&mut self) {
+		let _ = self.pipeline.set_state(gst::State::Null);
+	}
+}
+
+fn to_image(data: &[u8], info: &VideoInfo) -> Result<DynamicImage, Error> {
+	let (width, height) = (info.width(), info.height());
+	let row_length = width as usize * 3;
+
+	let buffer: Vec<u8> = data
+		.chunks(info.stride()[0] as usize)
+		.take(height as usize)
+		.flat_map(|row| &row[..row_length])
+		.copied()
+		.collect();
+
+	let image = RgbImage::from_raw(width, height, buffer)
+		.ok_or_else(|| Error::DecodeError("Failed to create image from buffer".to_string()))?;
+	Ok(DynamicImage::ImageRgb8(image))
+}
@@ -0,0 +1,192 @@
+use super::{Frame, FrameSource, Picture};
+use crate::Error;
+use ffmpeg_next as ffmpeg;
+use ffmpeg_next::format::Pixel;
+use ffmpeg_next::software::scaling::{self, Flags};
+use ffmpeg_next::{Packet, codec, decoder, format, frame, media, packet};
+use image::{DynamicImage, RgbImage};
+use std::time::Duration;
+
+// Decodes through the ffmpeg libraries instead of piping rawvideo out of an ffmpeg process, which
+// keeps the real presentation timestamp of every frame.
+pub struct LibavSource {
+	input: format::context::Input,
+	decoder: decoder::Video,
+	scaler: scaling::Context,
+	stream_index: usize,
+	time_base: f64,
+	nominal_delay: Duration,
+	// Clockwise rotation in degrees that the player is meant to apply, as phones record portrait
+	// video sideways.
+	rotation: u32,
+	pending: Option<(DynamicImage, f64)>,
+	flushed: bool,
+}
+
+impl LibavSource {
+	pub fn open(path: &str) -> Result<Self, Error> {
+		ffmpeg::init()?;
+
+		let input = format::input(path)?;
+		let stream = input
+			.streams()
+			.best(media::Type::Video)
+			.ok_or_else(|| Error::BadInput(format!("{} has no video stream", path)))?;
+		let stream_index = stream.index();
+		let rotation = rotation(&stream);
+		let time_base = f64::from(stream.time_base());
+		let frame_rate = f64::from(stream.avg_frame_rate());
+		let nominal_delay = if frame_rate > 0.0 {
+			Duration::from_secs_f64(1.0 / frame_rate)
+		} else {
+			Duration::from_secs_f64(1.0 / crate::OUTPUT_FPS as f64)
+		};
+
+		let decoder = codec::context::Context::from_parameters(stream.parameters())?
+			.decoder()
+			.video()?;
+		// Anamorphic video is stretched to square pixels, which is what the ASCII conversion assumes.
+		let sample_aspect_ratio = decoder.aspect_ratio();
+		let output_width = if sample_aspect_ratio.numerator() > 0 && sample_aspect_ratio.denominator() > 0 {
+			(decoder.width() as f64 * f64::from(sample_aspect_ratio))
+				.round()
+				.max(1.0) as u32
+		} else {
+			decoder.width()
+		};
+		let scaler = scaling::Context::get(
+			decoder.format(),
+			decoder.width(),
+			decoder.height(),
+			Pixel::RGB24,
+			output_width,
+			decoder.height(),
+			Flags::BILINEAR,
+		)?;
+
+		Ok(Self {
+			input,
+			decoder,
+			scaler,
+			stream_index,
+			time_base,
+			nominal_delay,
+			rotation,
+			pending: None,
+			flushed: false,
+		})
+	}
+
+	fn receive(&mut self) -> Result<Option<(DynamicImage, f64)>, Error> {
+		loop {
+			let mut decoded = frame::Video::empty();
+			if self.decoder.receive_frame(&mut decoded).is_ok() {
+				let mut rgb = frame::Video::empty();
+				self.scaler.run(&decoded, &mut rgb)?;
+				let timestamp = decoded.timestamp().or(decoded.pts()).unwrap_or_default() as f64 * self.time_base;
+				let image = match self.rotation {
+					90 => to_image(&rgb)?.rotate90(),
+					180 => to_image(&rgb)?.rotate180(),
+					270 => to_image(&rgb)?.rotate270(),
+					_ => to_image(&rgb)?,
+				};
+				return Ok(Some((image, timestamp)));
+			}
+
+			if self.flushed {
+				return Ok(None);
+			}
+
+			let mut packet = Packet::empty();
+			match packet.read(&mut self.input) {
+				Ok(()) if packet.stream() == self.stream_index => self.decoder.send_packet(&packet)?,
+				Ok(()) => {},
+				Err(ffmpeg::Error::Eof) => {
+					self.decoder.send_eof()?;
+					self.flushed = true;
+				},
+				Err(error) => return Err(error.into()),
+			}
+		}
+	}
+}
+
+impl FrameSource for LibavSource {
+	// A frame is only emitted once its successor has been decoded, since the gap between the two
+	// timestamps is how long it has to stay on screen.
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		if self.pending.is_none() {
+			self.pending = self.receive()?;
+		}
+		let Some((image, timestamp)) = self.pending.take() else {
+			return Ok(None);
+		};
+
+		self.pending = self.receive()?;
+		let delay = match &self.pending {
+			Some((_, next_timestamp)) if *next_timestamp > timestamp => {
+				Duration::from_secs_f64(next_timestamp - timestamp)
+			},
+			_ => self.nominal_delay,
+		};
+
+		Ok(Some(Frame {
+			picture: Picture::Image(image),
+			delay,
+		}))
+	}
+
+	fn frame_rate(&self) -> f64 {
+		1.0 / self.nominal_delay.as_secs_f64()
+	}
+}
+
+fn rotation(stream: &format::stream::Stream) -> u32 {
+	let display_matrix = stream
+		.side_data()
+		.find(|side_data| side_data.kind() == packet::side_data::Type::DisplayMatrix);
+
+	let degrees = match display_matrix {
+		// Same computation as av_display_rotation_get, without its sign flip: the matrix holds 16.16
+		// fixed point values and only the scale-normalized first two columns matter.
+		Some(side_data) if side_data.data().len() >= 36 => {
+			let matrix: Vec<f64> = side_data
+				.data()
+				.chunks_exact(4)
+				.map(|bytes| i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64)
+				.collect();
+			let scale_x = matrix[0].hypot(matrix[3]);
+			let scale_y = matrix[1].hypot(matrix[4]);
+			(matrix[1] / scale_y).atan2(matrix[0] / scale_x).to_degrees()
+		},
+		// Older muxers wrote the rotation as a plain tag instead.
+		_ => match stream
+			.metadata()
+			.get("rotate")
+			.and_then(|rotate| rotate.parse::<f64>().ok())
+		{
+			Some(degrees) => degrees,
+			None => return 0,
+		},
+	};
+
+	(degrees.round() as i64).rem_euclid(360) as u32
+}
+
+fn to_image(rgb: &frame::Video) -> Result<DynamicImage, Error> {
+	let (width, height) = (rgb.width(), rgb.height());
+	let row_length = width as usize * 3;
+
+	// Rows are padded to the stride, so they have to be copied one by one.
+	let buffer: Vec<u8> = rgb
+		.data(0)
+		.chunks(rgb.stride(0))
+		.take(height as usize)
+		.flat_map(|row| &row[..row_length])
+		.copied()
+		.collect();
+
+	let image = RgbImage::from_raw(width, height, buffer)
+		.ok_or_else(|| Error::DecodeError("Failed to create image from buffer".to_string()))?;
+	Ok(DynamicImage::ImageRgb8(image))
+}
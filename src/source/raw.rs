@@ -0,0 +1,87 @@
+use super::{Frame, FrameSource, Picture};
+use crate::{Error, OUTPUT_FPS};
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PixelFormat {
+	Rgb24,
+	Bgr24,
+	Rgba,
+	Bgra,
+	Gray,
+}
+
+impl PixelFormat {
+	fn bytes_per_pixel(self) -> usize {
+		match self {
+			PixelFormat::Rgb24 | PixelFormat::Bgr24 => 3,
+			PixelFormat::Rgba | PixelFormat::Bgra => 4,
+			PixelFormat::Gray => 1,
+		}
+	}
+}
+
+pub struct RawSource<R> {
+	reader: R,
+	width: u32,
+	height: u32,
+	pixel_format: PixelFormat,
+}
+
+impl<R: Read> RawSource<R> {
+	pub fn new(reader: R, width: u32, height: u32, pixel_format: PixelFormat) -> Self {
+		Self {
+			reader,
+			width,
+			height,
+			pixel_format,
+		}
+	}
+}
+
+impl<R: Read> FrameSource for RawSource<R> {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		let frame_size = (self.width as usize)
+			.checked_mul(self.height as usize)
+			.and_then(|pixels| pixels.checked_mul(self.pixel_format.bytes_per_pixel()))
+			.filter(|&size| size > 0)
+			.ok_or_else(|| Error::BadOptions(format!("Raw frames can't be {}x{}", self.width, self.height)))?;
+		let mut buffer = vec![0u8; frame_size];
+		match self.reader.read_exact(&mut buffer) {
+			Ok(()) => {},
+			// A frame cut short at the end of the input is dropped.
+			Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+			Err(error) => return Err(Error::Io(error)),
+		}
+
+		if matches!(self.pixel_format, PixelFormat::Bgr24 | PixelFormat::Bgra) {
+			let stride = self.pixel_format.bytes_per_pixel();
+			buffer.chunks_exact_mut(stride).for_each(|pixel| pixel.swap(0, 2));
+		}
+
+		let (width, height) = (self.width, self.height);
+		let image = match self.pixel_format {
+			PixelFormat::Rgb24 | PixelFormat::Bgr24 => {
+				RgbImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgb8)
+			},
+			PixelFormat::Rgba | PixelFormat::Bgra => {
+				RgbaImage::from_raw(width, height, buffer).map(DynamicImage::ImageRgba8)
+			},
+			PixelFormat::Gray => GrayImage::from_raw(width, height, buffer).map(DynamicImage::ImageLuma8),
+		}
+		.ok_or_else(|| Error::DecodeError("Failed to create image from buffer".to_string()))?;
+
+		Ok(Some(Frame {
+			picture: Picture::Image(image),
+			delay: Duration::from_secs_f64(1.0 / OUTPUT_FPS as f64),
+		}))
+	}
+
+	// Raw pixels carry no timing, so they are taken to come at the rate everything is converted at.
+	fn frame_rate(&self) -> f64 {
+		OUTPUT_FPS as f64
+	}
+}
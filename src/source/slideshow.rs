@@ -0,0 +1,145 @@
+use super::{Frame, FrameSource, Picture};
+use crate::{Error, OUTPUT_FPS};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TRANSITION_FRAMES: u32 = 12;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Transition {
+	None,
+	Fade,
+}
+
+pub struct SlideshowSource {
+	paths: Vec<PathBuf>,
+	index: usize,
+	slide_duration: Duration,
+	transition: Transition,
+	previous: Option<RgbaImage>,
+	pending: VecDeque<Frame>,
+}
+
+impl SlideshowSource {
+	pub fn open(directory: &Path, slide_duration: Duration, transition: Transition) -> Result<Self, Error> {
+		let mut paths: Vec<PathBuf> = fs::read_dir(directory)?
+			.map(|entry| entry.map(|entry| entry.path()))
+			.collect::<Result<_, _>>()?;
+		// Only formats the image crate was built to decode, so one stray file can't end the slideshow.
+		paths
+			.retain(|path| path.is_file() && ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled()));
+		paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+
+		if paths.is_empty() {
+			return Err(Error::BadInput(format!("No images found in {}", directory.display())));
+		}
+
+		Ok(Self {
+			paths,
+			index: 0,
+			slide_duration,
+			transition,
+			previous: None,
+			pending: VecDeque::new(),
+		})
+	}
+
+	// Every slide is letterboxed onto a canvas the size of the first one, so that the converted
+	// frames keep a stable geometry and can be blended into each other.
+	fn fit(&self, image: DynamicImage) -> RgbaImage {
+		let Some(previous) = &self.previous else {
+			return image.to_rgba8();
+		};
+		let (width, height) = previous.dimensions();
+
+		let resized = image.resize(width, height, FilterType::Triangle).to_rgba8();
+		let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+		let x = (width - resized.width()) / 2;
+		let y = (height - resized.height()) / 2;
+		imageops::overlay(&mut canvas, &resized, x as i64, y as i64);
+		canvas
+	}
+}
+
+impl FrameSource for SlideshowSource {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		if let Some(frame) = self.pending.pop_front() {
+			return Ok(Some(frame));
+		}
+		let Some(path) = self.paths.get(self.index) else {
+			return Ok(None);
+		};
+		self.index += 1;
+
+		let slide = self.fit(image::open(path)?);
+
+		if let (Transition::Fade, Some(previous)) = (self.transition, &self.previous) {
+			let delay = Duration::from_secs_f64(1.0 / OUTPUT_FPS as f64);
+			for step in 1..TRANSITION_FRAMES {
+				let blended = blend(previous, &slide, step as f32 / TRANSITION_FRAMES as f32);
+				self.pending.push_back(Frame {
+					picture: Picture::Image(DynamicImage::ImageRgba8(blended)),
+					delay,
+				});
+			}
+		}
+
+		self.pending.push_back(Frame {
+			picture: Picture::Image(DynamicImage::ImageRgba8(slide.clone())),
+			delay: self.slide_duration,
+		});
+		self.previous = Some(slide);
+
+		Ok(self.pending.pop_front())
+	}
+
+	// Slides, leaving out the frames fading between them.
+	fn frame_rate(&self) -> f64 {
+		1.0 / self.slide_duration.as_secs_f64()
+	}
+}
+
+fn blend(from: &RgbaImage, to: &RgbaImage, amount: f32) -> RgbaImage {
+	RgbaImage::from_fn(from.width(), from.height(), |x, y| {
+		let (a, b) = (from.get_pixel(x, y), to.get_pixel(x, y));
+		Rgba(std::array::from_fn(|channel| {
+			(a[channel] as f32 + (b[channel] as f32 - a[channel] as f32) * amount).round() as u8
+		}))
+	})
+}
+
+// Compares runs of digits by their numeric value, so that "slide2" sorts before "slide10".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+	let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+
+	loop {
+		match (a.peek().copied(), b.peek().copied()) {
+			(None, None) => return Ordering::Equal,
+			(None, Some(_)) => return Ordering::Less,
+			(Some(_), None) => return Ordering::Greater,
+			(Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+				let x: String = std::iter::from_fn(|| a.next_if(char::is_ascii_digit)).collect();
+				let y: String = std::iter::from_fn(|| b.next_if(char::is_ascii_digit)).collect();
+				let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+				match x.len().cmp(&y.len()).then_with(|| x.cmp(y)) {
+					Ordering::Equal => {},
+					ordering => return ordering,
+				}
+			},
+			(Some(x), Some(y)) => {
+				match x.to_lowercase().cmp(y.to_lowercase()) {
+					Ordering::Equal => {},
+					ordering => return ordering,
+				}
+				a.next();
+				b.next();
+			},
+		}
+	}
+}
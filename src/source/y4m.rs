@@ -0,0 +1,274 @@
+use super::{Frame, FrameSource, Picture};
+use crate::Error;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, RgbImage};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+// Decodes YUV4MPEG2 streams without ffmpeg. Only 8-bit planar formats are supported.
+pub struct Y4mSource<R> {
+	reader: R,
+	width: u32,
+	height: u32,
+	// Chroma subsampling as horizontal and vertical divisors, None for grayscale.
+	subsampling: Option<(u32, u32)>,
+	// Bytes in each frame's luma plane and in both its chroma planes together.
+	luma_size: usize,
+	chroma_size: usize,
+	// Width the frames are stretched to when the stream has non-square pixels.
+	display_width: u32,
+	delay: Duration,
+}
+
+impl Y4mSource<BufReader<File>> {
+	pub fn open(path: &str) -> Result<Self, Error> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+
+impl<R: BufRead> Y4mSource<R> {
+	pub fn new(mut reader: R) -> Result<Self, Error> {
+		let header = read_line(&mut reader)?.ok_or_else(|| invalid("Empty Y4M stream"))?;
+		let mut params = header.split(' ');
+		if params.next() != Some("YUV4MPEG2") {
+			return Err(invalid("Not a YUV4MPEG2 stream"));
+		}
+
+		let (mut width, mut height) = (0, 0);
+		let mut frame_rate = (crate::OUTPUT_FPS, 1);
+		let mut subsampling = Some((2, 2));
+		let mut pixel_aspect = (1, 1);
+		for param in params.filter(|param| !param.is_empty()) {
+			let tag = param.chars().next().expect("empty parameters were filtered out");
+			let value = &param[tag.len_utf8()..];
+			match tag {
+				'W' => width = value.parse().map_err(|_| invalid("Invalid Y4M frame width"))?,
+				'H' => height = value.parse().map_err(|_| invalid("Invalid Y4M frame height"))?,
+				'F' => {
+					frame_rate = ratio(value).ok_or_else(|| invalid("Invalid Y4M frame rate"))?;
+				},
+				'A' => {
+					pixel_aspect = ratio(value).ok_or_else(|| invalid("Invalid Y4M pixel aspect"))?;
+				},
+				'C' => {
+					subsampling = match value {
+						"420" | "420jpeg" | "420paldv" | "420mpeg2" => Some((2, 2)),
+						"422" => Some((2, 1)),
+						"444" => Some((1, 1)),
+						"mono" => None,
+						_ => return Err(Error::BadInput(format!("Unsupported Y4M colorspace {}", value))),
+					}
+				},
+				_ => {},
+			}
+		}
+
+		if width == 0 || height == 0 {
+			return Err(invalid("Y4M header is missing the frame size"));
+		}
+		// Sizes come from the file, so they are checked before anything is allocated from them.
+		let too_big = || Error::BadInput(format!("Y4M frames can't be {}x{}", width, height));
+		let luma_size = width.checked_mul(height).ok_or_else(too_big)?;
+		let chroma_size = match subsampling {
+			None => 0,
+			Some((sub_x, sub_y)) => width
+				.div_ceil(sub_x)
+				.checked_mul(height.div_ceil(sub_y))
+				.and_then(|size| size.checked_mul(2))
+				.ok_or_else(too_big)?,
+		};
+		let display_width = match pixel_aspect {
+			// 0:0 means the aspect is unknown.
+			(numerator, denominator) if numerator > 0 && denominator > 0 && numerator != denominator => {
+				(width as f64 * numerator as f64 / denominator as f64).round().max(1.0) as u32
+			},
+			_ => width,
+		};
+		let delay = match frame_rate {
+			(numerator, denominator) if numerator > 0 && denominator > 0 => {
+				Duration::from_secs_f64(denominator as f64 / numerator as f64)
+			},
+			_ => Duration::from_secs_f64(1.0 / crate::OUTPUT_FPS as f64),
+		};
+
+		Ok(Self {
+			reader,
+			width,
+			height,
+			subsampling,
+			luma_size: luma_size as usize,
+			chroma_size: chroma_size as usize,
+			display_width,
+			delay,
+		})
+	}
+}
+
+impl<R: BufRead> Y4mSource<R> {
+	pub fn next_image(&mut self) -> Result<Option<DynamicImage>, Error> {
+		match read_line(&mut self.reader)? {
+			Some(line) if line.starts_with("FRAME") => {},
+			Some(_) => return Err(invalid("Invalid Y4M frame header")),
+			None => return Ok(None),
+		}
+
+		let (width, height) = (self.width, self.height);
+		let mut luma = vec![0u8; self.luma_size];
+		self.reader.read_exact(&mut luma)?;
+
+		let image = match self.subsampling {
+			None => DynamicImage::ImageLuma8(
+				GrayImage::from_raw(width, height, luma)
+					.ok_or_else(|| Error::DecodeError("Failed to create image from buffer".to_string()))?,
+			),
+			Some((sub_x, sub_y)) => {
+				let chroma_width = width.div_ceil(sub_x);
+				let mut chroma = vec![0u8; self.chroma_size];
+				self.reader.read_exact(&mut chroma)?;
+				let (u_plane, v_plane) = chroma.split_at(chroma.len() / 2);
+
+				DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+					let index = ((y / sub_y) * chroma_width + x / sub_x) as usize;
+					yuv_to_rgb(luma[(y * width + x) as usize], u_plane[index], v_plane[index])
+				}))
+			},
+		};
+
+		let image = if self.display_width == width {
+			image
+		} else {
+			image.resize_exact(self.display_width, height, FilterType::Triangle)
+		};
+
+		Ok(Some(image))
+	}
+}
+
+impl<R: BufRead> FrameSource for Y4mSource<R> {
+	fn next_frame(&mut self) -> Result<Option<Frame>, Error> {
+		Ok(self.next_image()?.map(|image| Frame {
+			picture: Picture::Image(image),
+			delay: self.delay,
+		}))
+	}
+
+	fn frame_rate(&self) -> f64 {
+		1.0 / self.delay.as_secs_f64()
+	}
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>, Error> {
+	let mut line = Vec::new();
+	if reader.read_until(b'\n', &mut line)? == 0 {
+		return Ok(None);
+	}
+	if line.last() == Some(&b'\n') {
+		line.pop();
+	}
+	String::from_utf8(line)
+		.map(Some)
+		.map_err(|_| invalid("A Y4M header isn't valid UTF-8"))
+}
+
+// Two numbers separated by a colon, as frame rates and pixel aspects are given.
+fn ratio(value: &str) -> Option<(u64, u64)> {
+	let (numerator, denominator) = value.split_once(':')?;
+	Some((numerator.parse().ok()?, denominator.parse().ok()?))
+}
+
+fn invalid(message: &str) -> Error {
+	Error::BadInput(message.to_string())
+}
+
+// BT.601 with limited range, which is what Y4M streams carry unless told otherwise.
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> image::Rgb<u8> {
+	let y = (y as f32 - 16.0) * 1.164;
+	let u = u as f32 - 128.0;
+	let v = v as f32 - 128.0;
+
+	let r = y + 1.596 * v;
+	let g = y - 0.392 * u - 0.813 * v;
+	let b = y + 2.017 * u;
+
+	image::Rgb([r, g, b].map(|channel| channel.round().clamp(0.0, 255.0) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+
+	fn stream(header: &str, frames: &[&[u8]]) -> Cursor<Vec<u8>> {
+		let mut data = format!("{}\n", header).into_bytes();
+		for frame in frames {
+			data.extend_from_slice(b"FRAME\n");
+			data.extend_from_slice(frame);
+		}
+		Cursor::new(data)
+	}
+
+	#[test]
+	fn frames_are_decoded_at_the_stream_rate() {
+		// A white 2x2 frame: four luma samples and one each of U and V.
+		let white: &[u8] = &[235, 235, 235, 235, 128, 128];
+		let mut source = Y4mSource::new(stream("YUV4MPEG2 W2 H2 F25:1 C420jpeg", &[white, white])).unwrap();
+		assert!((source.frame_rate() - 25.0).abs() < 1e-9);
+		for _ in 0..2 {
+			let image = source.next_image().unwrap().unwrap().to_rgb8();
+			assert_eq!(image.dimensions(), (2, 2));
+			assert!(image.pixels().all(|pixel| pixel.0 == [255, 255, 255]));
+		}
+		assert!(source.next_image().unwrap().is_none());
+	}
+
+	#[test]
+	fn mono_streams_have_no_chroma() {
+		let mut source = Y4mSource::new(stream("YUV4MPEG2 W3 H1 Cmono", &[&[0, 128, 255]])).unwrap();
+		let image = source.next_image().unwrap().unwrap().to_luma8();
+		assert_eq!(image.into_raw(), [0, 128, 255]);
+	}
+
+	#[test]
+	fn non_square_pixels_are_stretched() {
+		let frame: &[u8] = &[16; 4 + 2 * 4];
+		let mut source = Y4mSource::new(stream("YUV4MPEG2 W4 H1 A2:1 C444", &[frame])).unwrap();
+		assert_eq!(source.next_image().unwrap().unwrap().width(), 8);
+	}
+
+	#[test]
+	fn bad_headers_are_refused() {
+		for header in [
+			"YUV4MPEG W2 H2",
+			"YUV4MPEG2 W2",
+			"YUV4MPEG2 W2 H2 C411",
+			"YUV4MPEG2 Wx H2",
+		] {
+			assert!(
+				matches!(Y4mSource::new(stream(header, &[])), Err(Error::BadInput(_))),
+				"{}",
+				header
+			);
+		}
+	}
+
+	#[test]
+	fn frame_sizes_that_overflow_are_refused() {
+		let source = Y4mSource::new(stream("YUV4MPEG2 W65536 H65536", &[]));
+		assert!(matches!(source, Err(Error::BadInput(_))));
+		let source = Y4mSource::new(stream("YUV4MPEG2 W4294967295 H1 C444", &[]));
+		assert!(matches!(source, Err(Error::BadInput(_))));
+	}
+
+	#[test]
+	fn unknown_parameters_are_skipped_whatever_they_start_with() {
+		let source = Y4mSource::new(stream("YUV4MPEG2 W2 H2 éx XYSCSS=420 C444", &[])).unwrap();
+		assert_eq!(source.subsampling, Some((1, 1)));
+	}
+
+	#[test]
+	fn truncated_frames_are_an_error() {
+		let mut source = Y4mSource::new(stream("YUV4MPEG2 W2 H2", &[&[16, 16, 16]])).unwrap();
+		assert!(source.next_image().is_err());
+	}
+}
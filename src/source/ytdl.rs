@@ -0,0 +1,51 @@
+use crate::Error;
+use std::io::ErrorKind;
+use std::process::Command;
+
+const YTDL_HOSTS: &[&str] = &[
+	"youtube.com",
+	"youtu.be",
+	"twitch.tv",
+	"vimeo.com",
+	"dailymotion.com",
+	"streamable.com",
+	"tiktok.com",
+];
+
+pub fn needs_ytdl(input: &str) -> bool {
+	let Some((_, rest)) = input.split_once("://") else {
+		return false;
+	};
+	let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+	let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+	let host = host.split(':').next().unwrap_or_default();
+
+	YTDL_HOSTS
+		.iter()
+		.any(|known| host == *known || host.ends_with(&format!(".{}", known)))
+}
+
+pub fn resolve_ytdl(url: &str, format: &str) -> Result<String, Error> {
+	let output = Command::new("yt-dlp")
+		.args(["--get-url", "--no-playlist", "--format", format, "--", url])
+		.output()
+		.map_err(|error| match error.kind() {
+			ErrorKind::NotFound => Error::YtdlpNotFound,
+			_ => error.into(),
+		})?;
+
+	if !output.status.success() {
+		return Err(Error::BadInput(format!(
+			"yt-dlp failed: {}",
+			String::from_utf8_lossy(&output.stderr).trim()
+		)));
+	}
+
+	// Formats that merge separate streams print one URL per stream, video first.
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let stream_url = stdout
+		.lines()
+		.next()
+		.ok_or_else(|| Error::BadInput("yt-dlp did not return a stream URL".to_string()))?;
+	Ok(stream_url.to_string())
+}
@@ -0,0 +1,204 @@
+use crate::export::indexed_color;
+use std::fmt::Write;
+#[cfg(feature = "cli")]
+use toml::Table;
+
+#[cfg(feature = "cli")]
+const BUILT_IN: [&str; 4] = ["default", "matrix", "amber", "mono"];
+
+// How playback looks: the characters frames are drawn with, the colors of the OSD, the progress
+// bar shown when seeking and, when set, the palette colors are snapped to for 16 and 256-color
+// terminals.
+#[derive(Clone)]
+pub struct Theme {
+	pub charset: Option<String>,
+	// SGR parameters the OSD is drawn with.
+	osd: String,
+	// The filled and empty parts of the progress bar.
+	progress: (char, char),
+	// Color numbers of the terminal's palette, empty to keep the frames' own colors.
+	palette: Vec<u8>,
+}
+
+impl Theme {
+	// Themes under [themes.NAME] in config.toml take precedence over the built-in ones, and start
+	// from the default theme for whatever they leave out.
+	#[cfg(feature = "cli")]
+	pub fn find(name: &str, custom: Option<&Table>) -> Result<Self, String> {
+		if let Some(settings) = custom.and_then(|themes| themes.get(name)) {
+			let settings = settings
+				.as_table()
+				.ok_or_else(|| format!("[themes.{}] has to be a table", name))?;
+			return Self::from_settings(name, settings);
+		}
+		Self::built_in(name).ok_or_else(|| {
+			let mut names: Vec<&str> = BUILT_IN.to_vec();
+			names.extend(custom.into_iter().flat_map(|themes| themes.keys().map(String::as_str)));
+			format!("there is no `{}` theme, try one of {}", name, names.join(", "))
+		})
+	}
+
+	pub fn built_in(name: &str) -> Option<Self> {
+		let theme = match name {
+			"default" => Self::default(),
+			"matrix" => Self {
+				charset: Some("@0O1o:. ".into()),
+				osd: "38;5;46;48;5;22".into(),
+				progress: ('█', '░'),
+				palette: vec![22, 28, 34, 40, 46, 82, 118, 157],
+			},
+			"amber" => Self {
+				charset: None,
+				osd: "38;5;16;48;5;214".into(),
+				progress: ('=', '-'),
+				palette: vec![52, 94, 130, 136, 172, 178, 214, 220],
+			},
+			"mono" => Self {
+				charset: None,
+				osd: "7".into(),
+				progress: ('#', '.'),
+				palette: (232..=255).collect(),
+			},
+			_ => return None,
+		};
+		Some(theme)
+	}
+
+	#[cfg(feature = "cli")]
+	fn from_settings(name: &str, settings: &Table) -> Result<Self, String> {
+		let invalid = |key: &str, expected: &str| format!("`{}` in [themes.{}] has to be {}", key, name, expected);
+		let color = |key: &str| -> Result<Option<u8>, String> {
+			settings
+				.get(key)
+				.map(|value| {
+					value
+						.as_integer()
+						.and_then(|number| u8::try_from(number).ok())
+						.ok_or_else(|| invalid(key, "a color number from 0 to 255"))
+				})
+				.transpose()
+		};
+
+		let mut theme = Self::default();
+		for key in settings.keys() {
+			if !["charset", "osd-foreground", "osd-background", "progress", "palette"].contains(&key.as_str()) {
+				return Err(format!("unknown setting `{}` in [themes.{}]", key, name));
+			}
+		}
+		if let Some(charset) = settings.get("charset") {
+			theme.charset = Some(charset.as_str().ok_or_else(|| invalid("charset", "a string"))?.into());
+		}
+		match (color("osd-foreground")?, color("osd-background")?) {
+			(None, None) => {},
+			(foreground, background) => {
+				let mut osd = Vec::new();
+				osd.extend(foreground.map(|color| format!("38;5;{}", color)));
+				osd.extend(background.map(|color| format!("48;5;{}", color)));
+				theme.osd = osd.join(";");
+			},
+		}
+		if let Some(progress) = settings.get("progress") {
+			let expected = "blocks, ascii, dots or two characters";
+			let progress = progress.as_str().ok_or_else(|| invalid("progress", expected))?;
+			theme.progress = match progress {
+				"blocks" => ('█', '░'),
+				"ascii" => ('=', '-'),
+				"dots" => ('●', '·'),
+				custom => {
+					let mut chars = custom.chars();
+					match (chars.next(), chars.next(), chars.next()) {
+						(Some(filled), Some(empty), None) => (filled, empty),
+						_ => return Err(invalid("progress", expected)),
+					}
+				},
+			};
+		}
+		if let Some(palette) = settings.get("palette") {
+			let expected = "a list of color numbers from 0 to 255";
+			theme.palette = palette
+				.as_array()
+				.and_then(|colors| {
+					colors
+						.iter()
+						.map(|color| color.as_integer().and_then(|number| u8::try_from(number).ok()))
+						.collect()
+				})
+				.ok_or_else(|| invalid("palette", expected))?;
+		}
+		Ok(theme)
+	}
+
+	pub fn osd_style(&self) -> &str {
+		&self.osd
+	}
+
+	pub fn palette(&self) -> &[u8] {
+		&self.palette
+	}
+
+	pub fn progress_bar(&self, fraction: f64, width: usize) -> String {
+		let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+		let (full, empty) = self.progress;
+		std::iter::repeat_n(full, filled)
+			.chain(std::iter::repeat_n(empty, width - filled))
+			.collect()
+	}
+
+	// Replaces truecolor escapes with the nearest palette color, written the way 16-color
+	// terminals understand for the first 16 and as 256-color escapes for the rest.
+	pub fn quantize(&self, line: &str) -> String {
+		const TRUECOLOR: &str = "\u{1b}[38;2;";
+		let mut quantized = String::with_capacity(line.len());
+		let mut rest = line;
+		while let Some(start) = rest.find(TRUECOLOR) {
+			quantized.push_str(&rest[..start]);
+			let after = &rest[start + TRUECOLOR.len()..];
+			let Some(end) = after.find('m') else {
+				break;
+			};
+			let rgb: Vec<u8> = after[..end].split(';').filter_map(|part| part.parse().ok()).collect();
+			match rgb[..] {
+				[red, green, blue] => {
+					let color = self.nearest([red, green, blue]);
+					match color {
+						0..=7 => write!(quantized, "\u{1b}[{}m", 30 + color as u16),
+						8..=15 => write!(quantized, "\u{1b}[{}m", 90 + color as u16 - 8),
+						_ => write!(quantized, "\u{1b}[38;5;{}m", color),
+					}
+					.expect("writing to a String can't fail");
+				},
+				_ => quantized.push_str(&rest[start..start + TRUECOLOR.len() + end + 1]),
+			}
+			rest = &after[end + 1..];
+		}
+		quantized.push_str(rest);
+		quantized
+	}
+
+	fn nearest(&self, rgb: [u8; 3]) -> u8 {
+		let distance = |color: u8| {
+			indexed_color(color as u32)
+				.0
+				.iter()
+				.zip(rgb)
+				.map(|(&a, b)| (a as i32 - b as i32).pow(2))
+				.sum::<i32>()
+		};
+		self.palette
+			.iter()
+			.copied()
+			.min_by_key(|&color| distance(color))
+			.expect("quantizing only happens with a palette")
+	}
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		Self {
+			charset: None,
+			osd: "7".into(),
+			progress: ('█', '░'),
+			palette: Vec::new(),
+		}
+	}
+}
@@ -0,0 +1,153 @@
+use crate::convert::ConvertedFrames;
+use crate::frame::AsciiFrame;
+use crate::schedule;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::widgets::{StatefulWidget, Widget};
+use std::time::{Duration, Instant};
+
+// Draws a frame into part of a ratatui buffer, centered in it like the player centers frames in the
+// terminal and cut off where it doesn't fit. Cells without a color of their own keep the buffer's.
+// Rendered as a Widget it draws the frame it was made with; as a StatefulWidget it draws whichever
+// frame BadAsciiState is at.
+#[derive(Default)]
+pub struct BadAsciiWidget<'a> {
+	frame: Option<&'a AsciiFrame>,
+}
+
+impl<'a> BadAsciiWidget<'a> {
+	pub fn new(frame: &'a AsciiFrame) -> Self {
+		Self { frame: Some(frame) }
+	}
+}
+
+impl Widget for BadAsciiWidget<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		if let Some(frame) = self.frame {
+			draw(frame, area, buf);
+		}
+	}
+}
+
+impl StatefulWidget for BadAsciiWidget<'_> {
+	type State = BadAsciiState;
+
+	fn render(self, area: Rect, buf: &mut Buffer, state: &mut BadAsciiState) {
+		draw(state.current(), area, buf);
+	}
+}
+
+fn draw(frame: &AsciiFrame, area: Rect, buf: &mut Buffer) {
+	let area = area.intersection(buf.area);
+	let (width, height) = (area.width as usize, area.height as usize);
+	// Frames larger than the area lose as much of each side.
+	let (left, skip_cols) = (
+		width.saturating_sub(frame.cols) / 2,
+		frame.cols.saturating_sub(width) / 2,
+	);
+	let (top, skip_rows) = (
+		height.saturating_sub(frame.rows) / 2,
+		frame.rows.saturating_sub(height) / 2,
+	);
+
+	for row in 0..frame.rows.min(height) {
+		let cells = &frame.row(skip_rows + row)[skip_cols..];
+		for (col, cell) in cells.iter().take(width).enumerate() {
+			let position = (area.x + (left + col) as u16, area.y + (top + row) as u16);
+			let Some(target) = buf.cell_mut(position) else {
+				continue;
+			};
+			target.set_char(cell.ch);
+			if let Some(color) = cell.fg {
+				target.set_fg(Color::Rgb(color[0], color[1], color[2]));
+			}
+			if let Some(color) = cell.bg {
+				target.set_bg(Color::Rgb(color[0], color[1], color[2]));
+			}
+		}
+	}
+}
+
+// Where playback of converted frames is, going by the clock from when it was made. The app only
+// has to redraw often enough; the frame due then is picked when the widget is rendered.
+pub struct BadAsciiState {
+	frames: Vec<Vec<String>>,
+	timestamps: Vec<f64>,
+	duration: f64,
+	looping: bool,
+	// Where playback was at the anchor, and the anchor itself unless paused.
+	anchor: Option<Instant>,
+	anchor_position: f64,
+	// The frame last drawn, kept as cells so it isn't parsed again until another is due.
+	shown: Option<(usize, AsciiFrame)>,
+}
+
+impl BadAsciiState {
+	pub fn new((frames, delays): ConvertedFrames) -> Self {
+		Self {
+			timestamps: schedule::timestamps(&delays),
+			duration: delays.iter().sum::<Duration>().as_secs_f64(),
+			frames,
+			looping: false,
+			anchor: Some(Instant::now()),
+			anchor_position: 0.0,
+			shown: None,
+		}
+	}
+
+	pub fn loop_(mut self, looping: bool) -> Self {
+		self.looping = looping;
+		self
+	}
+
+	pub fn position(&self) -> Duration {
+		let elapsed = self.anchor.map_or(0.0, |anchor| anchor.elapsed().as_secs_f64());
+		let position = self.anchor_position + elapsed;
+		if self.looping && self.duration > 0.0 {
+			Duration::from_secs_f64(position % self.duration)
+		} else {
+			Duration::from_secs_f64(position.min(self.duration))
+		}
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.anchor.is_none()
+	}
+
+	pub fn pause(&mut self) {
+		self.anchor_position = self.position().as_secs_f64();
+		self.anchor = None;
+	}
+
+	pub fn resume(&mut self) {
+		if self.anchor.is_none() {
+			self.anchor = Some(Instant::now());
+		}
+	}
+
+	// Positions past the end are taken as the end.
+	pub fn seek(&mut self, position: Duration) {
+		self.anchor_position = position.as_secs_f64().min(self.duration);
+		if self.anchor.is_some() {
+			self.anchor = Some(Instant::now());
+		}
+	}
+
+	// Whether playback that doesn't loop has reached the end.
+	pub fn is_finished(&self) -> bool {
+		!self.looping && self.position().as_secs_f64() >= self.duration
+	}
+
+	pub fn current(&mut self) -> &AsciiFrame {
+		let index = schedule::frame_at(&self.timestamps, self.position().as_secs_f64());
+		if self.shown.as_ref().is_none_or(|(shown, _)| *shown != index) {
+			let frame = self
+				.frames
+				.get(index)
+				.map_or_else(AsciiFrame::default, |lines| AsciiFrame::from_lines(lines));
+			self.shown = Some((index, frame));
+		}
+		&self.shown.as_ref().expect("a frame was just picked").1
+	}
+}
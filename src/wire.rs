@@ -0,0 +1,364 @@
+use bad_ascii::{Error, baa};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+// The frame streaming protocol spoken between `serve --stream` and `connect`, over any reliable
+// byte stream. Everything is little endian, like the .baa container whose frame records it
+// carries.
+//
+// Every message is a type byte, the payload's length as a u32 and the payload. Receivers skip
+// messages of types they don't know, so later versions can add messages without breaking older
+// peers; anything a peer must understand comes with a version bump instead.
+//
+// The client opens with
+//   HELLO      "BAASTRM\0", the highest version it speaks (u16), the capabilities it supports
+//              (u32) and its terminal's columns and rows (u16 each)
+// and the server answers with
+//   WELCOME    the version both will speak (u16), the capabilities in use (u32) and the frame
+//              rate (f32, 0 when it varies)
+// or with ERROR and hangs up. After that the server sends
+//   FORMAT     the columns and rows of the frames that follow (u16 each) and whether they carry
+//              ANSI colors (u8); sent before the first frame and whenever the size changes, and
+//              the frame after it is always a keyframe
+//   FRAME      its presentation time in microseconds since the stream started (u64), followed by
+//              a .baa frame record: the frame type, how long it's shown for and the frame, whole
+//              or as the cells that changed since the one before. With CAP_ZSTD the record is zstd
+//              compressed. Frames arrive when they are due, the server keeps the pace.
+// while the client may send
+//   RESIZE     its terminal's new columns and rows (u16 each), which the server may answer with a
+//              FORMAT and frames converted to fit
+// and either side
+//   KEEPALIVE  empty, whenever it has sent nothing else for KEEPALIVE_INTERVAL; a peer that hasn't
+//              been heard from for PEER_TIMEOUT is given up on
+//   ERROR      why the sender is hanging up, as UTF-8 text
+pub const MAGIC: &[u8; 8] = b"BAASTRM\0";
+pub const VERSION: u16 = 1;
+
+// Frame records are compressed one at a time.
+pub const CAP_ZSTD: u32 = 1 << 0;
+// The client's terminal shows colors, so they can be left in the frames.
+pub const CAP_COLOR: u32 = 1 << 1;
+pub const CAPABILITIES: u32 = CAP_ZSTD | CAP_COLOR;
+
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+
+const HELLO: u8 = 0x01;
+const WELCOME: u8 = 0x02;
+const FORMAT: u8 = 0x03;
+const FRAME: u8 = 0x04;
+const RESIZE: u8 = 0x05;
+const KEEPALIVE: u8 = 0x06;
+const ERROR: u8 = 0x07;
+
+// Nothing legitimate comes close, so a larger length means the stream isn't this protocol. Only
+// frames can be anywhere near that big, the rest are held to far less before anything is allocated
+// for them.
+const MAX_PAYLOAD: u32 = 64 << 20;
+const MAX_CONTROL_PAYLOAD: u32 = 64;
+const MAX_ERROR_PAYLOAD: u32 = 4096;
+
+fn max_payload(kind: u8) -> u32 {
+	match kind {
+		HELLO | WELCOME | FORMAT | RESIZE | KEEPALIVE => MAX_CONTROL_PAYLOAD,
+		ERROR => MAX_ERROR_PAYLOAD,
+		_ => MAX_PAYLOAD,
+	}
+}
+
+pub enum Message {
+	Hello {
+		version: u16,
+		capabilities: u32,
+		columns: u16,
+		rows: u16,
+	},
+	Welcome {
+		version: u16,
+		capabilities: u32,
+		fps: f32,
+	},
+	Format {
+		columns: u16,
+		rows: u16,
+		color: bool,
+	},
+	// The record as it was sent, decoded with decode_frame once the capabilities are known.
+	Frame {
+		pts: Duration,
+		record: Vec<u8>,
+	},
+	Resize {
+		columns: u16,
+		rows: u16,
+	},
+	KeepAlive,
+	Error(String),
+}
+
+impl Message {
+	pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+		let mut payload = Vec::new();
+		let kind = match self {
+			Message::Hello {
+				version,
+				capabilities,
+				columns,
+				rows,
+			} => {
+				payload.extend_from_slice(MAGIC);
+				payload.extend_from_slice(&version.to_le_bytes());
+				payload.extend_from_slice(&capabilities.to_le_bytes());
+				payload.extend_from_slice(&columns.to_le_bytes());
+				payload.extend_from_slice(&rows.to_le_bytes());
+				HELLO
+			},
+			Message::Welcome {
+				version,
+				capabilities,
+				fps,
+			} => {
+				payload.extend_from_slice(&version.to_le_bytes());
+				payload.extend_from_slice(&capabilities.to_le_bytes());
+				payload.extend_from_slice(&fps.to_le_bytes());
+				WELCOME
+			},
+			Message::Format { columns, rows, color } => {
+				payload.extend_from_slice(&columns.to_le_bytes());
+				payload.extend_from_slice(&rows.to_le_bytes());
+				payload.push(*color as u8);
+				FORMAT
+			},
+			Message::Frame { pts, record } => {
+				payload.extend_from_slice(&(pts.as_micros() as u64).to_le_bytes());
+				payload.extend_from_slice(record);
+				FRAME
+			},
+			Message::Resize { columns, rows } => {
+				payload.extend_from_slice(&columns.to_le_bytes());
+				payload.extend_from_slice(&rows.to_le_bytes());
+				RESIZE
+			},
+			Message::KeepAlive => KEEPALIVE,
+			Message::Error(reason) => {
+				payload.extend_from_slice(reason.as_bytes());
+				ERROR
+			},
+		};
+		out.write_all(&[kind])?;
+		out.write_all(&(payload.len() as u32).to_le_bytes())?;
+		out.write_all(&payload)?;
+		out.flush()
+	}
+
+	// Skips over messages of unknown types rather than returning them.
+	pub fn read(input: &mut impl Read) -> Result<Self, Error> {
+		loop {
+			let [kind] = read_array(input)?;
+			let length = u32::from_le_bytes(read_array(input)?);
+			if length > max_payload(kind) {
+				return Err(Error::Network("Not a bad_ascii stream, or a corrupted one".to_string()));
+			}
+			if ![HELLO, WELCOME, FORMAT, FRAME, RESIZE, KEEPALIVE, ERROR].contains(&kind) {
+				io::copy(&mut input.by_ref().take(length as u64), &mut io::sink())?;
+				continue;
+			}
+			let mut payload = vec![0; length as usize];
+			input.read_exact(&mut payload)?;
+			let payload = &mut payload.as_slice();
+
+			let message = match kind {
+				HELLO => {
+					if read_array(payload)? != *MAGIC {
+						return Err(Error::Network("Not a bad_ascii stream".to_string()));
+					}
+					Message::Hello {
+						version: u16::from_le_bytes(read_array(payload)?),
+						capabilities: u32::from_le_bytes(read_array(payload)?),
+						columns: u16::from_le_bytes(read_array(payload)?),
+						rows: u16::from_le_bytes(read_array(payload)?),
+					}
+				},
+				WELCOME => Message::Welcome {
+					version: u16::from_le_bytes(read_array(payload)?),
+					capabilities: u32::from_le_bytes(read_array(payload)?),
+					fps: f32::from_le_bytes(read_array(payload)?),
+				},
+				FORMAT => Message::Format {
+					columns: u16::from_le_bytes(read_array(payload)?),
+					rows: u16::from_le_bytes(read_array(payload)?),
+					color: read_array::<1>(payload)?[0] != 0,
+				},
+				FRAME => Message::Frame {
+					pts: Duration::from_micros(u64::from_le_bytes(read_array(payload)?)),
+					record: payload.to_vec(),
+				},
+				RESIZE => Message::Resize {
+					columns: u16::from_le_bytes(read_array(payload)?),
+					rows: u16::from_le_bytes(read_array(payload)?),
+				},
+				KEEPALIVE => Message::KeepAlive,
+				ERROR => Message::Error(String::from_utf8_lossy(payload).into_owned()),
+				_ => unreachable!("unknown messages were skipped"),
+			};
+			return Ok(message);
+		}
+	}
+}
+
+// A FRAME's record, made from the frame before it as the last one sent.
+pub fn encode_frame(
+	previous: Option<&[String]>,
+	frame: &[String],
+	delay: Duration,
+	capabilities: u32,
+) -> Result<Vec<u8>, Error> {
+	let delay = u32::try_from(delay.as_micros())
+		.map_err(|_| Error::EncodeError("Frame delay is too long to stream".to_string()))?;
+	let (record, _) = baa::encode_record(previous, frame, delay)?;
+	Ok(if capabilities & CAP_ZSTD != 0 {
+		zstd::bulk::compress(&record, baa::DEFAULT_LEVEL)?
+	} else {
+		record
+	})
+}
+
+// Returns the frame and how long it's shown for.
+pub fn decode_frame(
+	record: &[u8],
+	previous: Option<&[String]>,
+	capabilities: u32,
+) -> Result<(Duration, Vec<String>), Error> {
+	if capabilities & CAP_ZSTD != 0 {
+		baa::decode_record(&mut zstd::decode_all(record)?.as_slice(), previous)
+	} else {
+		baa::decode_record(&mut &record[..], previous)
+	}
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	input.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn round_trip(message: Message) -> Message {
+		let mut sent = Vec::new();
+		message.write(&mut sent).unwrap();
+		let mut input = sent.as_slice();
+		let received = Message::read(&mut input).unwrap();
+		assert!(input.is_empty(), "the whole message was read");
+		received
+	}
+
+	#[test]
+	fn messages_round_trip() {
+		let hello = Message::Hello {
+			version: VERSION,
+			capabilities: CAPABILITIES,
+			columns: 80,
+			rows: 24,
+		};
+		assert!(matches!(
+			round_trip(hello),
+			Message::Hello {
+				version: VERSION,
+				capabilities: CAPABILITIES,
+				columns: 80,
+				rows: 24,
+			}
+		));
+		let welcome = Message::Welcome {
+			version: VERSION,
+			capabilities: CAP_ZSTD,
+			fps: 24.0,
+		};
+		assert!(matches!(
+			round_trip(welcome),
+			Message::Welcome { version: VERSION, capabilities: CAP_ZSTD, fps } if fps == 24.0
+		));
+		let format = Message::Format {
+			columns: 160,
+			rows: 45,
+			color: true,
+		};
+		assert!(matches!(
+			round_trip(format),
+			Message::Format {
+				columns: 160,
+				rows: 45,
+				color: true,
+			}
+		));
+		let frame = Message::Frame {
+			pts: Duration::from_millis(1500),
+			record: vec![1, 2, 3],
+		};
+		assert!(matches!(
+			round_trip(frame),
+			Message::Frame { pts, record } if pts == Duration::from_millis(1500) && record == [1, 2, 3]
+		));
+		assert!(matches!(
+			round_trip(Message::Resize { columns: 100, rows: 30 }),
+			Message::Resize { columns: 100, rows: 30 }
+		));
+		assert!(matches!(round_trip(Message::KeepAlive), Message::KeepAlive));
+		assert!(matches!(
+			round_trip(Message::Error("bye".into())),
+			Message::Error(reason) if reason == "bye"
+		));
+	}
+
+	#[test]
+	fn unknown_messages_are_skipped() {
+		let mut sent = vec![0x7f];
+		sent.extend_from_slice(&3u32.to_le_bytes());
+		sent.extend_from_slice(b"new");
+		Message::KeepAlive.write(&mut sent).unwrap();
+		assert!(matches!(Message::read(&mut sent.as_slice()), Ok(Message::KeepAlive)));
+	}
+
+	#[test]
+	fn oversized_lengths_are_refused() {
+		// A HELLO claiming to be a megabyte long is refused before any of it is read.
+		let mut sent = vec![HELLO];
+		sent.extend_from_slice(&(1u32 << 20).to_le_bytes());
+		assert!(matches!(Message::read(&mut sent.as_slice()), Err(Error::Network(_))));
+
+		let mut sent = vec![FRAME];
+		sent.extend_from_slice(&(MAX_PAYLOAD + 1).to_le_bytes());
+		assert!(matches!(Message::read(&mut sent.as_slice()), Err(Error::Network(_))));
+	}
+
+	#[test]
+	fn hello_needs_the_magic() {
+		let mut sent = Vec::new();
+		Message::Hello {
+			version: VERSION,
+			capabilities: 0,
+			columns: 80,
+			rows: 24,
+		}
+		.write(&mut sent)
+		.unwrap();
+		sent[5] = b'X';
+		assert!(matches!(Message::read(&mut sent.as_slice()), Err(Error::Network(_))));
+	}
+
+	#[test]
+	fn frames_round_trip_with_and_without_zstd() {
+		let previous = vec!["abc".to_string(), "def".to_string()];
+		let frame = vec!["abc".to_string(), "dXf".to_string()];
+		let delay = Duration::from_millis(40);
+		for capabilities in [0, CAP_ZSTD] {
+			let record = encode_frame(Some(&previous), &frame, delay, capabilities).unwrap();
+			let (decoded_delay, decoded) = decode_frame(&record, Some(&previous), capabilities).unwrap();
+			assert_eq!(decoded_delay, delay);
+			assert_eq!(decoded, frame);
+		}
+	}
+}